@@ -0,0 +1,185 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use voxircle::app::data_structures::blocks::Blocks;
+use voxircle::app::data_structures::slice_parameters::SliceParameters;
+use voxircle::app::generation::percentage::CoverageEstimator;
+use voxircle::app::generation::{Algorithm, Generator};
+use voxircle::app::metrics::convex_hull::get_convex_hull;
+use voxircle::app::sampling::sampled_parameters::LayerParameters;
+use voxircle::app::sampling::SampleCombineMethod;
+
+const RADII: [f64; 3] = [10.0, 50.0, 200.0];
+
+const ALGORITHMS: [Algorithm; 5] = [
+    Algorithm::Centerpoint,
+    Algorithm::Conservative(0.0),
+    Algorithm::Contained(0.0),
+    Algorithm::Percentage(0.5, CoverageEstimator::Exact),
+    Algorithm::Empty,
+];
+
+/// Conservative and Contained decide the blocks near the boundary by the distance to it, which is
+///  the slow part of growing or shrinking a shape
+const MARGIN_ALGORITHMS: [Algorithm; 4] = [
+    Algorithm::Conservative(0.0),
+    Algorithm::Conservative(1.5),
+    Algorithm::Contained(0.0),
+    Algorithm::Contained(-1.5),
+];
+
+const COMBINE_METHODS: [SampleCombineMethod; 3] = [
+    SampleCombineMethod::AllSamples,
+    SampleCombineMethod::AnySamples,
+    SampleCombineMethod::Percentage(0.5),
+];
+
+/// A single sample of a slightly tilted and offset shape, so that no algorithm gets an easy case
+fn layer_parameters(algorithm: Algorithm, radius: f64) -> LayerParameters {
+    LayerParameters {
+        nr_samples: 1,
+        algorithm,
+        parameters: vec![SliceParameters {
+            algorithm,
+            radius_a: radius,
+            radius_b: radius,
+            tilt: 0.1,
+            center_offset_x: 0.3,
+            center_offset_y: 0.2,
+            ..Default::default()
+        }],
+    }
+}
+
+/// A thin, tilted shape: the distance to the boundary of this is the hardest to find
+fn thin_layer_parameters(
+    algorithm: Algorithm,
+    radius: f64,
+    squircle_parameter: f64,
+) -> LayerParameters {
+    LayerParameters {
+        nr_samples: 1,
+        algorithm,
+        parameters: vec![SliceParameters {
+            algorithm,
+            radius_a: radius,
+            radius_b: radius / 20.0,
+            tilt: 0.5,
+            squircle_parameter,
+            ..Default::default()
+        }],
+    }
+}
+
+/// Several samples with growing radius, as for a layer with a sloped side
+fn sampled_layer_parameters(radius: f64, nr_samples: usize) -> LayerParameters {
+    LayerParameters {
+        nr_samples,
+        algorithm: Algorithm::Centerpoint,
+        parameters: (0..nr_samples)
+            .map(|i| SliceParameters {
+                radius_a: radius + i as f64 * 0.1,
+                radius_b: radius + i as f64 * 0.1,
+                ..Default::default()
+            })
+            .collect(),
+    }
+}
+
+fn shape(radius: f64, generator: &Generator) -> Blocks {
+    layer_parameters(Algorithm::Centerpoint, radius)
+        .generate(&SampleCombineMethod::AllSamples, generator)
+}
+
+fn bench_algorithms(c: &mut Criterion) {
+    let generator = Generator::default();
+    let mut group = c.benchmark_group("algorithms");
+    for algorithm in ALGORITHMS {
+        for radius in RADII {
+            let layer_parameters = layer_parameters(algorithm, radius);
+            group.bench_with_input(
+                BenchmarkId::new(algorithm.to_string(), radius),
+                &layer_parameters,
+                |b, layer_parameters| {
+                    b.iter(|| {
+                        layer_parameters
+                            .generate(black_box(&SampleCombineMethod::AllSamples), &generator)
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_margins(c: &mut Criterion) {
+    let generator = Generator::default();
+    let mut group = c.benchmark_group("margins");
+    for algorithm in MARGIN_ALGORITHMS {
+        for radius in RADII {
+            for (name, layer_parameters) in [
+                ("round", layer_parameters(algorithm, radius)),
+                ("thin", thin_layer_parameters(algorithm, radius, 2.0)),
+                ("thin star", thin_layer_parameters(algorithm, radius, 0.6)),
+            ] {
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{} ({})", algorithm, name), radius),
+                    &layer_parameters,
+                    |b, layer_parameters| {
+                        b.iter(|| {
+                            layer_parameters
+                                .generate(black_box(&SampleCombineMethod::AllSamples), &generator)
+                        })
+                    },
+                );
+            }
+        }
+    }
+    group.finish();
+}
+
+fn bench_combine_methods(c: &mut Criterion) {
+    let generator = Generator::default();
+    let mut group = c.benchmark_group("combine_methods");
+    for combine_method in COMBINE_METHODS {
+        for radius in RADII {
+            let layer_parameters = sampled_layer_parameters(radius, 5);
+            group.bench_with_input(
+                BenchmarkId::new(combine_method.to_string(), radius),
+                &layer_parameters,
+                |b, layer_parameters| {
+                    b.iter(|| layer_parameters.generate(black_box(&combine_method), &generator))
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_metrics(c: &mut Criterion) {
+    let generator = Generator::default();
+    let mut group = c.benchmark_group("metrics");
+    for radius in RADII {
+        let blocks = shape(radius, &generator);
+        group.bench_with_input(
+            BenchmarkId::new("symmetry_type", radius),
+            &blocks,
+            |b, blocks| b.iter(|| black_box(blocks).get_symmetry_type()),
+        );
+
+        let outer_corners = blocks.get_outer_corners();
+        group.bench_with_input(
+            BenchmarkId::new("convex_hull", radius),
+            &outer_corners,
+            |b, outer_corners| b.iter(|| get_convex_hull(black_box(outer_corners))),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_algorithms,
+    bench_margins,
+    bench_combine_methods,
+    bench_metrics
+);
+criterion_main!(benches);
@@ -1,40 +1,151 @@
 use std::collections::VecDeque;
 use std::default::Default;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::Duration;
 
 use eframe::egui::{self};
 use eframe::egui::{Direction, Layout};
 use eframe::emath::Align;
 
+use crate::app::actions::{close_editing_windows, Action, CommandPalette, OPEN_COMMAND_PALETTE};
+use crate::app::algorithm_field::{AlgorithmField, CustomPredicateField};
+use crate::app::arch::ArchTool;
+use crate::app::block_budget::{scale_slice_parameters, BlockBudget};
+use crate::app::block_cache::{BlockCache, BLOCK_CACHE_ENABLED_KEY, BLOCK_CACHE_KEY};
+use crate::app::calculator::Calculator;
 use crate::app::control::Control;
-use crate::app::view::View;
+use crate::app::error::VoxircleError;
+use crate::app::export::{
+    build_order_save_request, metrics_save_request, ComputedMetrics, McfunctionExport,
+};
+use crate::app::export_script::ExportScript;
+use crate::app::find_replace::FindReplace;
+use crate::app::fit_to_points::{with_fitted_shape, FitToPoints};
+use crate::app::generation::cell_aspect::{from_cells, to_cells};
+use crate::app::generation::script_shape::{is_script_file, ShapeLibrary, SHAPE_SCRIPT_PATHS_KEY};
+use crate::app::generation::{Algorithm, Generator};
+use crate::app::io::{dropped_file_name, read_dropped_bytes, read_dropped_file, IoService};
+use crate::app::layer_playback::LayerPlayback;
+use crate::app::layout::StructureLayout;
+use crate::app::lean::{Lean, LeanPreview};
+use crate::app::log::log_warning;
+use crate::app::math::random::RANDOM_SEED_KEY;
+use crate::app::metrics::boundary_segments::BoundarySegments;
+use crate::app::metrics::boundary_tour::BoundaryTour;
+use crate::app::metrics::depth_shading::get_block_depths;
+use crate::app::metrics::distance_bands::{get_distance_bands, DistanceBands};
+use crate::app::metrics::holes::Holes;
+use crate::app::metrics::repeated_layers::LayerRun;
+use crate::app::metrics::vertical_slice::SliceAxis;
+use crate::app::new_layers::{fill_new_layers, NewLayerFill};
+use crate::app::parameter_history::ParameterHistory;
+use crate::app::reference_image::{
+    is_image_file, ImagePlacement, ReferenceImage, REFERENCE_IMAGE_PATH_KEY,
+    REFERENCE_IMAGE_PLACEMENT_KEY,
+};
+use crate::app::scaling::{shape_area, ScaleTool};
+use crate::app::stamp::{Stamp, StampTool};
+use crate::app::sub_blocks::{get_sub_blocks, SubBlock};
+use crate::app::templates::Template;
+use crate::app::tutorial::{Tutorial, TutorialStep, TUTORIAL_FINISHED_KEY};
+use crate::app::variations::VariationGallery;
+use crate::app::view::{View, ZOOM_TO_LAYER_KEY};
 use data_structures::blocks::Blocks;
+use data_structures::lattice::Lattice;
+use data_structures::layer_bookmark::LayerBookmark;
+use data_structures::layer_group::LayerGroup;
+use data_structures::parameter_constraint::{is_constrained, Parameter, ParameterConstraint};
 use data_structures::slice_parameters::SliceParameters;
 use data_structures::symmetry_type::SymmetryType;
 use data_structures::zvec::ZVec;
 use lua_field::LuaField;
+use sampling::percentage_suggestion::PercentageSuggestion;
+use sampling::presets::SamplingPreset;
 use sampling::sampled_parameters::LayerParameters;
-use sampling::{SampleCombineMethod, SampleDistributeMethod};
-use ui::generation::ui_generation;
-use ui::layer_navigation::ui_layer_navigation;
-use ui::options::ui_options;
+use sampling::{determine_sampling_points, SampleCombineMethod, SampleDistributeMethod};
+use ui::arch::ui_arch;
+use ui::block_budget::ui_block_budget;
+use ui::calculator::ui_calculator;
+use ui::command_palette::ui_command_palette;
+use ui::export_script::ui_export_script;
+use ui::find_replace::ui_find_replace;
+use ui::fit_to_points::{ui_fit_to_points, ui_shape_through_points};
+use ui::generation::{ui_cell_aspect, ui_fixed_grid_size, ui_generation};
+use ui::layer_navigation::{
+    ui_layer_bookmarks, ui_layer_groups, ui_layer_navigation, ui_layer_playback, ui_new_layer_fill,
+    ui_repeated_layers,
+};
+use ui::layout_canvas::ui_layout_canvas;
+use ui::lean::ui_lean;
+use ui::log_console::ui_log_console;
+use ui::options::{ui_options, OptionsCode, ParameterControls};
+use ui::parameter_history::ui_parameter_history;
+use ui::reference_image::ui_reference_image;
 use ui::sampling::ui_sampling;
-use ui::viewport::ui_viewport;
-use ui::viewport_options::ui_viewport_options;
-use update::logic::{blocks_update, parameters_update, sampling_points_update};
-use update::metrics::update_metrics;
+use ui::scaling::ui_scaling;
+use ui::stamp::ui_text_stamp;
+use ui::template_gallery::ui_template_gallery;
+use ui::toasts::{ui_drop_hint, ui_toasts};
+use ui::tutorial::ui_tutorial;
+use ui::variation_gallery::ui_variation_gallery;
+use ui::vertical_slice::ui_vertical_slice;
+use ui::viewport::{ui_viewport, ViewportLayer, ViewportMetrics, ViewportPreviews, ViewportZoom};
+use ui::viewport_options::{
+    ui_boundary_segments_legend, ui_build_order_export, ui_holes_summary, ui_image_export,
+    ui_mcfunction_export, ui_metrics_export, ui_octant_counts, ui_outline_export,
+    ui_sub_blocks_summary, ui_viewport_options,
+};
+use update::logic::{
+    blocks_update, parameters_between_layers, parameters_update, sampling_points_update,
+    BlockInputs, ParameterInputs, ParameterStacks, UpdateControls,
+};
+use update::metrics::{update_metrics, CheapMetrics, EnabledMetrics};
+use update::metrics_worker::{MetricsJob, MetricsWorker};
 
+mod actions;
+mod algorithm_field;
+mod arch;
+mod block_budget;
+mod block_cache;
+mod calculator;
 mod colors;
 mod control;
-mod data_structures;
+pub mod data_structures;
+mod error;
+mod export;
+mod export_script;
+mod find_replace;
+mod fit_to_points;
 mod formatting;
-mod generation;
+pub mod generation;
+mod image_export;
+mod io;
+mod layer_playback;
+mod layout;
+mod lean;
+mod log;
 mod lua_field;
-mod math;
-mod metrics;
+mod materials;
+pub mod math;
+pub mod metrics;
+mod new_layers;
+mod parameter_history;
+mod pixel_font;
 mod plotting;
-mod sampling;
+mod reference_image;
+pub mod sampling;
+mod scaling;
+mod scripting;
+mod stamp;
+mod sub_blocks;
+mod supports;
+mod svg_export;
+mod templates;
+mod tutorial;
 mod ui;
 mod update;
+mod variations;
 mod view;
 
 pub struct App {
@@ -47,20 +158,17 @@ pub struct App {
     stack_layer_parameters: ZVec<LayerParameters>, // Store the sampled parameters for each layer, handily indexed by integers
     stack_blocks: ZVec<Blocks>,                    // Store the blocks for each layer
 
+    frame_error: Option<VoxircleError>, // of the last frame, shown once
     recompute_metrics: bool, // If the current layer has changed, recompute the metrics. By update order, this needs to be a global variable
     // longterm: there is no need for recompute_metrics_control right now... though it might be good if recomputing metrics gets slow later on
 
     // Metrics
-    nr_blocks_total: u64,
-    nr_blocks_interior: u64,
-    nr_blocks_boundary: u64,
-    boundary_2d: Blocks,
-    interior_2d: Blocks,
-    complement_2d: Blocks,
+    metrics: CheapMetrics,
     boundary_3d: ZVec<Blocks>,
     interior_3d: ZVec<Blocks>,
     convex_hull: Vec<[f64; 2]>,
-    outer_corners: Vec<[f64; 2]>,
+    boundary_tour: BoundaryTour,
+    boundary_segments: BoundarySegments,
 
     // Generate new shape on this layer automatically from the provided parameters
     blocks_current_layer_control: Control,
@@ -68,8 +176,23 @@ pub struct App {
     blocks_all_layers_control: Control,
 
     single_radius: bool,
+    parameter_constraints: Vec<ParameterConstraint>, // lock parameters to others
+    locked_parameters: Vec<Parameter>, // kept as they are by the variations and the block budget
+    nudge_step: f64,                   // change of a parameter by its - and + buttons
+    center_snap: Option<Lattice>,      // the center offset sliders stay on the lattice
     layers_enabled: bool,
     lock_stack_size: bool,
+    new_layer_fill: NewLayerFill, // parameters of the layers that are added when the stack grows
+    new_layer_default: SliceParameters,
+    layer_bookmarks: Vec<LayerBookmark>, // named layers to jump to, sorted by layer
+    new_bookmark_name: String,
+    stamps: Vec<Stamp>, // text on the layers that overrides their generated blocks
+    stamp_tool: StampTool,
+    layer_groups: Vec<LayerGroup>, // floors of layers that share their parameters, sorted by layer
+    new_group_name: String,
+    new_group_layers: [isize; 2],
+    mirror_repeat_top_layer: bool, // when mirroring the stack, repeat the top layer (for even heights)
+    layer_playback: LayerPlayback,
 
     // Code mode
     code_enabled: bool,
@@ -82,16 +205,53 @@ pub struct App {
     only_sample_half_of_top_layer: bool,
     nr_samples_per_layer: usize,
     sample_combine_method: SampleCombineMethod,
+    fixed_grid_size: Option<usize>, // same grid for all layers, so that they line up cell for cell
+    generator: Generator,           // the height of the cells and the rule of the Custom algorithm
+    block_cache: BlockCache,
     sample_distribute_method: SampleDistributeMethod,
+    sampling_presets: Vec<SamplingPreset>,
+    new_sampling_preset_name: String, // name for saving the current options as a preset
+    percentage_suggestion: Option<PercentageSuggestion>, // smoothest Percentage combination
     stack_sampling_points: ZVec<Vec<f64>>,
+    stack_locked: ZVec<bool>, // Locked layers are not edited or regenerated
     sampling_points_control: Control,
 
     // Viewport options
     view: View,
+    holes: Holes,
+    thin_necks: Vec<[f64; 2]>, // corners where blocks only touch diagonally
+    sub_blocks: Vec<([f64; 2], SubBlock)>, // slabs and stairs of the current layer, while shown
     symmetry_type: SymmetryType,
-    block_center_coord: [f64; 2],
+    enabled_metrics: EnabledMetrics, // the expensive metrics that were computed last time
+    metrics_worker: MetricsWorker,
+    distance_bands: DistanceBands,
 
-    global_bounding_box: [[f64; 2]; 2], // Is for viewport zoom. Update with metrics
+    // Side view (plane through the stack)
+    vertical_slice_axis: SliceAxis,
+    vertical_slice_coord: isize,
+
+    random_seed: u64, // all randomness is derived from this (see `RandomStream`)
+    applied_world_anchor_shift: [f64; 2], // see `View::world_anchor_shift`
+    variation_gallery: VariationGallery,
+    find_replace: FindReplace,
+    fit_to_points: FitToPoints,
+    reference_image: Option<ReferenceImage>, // shown under the blocks to trace shapes over
+    shape_library: ShapeLibrary,             // shapes from scripts, for the Custom algorithm
+    lean: Lean,
+    parameter_history: ParameterHistory,
+    command_palette: CommandPalette,
+    export_script: ExportScript,
+    calculator: Calculator,
+    mcfunction_export: McfunctionExport,
+    layout: StructureLayout,
+    templates: Vec<Template>,
+    arch_tool: ArchTool,
+    block_budget: BlockBudget,
+    scale_tool: ScaleTool,
+    tutorial: Tutorial,
+    io: IoService,
+
+    repeated_layers: Vec<LayerRun>, // runs of identical consecutive layers
 
     // Zoom options (used for double click to reset zoom)
     reset_zoom_once: bool,
@@ -102,6 +262,8 @@ pub struct App {
     // Longterm: for easily adding more shapes with potentially variable inputs, make this attached to the algorithm?
     // longterm: Option to run an external lua file
     // longterm: sliders for "Dummy variables" that can be referenced in code (for easier visual tweaking)
+    algorithm_field: AlgorithmField,
+    custom_predicate_field: CustomPredicateField,
     lua_field_radius_a: LuaField,
     lua_field_radius_b: LuaField,
     lua_field_tilt: LuaField,
@@ -119,12 +281,71 @@ impl App {
             style.spacing.combo_width = 200.0;
         });
 
+        // Start the tutorial on the first run
+        let tutorial_finished = cc
+            .storage
+            .and_then(|storage| storage.get_string(TUTORIAL_FINISHED_KEY))
+            .is_some_and(|finished| finished == "true");
+        let zoom_to_layer = cc
+            .storage
+            .and_then(|storage| storage.get_string(ZOOM_TO_LAYER_KEY))
+            .is_some_and(|zoom_to_layer| zoom_to_layer == "true");
+        let random_seed = cc
+            .storage
+            .and_then(|storage| storage.get_string(RANDOM_SEED_KEY))
+            .and_then(|random_seed| random_seed.parse().ok())
+            .unwrap_or(0);
+        // The generated layers of the last run (remembered unless turned off)
+        let block_cache = cc
+            .storage
+            .map(|storage| {
+                let enabled =
+                    storage.get_string(BLOCK_CACHE_ENABLED_KEY).as_deref() != Some("false");
+                let layers = storage.get_string(BLOCK_CACHE_KEY).unwrap_or_default();
+                BlockCache::from_storage_string(enabled, &layers)
+            })
+            .unwrap_or_default();
+        // The reference image of the last run, where it was placed
+        let reference_image = cc.storage.and_then(|storage| {
+            let path = PathBuf::from(storage.get_string(REFERENCE_IMAGE_PATH_KEY)?);
+            let placement = storage
+                .get_string(REFERENCE_IMAGE_PLACEMENT_KEY)
+                .and_then(|text| ImagePlacement::from_storage_string(&text))
+                .unwrap_or_default();
+            let bytes = std::fs::read(&path).ok()?;
+            let name = path.display().to_string();
+            ReferenceImage::new(name, Some(path), &bytes, placement)
+                .map_err(log_warning)
+                .ok()
+        });
+        // The shape scripts of the last run (the shapes start unused)
+        let mut shape_library = ShapeLibrary::default();
+        let mut generator = Generator::default();
+        let script_paths = cc
+            .storage
+            .and_then(|storage| storage.get_string(SHAPE_SCRIPT_PATHS_KEY))
+            .unwrap_or_default();
+        for path in script_paths.lines().map(PathBuf::from) {
+            if let Err(error) = std::fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|code| {
+                    shape_library.open_script(
+                        Some(path.clone()),
+                        &code,
+                        &mut generator.custom_predicate,
+                    )
+                })
+            {
+                log_warning(format!("Couldn't open {}: {}", path.display(), error));
+            }
+        }
+
         // persist lua between layer switching and frames and so on
         // let lua = Lua::new();
         // // give lua as little information as possible about the configurations... handle that all in rust
         // lua.globals().set("layer", 0).unwrap();
         // lua.globals().set("l", 0).unwrap(); // short layer alias
-        // 
+        //
         // // Make math functions global for easier access (so `sqrt` instead of `math.sqrt`)
         // lua.load(
         //     r#"
@@ -152,26 +373,38 @@ impl App {
             stack_blocks: ZVec::new(VecDeque::from(vec![Blocks::default()]), 0),
 
             // Compute the metrics on the first update
+            frame_error: None,
             recompute_metrics: true,
 
             // Initialize empty metrics
-            nr_blocks_total: Default::default(),
-            nr_blocks_interior: Default::default(),
-            nr_blocks_boundary: Default::default(),
-            boundary_2d: Default::default(),
-            interior_2d: Default::default(),
-            complement_2d: Default::default(),
+            metrics: CheapMetrics::default(),
             boundary_3d: ZVec::new(VecDeque::from(vec![Blocks::default()]), 0),
             interior_3d: ZVec::new(VecDeque::from(vec![Blocks::default()]), 0),
             convex_hull: Default::default(),
-            outer_corners: Default::default(),
+            boundary_tour: Default::default(),
+            boundary_segments: Default::default(),
 
             // Initialize on simplest working mode of operation
             blocks_current_layer_control: Control::AUTO_UPDATE,
             blocks_all_layers_control: Control::FIRST_FRAME_UPDATE,
             single_radius: true,
+            parameter_constraints: vec![],
+            locked_parameters: vec![],
+            nudge_step: 0.1,
+            center_snap: None,
             layers_enabled: false,
             lock_stack_size: false,
+            new_layer_fill: NewLayerFill::CopyNearest,
+            new_layer_default: SliceParameters::default(),
+            layer_bookmarks: vec![],
+            new_bookmark_name: String::new(),
+            stamps: vec![],
+            stamp_tool: Default::default(),
+            layer_groups: vec![],
+            new_group_name: String::new(),
+            new_group_layers: [0, 0],
+            mirror_repeat_top_layer: false,
+            layer_playback: Default::default(),
 
             // Code mode
             code_enabled: false,
@@ -180,26 +413,69 @@ impl App {
 
             // Sampling
             sampling_enabled: false,
-            only_sample_half_of_bottom_layer: false, // todo: think about defaults
+            // Sample whole layers, the shape usually continues above and below the stack (presets
+            //  like the dome turn these on)
+            only_sample_half_of_bottom_layer: false,
             only_sample_half_of_top_layer: false,
             nr_samples_per_layer: 1,
             sample_combine_method: SampleCombineMethod::AnySamples,
+            fixed_grid_size: None,
+            generator,
+            block_cache,
             sample_distribute_method: SampleDistributeMethod::IncludeEndpoints,
+            sampling_presets: SamplingPreset::built_in(),
+            new_sampling_preset_name: String::new(),
+            percentage_suggestion: None,
             stack_sampling_points: ZVec::new(VecDeque::from([vec![0.0]]), 0), // start with middle sample
+            stack_locked: ZVec::new(VecDeque::from([false]), 0),
             sampling_points_control: Control::AUTO_UPDATE,
 
             // Simplest working configuration
-            view: Default::default(),
+            view: View {
+                zoom_to_layer,
+                ..Default::default()
+            },
+            holes: Holes::default(),
+            thin_necks: vec![],
+            sub_blocks: vec![],
             symmetry_type: SymmetryType::NoSymmetry,
-            block_center_coord: [0.0; 2],
+            enabled_metrics: EnabledMetrics::default(),
+            metrics_worker: MetricsWorker::new(cc.egui_ctx.clone()),
+            distance_bands: DistanceBands::default(),
 
-            global_bounding_box: [[0.0; 2]; 2],
+            vertical_slice_axis: Default::default(),
+            vertical_slice_coord: 0,
+
+            random_seed,
+            applied_world_anchor_shift: [0.0, 0.0],
+            variation_gallery: Default::default(),
+            find_replace: Default::default(),
+            fit_to_points: Default::default(),
+            reference_image,
+            shape_library,
+            lean: Default::default(),
+            parameter_history: Default::default(),
+            command_palette: Default::default(),
+            export_script: Default::default(),
+            calculator: Default::default(),
+            mcfunction_export: Default::default(),
+            layout: Default::default(),
+            templates: Template::built_in(),
+            arch_tool: Default::default(),
+            block_budget: Default::default(),
+            scale_tool: Default::default(),
+            tutorial: Tutorial::new(tutorial_finished),
+            io: IoService::new(cc.egui_ctx.clone()),
+
+            repeated_layers: vec![],
 
             // Start with continuously updating zoom
             reset_zoom_once: false,
             reset_zoom_continuous: true,
 
             // Standard initializations, finite or nonnegative as necessary and sensible for the data type
+            algorithm_field: Default::default(),
+            custom_predicate_field: Default::default(),
             lua_field_radius_a: LuaField::new(true, true),
             lua_field_radius_b: LuaField::new(true, true),
             lua_field_tilt: LuaField::new(true, false),
@@ -210,150 +486,286 @@ impl App {
     }
 }
 
-impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Side panel
-        egui::SidePanel::right("options-panel").show(ctx, |ui| {
-            egui::ScrollArea::vertical().show(ui, |ui| {
-                let id = ui.make_persistent_id("parameters_collapsable");
-                egui::collapsing_header::CollapsingState::load_with_default_open(
-                    ui.ctx(),
-                    id,
-                    true,
-                )
-                .show_header(ui, |ui| {
-                    ui.label(egui::RichText::new("Parameters").strong().size(15.0));
-                })
-                .body(|ui| {
-                    ui_options(
-                        ui,
-                        self.stack_configuration_parameters
-                            .get_mut(self.current_layer)
-                            .unwrap(),
-                        &mut self.single_radius,
-                        self.code_enabled,
-                        &mut self.lua_field_radius_a,
-                        &mut self.lua_field_radius_b,
-                        &mut self.lua_field_tilt,
-                        &mut self.lua_field_center_offset_x,
-                        &mut self.lua_field_center_offset_y,
-                        &mut self.lua_field_squircle_parameter,
-                        &self.stack_sampling_points,
-                        &mut self.parameters_current_layer_control,
-                        &mut self.parameters_all_layers_control,
-                    );
-                });
+impl App {
+    /// The parameters of a single layer, which can't be edited if the layer is locked. Output:
+    ///  whether the parameters need to be sampled again.
+    fn ui_layer_options(
+        &mut self,
+        ui: &mut egui::Ui,
+        layer: isize,
+        show_code: bool,
+    ) -> Result<bool, VoxircleError> {
+        // Locked layers can't be edited (or regenerated)
+        if self.layers_enabled {
+            ui.checkbox(self.stack_locked.try_get_mut(layer)?, "Lock layer");
+        }
+        let slice_parameters = self.stack_configuration_parameters.try_get_mut(layer)?;
+        let locked = self.stack_locked.get(layer).unwrap_or(false);
 
-                let id = ui.make_persistent_id("layers_collapsable");
-                egui::collapsing_header::CollapsingState::load_with_default_open(
-                    ui.ctx(),
-                    id,
-                    false,
+        let changed = ui
+            .add_enabled_ui(!locked, |ui| {
+                ui_options(
+                    ui,
+                    slice_parameters,
+                    ParameterControls {
+                        single_radius: &mut self.single_radius,
+                        parameter_constraints: &mut self.parameter_constraints,
+                        locked_parameters: &mut self.locked_parameters,
+                        nudge_step: &mut self.nudge_step,
+                        center_snap: &mut self.center_snap,
+                    },
+                    self.code_enabled && show_code,
+                    OptionsCode {
+                        algorithm_field: &mut self.algorithm_field,
+                        custom_predicate_field: &mut self.custom_predicate_field,
+                        shape_library: &mut self.shape_library,
+                        generator: &mut self.generator,
+                        io: &mut self.io,
+                    },
+                    &mut self.lua_field_radius_a,
+                    &mut self.lua_field_radius_b,
+                    &mut self.lua_field_tilt,
+                    &mut self.lua_field_center_offset_x,
+                    &mut self.lua_field_center_offset_y,
+                    &mut self.lua_field_squircle_parameter,
+                    &self.stack_sampling_points,
                 )
-                .show_header(ui, |ui| {
-                    ui.checkbox(
-                        &mut self.layers_enabled,
-                        egui::RichText::new("Layers").strong().size(15.0),
-                    );
-                })
-                .body(|ui| {
-                    ui.add_enabled(
-                        self.layers_enabled,
-                        egui::Checkbox::new(&mut self.lock_stack_size, "Lock stack size"),
-                    );
-                });
+            })
+            .inner;
 
-                let id = ui.make_persistent_id("code_collapsable");
-                egui::collapsing_header::CollapsingState::load_with_default_open(
-                    ui.ctx(),
-                    id,
-                    false,
-                )
-                .show_header(ui, |ui| {
-                    ui.checkbox(
-                        &mut self.code_enabled,
-                        egui::RichText::new("Code").strong().size(15.0),
-                    );
-                })
-                .body(|ui| {
-                    ui.add_enabled(self.layers_enabled, egui::Label::new("No options here yet"));
-                });
+        // Problems of the parameters the blocks are generated from (with code, those of the
+        //  samples)
+        if let Some((is_error, text)) = self
+            .stack_layer_parameters
+            .get(layer)
+            .and_then(|layer_parameters| layer_parameters.problem())
+        {
+            let color = if is_error {
+                ui.visuals().error_fg_color
+            } else {
+                ui.visuals().warn_fg_color
+            };
+            ui.colored_label(color, format!("⚠ {}", text));
+        }
+        Ok(changed)
+    }
 
-                let id = ui.make_persistent_id("sampling_collapsable");
-                egui::collapsing_header::CollapsingState::load_with_default_open(
-                    ui.ctx(),
-                    id,
-                    false,
-                )
-                .show_header(ui, |ui| {
-                    if ui
-                        .checkbox(
-                            &mut self.sampling_enabled,
-                            egui::RichText::new("Sampling").strong().size(15.0),
-                        )
-                        .changed()
-                        & !self.sampling_enabled
-                    {
-                        self.nr_samples_per_layer = 1; // set number of samples to 1 if sampling is off
-                    };
-                })
-                .body(|ui| {
-                    ui_sampling(
-                        ui,
-                        self.sampling_enabled,
-                        &mut self.only_sample_half_of_bottom_layer,
-                        &mut self.only_sample_half_of_top_layer,
-                        &mut self.nr_samples_per_layer,
-                        &mut self.sample_combine_method,
-                        &mut self.sample_distribute_method,
-                        &mut self.sampling_points_control,
-                    );
+    /// Open a file dropped on the window or given on the command line (which is how the system
+    ///  opens files with Voxircle). The block coordinates in it are loaded into "Fit shape to
+    ///  blocks", and a shape is fitted to them.
+    ///  Images are shown under the blocks instead (see "Reference image"), and the shapes of
+    ///  scripts are added to the shape types.
+    pub fn open_file(&mut self, file: &egui::DroppedFile) {
+        if is_image_file(&dropped_file_name(file)) {
+            self.open_reference_image(file);
+            return;
+        }
+        if is_script_file(&dropped_file_name(file)) {
+            self.open_shape_script(file);
+            return;
+        }
 
-                    ui.label(format!(
-                        "Total number of samples for all layers: {}",
-                        self.stack_sampling_points.data.iter().fold(
-                            0,
-                            |acc, samples_for_single_layer| {
-                                acc + samples_for_single_layer.len()
-                            }
-                        )
-                    ));
-                });
+        match read_dropped_file(file) {
+            Ok((name, text)) => {
+                self.fit_to_points.text = text;
+                self.fit_to_points
+                    .fit(self.view.world_translation(), self.view.flip_z);
+                self.view.fit_to_points = true;
+                self.io.notify(format!("Opened {}", name), false);
+            }
+            Err(text) => self.io.notify(text, true),
+        }
+    }
 
-                let id = ui.make_persistent_id("viewport_options_collapsable");
-                egui::collapsing_header::CollapsingState::load_with_default_open(
-                    ui.ctx(),
-                    id,
-                    true,
+    /// Show the image under the blocks, where the previous image was (if there was one)
+    fn open_reference_image(&mut self, file: &egui::DroppedFile) {
+        let placement = self
+            .reference_image
+            .as_ref()
+            .map(|image| image.placement)
+            .unwrap_or_default();
+        match read_dropped_bytes(file).and_then(|(name, bytes)| {
+            ReferenceImage::new(name, file.path.clone(), &bytes, placement)
+        }) {
+            Ok(image) => {
+                self.io.notify(format!("Opened {}", image.name), false);
+                self.reference_image = Some(image);
+                self.view.reference_image = true;
+            }
+            Err(text) => self.io.notify(text, true),
+        }
+    }
+
+    /// Add the shapes of the script to the shape types
+    fn open_shape_script(&mut self, file: &egui::DroppedFile) {
+        match read_dropped_file(file).and_then(|(name, code)| {
+            self.shape_library
+                .open_script(
+                    file.path.clone(),
+                    &code,
+                    &mut self.generator.custom_predicate,
                 )
-                .show_header(ui, |ui| {
-                    ui.label(egui::RichText::new("Viewport").strong().size(15.0));
-                })
-                .body(|ui| {
-                    ui_viewport_options(
-                        ui,
-                        self.layers_enabled,
-                        self.single_radius,
-                        &self.symmetry_type,
-                        &mut self.view,
-                    )
-                });
+                .map_err(|error| format!("Couldn't open {}: {}", name, error))
+                .map(|nr_shapes| (name, nr_shapes))
+        }) {
+            Ok((name, nr_shapes)) => self.io.notify(
+                format!("Opened {} with {} shape(s)", name, nr_shapes),
+                false,
+            ),
+            Err(text) => self.io.notify(text, true),
+        }
+    }
 
-                ui.separator();
+    /// Replace the whole stack by other layers with their blocks. What belonged to the old layers
+    ///  (locks, groups, bookmarks, stamps, constraints and the lean) is dropped, and the code is
+    ///  cleared, so that the parameters of the layers are kept as they are when they are sampled
+    ///  again. The current layer is the lowest layer.
+    fn replace_stack(
+        &mut self,
+        stack_layer_config: ZVec<SliceParameters>,
+        stack_blocks: ZVec<Blocks>,
+    ) {
+        self.layer_lowest = stack_layer_config.get_minimum();
+        self.layer_highest = stack_layer_config.get_maximum();
+        self.current_layer = self.layer_lowest;
+        self.stack_configuration_parameters = stack_layer_config;
+        self.stack_blocks = stack_blocks;
+        self.layers_enabled = true;
+        self.lock_stack_size = false;
+        self.single_radius = false;
 
-                ui_generation(
-                    ui,
-                    &mut self.blocks_current_layer_control,
-                    &mut self.blocks_all_layers_control,
-                    &mut self.parameters_current_layer_control,
-                    &mut self.parameters_all_layers_control,
+        let nr_layers = self.stack_configuration_parameters.data.len();
+        self.stack_layer_parameters = ZVec::new(
+            VecDeque::from(vec![LayerParameters::default(); nr_layers]),
+            self.layer_lowest,
+        );
+        self.stack_locked = ZVec::new(VecDeque::from(vec![false; nr_layers]), self.layer_lowest);
+        self.layer_groups = vec![];
+        self.layer_bookmarks = vec![];
+        self.parameter_constraints = vec![];
+        self.stamps = vec![];
+        self.lean = Lean::default();
+
+        // The samples are at the heights of the new layers
+        self.recompute_sampling_points();
+        for lua_field in [
+            &mut self.lua_field_radius_a,
+            &mut self.lua_field_radius_b,
+            &mut self.lua_field_tilt,
+            &mut self.lua_field_center_offset_x,
+            &mut self.lua_field_center_offset_y,
+            &mut self.lua_field_squircle_parameter,
+        ] {
+            lua_field.set_code("", &self.stack_sampling_points);
+        }
+        self.algorithm_field = AlgorithmField::default();
+        self.recompute_metrics = true;
+    }
+
+    /// Replace the stack by the layers of the arch, with the algorithm of the current layer. The
+    ///  blocks are generated right away (with a single sample per layer), like for a template.
+    fn generate_arch(&mut self) {
+        let base = self
+            .stack_configuration_parameters
+            .get(self.current_layer)
+            .unwrap_or_default();
+        let stack_layer_config = self.arch_tool.stack_layer_config(&base);
+        let stack_blocks = ZVec::new(
+            stack_layer_config
+                .data
+                .iter()
+                .map(|slice_parameters| LayerParameters::single(slice_parameters, &self.generator))
+                .collect(),
+            stack_layer_config.get_minimum(),
+        );
+        let nr_layers = stack_blocks.data.len();
+        self.replace_stack(stack_layer_config, stack_blocks);
+
+        self.io
+            .notify(format!("New arch of {} layers", nr_layers), false);
+    }
+
+    /// Do what was chosen in the command palette or with a keyboard shortcut
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::GenerateCurrentLayer => self.blocks_current_layer_control.once(),
+            Action::GenerateAllLayers => self.blocks_all_layers_control.once(),
+            Action::SampleCurrentLayer => self.parameters_current_layer_control.once(),
+            Action::SampleAllLayers => self.parameters_all_layers_control.once(),
+            Action::ResetZoom => self.reset_zoom_once = true,
+            Action::PreviousLayer => self.go_to_layer(self.current_layer - 1),
+            Action::NextLayer => self.go_to_layer(self.current_layer + 1),
+            Action::LowestLayer => self.go_to_layer(self.layer_lowest),
+            Action::HighestLayer => self.go_to_layer(self.layer_highest),
+            Action::JumpToLayer(layer) => self.go_to_layer(layer),
+            Action::SetGroundLayer => self.set_ground_layer(),
+            Action::PlayLayers => self.layer_playback.toggle(),
+            Action::MoveLayerUp => self.move_current_layer(true),
+            Action::MoveLayerDown => self.move_current_layer(false),
+            Action::ReverseStack => self.reverse_stack(),
+            Action::Toggle(toggle) => {
+                let shown = (toggle.shown_mut)(&mut self.view);
+                *shown = !*shown;
+            }
+            Action::ToggleCleanView => self.view.set_clean_view(!self.view.clean_view),
+            Action::ExportBuildOrderCsv | Action::ExportBuildOrderJson => {
+                self.io.save(build_order_save_request(
+                    &self.stack_blocks,
+                    self.current_layer,
                     self.layers_enabled,
-                    self.code_enabled,
-                    self.sampling_enabled,
-                );
-            });
-        });
+                    &self.view,
+                    matches!(action, Action::ExportBuildOrderJson),
+                ));
+            }
+            Action::ExportMetricsJson => {
+                self.io.save(metrics_save_request(
+                    &self.stack_blocks,
+                    self.current_layer,
+                    self.layers_enabled,
+                    &self.view,
+                    ComputedMetrics::new(
+                        &self.enabled_metrics,
+                        self.metrics_worker.is_pending,
+                        &self.symmetry_type,
+                        &self.convex_hull,
+                        &self.boundary_segments,
+                    ),
+                ));
+            }
+            Action::ShowTutorial => self.tutorial.start(),
+            Action::ToggleViewerMode => self.set_viewer_mode(!self.view.viewer_mode),
+        }
+    }
+
+    /// In viewer mode only the viewport, the layer navigation and the metrics are shown, for a
+    ///  co-builder who follows the plan without changing it
+    pub fn set_viewer_mode(&mut self, viewer_mode: bool) {
+        self.view.viewer_mode = viewer_mode;
+        if viewer_mode {
+            close_editing_windows(&mut self.view);
+        }
+    }
+
+    /// Are there updates that will happen on the next frame (changes after the update step of this
+    ///  frame)?
+    fn has_pending_updates(&self) -> bool {
+        self.recompute_metrics
+            || EnabledMetrics::from_view(&self.view, self.layers_enabled)
+                .newly_enabled(&self.enabled_metrics)
+            || [
+                &self.sampling_points_control,
+                &self.parameters_current_layer_control,
+                &self.parameters_all_layers_control,
+                &self.blocks_current_layer_control,
+                &self.blocks_all_layers_control,
+            ]
+            .iter()
+            .any(|control| control.is_pending())
+    }
 
+    /// Update what is outdated, in order: the sampling points, the parameters, the blocks and the
+    ///  metrics
+    fn update_pipeline(&mut self) -> Result<(), VoxircleError> {
         sampling_points_update(
             self.only_sample_half_of_bottom_layer,
             self.only_sample_half_of_top_layer,
@@ -367,63 +779,1007 @@ impl eframe::App for App {
             self.layer_highest,
         );
 
+        let mut controls = UpdateControls {
+            parameters_current_layer: &mut self.parameters_current_layer_control,
+            parameters_all_layers: &mut self.parameters_all_layers_control,
+            blocks_current_layer: &mut self.blocks_current_layer_control,
+            blocks_all_layers: &mut self.blocks_all_layers_control,
+        };
+
         parameters_update(
-            &mut self.stack_configuration_parameters,
-            &mut self.stack_layer_parameters,
-            &self.stack_sampling_points,
-            &mut self.parameters_current_layer_control,
-            &mut self.parameters_all_layers_control,
-            &mut self.blocks_current_layer_control,
-            &mut self.blocks_all_layers_control,
+            ParameterStacks {
+                layer_config: &mut self.stack_configuration_parameters,
+                sampled_parameters: &mut self.stack_layer_parameters,
+                sampling_points: &self.stack_sampling_points,
+                locked: &self.stack_locked,
+                layer_groups: &mut self.layer_groups,
+            },
+            &mut controls,
             self.current_layer,
             self.layer_lowest,
             self.layer_highest,
-            self.single_radius,
-            &mut self.lua_field_radius_a,
-            &mut self.lua_field_radius_b,
-            &mut self.lua_field_tilt,
-            &mut self.lua_field_center_offset_x,
-            &mut self.lua_field_center_offset_y,
-            &mut self.lua_field_squircle_parameter,
-        );
+            &mut ParameterInputs {
+                algorithm_field: &mut self.algorithm_field,
+                lua_fields: [
+                    &mut self.lua_field_radius_a,
+                    &mut self.lua_field_radius_b,
+                    &mut self.lua_field_tilt,
+                    &mut self.lua_field_center_offset_x,
+                    &mut self.lua_field_center_offset_y,
+                    &mut self.lua_field_squircle_parameter,
+                ],
+                single_radius: self.single_radius,
+                parameter_constraints: &self.parameter_constraints,
+                lean: &self.lean,
+            },
+        )?;
 
-        blocks_update(
+        if blocks_update(
             &self.stack_layer_parameters,
             &mut self.stack_blocks,
-            &mut self.blocks_current_layer_control,
-            &mut self.blocks_all_layers_control,
-            &mut self.recompute_metrics,
+            &self.stack_locked,
+            &mut controls,
             self.current_layer,
             self.layer_lowest,
-            &self.sample_combine_method,
-        );
+            &mut BlockInputs {
+                sample_combine_method: &self.sample_combine_method,
+                fixed_grid_size: self.fixed_grid_size,
+                generator: &self.generator,
+                stamps: &self.stamps,
+                block_cache: &mut self.block_cache,
+            },
+        )? {
+            self.recompute_metrics = true;
+        }
+
+        // Turning on a metric that is not up to date also needs the metrics to be recomputed
+        let enabled_metrics = EnabledMetrics::from_view(&self.view, self.layers_enabled);
+        if enabled_metrics.newly_enabled(&self.enabled_metrics) {
+            self.recompute_metrics = true;
+        }
+        self.enabled_metrics = enabled_metrics;
 
         if self.recompute_metrics {
             self.recompute_metrics = false;
             update_metrics(
+                self.current_layer,
+                self.stack_blocks.try_get(self.current_layer)?,
+                &self.stack_blocks,
+                // The bounding boxes and the center are those of the leaning shapes
+                &self
+                    .lean
+                    .apply_to_stack(&self.stack_configuration_parameters),
+                self.generator.cell_aspect,
+                &self.enabled_metrics,
+                &mut self.metrics,
+            )?;
+
+            // The expensive metrics are computed in the background, until then the old ones are
+            //  shown
+            self.metrics_worker.submit(MetricsJob {
+                enabled_metrics: self.enabled_metrics,
+                layer_lowest: self.layer_lowest,
+                layer_highest: self.layer_highest,
+                current_layer_blocks: self.stack_blocks.try_get(self.current_layer)?,
+                stack_blocks: self.stack_blocks.clone(),
+                boundary_2d: self.metrics.boundary_2d.clone(),
+                outer_corners: self.metrics.outer_corners.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Log the error and show it (once, not every frame while it keeps happening)
+    fn report_frame_error(&mut self, error: VoxircleError) {
+        if self.frame_error.as_ref() != Some(&error) {
+            log_warning(format!("Update skipped: {}", error));
+            self.io.notify(format!("Update skipped: {}", error), true);
+        }
+        self.frame_error = Some(error);
+    }
+
+    /// Make the layer current, if it is in the stack
+    fn go_to_layer(&mut self, layer: isize) {
+        self.current_layer = layer.clamp(self.layer_lowest, self.layer_highest);
+        self.recompute_metrics = true;
+    }
+
+    /// Number the layers from the current layer, which becomes layer 0 (e.g. the ground level). The
+    ///  layers keep their parameters, blocks, bookmarks, stamps and groups, only their numbers change.
+    ///  Code for the parameters is evaluated at the new layer numbers.
+    fn set_ground_layer(&mut self) {
+        let offset = -self.current_layer;
+        if offset == 0 {
+            return;
+        }
+
+        self.stack_configuration_parameters.shift(offset);
+        self.stack_layer_parameters.shift(offset);
+        self.stack_blocks.shift(offset);
+        self.stack_locked.shift(offset);
+        self.boundary_3d.shift(offset);
+        self.interior_3d.shift(offset);
+        self.holes.holes_3d.shift(offset);
+        self.layer_lowest += offset;
+        self.layer_highest += offset;
+        self.current_layer = 0;
+
+        for bookmark in self.layer_bookmarks.iter_mut() {
+            bookmark.layer += offset;
+        }
+        for stamp in self.stamps.iter_mut() {
+            stamp.layer += offset;
+        }
+        for group in self.layer_groups.iter_mut() {
+            group.shift(offset);
+        }
+        self.new_group_layers = self.new_group_layers.map(|layer| layer + offset);
+        self.find_replace.layer_range = self.find_replace.layer_range.map(|layer| layer + offset);
+        for entry in self.parameter_history.entries.iter_mut() {
+            entry.layer += offset;
+        }
+        self.view.pinned_layer += offset;
+        self.view.split_layer += offset;
+        self.view.between_layers_height += offset as f64;
+
+        // The samples are at the new heights
+        self.recompute_sampling_points();
+        self.recompute_metrics = true;
+    }
+
+    /// Recompute the sampling points for the layers of the stack right away (whether "Auto
+    ///  recompute sampling points" is on or not), after layers were added, removed or renumbered.
+    ///  The parameters are sampled again with them.
+    fn recompute_sampling_points(&mut self) {
+        self.stack_sampling_points = determine_sampling_points(
+            self.sample_distribute_method,
+            self.layer_lowest,
+            self.layer_highest,
+            self.nr_samples_per_layer,
+            self.only_sample_half_of_bottom_layer,
+            self.only_sample_half_of_top_layer,
+        );
+        self.parameters_current_layer_control.set_outdated();
+        self.parameters_all_layers_control.set_outdated();
+    }
+
+    /// Move every layer to `new_layer(layer)`, a permutation of the stack: the parameters, blocks,
+    ///  locks, bookmarks, stamps and history go with the layer, the floors stay at their layers. The
+    ///  current layer follows its layer. Code for the parameters is evaluated at the new layers.
+    fn reorder_layers(&mut self, new_layer: impl Fn(isize) -> isize) {
+        self.stack_configuration_parameters.permute(&new_layer);
+        self.stack_layer_parameters.permute(&new_layer);
+        self.stack_blocks.permute(&new_layer);
+        self.stack_locked.permute(&new_layer);
+        self.current_layer = new_layer(self.current_layer);
+
+        for bookmark in self.layer_bookmarks.iter_mut() {
+            if (self.layer_lowest..=self.layer_highest).contains(&bookmark.layer) {
+                bookmark.layer = new_layer(bookmark.layer);
+            }
+        }
+        self.layer_bookmarks.sort_by_key(|bookmark| bookmark.layer);
+        for stamp in self.stamps.iter_mut() {
+            if (self.layer_lowest..=self.layer_highest).contains(&stamp.layer) {
+                stamp.layer = new_layer(stamp.layer);
+            }
+        }
+        for entry in self.parameter_history.entries.iter_mut() {
+            if (self.layer_lowest..=self.layer_highest).contains(&entry.layer) {
+                entry.layer = new_layer(entry.layer);
+            }
+        }
+
+        // The samples stay at their heights, the parameters there are those of the moved layers
+        self.parameters_current_layer_control.set_outdated();
+        self.parameters_all_layers_control.set_outdated();
+        self.recompute_metrics = true;
+    }
+
+    /// Swap the current layer with the layer above it (`up`) or below it
+    fn move_current_layer(&mut self, up: bool) {
+        let current = self.current_layer;
+        let other = if up { current + 1 } else { current - 1 };
+        if !(self.layer_lowest..=self.layer_highest).contains(&other) {
+            return;
+        }
+        self.reorder_layers(|layer| {
+            if layer == current {
+                other
+            } else if layer == other {
+                current
+            } else {
+                layer
+            }
+        });
+    }
+
+    /// Turn the stack upside down (for a design that was built from the top)
+    fn reverse_stack(&mut self) {
+        let (lowest, highest) = (self.layer_lowest, self.layer_highest);
+        self.reorder_layers(|layer| lowest + highest - layer);
+    }
+
+    /// Add the layers in reverse order on top of the stack (see `ZVec::mirror_upwards`). The
+    ///  bookmarks, stamps and groups of the layers are copied to the mirrored layers, a group with
+    ///  the top layer grows over its mirror image if the top layer is not repeated.
+    fn mirror_stack_upwards(&mut self) {
+        let repeat = self.mirror_repeat_top_layer;
+        let (lowest, highest) = (self.layer_lowest, self.layer_highest);
+        let mirror = |layer: isize| 2 * highest + repeat as isize - layer;
+        let is_copied =
+            |layer: isize| (lowest..=highest).contains(&layer) && mirror(layer) != layer;
+
+        self.stack_configuration_parameters.mirror_upwards(repeat);
+        self.stack_layer_parameters.mirror_upwards(repeat);
+        self.stack_blocks.mirror_upwards(repeat);
+        self.stack_locked.mirror_upwards(repeat);
+        self.layer_highest = self.stack_blocks.get_maximum();
+
+        let bookmarks: Vec<LayerBookmark> = self
+            .layer_bookmarks
+            .iter()
+            .filter(|bookmark| is_copied(bookmark.layer))
+            .map(|bookmark| LayerBookmark {
+                layer: mirror(bookmark.layer),
+                ..bookmark.clone()
+            })
+            .collect();
+        self.layer_bookmarks.extend(bookmarks);
+        self.layer_bookmarks.sort_by_key(|bookmark| bookmark.layer);
+
+        let stamps: Vec<Stamp> = self
+            .stamps
+            .iter()
+            .filter(|stamp| is_copied(stamp.layer))
+            .map(|stamp| Stamp {
+                layer: mirror(stamp.layer),
+                ..stamp.clone()
+            })
+            .collect();
+        self.stamps.extend(stamps);
+
+        let mut groups = vec![];
+        for group in self.layer_groups.iter_mut() {
+            let mirrored = group.mirrored(mirror);
+            if group.contains(mirrored.layers[0]) {
+                group.layers[1] = mirrored.layers[1];
+                group.overrides.extend(mirrored.overrides);
+                group.overrides.sort();
+                group.overrides.dedup();
+            } else {
+                groups.push(mirrored);
+            }
+        }
+        self.layer_groups.extend(groups);
+        self.layer_groups.sort_by_key(|group| group.layers[0]);
+
+        // The sampling points depend on the layer number, so they have to be recomputed for the
+        //  new layers
+        self.recompute_sampling_points();
+    }
+
+    /// Multiply the radii of the unlocked layers among `layers` by `scale` (except the locked radii)
+    fn scale_radii(&mut self, scale: f64, layers: RangeInclusive<isize>) {
+        for layer in layers {
+            if self.stack_locked.get(layer).unwrap_or(true) {
+                continue;
+            }
+            if let Some(slice_parameters) = self.stack_configuration_parameters.get_mut(layer) {
+                *slice_parameters =
+                    scale_slice_parameters(slice_parameters, scale, &self.locked_parameters);
+            }
+        }
+        self.parameters_current_layer_control.set_outdated();
+        self.parameters_all_layers_control.set_outdated();
+    }
+
+    /// The shape at a height in the stack, interpolated between the layers (None if the height is
+    ///  outside the stack)
+    fn parameters_at_height(&self, height: f64) -> Option<SliceParameters> {
+        parameters_between_layers(
+            height,
+            &self.stack_configuration_parameters,
+            [
+                &self.lua_field_radius_a,
+                &self.lua_field_radius_b,
+                &self.lua_field_tilt,
+                &self.lua_field_center_offset_x,
+                &self.lua_field_center_offset_y,
+                &self.lua_field_squircle_parameter,
+            ],
+            self.single_radius,
+            &self.parameter_constraints,
+        )
+        .map(|slice_parameters| self.lean.apply(&slice_parameters))
+    }
+
+    /// Slabs and stairs for the current layer, from the shape a quarter layer below and above it
+    ///  (clamped to the stack, so the lowest and highest layer have flat bottom and top halves)
+    fn update_sub_blocks(&mut self) -> Result<(), VoxircleError> {
+        if !(self.layers_enabled && self.view.sub_blocks) {
+            self.sub_blocks.clear();
+            return Ok(());
+        }
+        let current_layer_config = self
+            .stack_configuration_parameters
+            .try_get(self.current_layer)?;
+        let halves = [-0.25, 0.25].map(|offset| {
+            let height = (self.current_layer as f64 + offset)
+                .clamp(self.layer_lowest as f64, self.layer_highest as f64);
+            self.parameters_at_height(height)
+                .unwrap_or_else(|| current_layer_config.clone())
+        });
+        self.sub_blocks = get_sub_blocks([&halves[0], &halves[1]], &self.generator);
+        Ok(())
+    }
+
+    /// The center of the leaning shape of the current layer in cells, like the blocks
+    fn shape_center(&self) -> Result<[f64; 2], VoxircleError> {
+        let slice_parameters = self.lean.apply(
+            &self
+                .stack_configuration_parameters
+                .try_get(self.current_layer)?,
+        );
+        Ok(to_cells(
+            [
+                slice_parameters.center_offset_x,
+                slice_parameters.center_offset_y,
+            ],
+            self.generator.cell_aspect,
+        ))
+    }
+
+    /// Everything of a frame except the notifications and the tutorial, which are also shown
+    ///  when the rest of the frame is skipped
+    fn update_frame(&mut self, ctx: &egui::Context) -> Result<(), VoxircleError> {
+        // Keyboard shortcuts and the command palette (before the panels, which show the result)
+        if ctx.input_mut(|i| i.consume_shortcut(&OPEN_COMMAND_PALETTE)) {
+            self.command_palette.open = !self.command_palette.open;
+        }
+        for (action, shortcut) in Action::with_shortcuts(self.layers_enabled, self.view.viewer_mode)
+        {
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                self.perform(action);
+            }
+        }
+        if let Some(action) = ui_command_palette(
+            ctx,
+            &mut self.command_palette,
+            self.layers_enabled,
+            self.view.viewer_mode,
+        ) {
+            self.perform(action);
+        }
+
+        // Step through the layers while playing, with a frame for the next step
+        if self.layers_enabled {
+            let time = ctx.input(|i| i.time);
+            if let Some(layer) = self.layer_playback.step(
+                time,
                 self.current_layer,
                 self.layer_lowest,
                 self.layer_highest,
-                self.stack_blocks.get(self.current_layer).unwrap(),
-                &self.stack_blocks,
-                &self.stack_configuration_parameters,
-                &mut self.nr_blocks_total,
-                &mut self.nr_blocks_interior,
-                &mut self.nr_blocks_boundary,
-                &mut self.boundary_2d,
-                &mut self.interior_2d,
-                &mut self.complement_2d,
-                &mut self.boundary_3d,
-                &mut self.interior_3d,
-                &mut self.convex_hull,
-                &mut self.outer_corners,
-                &mut self.symmetry_type,
-                &mut self.block_center_coord,
-                &mut self.global_bounding_box,
-            )
+            ) {
+                self.go_to_layer(layer);
+            }
+            if self.layer_playback.playing {
+                ctx.request_repaint_after(Duration::from_secs_f64(
+                    self.layer_playback.time_to_next_step(time),
+                ));
+            }
+        }
+
+        self.update_sub_blocks()?;
+        let shape_center = self.shape_center()?;
+
+        // Side panel
+        egui::SidePanel::right("options-panel").show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // Viewer mode only shows the viewport options (with the navigation and the metrics)
+                if self.view.viewer_mode {
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new("Viewer mode").strong().size(15.0));
+                        if ui
+                            .button("Edit")
+                            .on_hover_text("Show the editing controls again")
+                            .clicked()
+                        {
+                            self.set_viewer_mode(false);
+                        }
+                    });
+                } else {
+                    let top = ui.cursor().top();
+                    let id = ui.make_persistent_id("parameters_collapsable");
+                    egui::collapsing_header::CollapsingState::load_with_default_open(
+                        ui.ctx(),
+                        id,
+                        true,
+                    )
+                    .show_header(ui, |ui| {
+                        ui.label(egui::RichText::new("Parameters").strong().size(15.0));
+                    })
+                    .body(|ui| {
+                        if self.layers_enabled {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut self.view.split_parameters, "Compare with layer");
+                                ui.add_enabled(
+                                    self.view.split_parameters,
+                                    egui::DragValue::new(&mut self.view.split_layer)
+                                        .speed(0.05)
+                                        .clamp_range(self.layer_lowest..=self.layer_highest),
+                                );
+                            });
+                        }
+
+                        if self.layers_enabled && self.view.split_parameters {
+                            // Two layers side by side, both can be edited. The code is only shown once.
+                            //  The stack may have shrunk since the layer was chosen.
+                            self.view.split_layer =
+                                self.view.split_layer.clamp(self.layer_lowest, self.layer_highest);
+                            let layers = [self.current_layer, self.view.split_layer];
+                            let mut changed = [false; 2];
+                            ui.columns(2, |columns| {
+                                for (index, column) in columns.iter_mut().enumerate() {
+                                    column.push_id(index, |ui| {
+                                        ui.strong(format!("Layer {}", layers[index]));
+                                        let locked =
+                                            self.stack_locked.get(layers[index]).unwrap_or(false);
+                                        if ui
+                                            .add_enabled(
+                                                !locked,
+                                                egui::Button::new(format!(
+                                                    "Copy from layer {}",
+                                                    layers[1 - index]
+                                                )),
+                                            )
+                                            .clicked()
+                                        {
+                                            let other = self
+                                                .stack_configuration_parameters
+                                                .try_get(layers[1 - index])?;
+                                            self.stack_configuration_parameters
+                                                .set(layers[index], other);
+                                            changed[index] = true;
+                                        }
+                                        changed[index] |= self.ui_layer_options(
+                                            ui,
+                                            layers[index],
+                                            index == 0,
+                                        )?;
+                                        Ok(())
+                                    })
+                                    .inner?;
+                                }
+                                Ok(())
+                            })?;
+
+                            // Sampling the current layer first makes the change show up right away
+                            if changed[0] || (changed[1] && layers[1] == self.current_layer) {
+                                self.parameters_current_layer_control.set_outdated();
+                            }
+                            if changed[0] || changed[1] {
+                                self.parameters_all_layers_control.set_outdated();
+                            }
+                        } else if self.ui_layer_options(ui, self.current_layer, true)? {
+                            self.parameters_current_layer_control.set_outdated();
+                            self.parameters_all_layers_control.set_outdated();
+                        }
+                        Ok(())
+                    })
+                    .2
+                    .map(|body| body.inner)
+                    .transpose()?;
+
+                    self.tutorial.mark(TutorialStep::Parameters, ui, top);
+
+                    let top = ui.cursor().top();
+                    let id = ui.make_persistent_id("layers_collapsable");
+                    egui::collapsing_header::CollapsingState::load_with_default_open(
+                        ui.ctx(),
+                        id,
+                        false,
+                    )
+                    .show_header(ui, |ui| {
+                        ui.checkbox(
+                            &mut self.layers_enabled,
+                            egui::RichText::new("Layers").strong().size(15.0),
+                        );
+                    })
+                    .body(|ui| {
+                        ui.add_enabled(
+                            self.layers_enabled,
+                            egui::Checkbox::new(&mut self.lock_stack_size, "Lock stack size"),
+                        );
+                        let current_layer_config = self
+                            .stack_configuration_parameters
+                            .try_get(self.current_layer)?;
+                        ui.add_enabled_ui(self.layers_enabled && !self.lock_stack_size, |ui| {
+                            ui_new_layer_fill(
+                                ui,
+                                &mut self.new_layer_fill,
+                                &mut self.new_layer_default,
+                                &current_layer_config,
+                            );
+                        });
+
+                        if ui
+                            .add_enabled(
+                                self.layers_enabled && self.current_layer != 0,
+                                egui::Button::new("Set current layer as ground (layer 0)"),
+                            )
+                            .on_hover_text(
+                                "Number the layers from the current layer, keeping the stack as it \
+                                is. Code for the parameters is evaluated at the new layer numbers.",
+                            )
+                            .clicked()
+                        {
+                            self.set_ground_layer();
+                        }
+
+                        ui.add_enabled_ui(self.layers_enabled, |ui| {
+                            ui.horizontal(|ui| {
+                                let hover_text = "Parameters, blocks, locks and bookmarks move with \
+                                    the layer, floors stay. Code for the parameters is evaluated at \
+                                    the new layers.";
+                                if ui
+                                    .add_enabled(
+                                        self.current_layer > self.layer_lowest,
+                                        egui::Button::new("Move layer down"),
+                                    )
+                                    .on_hover_text(hover_text)
+                                    .clicked()
+                                {
+                                    self.move_current_layer(false);
+                                }
+                                if ui
+                                    .add_enabled(
+                                        self.current_layer < self.layer_highest,
+                                        egui::Button::new("Move layer up"),
+                                    )
+                                    .on_hover_text(hover_text)
+                                    .clicked()
+                                {
+                                    self.move_current_layer(true);
+                                }
+                                if ui
+                                    .button("Reverse stack")
+                                    .on_hover_text(hover_text)
+                                    .clicked()
+                                {
+                                    self.reverse_stack();
+                                }
+                            });
+                        });
+
+                        // Complete a symmetric object (like a sphere) from its bottom half
+                        ui.add_enabled_ui(self.layers_enabled && !self.lock_stack_size, |ui| {
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .button("Mirror stack upwards")
+                                    .on_hover_text(
+                                        "Add the layers in reverse order on top of the stack. \
+                                        Code for the parameters is evaluated on the new layers as usual.",
+                                    )
+                                    .clicked()
+                                {
+                                    self.mirror_stack_upwards();
+                                }
+                                ui.checkbox(&mut self.mirror_repeat_top_layer, "Repeat top layer");
+                            });
+                        });
+                        Ok(())
+                    })
+                    .2
+                    .map(|body| body.inner)
+                    .transpose()?;
+
+                    self.tutorial.mark(TutorialStep::Layers, ui, top);
+
+                    let top = ui.cursor().top();
+                    let id = ui.make_persistent_id("code_collapsable");
+                    egui::collapsing_header::CollapsingState::load_with_default_open(
+                        ui.ctx(),
+                        id,
+                        false,
+                    )
+                    .show_header(ui, |ui| {
+                        ui.checkbox(
+                            &mut self.code_enabled,
+                            egui::RichText::new("Code").strong().size(15.0),
+                        );
+                    })
+                    .body(|ui| {
+                        ui.add_enabled(self.layers_enabled, egui::Label::new("No options here yet"));
+                    });
+
+                    self.tutorial.mark(TutorialStep::Code, ui, top);
+
+                    let top = ui.cursor().top();
+                    let id = ui.make_persistent_id("sampling_collapsable");
+                    egui::collapsing_header::CollapsingState::load_with_default_open(
+                        ui.ctx(),
+                        id,
+                        false,
+                    )
+                    .show_header(ui, |ui| {
+                        if ui
+                            .checkbox(
+                                &mut self.sampling_enabled,
+                                egui::RichText::new("Sampling").strong().size(15.0),
+                            )
+                            .changed()
+                            & !self.sampling_enabled
+                        {
+                            self.nr_samples_per_layer = 1; // set number of samples to 1 if sampling is off
+                        };
+                    })
+                    .body(|ui| {
+                        ui_sampling(
+                            ui,
+                            self.sampling_enabled,
+                            &mut self.only_sample_half_of_bottom_layer,
+                            &mut self.only_sample_half_of_top_layer,
+                            &mut self.nr_samples_per_layer,
+                            &mut self.sample_combine_method,
+                            &mut self.sample_distribute_method,
+                            &mut self.sampling_points_control,
+                            &mut self.sampling_presets,
+                            &mut self.new_sampling_preset_name,
+                            &self.stack_sampling_points,
+                            self.current_layer,
+                            &mut self.percentage_suggestion,
+                            &self.stack_layer_parameters,
+                            &self.stack_blocks,
+                            &self.stack_locked,
+                            &self.generator,
+                        );
+                        ui.add_enabled(
+                            self.sampling_enabled,
+                            egui::Checkbox::new(
+                                &mut self.view.sample_labels,
+                                "Label samples with height and radius",
+                            ),
+                        );
+
+                        ui.label(format!(
+                            "Total number of samples for all layers: {}",
+                            self.stack_sampling_points.data.iter().fold(
+                                0,
+                                |acc, samples_for_single_layer| {
+                                    acc + samples_for_single_layer.len()
+                                }
+                            )
+                        ));
+                    });
+
+                    self.tutorial.mark(TutorialStep::Sampling, ui, top);
+
+                    let id = ui.make_persistent_id("block_budget_collapsable");
+                    egui::collapsing_header::CollapsingState::load_with_default_open(
+                        ui.ctx(),
+                        id,
+                        false,
+                    )
+                    .show_header(ui, |ui| {
+                        ui.checkbox(
+                            &mut self.block_budget.enabled,
+                            egui::RichText::new("Block budget").strong().size(15.0),
+                        );
+                    })
+                    .body(|ui| {
+                        let nr_blocks = self.stack_blocks.data.iter().map(|b| b.get_nr_blocks()).sum();
+                        let (suggest, apply) =
+                            ui_block_budget(ui, &mut self.block_budget, nr_blocks);
+                        if suggest {
+                            self.block_budget.suggest_scale(
+                                &self.stack_layer_parameters,
+                                &self.stack_blocks,
+                                &self.stack_locked,
+                                &self.sample_combine_method,
+                                &self.generator,
+                                &self.locked_parameters,
+                            );
+                        }
+                        if let Some(scale) = apply {
+                            self.scale_radii(scale, self.layer_lowest..=self.layer_highest);
+                        }
+                        if self.code_enabled && self.block_budget.suggestion.is_some() {
+                            ui.small("Radii given by code are not scaled");
+                        }
+                    });
+
+                    let id = ui.make_persistent_id("scaling_collapsable");
+                    egui::collapsing_header::CollapsingState::load_with_default_open(
+                        ui.ctx(),
+                        id,
+                        false,
+                    )
+                    .show_header(ui, |ui| {
+                        ui.label(egui::RichText::new("Scale").strong().size(15.0));
+                    })
+                    .body(|ui| {
+                        let layers = if self.layers_enabled && self.scale_tool.all_layers {
+                            self.layer_lowest..=self.layer_highest
+                        } else {
+                            self.current_layer..=self.current_layer
+                        };
+                        let scaled_layers: Vec<_> = layers
+                            .clone()
+                            .filter_map(|layer| {
+                                Some((
+                                    self.stack_configuration_parameters.get(layer)?,
+                                    self.stack_layer_parameters.get(layer)?,
+                                    self.stack_blocks.get(layer)?,
+                                    self.stack_locked.get(layer)?,
+                                ))
+                            })
+                            .collect();
+                        let area = scaled_layers
+                            .iter()
+                            .map(|(slice_parameters, ..)| shape_area(slice_parameters))
+                            .sum();
+                        let nr_blocks = scaled_layers
+                            .iter()
+                            .map(|(_, _, blocks, _)| blocks.get_nr_blocks())
+                            .sum();
+
+                        if ui_scaling(ui, &mut self.scale_tool, self.layers_enabled, area, nr_blocks)
+                        {
+                            let scale = self.scale_tool.scale(
+                                &scaled_layers,
+                                &self.sample_combine_method,
+                                &self.generator,
+                                &self.locked_parameters,
+                            );
+                            self.scale_radii(scale, layers);
+                        }
+                        if self.code_enabled {
+                            ui.small("Radii given by code are not scaled");
+                        }
+                    });
+                }
+
+                let id = ui.make_persistent_id("viewport_options_collapsable");
+                egui::collapsing_header::CollapsingState::load_with_default_open(
+                    ui.ctx(),
+                    id,
+                    true,
+                )
+                .show_header(ui, |ui| {
+                    ui.label(egui::RichText::new("Viewport").strong().size(15.0));
+                })
+                .body(|ui| {
+                    ui_viewport_options(
+                        ui,
+                        self.layers_enabled,
+                        self.single_radius,
+                        &self.symmetry_type,
+                        &self.boundary_tour,
+                        |band_width| {
+                            self.stack_blocks
+                                .get(self.current_layer)
+                                .map(|blocks| get_distance_bands(&blocks, shape_center, band_width))
+                                .unwrap_or_default()
+                        },
+                        &mut self.view,
+                    );
+
+                    if self.view.boundary_segments {
+                        ui_boundary_segments_legend(
+                            ui,
+                            &self.boundary_segments,
+                            self.view.color_blind_palette,
+                        );
+                    }
+
+                    if self.view.holes {
+                        ui_holes_summary(ui, &self.holes, self.layers_enabled);
+                    }
+
+                    if self.view.thin_necks {
+                        ui.label(format!(
+                            "Diagonal-only connections: {}",
+                            self.thin_necks.len()
+                        ));
+                    }
+
+                    if self.view.octants {
+                        ui_octant_counts(ui, &self.metrics.octant_counts);
+                    }
+
+                    if self.layers_enabled && self.view.sub_blocks {
+                        ui_sub_blocks_summary(ui, &self.sub_blocks, self.view.flip_z);
+                    }
+
+                    if self.view.boundary_tour {
+                        ui_build_order_export(
+                            ui,
+                            &mut self.view,
+                            &self.stack_blocks,
+                            self.current_layer,
+                            self.layers_enabled,
+                            &mut self.io,
+                        );
+                    }
+
+                    ui_image_export(
+                        ui,
+                        &self.stack_blocks,
+                        self.current_layer,
+                        self.layers_enabled,
+                        &self.view,
+                        &mut self.io,
+                    );
+                    ui_outline_export(
+                        ui,
+                        &self.stack_configuration_parameters,
+                        self.current_layer,
+                        self.layers_enabled,
+                        &self.view,
+                        &mut self.io,
+                    );
+                    ui_metrics_export(
+                        ui,
+                        &self.stack_blocks,
+                        self.current_layer,
+                        self.layers_enabled,
+                        &self.view,
+                        ComputedMetrics::new(
+                            &self.enabled_metrics,
+                            self.metrics_worker.is_pending,
+                            &self.symmetry_type,
+                            &self.convex_hull,
+                            &self.boundary_segments,
+                        ),
+                        &mut self.io,
+                    );
+                    ui_mcfunction_export(
+                        ui,
+                        &mut self.mcfunction_export,
+                        &self.stack_blocks,
+                        self.current_layer,
+                        self.layers_enabled,
+                        &self.view,
+                        &mut self.io,
+                    );
+
+                    if self.metrics_worker.is_pending {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Updating metrics");
+                        });
+                    }
+                    Ok(())
+                })
+                .2
+                .map(|body| body.inner)
+                .transpose()?;
+
+                if !self.view.viewer_mode {
+                    ui.separator();
+
+                    let top = ui.cursor().top();
+                    ui_generation(
+                        ui,
+                        &mut self.blocks_current_layer_control,
+                        &mut self.blocks_all_layers_control,
+                        &mut self.parameters_current_layer_control,
+                        &mut self.parameters_all_layers_control,
+                        self.layers_enabled,
+                        self.code_enabled,
+                        self.sampling_enabled,
+                    );
+                    self.tutorial.mark(TutorialStep::Generate, ui, top);
+
+                    if ui_fixed_grid_size(ui, &mut self.fixed_grid_size) {
+                        self.blocks_all_layers_control.set_outdated();
+                    }
+
+                    if ui_cell_aspect(ui, &mut self.generator.cell_aspect) {
+                        self.blocks_all_layers_control.set_outdated();
+                    }
+
+                    let mut cache_blocks = self.block_cache.is_enabled();
+                    if ui
+                        .checkbox(&mut cache_blocks, "Remember generated layers")
+                        .on_hover_text(
+                            "Layers that are generated with the same parameters again (after an \
+                            undo, or on the next start) are not generated again",
+                        )
+                        .changed()
+                    {
+                        self.block_cache.set_enabled(cache_blocks);
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label("Random seed");
+                        ui.add(egui::DragValue::new(&mut self.random_seed)).on_hover_text(
+                            "All random results (like the variations) follow from this seed, the same \
+                            seed gives the same results on every machine",
+                        );
+                    });
+
+                    if ui.button("Show tutorial").clicked() {
+                        self.tutorial.start();
+                    }
+                    if ui
+                        .button("Viewer mode")
+                        .on_hover_text("Hide the editing controls, to follow the plan")
+                        .clicked()
+                    {
+                        self.set_viewer_mode(true);
+                    }
+                }
+                Ok(())
+            })
+            .inner
+        })
+        .inner?;
+
+        // Anchoring the shape center in the world shifts the center offsets of all (unlocked) layers
+        //  by the position of the center within its block (and back when the anchor is moved or
+        //  removed, or the cell aspect changes), so that offsets of single layers and the lean are
+        //  kept
+        let world_anchor_shift = self.view.world_anchor_shift(self.generator.cell_aspect);
+        if world_anchor_shift != self.applied_world_anchor_shift {
+            let [new_x, new_y] = world_anchor_shift;
+            let [old_x, old_y] = self.applied_world_anchor_shift;
+            for (slice_parameters, locked) in self
+                .stack_configuration_parameters
+                .data
+                .iter_mut()
+                .zip(self.stack_locked.data.iter())
+            {
+                if !locked {
+                    slice_parameters.center_offset_x += new_x - old_x;
+                    slice_parameters.center_offset_y += new_y - old_y;
+                }
+            }
+            self.parameters_current_layer_control.set_outdated();
+            self.parameters_all_layers_control.set_outdated();
+            self.applied_world_anchor_shift = world_anchor_shift;
+        }
+
+        // Sampling points, parameters, blocks and metrics
+        self.update_pipeline()?;
+
+        if let Some(metrics) = self.metrics_worker.poll() {
+            if let Some(symmetry_type) = metrics.symmetry_type {
+                self.symmetry_type = symmetry_type;
+            }
+            if let Some(convex_hull) = metrics.convex_hull {
+                self.convex_hull = convex_hull;
+            }
+            if let Some(boundary_segments) = metrics.boundary_segments {
+                self.boundary_segments = boundary_segments;
+            }
+            if let Some(boundary_tour) = metrics.boundary_tour {
+                self.boundary_tour = boundary_tour;
+            }
+            if let Some(boundary_3d) = metrics.boundary_3d {
+                self.boundary_3d = boundary_3d;
+            }
+            if let Some(interior_3d) = metrics.interior_3d {
+                self.interior_3d = interior_3d;
+            }
+            if let Some(holes) = metrics.holes {
+                self.holes = holes;
+            }
+            if let Some(thin_necks) = metrics.thin_necks {
+                self.thin_necks = thin_necks;
+            }
+            if let Some(repeated_layers) = metrics.repeated_layers {
+                self.repeated_layers = repeated_layers;
+            }
         }
 
         // Status bar (bottom)
+        let diameters = self
+            .stack_blocks
+            .try_get_mut(self.current_layer)?
+            .get_diameters();
         egui::TopBottomPanel::bottom("status-bar").show(ctx, |ui| {
             ui.with_layout(Layout {
                 main_dir: Direction::LeftToRight,
@@ -437,11 +1793,15 @@ impl eframe::App for App {
                 ui.label(
                     format!(
                         // "nr. blocks: {}, nr. boundary blocks: {}, nr. interior blocks: {}, {}, build sequence: {:?}, program by Basyniae",
-                        "nr. blocks: {}, nr. boundary blocks: {}, nr. interior blocks: {}, {}, program by Basyniae",
-                        formatting::format_block_count(self.nr_blocks_total),
-                        formatting::format_block_count(self.nr_blocks_boundary),
-                        formatting::format_block_count(self.nr_blocks_interior),
-                        formatting::format_block_diameter(self.stack_blocks.get_mut(self.current_layer).unwrap().get_diameters()),
+                        "nr. blocks: {}, nr. boundary blocks: {}, nr. interior blocks: {}, {}, {}, materials: {}, {}{}program by Basyniae",
+                        formatting::format_block_count(self.metrics.nr_blocks_total),
+                        formatting::format_block_count(self.metrics.nr_blocks_boundary),
+                        formatting::format_block_count(self.metrics.nr_blocks_interior),
+                        formatting::format_block_diameter(diameters),
+                        formatting::format_shape_measures(self.metrics.shape_area, self.metrics.shape_perimeter, self.metrics.nr_blocks_total),
+                        formatting::format_materials(if self.layers_enabled { self.metrics.nr_blocks_stack } else { self.metrics.nr_blocks_total }),
+                        formatting::format_block_budget(&self.block_budget, self.metrics.nr_blocks_stack),
+                        if self.layers_enabled { formatting::format_repeated_layers(&self.repeated_layers, self.current_layer) } else { String::new() },
                         //self.blocks_all.get_build_sequence() //longterm: Redo build sequence, note it doesn't make sense for *tilted* superellipses (or non-centered ones?)
                     )
                 )
@@ -450,78 +1810,584 @@ impl eframe::App for App {
 
         // Layer navigation bar (top)
         if self.layers_enabled {
-            egui::TopBottomPanel::top("layer-navigation").show(ctx, |ui| {
-                let (old_layer, has_layer_stack_changed, _has_layer_changed) = ui_layer_navigation(
-                    ui,
-                    &mut self.current_layer,
-                    &mut self.layer_lowest,
-                    &mut self.layer_highest,
-                    self.lock_stack_size,
-                );
-
-                if has_layer_stack_changed {
-                    // The sampling points are (possibly) out of date.
-                    // This happens certainly if the stack grows, and if the stack shrinks only if
-                    //  only_sample_half_of_bottom_layer or only_sample_half_of_top_layer is true
-                    self.sampling_points_control.set_outdated();
+            egui::TopBottomPanel::top("layer-navigation")
+                .show(ctx, |ui| {
+                    ui_layer_bookmarks(
+                        ui,
+                        &mut self.layer_bookmarks,
+                        &mut self.new_bookmark_name,
+                        &mut self.current_layer,
+                        self.layer_lowest,
+                        self.layer_highest,
+                        !self.view.viewer_mode,
+                    );
 
-                    // Resize all the stack objects
-                    {
-                        self.stack_configuration_parameters.resize(
-                            self.layer_lowest,
-                            self.layer_highest,
-                            &self.stack_configuration_parameters.get(old_layer).unwrap(),
+                    let (old_layer, has_layer_stack_changed, _has_layer_changed) =
+                        ui_layer_navigation(
+                            ui,
+                            &mut self.current_layer,
+                            &mut self.layer_lowest,
+                            &mut self.layer_highest,
+                            self.lock_stack_size || self.view.viewer_mode,
                         );
 
-                        self.stack_layer_parameters.resize(
-                            self.layer_lowest,
-                            self.layer_highest,
-                            &self.stack_layer_parameters.get(old_layer).unwrap(),
-                        );
+                    ui_repeated_layers(ui, &self.repeated_layers, self.current_layer);
+                    ui_layer_playback(ui, &mut self.layer_playback);
 
-                        self.stack_blocks.resize(
-                            self.layer_lowest,
-                            self.layer_highest,
-                            &self.stack_blocks.get(old_layer).unwrap(),
-                        );
+                    if ui_layer_groups(
+                        ui,
+                        &mut self.layer_groups,
+                        &mut self.new_group_name,
+                        &mut self.new_group_layers,
+                        &mut self.current_layer,
+                        !self.view.viewer_mode,
+                    ) {
+                        self.parameters_all_layers_control.set_outdated();
+                        self.blocks_all_layers_control.set_outdated();
+                    }
 
-                        self.stack_sampling_points.resize(
-                            self.layer_lowest,
-                            self.layer_highest,
-                            &self.stack_sampling_points.get(old_layer).unwrap(),
-                        );
+                    if has_layer_stack_changed {
+                        // The sampling points are out of date: new layers have none, and the old bottom
+                        //  and top layer may have been sampled in half. They only depend on the
+                        //  sampling options, so they are recomputed right away (even without
+                        //  "Auto recompute sampling points"), and the parameters with them.
+                        self.recompute_sampling_points();
+
+                        // Resize all the stack objects, the new layers get their parameters by the
+                        //  new layer policy (their blocks are outdated until they are generated)
+                        {
+                            // The layer that was current is copied (it is in the old stack, unless the
+                            //  layer changed while the stack was resized)
+                            let old_layer = old_layer.clamp(
+                                self.stack_blocks.get_minimum(),
+                                self.stack_blocks.get_maximum(),
+                            );
+                            let old_layer_config = self.stack_configuration_parameters.clone();
+                            self.stack_configuration_parameters.resize(
+                                self.layer_lowest,
+                                self.layer_highest,
+                                &self.stack_configuration_parameters.try_get(old_layer)?,
+                            );
+                            fill_new_layers(
+                                &mut self.stack_configuration_parameters,
+                                &old_layer_config,
+                                self.new_layer_fill,
+                                &self.new_layer_default,
+                            );
+                            self.blocks_all_layers_control.set_outdated();
+
+                            self.stack_layer_parameters.resize(
+                                self.layer_lowest,
+                                self.layer_highest,
+                                &self.stack_layer_parameters.try_get(old_layer)?,
+                            );
+
+                            self.stack_blocks.resize(
+                                self.layer_lowest,
+                                self.layer_highest,
+                                &self.stack_blocks.try_get(old_layer)?,
+                            );
+
+                            self.stack_locked
+                                .resize(self.layer_lowest, self.layer_highest, &false);
+                        }
+
+                        // Groups only keep the layers that are still in the stack
+                        let layers = [self.layer_lowest, self.layer_highest];
+                        self.layer_groups.retain_mut(|group| group.clip(layers));
                     }
-                }
 
-                // Updating the field state when the bounds increase is not necessary,
-                //  as the changing sampling points invalidates the field states already.
-            });
+                    // Updating the field state when the bounds increase is not necessary,
+                    //  as the changing sampling points invalidates the field states already.
+                    Ok(())
+                })
+                .inner?;
         }
 
+        // Shape (and provisional blocks) at the height between layers, not stored since they only
+        //  depend on the parameters that are already stored
+        let between_layers = (self.layers_enabled && self.view.between_layers)
+            .then(|| self.parameters_at_height(self.view.between_layers_height))
+            .flatten();
+        let between_layers_blocks = between_layers
+            .as_ref()
+            .filter(|_| self.view.between_layers_blocks)
+            .map(|slice_parameters| LayerParameters::single(slice_parameters, &self.generator));
+
+        // While the window is open, clicks in the viewport construct a shape
+        let current_layer_config = self
+            .stack_configuration_parameters
+            .try_get(self.current_layer)?;
+        let shape_through_points = self.view.shape_through_points.then(|| {
+            self.fit_to_points
+                .fit_clicked_points()
+                .map(|shape| with_fitted_shape(&shape, &current_layer_config))
+        });
+
+        // While the window is open, a click in the viewport places the text stamp
+        let stamp_preview = self.view.text_stamp.then(|| {
+            self.stamp_tool
+                .stamp(self.current_layer)
+                .map(|stamp| stamp.coords())
+                .unwrap_or_default()
+        });
+
+        let block_depths = (self.layers_enabled && self.view.depth_shading)
+            .then(|| get_block_depths(&self.stack_blocks, self.current_layer));
+
+        let reference_image = self
+            .reference_image
+            .as_mut()
+            .filter(|image| image.shown && !self.view.clean_view)
+            .map(|image| image.plot_image(ctx, self.generator.cell_aspect));
+
+        // The script shape of a layer with the Custom algorithm
+        let script_outline = (current_layer_config.algorithm == Algorithm::Custom)
+            .then(|| self.shape_library.outline())
+            .flatten();
+
+        // The leaning stack, while the lean is edited
+        let lean_preview =
+            (self.layers_enabled && self.view.lean && !self.view.clean_view).then(|| {
+                LeanPreview::new(
+                    &self.lean,
+                    &self.stack_configuration_parameters,
+                    self.generator.cell_aspect,
+                )
+            });
+
         // Viewport
-        egui::CentralPanel::default().show(ctx, |ui| {
+        let sampled_parameters = self.stack_layer_parameters.try_get(self.current_layer)?;
+        let distance_bands = if self.view.distance_bands {
+            let shape_center = self.shape_center()?;
+            let blocks = self.stack_blocks.try_get(self.current_layer)?;
+            Some(
+                self.distance_bands
+                    .get(&blocks, shape_center, self.view.distance_band_width),
+            )
+        } else {
+            None
+        };
+        let viewport = egui::CentralPanel::default().show(ctx, |ui| {
             ui_viewport(
                 ui,
-                self.stack_configuration_parameters
-                    .get(self.current_layer)
-                    .unwrap(),
-                self.stack_layer_parameters.get(self.current_layer).unwrap(),
-                self.stack_blocks.get(self.current_layer).as_ref(),
-                self.sampling_enabled,
+                ViewportLayer {
+                    slice_parameters: self.lean.apply(&current_layer_config),
+                    sampled_parameters,
+                    blocks: self.stack_blocks.get(self.current_layer).as_ref(),
+                    sampling_enabled: self.sampling_enabled,
+                    sampling_points: self
+                        .stack_sampling_points
+                        .get(self.current_layer)
+                        .as_deref()
+                        .unwrap_or_default(),
+                    current_layer: self.current_layer,
+                },
                 &self.view,
-                &mut self.reset_zoom_once,
-                &mut self.reset_zoom_continuous,
-                Some(&self.boundary_2d),
-                Some(&self.interior_2d),
-                Some(&self.complement_2d),
-                self.boundary_3d.get(self.current_layer).as_ref(),
-                self.interior_3d.get(self.current_layer).as_ref(),
-                &self.convex_hull,
-                &self.outer_corners,
-                &self.symmetry_type,
-                &self.block_center_coord,
-                &self.global_bounding_box,
-            );
+                self.generator.cell_aspect,
+                ViewportZoom {
+                    reset_zoom_once: &mut self.reset_zoom_once,
+                    reset_zoom_continuous: &mut self.reset_zoom_continuous,
+                    global_bounding_box: self.metrics.global_bounding_box,
+                    global_blocks_bounding_box: self.metrics.global_blocks_bounding_box,
+                    layer_bounding_box: self.metrics.layer_bounding_box,
+                    layer_blocks_bounding_box: self.metrics.layer_blocks_bounding_box,
+                },
+                ViewportMetrics {
+                    boundary_2d: Some(&self.metrics.boundary_2d),
+                    interior_2d: Some(&self.metrics.interior_2d),
+                    complement_2d: Some(&self.metrics.complement_2d),
+                    boundary_3d_slice: self.boundary_3d.get(self.current_layer).as_ref(),
+                    interior_3d_slice: self.interior_3d.get(self.current_layer).as_ref(),
+                    holes_2d: Some(&self.holes.holes_2d),
+                    holes_3d_slice: self
+                        .layers_enabled
+                        .then(|| self.holes.holes_3d.get(self.current_layer))
+                        .flatten()
+                        .as_ref(),
+                    projection_union: Some(&self.metrics.projection_union),
+                    projection_intersection: Some(&self.metrics.projection_intersection),
+                    convex_hull: &self.convex_hull,
+                    outer_corners: &self.metrics.outer_corners,
+                    thin_necks: &self.thin_necks,
+                    boundary_tour: &self.boundary_tour,
+                    boundary_segments: &self.boundary_segments,
+                    symmetry_type: &self.symmetry_type,
+                    octant_counts: &self.metrics.octant_counts,
+                    center_coord: &self.metrics.center_coord,
+                    distance_bands,
+                },
+                ViewportPreviews {
+                    vertical_slice_plane: (self.layers_enabled && self.view.vertical_slice)
+                        .then_some((self.vertical_slice_axis, self.vertical_slice_coord)),
+                    // Nothing to show if the pinned layer is no longer in the stack
+                    pinned_outline: if self.layers_enabled && self.view.pinned_outline {
+                        self.stack_configuration_parameters
+                            .get(self.view.pinned_layer)
+                            .map(|slice_parameters| self.lean.apply(&slice_parameters))
+                    } else {
+                        None
+                    },
+                    between_layers: between_layers.map(|slice_parameters| {
+                        (self.view.between_layers_height, slice_parameters)
+                    }),
+                    between_layers_blocks: between_layers_blocks.as_ref(),
+                    sub_blocks: (self.layers_enabled && self.view.sub_blocks)
+                        .then_some(self.sub_blocks.as_slice()),
+                    clicked_points: shape_through_points
+                        .map(|shape| (self.fit_to_points.clicked_points.as_slice(), shape)),
+                    stamp_preview: stamp_preview
+                        .as_deref()
+                        .map(|coords| (coords, self.stamp_tool.mode)),
+                    block_depths: block_depths.as_deref(),
+                    reference_image,
+                    script_outline,
+                    lean_preview: lean_preview.as_ref(),
+                },
+            )
         });
+        self.tutorial
+            .mark_rect(TutorialStep::Viewport, viewport.response.rect);
+        if let Some(point) = viewport.inner {
+            // The viewport is in cells, the shape through the points isn't
+            if self.view.shape_through_points {
+                self.fit_to_points
+                    .click(from_cells(point, self.generator.cell_aspect));
+            }
+            if self.view.text_stamp {
+                self.stamp_tool.click(point);
+            }
+        }
+
+        // Side view
+        if self.layers_enabled {
+            egui::Window::new("Side view")
+                .open(&mut self.view.vertical_slice)
+                .show(ctx, |ui| {
+                    ui_vertical_slice(
+                        ui,
+                        &self.stack_blocks,
+                        self.current_layer,
+                        &mut self.vertical_slice_axis,
+                        &mut self.vertical_slice_coord,
+                        lean_preview.as_ref(),
+                    )
+                });
+
+            // Lean of the whole stack
+            egui::Window::new("Lean the stack")
+                .open(&mut self.view.lean)
+                .show(ctx, |ui| {
+                    if ui_lean(ui, &mut self.lean, &self.stack_sampling_points) {
+                        self.parameters_current_layer_control.set_outdated();
+                        self.parameters_all_layers_control.set_outdated();
+                    }
+                });
+        }
+
+        // Variation gallery
+        let slice_parameters = self
+            .stack_configuration_parameters
+            .try_get_mut(self.current_layer)?;
+        egui::Window::new("Variations")
+            .open(&mut self.view.variation_gallery)
+            .show(ctx, |ui| {
+                if ui_variation_gallery(
+                    ui,
+                    slice_parameters,
+                    &mut self.variation_gallery,
+                    self.random_seed,
+                    &self.locked_parameters,
+                    &self.generator,
+                ) {
+                    self.parameters_current_layer_control.set_outdated();
+                    self.parameters_all_layers_control.set_outdated();
+                }
+            });
+
+        // Find & replace
+        egui::Window::new("Find & replace")
+            .open(&mut self.view.find_replace)
+            .show(ctx, |ui| {
+                if ui_find_replace(
+                    ui,
+                    &mut self.find_replace,
+                    &mut self.stack_configuration_parameters,
+                    &self.stack_locked,
+                    &self.locked_parameters,
+                    self.layer_lowest,
+                    self.layer_highest,
+                ) {
+                    self.parameters_current_layer_control.set_outdated();
+                    self.parameters_all_layers_control.set_outdated();
+                }
+            });
+
+        // Fit a shape to the coordinates of an existing build
+        let (translation, flip_z) = (self.view.world_translation(), self.view.flip_z);
+        let slice_parameters = self
+            .stack_configuration_parameters
+            .try_get_mut(self.current_layer)?;
+        egui::Window::new("Fit shape to blocks")
+            .open(&mut self.view.fit_to_points)
+            .show(ctx, |ui| {
+                if ui_fit_to_points(
+                    ui,
+                    &mut self.fit_to_points,
+                    translation,
+                    flip_z,
+                    slice_parameters,
+                    &mut self.single_radius,
+                    self.stack_locked.get(self.current_layer).unwrap_or(false),
+                ) {
+                    self.parameters_current_layer_control.set_outdated();
+                    self.parameters_all_layers_control.set_outdated();
+                }
+            });
+
+        // Circle or ellipse through points clicked in the viewport
+        let slice_parameters = self
+            .stack_configuration_parameters
+            .try_get_mut(self.current_layer)?;
+        egui::Window::new("Shape through points")
+            .open(&mut self.view.shape_through_points)
+            .show(ctx, |ui| {
+                if ui_shape_through_points(
+                    ui,
+                    &mut self.fit_to_points,
+                    slice_parameters,
+                    &mut self.single_radius,
+                    self.stack_locked.get(self.current_layer).unwrap_or(false),
+                ) {
+                    self.parameters_current_layer_control.set_outdated();
+                    self.parameters_all_layers_control.set_outdated();
+                }
+            });
+
+        // Image to trace the shapes over
+        egui::Window::new("Reference image")
+            .open(&mut self.view.reference_image)
+            .show(ctx, |ui| {
+                ui_reference_image(ui, &mut self.reference_image, &mut self.io);
+            });
+
+        // Text in blocks on the current layer
+        egui::Window::new("Text stamp")
+            .open(&mut self.view.text_stamp)
+            .show(ctx, |ui| {
+                if ui_text_stamp(
+                    ui,
+                    &mut self.stamp_tool,
+                    &mut self.stamps,
+                    self.current_layer,
+                    self.stack_locked.get(self.current_layer).unwrap_or(false),
+                ) {
+                    self.blocks_current_layer_control.set_outdated();
+                }
+            });
+
+        // Layout of several structures
+        let mut load_structure = None;
+        egui::Window::new("Layout")
+            .open(&mut self.view.layout_canvas)
+            .show(ctx, |ui| {
+                load_structure = ui_layout_canvas(
+                    ui,
+                    &mut self.layout,
+                    &self.stack_configuration_parameters,
+                    &self.stack_blocks,
+                    self.view.color_blind_palette,
+                );
+            });
+        if let Some(index) = load_structure {
+            // Only the parameters and blocks are stored, the rest is recomputed
+            let structure = self.layout.structures[index].clone();
+            let current_layer = self.current_layer;
+            self.replace_stack(structure.stack_layer_config, structure.stack_blocks);
+            self.current_layer = current_layer.clamp(self.layer_lowest, self.layer_highest);
+        }
+
+        // Templates
+        let mut chosen_template = None;
+        egui::Window::new("Templates")
+            .open(&mut self.view.template_gallery)
+            .show(ctx, |ui| {
+                chosen_template = ui_template_gallery(ui, &mut self.templates, &self.generator);
+            });
+        if let Some(index) = chosen_template {
+            let template = &mut self.templates[index];
+            let stack_layer_config = template.get_stack_layer_config();
+            let stack_blocks = template.generate_preview(&self.generator).clone();
+            self.replace_stack(stack_layer_config, stack_blocks);
+
+            // The sliders show the parameters given by the code on each layer
+            let template = &self.templates[index];
+            self.code_enabled = true;
+            self.single_radius = template.single_radius;
+            [
+                self.lua_field_radius_a,
+                self.lua_field_radius_b,
+                self.lua_field_tilt,
+                self.lua_field_center_offset_x,
+                self.lua_field_center_offset_y,
+                self.lua_field_squircle_parameter,
+            ] = template.get_lua_fields(&self.stack_sampling_points);
+        }
+
+        // Arch between two points
+        let mut generate_arch = false;
+        egui::Window::new("New arch")
+            .open(&mut self.view.arch)
+            .show(ctx, |ui| generate_arch = ui_arch(ui, &mut self.arch_tool));
+        if generate_arch {
+            self.generate_arch();
+        }
+
+        // Log
+        egui::Window::new("Log")
+            .open(&mut self.view.log_console)
+            .show(ctx, |ui| ui_log_console(ui, &mut self.io));
+
+        // Parameter history (of the current layer, after this frame's edits)
+        let time = ctx.input(|i| i.time);
+        self.parameter_history.record(
+            time,
+            self.current_layer,
+            &self
+                .stack_configuration_parameters
+                .try_get(self.current_layer)?,
+        );
+        let editable = !self.view.viewer_mode
+            && !self.stack_locked.get(self.current_layer).unwrap_or(false)
+            && !is_constrained(
+                &self.parameter_constraints,
+                self.parameter_history.parameter,
+            );
+        let mut restored = None;
+        egui::Window::new("Parameter history")
+            .open(&mut self.view.parameter_history)
+            .show(ctx, |ui| {
+                restored = ui_parameter_history(
+                    ui,
+                    &mut self.parameter_history,
+                    self.current_layer,
+                    time,
+                    editable,
+                );
+            });
+        if let Some(value) = restored {
+            *self.parameter_history.parameter.get_mut(
+                self.stack_configuration_parameters
+                    .try_get_mut(self.current_layer)?,
+            ) = value;
+            self.parameters_current_layer_control.set_outdated();
+            self.parameters_all_layers_control.set_outdated();
+        }
+
+        // Export with a script
+        let mut view_export_script = self.view.export_script;
+        egui::Window::new("Export script")
+            .open(&mut view_export_script)
+            .show(ctx, |ui| {
+                ui_export_script(
+                    ui,
+                    &mut self.export_script,
+                    &self.stack_blocks,
+                    self.current_layer,
+                    self.layers_enabled,
+                    &mut self.view,
+                    &mut self.io,
+                );
+            });
+        self.view.export_script = view_export_script;
+
+        // Calculator
+        let current_layer_config = self
+            .stack_configuration_parameters
+            .try_get(self.current_layer)?;
+        egui::Window::new("Calculator")
+            .open(&mut self.view.calculator)
+            .show(ctx, |ui| {
+                ui_calculator(
+                    ui,
+                    &mut self.calculator,
+                    &current_layer_config,
+                    self.current_layer,
+                );
+            });
+
+        // Files dropped on the window
+        for file in ctx.input(|i| i.raw.dropped_files.clone()) {
+            self.open_file(&file);
+        }
+        ui_drop_hint(ctx);
+
+        Ok(())
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // If a layer is missing somewhere (the stack changed while it was used), the rest of the
+        //  frame is skipped instead of crashing
+        match self.update_frame(ctx) {
+            Ok(()) => self.frame_error = None,
+            Err(error) => self.report_frame_error(error),
+        }
+
+        // Notifications of file operations
+        self.io.poll(ctx.input(|i| i.time));
+        for file in std::mem::take(&mut self.io.opened) {
+            self.open_file(&file);
+        }
+        ui_toasts(ctx, &self.io);
+
+        // Tutorial (on top of everything)
+        ui_tutorial(ctx, &mut self.tutorial);
+
+        // egui only repaints on input, so ask for another frame while something changed this
+        //  frame still has to be updated (otherwise the app stays idle)
+        if self.has_pending_updates() {
+            ctx.request_repaint();
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        storage.set_string(TUTORIAL_FINISHED_KEY, self.tutorial.finished.to_string());
+        storage.set_string(ZOOM_TO_LAYER_KEY, self.view.zoom_to_layer.to_string());
+        storage.set_string(RANDOM_SEED_KEY, self.random_seed.to_string());
+        storage.set_string(
+            BLOCK_CACHE_ENABLED_KEY,
+            self.block_cache.is_enabled().to_string(),
+        );
+        storage.set_string(BLOCK_CACHE_KEY, self.block_cache.to_storage_string());
+        // Forget the reference image when it was removed (or can't be opened again)
+        let reference_image = self.reference_image.as_ref();
+        storage.set_string(
+            REFERENCE_IMAGE_PATH_KEY,
+            reference_image
+                .and_then(|image| image.path.as_ref())
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+        );
+        if let Some(image) = reference_image {
+            storage.set_string(
+                REFERENCE_IMAGE_PLACEMENT_KEY,
+                image.placement.to_storage_string(),
+            );
+        }
+        let script_paths: Vec<String> = self
+            .shape_library
+            .paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        storage.set_string(SHAPE_SCRIPT_PATHS_KEY, script_paths.join("\n"));
+    }
+
+    // Only the tutorial state, the zoom mode, the random seed, the reference image, the shape
+    //  scripts and the generated layers are stored, the panels open as usual
+    fn persist_egui_memory(&self) -> bool {
+        false
     }
 }
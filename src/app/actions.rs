@@ -0,0 +1,333 @@
+use std::fmt::{Display, Formatter};
+
+use eframe::egui::{Key, KeyboardShortcut, Modifiers};
+
+use crate::app::view::View;
+
+pub const OPEN_COMMAND_PALETTE: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::P);
+
+/// A viewport option or window that can be switched on and off
+#[derive(Debug, Clone, Copy)]
+pub struct ViewToggle {
+    pub name: &'static str,
+    pub shown_mut: fn(&mut View) -> &mut bool,
+    pub needs_layers: bool, // only makes sense for a stack of layers
+    pub edits: bool,        // a window that changes the shape (hidden in viewer mode)
+}
+
+const VIEW_TOGGLES: [ViewToggle; 38] = [
+    toggle("Blocks", |view| &mut view.blocks, false),
+    toggle("Layer boundary", |view| &mut view.boundary_2d, false),
+    toggle("Layer interior", |view| &mut view.interior_2d, false),
+    toggle("3D boundary", |view| &mut view.boundary_3d, true),
+    toggle("3D interior", |view| &mut view.interior_3d, true),
+    toggle("Union of layers", |view| &mut view.projection_union, true),
+    toggle(
+        "Intersection of layers",
+        |view| &mut view.projection_intersection,
+        true,
+    ),
+    toggle("Complement", |view| &mut view.complement, false),
+    toggle("Convex hull", |view| &mut view.convex_hull, false),
+    toggle("Outer corners", |view| &mut view.outer_corners, false),
+    toggle("Center blocks", |view| &mut view.center_blocks, false),
+    toggle("Bounds", |view| &mut view.bounds, false),
+    toggle("Mirrors", |view| &mut view.mirrors, false),
+    toggle("Octants", |view| &mut view.octants, false),
+    toggle(
+        "Boundary segments",
+        |view| &mut view.boundary_segments,
+        false,
+    ),
+    toggle("Holes", |view| &mut view.holes, false),
+    toggle(
+        "Diagonal-only connections",
+        |view| &mut view.thin_necks,
+        false,
+    ),
+    toggle("Supports", |view| &mut view.supports, false),
+    toggle("Boundary tour", |view| &mut view.boundary_tour, false),
+    toggle("Distance bands", |view| &mut view.distance_bands, false),
+    toggle("Minecraft axes", |view| &mut view.minecraft_axes, false),
+    toggle("Side view", |view| &mut view.vertical_slice, true),
+    toggle("Slabs and stairs", |view| &mut view.sub_blocks, true),
+    toggle("Depth shading", |view| &mut view.depth_shading, true),
+    editor(
+        "Variation gallery",
+        |view| &mut view.variation_gallery,
+        false,
+    ),
+    editor(
+        "Layout of several structures",
+        |view| &mut view.layout_canvas,
+        false,
+    ),
+    editor(
+        "New from template",
+        |view| &mut view.template_gallery,
+        false,
+    ),
+    editor("New arch", |view| &mut view.arch, false),
+    editor("Fit shape to blocks", |view| &mut view.fit_to_points, false),
+    editor(
+        "Shape through clicked points",
+        |view| &mut view.shape_through_points,
+        false,
+    ),
+    editor("Text stamp", |view| &mut view.text_stamp, false),
+    toggle("Reference image", |view| &mut view.reference_image, false),
+    toggle(
+        "Parameter history",
+        |view| &mut view.parameter_history,
+        false,
+    ),
+    editor(
+        "Find & replace in layers",
+        |view| &mut view.find_replace,
+        true,
+    ),
+    editor("Lean the stack", |view| &mut view.lean, true),
+    toggle(
+        "Export with a script",
+        |view| &mut view.export_script,
+        false,
+    ),
+    toggle("Calculator", |view| &mut view.calculator, false),
+    toggle("Log", |view| &mut view.log_console, false),
+];
+
+const fn toggle(
+    name: &'static str,
+    shown_mut: fn(&mut View) -> &mut bool,
+    needs_layers: bool,
+) -> ViewToggle {
+    ViewToggle {
+        name,
+        shown_mut,
+        needs_layers,
+        edits: false,
+    }
+}
+
+/// Toggle of a window that changes the shape
+const fn editor(
+    name: &'static str,
+    shown_mut: fn(&mut View) -> &mut bool,
+    needs_layers: bool,
+) -> ViewToggle {
+    ViewToggle {
+        edits: true,
+        ..toggle(name, shown_mut, needs_layers)
+    }
+}
+
+/// Close the windows that change the shape (for viewer mode)
+pub fn close_editing_windows(view: &mut View) {
+    for toggle in VIEW_TOGGLES.iter().filter(|toggle| toggle.edits) {
+        *(toggle.shown_mut)(view) = false;
+    }
+}
+
+/// Everything that can be done from the command palette (and with keyboard shortcuts). See
+///  `App::perform` for what they do.
+#[derive(Debug, Clone, Copy)]
+pub enum Action {
+    GenerateCurrentLayer,
+    GenerateAllLayers,
+    SampleCurrentLayer,
+    SampleAllLayers,
+    ResetZoom,
+    PreviousLayer,
+    NextLayer,
+    LowestLayer,
+    HighestLayer,
+    JumpToLayer(isize),
+    SetGroundLayer, // the current layer becomes layer 0
+    PlayLayers,     // play or pause stepping through the layers
+    MoveLayerUp,    // swap the current layer with the one above
+    MoveLayerDown,
+    ReverseStack,
+    Toggle(ViewToggle),
+    ToggleCleanView, // only the blocks in the viewport
+    ExportBuildOrderCsv,
+    ExportBuildOrderJson,
+    ExportMetricsJson,
+    ShowTutorial,
+    ToggleViewerMode,
+}
+
+impl Action {
+    /// Does the action only make sense for a stack of layers?
+    pub fn needs_layers(&self) -> bool {
+        match self {
+            Action::GenerateAllLayers
+            | Action::SampleAllLayers
+            | Action::PreviousLayer
+            | Action::NextLayer
+            | Action::LowestLayer
+            | Action::HighestLayer
+            | Action::JumpToLayer(_)
+            | Action::SetGroundLayer
+            | Action::PlayLayers
+            | Action::MoveLayerUp
+            | Action::MoveLayerDown
+            | Action::ReverseStack => true,
+            Action::Toggle(toggle) => toggle.needs_layers,
+            _ => false,
+        }
+    }
+
+    /// Does the action change the shape? These are not available in viewer mode.
+    pub fn edits(&self) -> bool {
+        match self {
+            Action::GenerateCurrentLayer
+            | Action::GenerateAllLayers
+            | Action::SampleCurrentLayer
+            | Action::SampleAllLayers
+            | Action::SetGroundLayer
+            | Action::MoveLayerUp
+            | Action::MoveLayerDown
+            | Action::ReverseStack => true,
+            Action::Toggle(toggle) => toggle.edits,
+            _ => false,
+        }
+    }
+
+    pub fn shortcut(&self) -> Option<KeyboardShortcut> {
+        match self {
+            Action::GenerateCurrentLayer => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::G)),
+            Action::GenerateAllLayers => Some(KeyboardShortcut::new(
+                Modifiers::COMMAND.plus(Modifiers::SHIFT),
+                Key::G,
+            )),
+            Action::ResetZoom => Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::Num0)),
+            Action::PreviousLayer => Some(KeyboardShortcut::new(Modifiers::NONE, Key::PageDown)),
+            Action::NextLayer => Some(KeyboardShortcut::new(Modifiers::NONE, Key::PageUp)),
+            Action::ToggleCleanView => Some(KeyboardShortcut::new(
+                Modifiers::COMMAND.plus(Modifiers::SHIFT),
+                Key::H,
+            )),
+            _ => None,
+        }
+    }
+
+    /// All actions available for the current mode (without jumping to a layer, which needs a
+    ///  number)
+    pub fn all(layers_enabled: bool, viewer_mode: bool) -> Vec<Action> {
+        let mut actions = vec![
+            Action::GenerateCurrentLayer,
+            Action::GenerateAllLayers,
+            Action::SampleCurrentLayer,
+            Action::SampleAllLayers,
+            Action::ResetZoom,
+            Action::PreviousLayer,
+            Action::NextLayer,
+            Action::LowestLayer,
+            Action::HighestLayer,
+            Action::SetGroundLayer,
+            Action::PlayLayers,
+            Action::MoveLayerUp,
+            Action::MoveLayerDown,
+            Action::ReverseStack,
+        ];
+        actions.extend(VIEW_TOGGLES.map(Action::Toggle));
+        actions.extend([
+            Action::ToggleCleanView,
+            Action::ExportBuildOrderCsv,
+            Action::ExportBuildOrderJson,
+            Action::ExportMetricsJson,
+            Action::ShowTutorial,
+            Action::ToggleViewerMode,
+        ]);
+        actions.retain(|action| {
+            (layers_enabled || !action.needs_layers()) && !(viewer_mode && action.edits())
+        });
+        actions
+    }
+
+    /// The actions of `all` with a shortcut, those with more modifiers first: egui matches shortcuts
+    ///  ignoring extra modifiers (Shift), so Cmd+G would also take Cmd+Shift+G if it came first
+    pub fn with_shortcuts(
+        layers_enabled: bool,
+        viewer_mode: bool,
+    ) -> Vec<(Action, KeyboardShortcut)> {
+        let mut actions: Vec<(Action, KeyboardShortcut)> = Action::all(layers_enabled, viewer_mode)
+            .into_iter()
+            .filter_map(|action| action.shortcut().map(|shortcut| (action, shortcut)))
+            .collect();
+        actions.sort_by_key(|(_, shortcut)| {
+            let modifiers = shortcut.modifiers;
+            let command = modifiers.command || modifiers.ctrl || modifiers.mac_cmd;
+            let nr_modifiers = [modifiers.alt, modifiers.shift, command]
+                .into_iter()
+                .filter(|pressed| *pressed)
+                .count();
+            std::cmp::Reverse(nr_modifiers)
+        });
+        actions
+    }
+
+    /// The actions whose name contains all words of the query (ignoring case). A number (or
+    ///  "layer" and a number) jumps to that layer.
+    pub fn search(query: &str, layers_enabled: bool, viewer_mode: bool) -> Vec<Action> {
+        let query = query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+
+        let mut actions = vec![];
+        let layer = match words[..] {
+            [number] | ["layer", number] => number.parse::<isize>().ok(),
+            _ => None,
+        };
+        if let Some(layer) = layer.filter(|_| layers_enabled) {
+            actions.push(Action::JumpToLayer(layer));
+        }
+
+        actions.extend(
+            Action::all(layers_enabled, viewer_mode)
+                .into_iter()
+                .filter(|action| {
+                    let name = action.to_string().to_lowercase();
+                    words.iter().all(|word| name.contains(word))
+                }),
+        );
+        actions
+    }
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::GenerateCurrentLayer => write!(f, "Generate blocks on current layer"),
+            Action::GenerateAllLayers => write!(f, "Generate blocks on all layers"),
+            Action::SampleCurrentLayer => write!(f, "Sample parameters for current layer"),
+            Action::SampleAllLayers => write!(f, "Sample parameters for all layers"),
+            Action::ResetZoom => write!(f, "Reset zoom"),
+            Action::PreviousLayer => write!(f, "Previous layer"),
+            Action::NextLayer => write!(f, "Next layer"),
+            Action::LowestLayer => write!(f, "Go to lowest layer"),
+            Action::HighestLayer => write!(f, "Go to highest layer"),
+            Action::JumpToLayer(layer) => write!(f, "Go to layer {}", layer),
+            Action::SetGroundLayer => write!(f, "Set current layer as ground (layer 0)"),
+            Action::PlayLayers => write!(f, "Play or pause the layers"),
+            Action::MoveLayerUp => write!(f, "Move current layer up"),
+            Action::MoveLayerDown => write!(f, "Move current layer down"),
+            Action::ReverseStack => write!(f, "Reverse the stack (upside down)"),
+            Action::Toggle(toggle) => write!(f, "Show or hide: {}", toggle.name),
+            Action::ToggleCleanView => write!(f, "Show only the blocks (clean view)"),
+            Action::ExportBuildOrderCsv => write!(f, "Export build order as CSV"),
+            Action::ExportBuildOrderJson => write!(f, "Export build order as JSON"),
+            Action::ExportMetricsJson => write!(f, "Export metrics as JSON"),
+            Action::ShowTutorial => write!(f, "Show tutorial"),
+            Action::ToggleViewerMode => write!(f, "Enter or leave viewer mode"),
+        }
+    }
+}
+
+/// The command palette (opened with `OPEN_COMMAND_PALETTE`): search the actions by name
+#[derive(Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    pub query: String,
+    pub selected: usize, // index in the search results
+}
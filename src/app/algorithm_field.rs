@@ -0,0 +1,207 @@
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::generation::custom::{CustomPredicate, DEFAULT_CUSTOM_CODE};
+use crate::app::generation::Algorithm;
+use crate::app::log::log_warning;
+use crate::app::lua_field::{code_edit, FieldState};
+use crate::app::scripting::new_engine;
+use eframe::egui::Ui;
+use rhai::{Dynamic, Engine, Scope, AST};
+
+/// Code that picks the algorithm of each layer, e.g. `if l < 10 { 0.4 } else { "contained" }`. The
+///  value is the name of an algorithm, or a number for the Percentage algorithm with that
+///  percentage. The other options of the algorithm (the margin, the coverage estimator) are the
+///  ones last used on the layer.
+///
+/// Unlike the parameters, the algorithm is evaluated once per layer (at the layer itself, not at
+///  the samples) and stored in the configuration of the layer, so it can still be changed by hand
+///  or with find & replace afterwards. The code is compiled once when it is edited and then run in
+///  the same engine for all layers.
+pub struct AlgorithmField {
+    code: String,
+    field_state: FieldState,
+    error_message: Option<String>,
+    engine: Engine,
+    ast: Option<AST>, // the compiled code, None if the field is empty or doesn't compile
+}
+
+impl Default for AlgorithmField {
+    fn default() -> Self {
+        Self {
+            code: String::new(),
+            field_state: FieldState::Empty,
+            error_message: None,
+            engine: new_engine(),
+            ast: None,
+        }
+    }
+}
+
+impl AlgorithmField {
+    pub fn show(&mut self, ui: &mut Ui, sampling_points: &ZVec<Vec<f64>>) {
+        if code_edit(
+            ui,
+            &mut self.code,
+            &self.field_state,
+            self.error_message.as_ref(),
+        ) {
+            self.update_field_state(sampling_points);
+        }
+    }
+
+    /// Compile the code and check it on all layers of the stack (the layers of the sampling points)
+    pub fn update_field_state(&mut self, sampling_points: &ZVec<Vec<f64>>) {
+        self.error_message = None;
+        self.ast = None;
+        if self.code.is_empty() {
+            self.field_state = FieldState::Empty;
+            return;
+        }
+
+        match self.engine.compile_expression(&self.code) {
+            Ok(ast) => self.ast = Some(ast),
+            Err(error) => {
+                self.field_state = FieldState::Invalid;
+                self.error_message = Some(error.to_string());
+                return;
+            }
+        }
+
+        let defaults = SliceParameters::default();
+        let error = (sampling_points.get_minimum()..=sampling_points.get_maximum())
+            .find_map(|layer| self.run(layer, &defaults).err());
+        match error {
+            None => self.field_state = FieldState::Changed,
+            Some(error) => {
+                self.field_state = FieldState::Invalid;
+                self.error_message = Some(error);
+            }
+        }
+    }
+
+    /// The algorithm for the layer, None if the field is empty or not valid. Failures mark the
+    ///  field as invalid (then the algorithm of the layer is kept).
+    pub fn eval(&mut self, layer: isize, slice_parameters: &SliceParameters) -> Option<Algorithm> {
+        if self.field_state != FieldState::Changed && self.field_state != FieldState::RunSuccess {
+            return None;
+        }
+
+        match self.run(layer, slice_parameters) {
+            Ok(algorithm) => Some(algorithm),
+            Err(error) => {
+                log_warning(format!(
+                    "Algorithm code `{}` failed ({}), keeping the algorithm of the layer",
+                    self.code, error
+                ));
+                self.field_state = FieldState::Invalid;
+                self.error_message = Some(error);
+                None
+            }
+        }
+    }
+
+    pub fn register_success(&mut self) {
+        if self.field_state == FieldState::Changed {
+            self.field_state = FieldState::RunSuccess;
+        }
+    }
+
+    fn run(&self, layer: isize, slice_parameters: &SliceParameters) -> Result<Algorithm, String> {
+        let Some(ast) = &self.ast else {
+            return Err("The code doesn't compile".to_string());
+        };
+        let mut scope = Scope::new();
+        scope.push_constant("layer", layer as f64);
+        scope.push_constant("l", layer as f64);
+
+        let value = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, ast)
+            .map_err(|error| format!("Error for layer {}: {}", layer, error))?;
+        to_algorithm(value, slice_parameters)
+            .map_err(|error| format!("{} for layer {}", error, layer))
+    }
+}
+
+/// The code of the Custom algorithm (see `CustomPredicate::set_code`), which all layers with the
+///  Custom algorithm share
+pub struct CustomPredicateField {
+    code: String,
+    field_state: FieldState,
+    error_message: Option<String>,
+}
+
+impl Default for CustomPredicateField {
+    fn default() -> Self {
+        Self {
+            code: DEFAULT_CUSTOM_CODE.to_string(),
+            field_state: FieldState::RunSuccess,
+            error_message: None,
+        }
+    }
+}
+
+impl CustomPredicateField {
+    /// Output: whether the code changed and is used from now on (then the layers with the Custom
+    ///  algorithm have to be generated again). Code that doesn't compile is not used.
+    pub fn show(&mut self, ui: &mut Ui, custom_predicate: &mut CustomPredicate) -> bool {
+        if !code_edit(
+            ui,
+            &mut self.code,
+            &self.field_state,
+            self.error_message.as_ref(),
+        ) {
+            return false;
+        }
+
+        match custom_predicate.set_code(&self.code) {
+            Ok(()) => {
+                self.field_state = FieldState::RunSuccess;
+                self.error_message = None;
+                true
+            }
+            Err(error) => {
+                self.field_state = FieldState::Invalid;
+                self.error_message = Some(error);
+                false
+            }
+        }
+    }
+}
+
+/// The algorithm with this name, or the Percentage algorithm for a number between 0 and 1
+fn to_algorithm(value: Dynamic, slice_parameters: &SliceParameters) -> Result<Algorithm, String> {
+    let percentage = if value.is_string() {
+        let name = value.into_string().unwrap_or_default();
+        return match name.to_lowercase().as_str() {
+            "centerpoint" => Ok(Algorithm::Centerpoint),
+            "conservative" => Ok(Algorithm::Conservative(slice_parameters.margin_cache)),
+            "contained" => Ok(Algorithm::Contained(slice_parameters.margin_cache)),
+            "percentage" => Ok(Algorithm::Percentage(
+                slice_parameters.percentage_cache,
+                slice_parameters.coverage_estimator_cache,
+            )),
+            "custom" => Ok(Algorithm::Custom),
+            "empty" => Ok(Algorithm::Empty),
+            _ => Err(format!("Unknown algorithm \"{}\"", name)),
+        };
+    } else if let Ok(percentage) = value.as_float() {
+        percentage
+    } else if let Ok(percentage) = value.as_int() {
+        percentage as f64
+    } else {
+        return Err(format!(
+            "Expected a name or a percentage, not {}",
+            value.type_name()
+        ));
+    };
+
+    if (0.0..=1.0).contains(&percentage) {
+        Ok(Algorithm::Percentage(
+            percentage,
+            slice_parameters.coverage_estimator_cache,
+        ))
+    } else {
+        Err(format!("Percentage {} is not between 0 and 1", percentage))
+    }
+}
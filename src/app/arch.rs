@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use crate::app::data_structures::shape_array::ShapeArray;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+
+/// The curve of an arch, from one endpoint over the top to the other
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum ArchCurve {
+    #[default]
+    Circular, // a segment of a circle (a half circle if the rise is half the span)
+    Parabolic,
+}
+
+impl Display for ArchCurve {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchCurve::Circular => write!(f, "Circular"),
+            ArchCurve::Parabolic => write!(f, "Parabolic"),
+        }
+    }
+}
+
+/// An arch between two points on layer 0, rising `rise` layers in the vertical plane through them.
+///  Each layer is the part of the arch between its bottom and top: two short segments (one on each
+///  side) that meet in a single segment at the top.
+pub struct ArchTool {
+    pub start: [f64; 2], // the endpoints, in viewport coordinates
+    pub end: [f64; 2],
+    pub rise: f64, // height of the top above the endpoints, in layers
+    pub curve: ArchCurve,
+    pub thickness: f64, // horizontal thickness of the arch, along the span
+    pub width: f64,     // across the span (the depth of the arch)
+}
+
+impl Default for ArchTool {
+    fn default() -> Self {
+        Self {
+            start: [-8.0, 0.0],
+            end: [8.0, 0.0],
+            rise: 8.0,
+            curve: ArchCurve::Circular,
+            thickness: 2.0,
+            width: 3.0,
+        }
+    }
+}
+
+impl ArchTool {
+    /// Half of the distance between the endpoints
+    fn half_span(&self) -> f64 {
+        let [x, y] = [self.end[0] - self.start[0], self.end[1] - self.start[1]];
+        x.hypot(y) / 2.0
+    }
+
+    pub fn nr_layers(&self) -> usize {
+        self.rise.ceil().max(1.0) as usize
+    }
+
+    /// Radius of the circle of a circular arch, through the endpoints and the top (its center is
+    ///  below the top by the radius)
+    fn circle_radius(&self) -> f64 {
+        (self.half_span().powi(2) + self.rise.powi(2)) / (2.0 * self.rise)
+    }
+
+    /// Horizontal distance from the middle of the span to the curve at the height (0 at and above
+    ///  the top)
+    fn distance_at(&self, height: f64) -> f64 {
+        let (half_span, rise) = (self.half_span(), self.rise);
+        if height >= rise {
+            return 0.0;
+        }
+        match self.curve {
+            ArchCurve::Circular => {
+                let radius = self.circle_radius();
+                let above_center = height - (rise - radius);
+                (radius.powi(2) - above_center.powi(2)).max(0.0).sqrt()
+            }
+            ArchCurve::Parabolic => half_span * (1.0 - height / rise).sqrt(),
+        }
+    }
+
+    /// The least and greatest distance of the curve from the middle of the span within the layer.
+    ///  A circular arch that rises more than half its span (a horseshoe arch) is widest at the
+    ///  height of the center of its circle, the other arches narrow going up.
+    fn distance_range(&self, layer: usize) -> [f64; 2] {
+        let [bottom, top] = [layer as f64, layer as f64 + 1.0];
+        let mut distances = vec![self.distance_at(bottom), self.distance_at(top)];
+        if self.curve == ArchCurve::Circular {
+            let center = self.rise - self.circle_radius();
+            if bottom < center && center < top {
+                distances.push(self.distance_at(center));
+            }
+        }
+        [
+            distances.iter().copied().fold(f64::INFINITY, f64::min),
+            distances.iter().copied().fold(0.0, f64::max),
+        ]
+    }
+
+    /// The parameters of the layers of the arch (from layer 0 up): thin rectangles along the span
+    ///  (two copies in a linear array where the sides are separate). The rest of the parameters
+    ///  (the algorithm) are those of `base`.
+    pub fn stack_layer_config(&self, base: &SliceParameters) -> ZVec<SliceParameters> {
+        let [dx, dy] = [self.end[0] - self.start[0], self.end[1] - self.start[1]];
+        let length = dx.hypot(dy).max(f64::EPSILON);
+        let direction = [dx / length, dy / length];
+        let middle = [
+            (self.start[0] + self.end[0]) / 2.0,
+            (self.start[1] + self.end[1]) / 2.0,
+        ];
+
+        let layers: VecDeque<SliceParameters> = (0..self.nr_layers())
+            .map(|layer| {
+                // The layer covers the curve between its bottom and top, with at least the
+                //  thickness (of a block or more) at each side
+                let [nearest, furthest] = self.distance_range(layer);
+                let half_thickness = self.thickness.max(1.0) / 2.0;
+                let outer = furthest + half_thickness;
+                let inner = nearest - half_thickness;
+                let slice_parameters = SliceParameters {
+                    radius_b: self.width / 2.0,
+                    tilt: direction[1].atan2(direction[0]),
+                    squircle_parameter: f64::INFINITY, // rectangles
+                    layer: layer as f64,
+                    sector: None,
+                    ..base.clone()
+                };
+
+                if inner <= 0.0 {
+                    // The sides meet
+                    SliceParameters {
+                        radius_a: outer,
+                        center_offset_x: middle[0],
+                        center_offset_y: middle[1],
+                        shape_array: ShapeArray::Single,
+                        ..slice_parameters
+                    }
+                } else {
+                    let distance = (outer + inner) / 2.0;
+                    SliceParameters {
+                        radius_a: (outer - inner) / 2.0,
+                        center_offset_x: middle[0] - distance * direction[0],
+                        center_offset_y: middle[1] - distance * direction[1],
+                        shape_array: ShapeArray::Linear {
+                            count: 2,
+                            spacing: direction.map(|d| 2.0 * distance * d),
+                        },
+                        ..slice_parameters
+                    }
+                }
+            })
+            .collect();
+        ZVec::new(layers, 0)
+    }
+}
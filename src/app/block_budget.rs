@@ -0,0 +1,232 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::generation::Generator;
+use crate::app::sampling::sampled_parameters::LayerParameters;
+use crate::app::sampling::SampleCombineMethod;
+
+// Largest radius scale that is tried when the design is under budget
+const MAX_SCALE: f64 = 16.0;
+// The estimate of the scale is improved until it changes less than this (relative to the scale)
+const SCALE_TOLERANCE: f64 = 1e-4;
+// ... or at most this many times (each time the whole stack is generated)
+const REFINEMENT_STEPS: usize = 6;
+// If no estimate fits, the smallest one is shrunk by this factor at most this many times
+const SHRINK_FACTOR: f64 = 0.8;
+const SHRINK_STEPS: usize = 20;
+
+/// Maximum number of blocks of the whole stack, with a suggestion for scaling the radii so that
+///  the design fits
+pub struct BlockBudget {
+    pub enabled: bool,
+    pub max_blocks: u64,
+    // Largest scale of the radii that fits in the budget, with the number of blocks at that scale
+    pub suggestion: Option<(f64, u64)>,
+}
+
+impl Default for BlockBudget {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_blocks: 1000,
+            suggestion: None,
+        }
+    }
+}
+
+impl BlockBudget {
+    pub fn is_exceeded(&self, nr_blocks: u64) -> bool {
+        self.enabled && nr_blocks > self.max_blocks
+    }
+
+    /// Find the largest scale of the radii of all unlocked layers such that the stack fits in the
+    ///  budget (locked layers keep their blocks, locked radii keep their value). The number of
+    ///  blocks doesn't strictly increase with the radius, so this is the largest scale that was
+    ///  tried, which need not be the largest scale overall.
+    pub fn suggest_scale(
+        &mut self,
+        stack_layer_parameters: &ZVec<LayerParameters>,
+        stack_blocks: &ZVec<Blocks>,
+        stack_locked: &ZVec<bool>,
+        sample_combine_method: &SampleCombineMethod,
+        generator: &Generator,
+        locked_parameters: &[Parameter],
+    ) {
+        let nr_locked_blocks = stack_locked
+            .data
+            .iter()
+            .zip(stack_blocks.data.iter())
+            .filter(|(locked, _)| **locked)
+            .map(|(_, blocks)| blocks.get_nr_blocks())
+            .sum::<u64>();
+        let nr_blocks_at = |scale: f64| {
+            stack_layer_parameters
+                .data
+                .iter()
+                .zip(stack_locked.data.iter())
+                .filter(|(_, locked)| !**locked)
+                .map(|(layer_parameters, _)| {
+                    scale_layer_parameters(layer_parameters, scale, locked_parameters)
+                        .generate(sample_combine_method, generator)
+                        .get_nr_blocks()
+                })
+                .sum::<u64>()
+        };
+        let scale = estimate_fitting_scale(
+            |scale| nr_blocks_at(scale) as f64,
+            nr_locked_blocks as f64,
+            self.max_blocks as f64,
+        );
+        self.suggestion = Some((scale, nr_locked_blocks + nr_blocks_at(scale)));
+    }
+}
+
+/// The largest scale (at most `MAX_SCALE`) at which the amount (the number of blocks or the area
+///  of the stack) is at most the target. The amount is that of the scaled layers at the scale, on
+///  top of the `fixed` amount of the locked layers. It grows about with the square of the scale, so
+///  the scale is estimated from that and improved with the amount at the estimate. This takes a
+///  few evaluations instead of the many of a bisection, each of which can generate the whole stack
+///  (at most `REFINEMENT_STEPS + SHRINK_STEPS` in total, never at scale 0, where the radii would
+///  be degenerate). Output: the largest scale tried that fits (0 if nothing fits).
+pub fn estimate_fitting_scale(amount: impl Fn(f64) -> f64, fixed: f64, target: f64) -> f64 {
+    if fixed > target {
+        return 0.0;
+    }
+
+    let mut best = 0.0;
+    let mut smallest = f64::INFINITY;
+    let mut scale = 1.0;
+    for _ in 0..REFINEMENT_STEPS {
+        let scaled = amount(scale);
+        if scaled + fixed <= target {
+            best = f64::max(best, scale);
+        }
+        smallest = f64::min(smallest, scale);
+        let next = if scaled <= 0.0 {
+            2.0 * scale // nothing that grows yet
+        } else {
+            scale * ((target - fixed) / scaled).sqrt()
+        }
+        .min(MAX_SCALE);
+        if (next - scale).abs() <= SCALE_TOLERANCE * scale {
+            break;
+        }
+        scale = next;
+    }
+
+    // The estimates may all be just too large (the blocks along the boundary don't grow with the
+    //  area), then shrink the smallest one until it fits
+    if best == 0.0 {
+        let mut scale = smallest;
+        for _ in 0..SHRINK_STEPS {
+            scale *= SHRINK_FACTOR;
+            if amount(scale) + fixed <= target {
+                return scale;
+            }
+        }
+    }
+    best
+}
+
+/// Multiply both radii of all samples by `scale` (the center offsets are not scaled)
+pub fn scale_layer_parameters(
+    layer_parameters: &LayerParameters,
+    scale: f64,
+    locked_parameters: &[Parameter],
+) -> LayerParameters {
+    LayerParameters {
+        parameters: layer_parameters
+            .parameters
+            .iter()
+            .map(|slice_parameters| {
+                scale_slice_parameters(slice_parameters, scale, locked_parameters)
+            })
+            .collect(),
+        ..layer_parameters.clone()
+    }
+}
+
+/// Multiply the radii that aren't locked by `scale`
+pub fn scale_slice_parameters(
+    slice_parameters: &SliceParameters,
+    scale: f64,
+    locked_parameters: &[Parameter],
+) -> SliceParameters {
+    let [scale_a, scale_b] = [Parameter::RadiusA, Parameter::RadiusB].map(|parameter| {
+        if locked_parameters.contains(&parameter) {
+            1.0
+        } else {
+            scale
+        }
+    });
+    let [cached_a, cached_b] = slice_parameters.ellipse_radii_cache;
+    SliceParameters {
+        radius_a: slice_parameters.radius_a * scale_a,
+        radius_b: slice_parameters.radius_b * scale_b,
+        ellipse_radii_cache: [cached_a * scale_a, cached_b * scale_b],
+        ..slice_parameters.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_fitting_scale, scale_slice_parameters, BlockBudget, MAX_SCALE};
+    use crate::app::data_structures::parameter_constraint::Parameter;
+    use crate::app::data_structures::slice_parameters::SliceParameters;
+
+    #[test]
+    fn scale_for_quadratic_amount() {
+        // 100 fixed blocks and 50 per unit of scale squared: 1000 blocks at scale sqrt(18)
+        let amount = |scale: f64| 50.0 * scale * scale;
+        let scale = estimate_fitting_scale(amount, 100.0, 1000.0);
+        assert!(100.0 + amount(scale) <= 1000.0);
+        assert!((scale - 18.0_f64.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn scale_for_stepped_amount() {
+        // The number of blocks jumps, so the estimates overshoot and are shrunk until one fits
+        let amount = |scale: f64| (10.0 * scale).ceil().powi(2);
+        let scale = estimate_fitting_scale(amount, 0.0, 50.0);
+        assert!(scale > 0.0 && amount(scale) <= 50.0);
+    }
+
+    #[test]
+    fn scale_is_bounded() {
+        assert_eq!(estimate_fitting_scale(|_| 0.0, 0.0, 10.0), MAX_SCALE);
+        assert_eq!(estimate_fitting_scale(|scale| scale, 20.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn scale_zero_is_not_evaluated() {
+        // At scale 0 the radii are 0, which the layers can't be generated with
+        let amount = |scale: f64| {
+            assert!(scale > 0.0);
+            scale * scale
+        };
+        let scale = estimate_fitting_scale(amount, 5.0, 9.0);
+        assert!((scale - 2.0).abs() < 1e-3);
+        assert_eq!(estimate_fitting_scale(amount, 10.0, 9.0), 0.0);
+    }
+
+    #[test]
+    fn locked_radii_are_not_scaled() {
+        let slice_parameters = SliceParameters {
+            radius_a: 2.0,
+            radius_b: 3.0,
+            ..Default::default()
+        };
+        let scaled = scale_slice_parameters(&slice_parameters, 2.0, &[Parameter::RadiusB]);
+        assert_eq!([scaled.radius_a, scaled.radius_b], [4.0, 3.0]);
+    }
+
+    #[test]
+    fn budget_only_when_enabled() {
+        let mut budget = BlockBudget::default();
+        assert!(!budget.is_exceeded(budget.max_blocks + 1));
+        budget.enabled = true;
+        assert!(!budget.is_exceeded(budget.max_blocks));
+        assert!(budget.is_exceeded(budget.max_blocks + 1));
+    }
+}
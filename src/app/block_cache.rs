@@ -0,0 +1,201 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::generation::{Algorithm, Generator};
+use crate::app::sampling::sampled_parameters::LayerParameters;
+use crate::app::sampling::SampleCombineMethod;
+
+// Keys in the app storage, so that the generated layers are still there on the next start
+pub const BLOCK_CACHE_KEY: &str = "block_cache";
+pub const BLOCK_CACHE_ENABLED_KEY: &str = "block_cache_enabled";
+
+/// Generated layers that are remembered, all of them are forgotten when there are more
+const MAX_CACHED_LAYERS: usize = 256;
+
+/// Generated layers (before the stamps) by a hash of everything they are generated from, so that
+///  a layer that is generated with the same parameters again (after an undo, or on the next start)
+///  isn't generated again. Layers that change get a different hash, so the cache never has to be
+///  invalidated. Layers with the Custom algorithm are left out, since their code isn't part of the
+///  hash (`CustomPredicate` remembers its own blocks).
+pub struct BlockCache {
+    enabled: bool,
+    layers: HashMap<u64, Blocks>,
+}
+
+impl Default for BlockCache {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            layers: HashMap::new(),
+        }
+    }
+}
+
+impl BlockCache {
+    /// The hash of a layer, None if it can't be cached
+    pub fn key(
+        layer_parameters: &LayerParameters,
+        sample_combine_method: &SampleCombineMethod,
+        fixed_grid_size: Option<usize>,
+        generator: &Generator,
+    ) -> Option<u64> {
+        let is_custom = |algorithm: &Algorithm| matches!(algorithm, Algorithm::Custom);
+        if is_custom(&layer_parameters.algorithm)
+            || layer_parameters
+                .parameters
+                .iter()
+                .any(|slice_parameters| is_custom(&slice_parameters.algorithm))
+        {
+            return None;
+        }
+
+        // The debug output has every field, with the floats written out exactly
+        let mut hasher = DefaultHasher::new();
+        format!("{:?}", layer_parameters).hash(&mut hasher);
+        format!("{:?}", sample_combine_method).hash(&mut hasher);
+        fixed_grid_size.hash(&mut hasher);
+        generator.cell_aspect.to_bits().hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    /// The blocks of the layer, generated by `generate` if they aren't remembered
+    pub fn get_or_generate(
+        &mut self,
+        key: Option<u64>,
+        generate: impl FnOnce() -> Blocks,
+    ) -> Blocks {
+        let Some(key) = key.filter(|_| self.enabled) else {
+            return generate();
+        };
+        if let Some(blocks) = self.layers.get(&key) {
+            return blocks.clone();
+        }
+
+        let blocks = generate();
+        if self.layers.len() >= MAX_CACHED_LAYERS {
+            self.layers.clear();
+        }
+        self.layers.insert(key, blocks.clone());
+        blocks
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Forget the layers when the cache is turned off
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.layers.clear();
+        }
+    }
+
+    /// One line per layer: the hash, the grid size and the lengths of the runs of cells that are
+    ///  (alternately) no block and a block
+    pub fn to_storage_string(&self) -> String {
+        self.layers
+            .iter()
+            .map(|(key, blocks)| {
+                let mut runs = vec![];
+                let mut is_block = false;
+                let mut length = 0;
+                for block in &blocks.blocks {
+                    if *block != is_block {
+                        runs.push(length.to_string());
+                        (is_block, length) = (*block, 0);
+                    }
+                    length += 1;
+                }
+                runs.push(length.to_string());
+                format!("{} {} {}", key, blocks.grid_size, runs.join(","))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The layers of `to_storage_string`, lines that can't be read are skipped
+    pub fn from_storage_string(enabled: bool, text: &str) -> Self {
+        let layers = if enabled {
+            text.lines().filter_map(parse_layer).collect()
+        } else {
+            HashMap::new()
+        };
+        Self { enabled, layers }
+    }
+}
+
+fn parse_layer(line: &str) -> Option<(u64, Blocks)> {
+    let mut words = line.split_whitespace();
+    let key = words.next()?.parse().ok()?;
+    let grid_size: usize = words.next()?.parse().ok()?;
+    let mut blocks = Vec::with_capacity(grid_size.pow(2));
+    for (i, length) in words.next()?.split(',').enumerate() {
+        let length: usize = length.parse().ok()?;
+        blocks.extend(std::iter::repeat_n(i % 2 == 1, length));
+    }
+    (blocks.len() == grid_size.pow(2)).then(|| (key, Blocks::new(blocks, grid_size)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockCache;
+    use crate::app::data_structures::blocks::Blocks;
+    use crate::app::generation::{Algorithm, Generator};
+    use crate::app::sampling::sampled_parameters::LayerParameters;
+    use crate::app::sampling::SampleCombineMethod;
+
+    fn key(layer_parameters: &LayerParameters) -> Option<u64> {
+        BlockCache::key(
+            layer_parameters,
+            &SampleCombineMethod::AllSamples,
+            None,
+            &Generator::default(),
+        )
+    }
+
+    #[test]
+    fn key_follows_the_parameters() {
+        let layer_parameters = LayerParameters::default();
+        let mut larger = layer_parameters.clone();
+        larger.parameters[0].radius_a += 0.5;
+        assert_eq!(key(&layer_parameters), key(&layer_parameters.clone()));
+        assert_ne!(key(&layer_parameters), key(&larger));
+
+        let mut custom = layer_parameters;
+        custom.algorithm = Algorithm::Custom;
+        assert_eq!(key(&custom), None);
+    }
+
+    #[test]
+    fn generate_once() {
+        let mut cache = BlockCache::default();
+        let mut nr_generated = 0;
+        for _ in 0..2 {
+            cache.get_or_generate(Some(1), || {
+                nr_generated += 1;
+                Blocks::new(vec![true; 4], 2)
+            });
+        }
+        assert_eq!(nr_generated, 1);
+    }
+
+    #[test]
+    fn storage_string() {
+        let mut cache = BlockCache::default();
+        let blocks = Blocks::new(vec![true, true, false, true], 2);
+        cache.get_or_generate(Some(7), || blocks.clone());
+        assert_eq!(cache.to_storage_string(), "7 2 0,2,1,1");
+
+        let restored = BlockCache::from_storage_string(true, &cache.to_storage_string());
+        assert_eq!(restored.layers.get(&7), Some(&blocks));
+        assert!(BlockCache::from_storage_string(true, "7 2 0,2,1")
+            .layers
+            .is_empty());
+        assert!(BlockCache::from_storage_string(false, "7 2 0,2,1,1")
+            .layers
+            .is_empty());
+    }
+}
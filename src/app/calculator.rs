@@ -0,0 +1,70 @@
+use rhai::{Dynamic, Scope};
+
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::scripting::new_engine;
+
+// More than other code gets (see `new_engine`), since a calculation may go over many values
+const MAX_OPERATIONS: u64 = 1_000_000;
+// Calculations that are kept, the oldest are dropped
+const MAX_HISTORY: usize = 50;
+
+/// Evaluate Rhai expressions next to the shape, e.g. the length of a diagonal or the radius that
+///  gives a certain slope, with the parameters of the current layer as variables
+#[derive(Default)]
+pub struct Calculator {
+    pub expression: String,
+    pub history: Vec<(String, Result<String, String>)>, // expressions and their values, oldest first
+}
+
+impl Calculator {
+    /// Evaluate the expression, add it to the history and clear it. The parameters of the current
+    ///  layer are the variables `radius_a`, `radius_b`, `tilt`, `center_offset_x`,
+    ///  `center_offset_y` and `squircle_parameter`, the layer number is `layer` (or `l`) and the
+    ///  last value (of an expression without errors) is `ans`.
+    pub fn evaluate(&mut self, slice_parameters: &SliceParameters, layer: isize) {
+        if self.expression.trim().is_empty() {
+            return;
+        }
+
+        let mut engine = new_engine();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let mut scope = Scope::new();
+        for (name, value) in [
+            ("radius_a", slice_parameters.radius_a),
+            ("radius_b", slice_parameters.radius_b),
+            ("tilt", slice_parameters.tilt),
+            ("center_offset_x", slice_parameters.center_offset_x),
+            ("center_offset_y", slice_parameters.center_offset_y),
+            ("squircle_parameter", slice_parameters.squircle_parameter),
+        ] {
+            scope.push_constant(name, value);
+        }
+        scope.push_constant("layer", layer as i64);
+        scope.push_constant("l", layer as i64);
+        if let Some(ans) = self
+            .history
+            .iter()
+            .rev()
+            .find_map(|(_, value)| value.as_ref().ok())
+        {
+            // Numbers stay numbers, anything else is text
+            let ans = ans
+                .parse::<i64>()
+                .map(Dynamic::from_int)
+                .or_else(|_| ans.parse::<f64>().map(Dynamic::from_float))
+                .unwrap_or_else(|_| Dynamic::from(ans.clone()));
+            scope.push_constant("ans", ans);
+        }
+
+        let value = engine
+            .eval_with_scope::<Dynamic>(&mut scope, &self.expression)
+            .map(|value| value.to_string())
+            .map_err(|error| error.to_string());
+
+        self.history
+            .push((std::mem::take(&mut self.expression), value));
+        let nr_dropped = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..nr_dropped);
+    }
+}
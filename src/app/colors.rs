@@ -4,7 +4,14 @@ use eframe::egui::Color32;
 
 /// Viewport
 pub const COLOR_VIEWPORT_BACKGROUND: Color32 = Color32::from_rgb(25, 25, 25);
+pub const COLOR_VIEWPORT_BACKGROUND_LIGHT: Color32 = Color32::from_rgb(236, 236, 236);
+// Lines drawn on the viewport should have at least this contrast ratio with the background
+const MIN_CONTRAST_RATIO: f64 = 3.0;
 pub const COLOR_WIRE: Color32 = Color32::from_rgb(33, 33, 33);
+// default grid, lighter for the coarser lines (for counting)
+pub const COLOR_GRID_FINE: Color32 = Color32::from_rgb(48, 48, 48);
+pub const COLOR_GRID_MEDIUM: Color32 = Color32::from_rgb(72, 72, 72);
+pub const COLOR_GRID_COARSE: Color32 = Color32::from_rgb(100, 100, 100);
 
 // match target shape
 pub const COLOR_CENTER_DOT: Color32 = Color32::from_rgb(255, 255, 255);
@@ -20,23 +27,82 @@ pub const COLOR_OUTER_CORNERS: Color32 = Color32::from_rgb(162, 67, 10);
 // yellow
 pub const COLOR_BOUNDS: Color32 = Color32::from_rgb(111, 101, 15);
 pub const COLOR_MIRRORS: Color32 = Color32::from_rgb(205, 169, 43);
+// the octant boundaries and counts, a shade lighter than the mirrors
+pub const COLOR_OCTANTS: Color32 = Color32::from_rgb(225, 200, 110);
+// red like the north of a compass needle
+pub const COLOR_NORTH_ARROW: Color32 = Color32::from_rgb(220, 60, 60);
+// cyan line for the plane of the side view
+pub const COLOR_VERTICAL_SLICE: Color32 = Color32::from_rgb(64, 200, 220);
+// bright yellow outline around the part of the window explained by the tutorial
+pub const COLOR_TUTORIAL_HIGHLIGHT: Color32 = Color32::from_rgb(255, 210, 60);
+// red warning for blocks that only touch diagonally
+pub const COLOR_THIN_NECKS: Color32 = Color32::from_rgb(235, 70, 50);
+// light green, stands out against the purple boundary
+pub const COLOR_BOUNDARY_TOUR: Color32 = Color32::from_rgb(120, 220, 120);
 
 pub const COLOR_TARGET_SHAPE: Color32 = Color32::from_rgb(255, 255, 255);
+// dimmer than the target shape so the two can be told apart when they're close
+pub const COLOR_PINNED_OUTLINE: Color32 = Color32::from_rgb(240, 190, 90);
+// shape and provisional blocks at a height between two layers
+pub const COLOR_BETWEEN_LAYERS: Color32 = Color32::from_rgb(120, 210, 200);
+// points clicked to construct a shape, and the shape through them
+pub const COLOR_CLICKED_POINTS: Color32 = Color32::from_rgb(240, 120, 200);
+// text stamp that is being placed
+pub const COLOR_STAMP: Color32 = Color32::from_rgb(250, 150, 60);
+// axis of the leaning stack, through the centers of the layers
+pub const COLOR_LEAN_AXIS: Color32 = Color32::from_rgb(255, 100, 100);
 pub const COLOR_SAMPLE_A: Color32 = Color32::from_rgb(200, 200, 200);
 pub const COLOR_SAMPLE_B: Color32 = Color32::from_rgb(200, 200, 200);
 
 /// Blocks (in viewport)
 /// These should all have the same lightness as COLOR_BLOCKS (which is 73.3 in Gimp)
 pub const COLOR_BLOCKS: Color32 = Color32::from_rgb(170, 170, 170);
+// highlighted by the block pattern
+pub const COLOR_BLOCKS_PATTERN: Color32 = Color32::from_rgb(125, 125, 125);
+// blocks deepest inside the stack, with depth shading
+pub const COLOR_DEPTH_SHADE: Color32 = Color32::from_rgb(60, 60, 60);
 // purple for boundary, blue for interior. hotter for 3d
 pub const COLOR_BOUNDARY_2D: Color32 = Color32::from_rgb(220, 122, 246);
 pub const COLOR_BOUNDARY_3D: Color32 = Color32::from_rgb(231, 122, 208);
 pub const COLOR_INTERIOR_2D: Color32 = Color32::from_rgb(135, 156, 230);
 pub const COLOR_INTERIOR_3D: Color32 = Color32::from_rgb(155, 139, 241);
+// dark blue-gray for the footprint of all layers (drawn behind the blocks), green for the part
+//  that all layers have in common
+pub const COLOR_PROJECTION_UNION: Color32 = Color32::from_rgb(82, 88, 112);
+pub const COLOR_PROJECTION_INTERSECTION: Color32 = Color32::from_rgb(134, 184, 142);
 // dark cyan
 pub const COLOR_COMPLEMENT_2D: Color32 = Color32::from_rgb(27, 73, 72);
 // yellow to match the bounds and mirrors
+// Enclosed air
+pub const COLOR_HOLES_2D: Color32 = Color32::from_rgb(70, 170, 210);
+pub const COLOR_HOLES_3D: Color32 = Color32::from_rgb(40, 120, 190);
+
 pub const COLOR_CENTER_BLOCKS: Color32 = Color32::from_rgb(204, 177, 82);
+// Slabs and stairs, as seen from above: darker where the block is full height
+pub const COLOR_SUB_BLOCK_FULL: Color32 = Color32::from_rgb(96, 132, 170);
+pub const COLOR_SUB_BLOCK_BOTTOM: Color32 = Color32::from_rgb(168, 196, 224);
+pub const COLOR_SUB_BLOCK_TOP: Color32 = Color32::from_rgb(132, 112, 170);
+// brick red for the supports inside the shape
+pub const COLOR_SUPPORTS: Color32 = Color32::from_rgb(178, 86, 64);
+
+// Components (boundary segments, structures) get a color from `component_palette`. The hues are
+//  spread evenly at this lightness and chroma (in Oklab)
+const PALETTE_LIGHTNESS: [f64; 2] = [0.78, 0.66]; // alternated if there are many colors
+const PALETTE_CHROMA: f64 = 0.1;
+// Okabe-Ito colors (without black), distinguishable with the common types of color blindness
+const COLORS_COLOR_BLIND_SAFE: [Color32; 7] = [
+    Color32::from_rgb(230, 159, 0),
+    Color32::from_rgb(86, 180, 233),
+    Color32::from_rgb(0, 158, 115),
+    Color32::from_rgb(240, 228, 66),
+    Color32::from_rgb(0, 114, 178),
+    Color32::from_rgb(213, 94, 0),
+    Color32::from_rgb(204, 121, 167),
+];
+
+// distance bands go from warm near the center to cold at the outside
+pub const COLOR_BAND_INNER: Color32 = Color32::from_rgb(230, 160, 100);
+pub const COLOR_BAND_OUTER: Color32 = Color32::from_rgb(100, 150, 230);
 
 /// convex combination in RGB
 pub fn linear_gradient(color_a: Color32, color_b: Color32, t: f64) -> Color32 {
@@ -60,3 +126,102 @@ pub fn bilinear_gradient(color_a: Color32, color_b: Color32, color_c: Color32, t
         linear_gradient(color_b, color_c, 2.0 * t - 1.0)
     }
 }
+
+/// Color from Oklab lightness, chroma and hue (radians), clamped to the sRGB gamut
+fn from_oklch(lightness: f64, chroma: f64, hue: f64) -> Color32 {
+    let (a, b) = (chroma * hue.cos(), chroma * hue.sin());
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+    let gamma = |c: f64| {
+        let c = c.clamp(0.0, 1.0);
+        let c = if c <= 0.0031308 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (255.0 * c).round() as u8
+    };
+    Color32::from_rgb(
+        gamma(4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s),
+        gamma(-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s),
+        gamma(-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s),
+    )
+}
+
+/// `n` colors that are far apart, to tell components apart. The hues are evenly spaced at equal
+///  perceived lightness, so the hue difference between any two colors is at least 360°/n (above 6
+///  colors the lightness alternates as well). The color blind safe palette has only 7 colors,
+///  these repeat for larger `n`.
+pub fn component_palette(n: usize, color_blind_safe: bool) -> Vec<Color32> {
+    if color_blind_safe {
+        return COLORS_COLOR_BLIND_SAFE
+            .iter()
+            .cycle()
+            .take(n.max(1))
+            .copied()
+            .collect();
+    }
+
+    let n = n.max(1);
+    (0..n)
+        .map(|i| {
+            let lightness = if n > 6 {
+                PALETTE_LIGHTNESS[i % 2]
+            } else {
+                PALETTE_LIGHTNESS[0]
+            };
+            // start at red
+            let hue = 0.5 + std::f64::consts::TAU * i as f64 / n as f64;
+            from_oklch(lightness, PALETTE_CHROMA, hue)
+        })
+        .collect()
+}
+
+/// Background of the plots for the current theme
+pub fn viewport_background(dark_mode: bool) -> Color32 {
+    if dark_mode {
+        COLOR_VIEWPORT_BACKGROUND
+    } else {
+        COLOR_VIEWPORT_BACKGROUND_LIGHT
+    }
+}
+
+/// Relative luminance as in the WCAG definition of contrast
+fn relative_luminance(color: Color32) -> f64 {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(color.r()) + 0.7152 * linear(color.g()) + 0.0722 * linear(color.b())
+}
+
+/// Contrast ratio between 1 (same luminance) and 21 (black on white)
+pub fn contrast_ratio(color_a: Color32, color_b: Color32) -> f64 {
+    let l_a = relative_luminance(color_a);
+    let l_b = relative_luminance(color_b);
+    (l_a.max(l_b) + 0.05) / (l_a.min(l_b) + 0.05)
+}
+
+/// Mix the color with black (on a light background) or white (on a dark background) until it has
+///  enough contrast with the background to be readable
+pub fn with_contrast(color: Color32, background: Color32) -> Color32 {
+    let extreme = if relative_luminance(background) > 0.5 {
+        Color32::BLACK
+    } else {
+        Color32::WHITE
+    };
+
+    let mut t = 0.0;
+    let mut adjusted = color;
+    while contrast_ratio(adjusted, background) < MIN_CONTRAST_RATIO && t < 1.0 {
+        t += 0.1;
+        adjusted = linear_gradient(color, extreme, t);
+    }
+    adjusted
+}
@@ -21,6 +21,11 @@ impl Control {
         self.is_outdated = true;
     }
 
+    /// Is an update allowed that has not happened yet? Then another frame is needed.
+    pub fn is_pending(&self) -> bool {
+        self.is_outdated && (self.do_once || self.do_auto)
+    }
+
     /// Return true if it makes sense to update on this frame.
     pub fn update(&mut self) -> bool {
         self.do_once = false;
@@ -2,6 +2,8 @@ use crate::app::data_structures::symmetry_type::SymmetryType;
 use crate::app::math::linear_algebra::Vec2;
 use crate::app::sampling::SampleCombineMethod;
 use itertools::Itertools;
+use std::borrow::Borrow;
+use std::f64::consts::PI;
 
 /// Captures a bit matrix. The length of the vector should always be edge_length**2
 #[derive(Default, Debug, Clone, PartialEq)]
@@ -186,6 +188,39 @@ impl Blocks {
 
         output
     }
+
+    /// Split the blocks into pieces that are connected through the sides of the blocks. Each piece
+    ///  is given by the global coordinates (of the left bottom corners) of its blocks.
+    pub fn get_connected_components(&self) -> Vec<Vec<[isize; 2]>> {
+        let mut visited = vec![false; self.grid_size.pow(2)];
+        let mut components = vec![];
+
+        for start in 0..self.grid_size.pow(2) {
+            if !self.blocks[start] || visited[start] {
+                continue;
+            }
+
+            // Depth first search from the start block
+            visited[start] = true;
+            let mut stack = vec![start];
+            let mut component = vec![];
+            while let Some(i) = stack.pop() {
+                let [x, y] = self.get_global_coord_usize_from_index(i);
+                component.push([x, y]);
+                for neighbor in [[x + 1, y], [x - 1, y], [x, y + 1], [x, y - 1]] {
+                    if let Some(j) = self.get_index_from_global_coord_usize(neighbor) {
+                        if self.blocks[j] && !visited[j] {
+                            visited[j] = true;
+                            stack.push(j);
+                        }
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        components
+    }
 }
 
 /// Methods for getting bounds
@@ -290,9 +325,9 @@ impl Blocks {
 impl Blocks {
     /// A block is in the output iff there is a block at the same global position for any layer in
     ///  the input.
-    fn combine_any(stack: Vec<Self>) -> Self {
+    fn combine_any(stack: &[impl Borrow<Self>]) -> Self {
         // determine largest grid size
-        let grid_size = stack.iter().map(|b| b.grid_size).max().unwrap();
+        let grid_size = stack.iter().map(|b| b.borrow().grid_size).max().unwrap();
         // throws an error only if the vector above is empty
         let origin_usize = [grid_size / 2, grid_size / 2];
 
@@ -306,7 +341,7 @@ impl Blocks {
 
                     stack
                         .iter()
-                        .map(|b| b.is_block_on_global_coord(global_coord))
+                        .map(|b| b.borrow().is_block_on_global_coord(global_coord))
                         .fold(false, |a, b| a || b)
                 })
                 .collect(),
@@ -316,9 +351,9 @@ impl Blocks {
 
     /// A block is in the output iff for every layer in the input, there is a block at the same
     ///  global position
-    fn combine_all(stack: Vec<Self>) -> Self {
+    fn combine_all(stack: &[impl Borrow<Self>]) -> Self {
         // determine largest grid size
-        let grid_size = stack.iter().map(|b| b.grid_size).max().unwrap();
+        let grid_size = stack.iter().map(|b| b.borrow().grid_size).max().unwrap();
         // throws an error only if the vector above is empty
         let origin_usize = [grid_size / 2, grid_size / 2];
 
@@ -332,7 +367,7 @@ impl Blocks {
 
                     stack
                         .iter()
-                        .map(|b| b.is_block_on_global_coord(global_coord))
+                        .map(|b| b.borrow().is_block_on_global_coord(global_coord))
                         .fold(true, |a, b| a && b)
                 })
                 .collect(),
@@ -342,10 +377,10 @@ impl Blocks {
 
     /// A block is in the output iff there is a block at the same global position for more than the
     ///  given percentage of layers
-    fn combine_percentage(stack: Vec<Self>, percentage: f64) -> Self {
+    fn combine_percentage(stack: &[impl Borrow<Self>], percentage: f64) -> Self {
         // determine the largest grid size & associated origin
         // throws an error only if the vector above is empty
-        let grid_size = stack.iter().map(|b| b.grid_size).max().unwrap();
+        let grid_size = stack.iter().map(|b| b.borrow().grid_size).max().unwrap();
         let origin_usize = [grid_size / 2, grid_size / 2];
         // determine target number of layers (we specifically allow any f64 for percentage, but
         //  the output will be trivial for it not between zero and one).
@@ -361,7 +396,7 @@ impl Blocks {
 
                     stack
                         .iter()
-                        .map(|b| b.is_block_on_global_coord(global_coord))
+                        .map(|b| b.borrow().is_block_on_global_coord(global_coord))
                         .fold(0.0, |a, b| a + (b as usize) as f64)
                         >= target_nr_layers
                 })
@@ -370,7 +405,11 @@ impl Blocks {
         )
     }
 
-    pub fn combine(sample_combine_method: &SampleCombineMethod, stack: Vec<Self>) -> Self {
+    /// Combine the layers (owned or borrowed) into one
+    pub fn combine(
+        sample_combine_method: &SampleCombineMethod,
+        stack: &[impl Borrow<Self>],
+    ) -> Self {
         match sample_combine_method {
             SampleCombineMethod::AllSamples => Self::combine_all(stack),
             SampleCombineMethod::AnySamples => Self::combine_any(stack),
@@ -383,6 +422,45 @@ impl Blocks {
 
 /// Methods for modifying blocks (flipping and rotating)
 impl Blocks {
+    /// The same blocks on a grid of the given size (centered at the origin as usual). Blocks that
+    ///  fall outside of a smaller grid are cut off.
+    pub fn with_grid_size(&self, grid_size: usize) -> Self {
+        let origin_usize = [grid_size / 2, grid_size / 2];
+
+        Blocks::new(
+            (0..grid_size.pow(2))
+                .map(|i| {
+                    self.is_block_on_global_coord([
+                        (i % grid_size) as isize - (origin_usize[0] as isize),
+                        (i / grid_size) as isize - (origin_usize[1] as isize),
+                    ])
+                })
+                .collect(),
+            grid_size,
+        )
+    }
+
+    /// Keep only the blocks whose center is in the sector (pie slice) around `center` from
+    ///  `angles[0]` to `angles[1]` (radians, counterclockwise from the positive x-axis).
+    pub fn restrict_to_sector(&self, center: [f64; 2], angles: [f64; 2]) -> Self {
+        let sector_width = angles[1] - angles[0];
+
+        Blocks::new(
+            (0..self.grid_size.pow(2))
+                .map(|index| {
+                    let [x, y] = self.get_global_coord_usize_from_index(index);
+                    let [dx, dy] = [x as f64 + 0.5 - center[0], y as f64 + 0.5 - center[1]];
+                    let in_sector = sector_width >= 2.0 * PI
+                        || (dx == 0.0 && dy == 0.0)
+                        || (dy.atan2(dx) - angles[0]).rem_euclid(2.0 * PI)
+                            <= sector_width.rem_euclid(2.0 * PI);
+                    self.blocks[index] && in_sector
+                })
+                .collect(),
+            self.grid_size,
+        )
+    }
+
     /// Flip the blocks along the vertical axis through the center of the bounds.
     fn flip_horizontal(&self, bounds: [[isize; 2]; 2]) -> Self {
         let [[_, y_1], [_, y_2]] = bounds;
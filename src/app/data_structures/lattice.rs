@@ -0,0 +1,55 @@
+use std::fmt::{Display, Formatter};
+
+/// The two square lattices of the grid: the corners of the blocks (whole coordinates) and the
+///  centers of the blocks (halfway in between). Walls in Minecraft are often thought of as on the
+///  centers of a row of blocks, shapes centered there are "odd".
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum Lattice {
+    #[default]
+    Corners,
+    Centers,
+}
+
+impl Lattice {
+    /// How far the lattice is from the whole coordinates
+    pub fn offset(&self) -> f64 {
+        match self {
+            Lattice::Corners => 0.0,
+            Lattice::Centers => 0.5,
+        }
+    }
+
+    /// The nearest coordinate on the lattice
+    pub fn snap(&self, coordinate: f64) -> f64 {
+        (coordinate - self.offset()).round() + self.offset()
+    }
+}
+
+impl Display for Lattice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Lattice::Corners => write!(f, "Block corners"),
+            Lattice::Centers => write!(f, "Block centers"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lattice;
+
+    #[test]
+    fn snap_to_corners() {
+        assert_eq!(Lattice::Corners.snap(2.3), 2.0);
+        assert_eq!(Lattice::Corners.snap(-0.7), -1.0);
+        assert_eq!(Lattice::Corners.snap(4.0), 4.0);
+    }
+
+    #[test]
+    fn snap_to_centers() {
+        assert_eq!(Lattice::Centers.snap(2.3), 2.5);
+        assert_eq!(Lattice::Centers.snap(-0.7), -0.5);
+        assert_eq!(Lattice::Centers.snap(4.0), 4.5);
+        assert_eq!(Lattice::Centers.snap(-1.5), -1.5);
+    }
+}
@@ -0,0 +1,18 @@
+/// Named marker on a layer of the stack (like "dome start"), to jump to from the navigation bar
+#[derive(Debug, Clone)]
+pub struct LayerBookmark {
+    pub name: String,
+    pub layer: isize,
+}
+
+/// Add a bookmark, keeping the bookmarks sorted by layer (then by name). An empty name is
+///  replaced by the layer number.
+pub fn add_bookmark(bookmarks: &mut Vec<LayerBookmark>, name: &str, layer: isize) {
+    let name = if name.trim().is_empty() {
+        format!("Layer {}", layer)
+    } else {
+        name.trim().to_string()
+    };
+    bookmarks.push(LayerBookmark { name, layer });
+    bookmarks.sort_by(|a, b| a.layer.cmp(&b.layer).then(a.name.cmp(&b.name)));
+}
@@ -0,0 +1,114 @@
+/// Consecutive layers (like a floor of a building) that share one set of parameters: editing one
+///  of them edits all of them. Layers can override the group to keep their own parameters.
+#[derive(Debug, Clone)]
+pub struct LayerGroup {
+    pub name: String,
+    pub layers: [isize; 2],    // lowest and highest layer (inclusive)
+    pub overrides: Vec<isize>, // layers of the group with their own parameters
+    pub source: isize,         // the layer whose parameters are shared (the last one edited)
+}
+
+impl LayerGroup {
+    pub fn contains(&self, layer: isize) -> bool {
+        (self.layers[0]..=self.layers[1]).contains(&layer)
+    }
+
+    /// Does the layer have the parameters of the group?
+    pub fn shares(&self, layer: isize) -> bool {
+        self.contains(layer) && !self.overrides.contains(&layer)
+    }
+
+    /// Move the group `offset` layers up (down for a negative offset)
+    pub fn shift(&mut self, offset: isize) {
+        self.layers = self.layers.map(|layer| layer + offset);
+        for layer in self.overrides.iter_mut() {
+            *layer += offset;
+        }
+        self.source += offset;
+    }
+
+    /// The group at the layers `mirror(layer)`, for a `mirror` that reverses the order of the
+    ///  layers
+    pub fn mirrored(&self, mirror: impl Fn(isize) -> isize) -> LayerGroup {
+        let mut overrides: Vec<isize> = self.overrides.iter().map(|layer| mirror(*layer)).collect();
+        overrides.sort();
+        LayerGroup {
+            name: self.name.clone(),
+            layers: [mirror(self.layers[1]), mirror(self.layers[0])],
+            overrides,
+            source: mirror(self.source),
+        }
+    }
+
+    /// Keep only the layers of the group from `lowest` to `highest` (after the stack was resized).
+    ///  Output: whether any layer is left.
+    pub fn clip(&mut self, [lowest, highest]: [isize; 2]) -> bool {
+        self.layers = [self.layers[0].max(lowest), self.layers[1].min(highest)];
+        if self.layers[0] > self.layers[1] {
+            return false;
+        }
+        self.overrides
+            .retain(|layer| self.layers[0] <= *layer && *layer <= self.layers[1]);
+        if !self.contains(self.source) {
+            self.source = (self.layers[0]..=self.layers[1])
+                .find(|layer| self.shares(*layer))
+                .unwrap_or(self.layers[0]);
+        }
+        true
+    }
+
+    /// Let the layer keep its own parameters, or give it the parameters of the group again
+    pub fn toggle_override(&mut self, layer: isize) {
+        if let Some(index) = self.overrides.iter().position(|l| *l == layer) {
+            self.overrides.remove(index);
+        } else {
+            self.overrides.push(layer);
+            self.overrides.sort();
+            if layer == self.source {
+                self.source = (self.layers[0]..=self.layers[1])
+                    .find(|layer| self.shares(*layer))
+                    .unwrap_or(layer);
+            }
+        }
+    }
+}
+
+/// Add a group of the layers from `lowest` to `highest` with the parameters of `source` (or of the
+///  lowest layer if `source` is not in the group), keeping the groups sorted by layer. A layer can
+///  only be in one group. An empty name is replaced by the layers.
+pub fn add_group(
+    groups: &mut Vec<LayerGroup>,
+    name: &str,
+    [lowest, highest]: [isize; 2],
+    source: isize,
+) -> Result<(), String> {
+    let layers = [lowest.min(highest), lowest.max(highest)];
+    if let Some(other) = groups
+        .iter()
+        .find(|group| group.layers[0] <= layers[1] && layers[0] <= group.layers[1])
+    {
+        return Err(format!(
+            "Layers {} to {} overlap with {}",
+            layers[0], layers[1], other.name
+        ));
+    }
+
+    let name = if name.trim().is_empty() {
+        format!("Layers {} to {}", layers[0], layers[1])
+    } else {
+        name.trim().to_string()
+    };
+    let source = if (layers[0]..=layers[1]).contains(&source) {
+        source
+    } else {
+        layers[0]
+    };
+    groups.push(LayerGroup {
+        name,
+        layers,
+        overrides: vec![],
+        source,
+    });
+    groups.sort_by_key(|group| group.layers[0]);
+    Ok(())
+}
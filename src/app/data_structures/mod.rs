@@ -1,5 +1,10 @@
 pub mod blocks;
+pub mod lattice;
+pub mod layer_bookmark;
+pub mod layer_group;
+pub mod parameter_constraint;
+pub mod shape_array;
 pub mod slice_parameters;
+pub mod sparse_blocks;
 pub(crate) mod symmetry_type;
 pub mod zvec;
-mod sparse_blocks;
@@ -0,0 +1,217 @@
+use std::fmt::{Display, Formatter};
+
+use crate::app::data_structures::slice_parameters::SliceParameters;
+
+/// The fields of `SliceParameters` that can be locked (the ones in `ALL` can also be constrained)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Parameter {
+    RadiusA,
+    RadiusB,
+    Tilt,
+    CenterOffsetX,
+    CenterOffsetY,
+    SquircleParameter,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 5] = [
+        Parameter::RadiusA,
+        Parameter::RadiusB,
+        Parameter::Tilt,
+        Parameter::CenterOffsetX,
+        Parameter::CenterOffsetY,
+    ];
+
+    pub fn get(&self, slice_parameters: &SliceParameters) -> f64 {
+        match self {
+            Parameter::RadiusA => slice_parameters.radius_a,
+            Parameter::RadiusB => slice_parameters.radius_b,
+            Parameter::Tilt => slice_parameters.tilt,
+            Parameter::CenterOffsetX => slice_parameters.center_offset_x,
+            Parameter::CenterOffsetY => slice_parameters.center_offset_y,
+            Parameter::SquircleParameter => slice_parameters.squircle_parameter,
+        }
+    }
+
+    pub fn get_mut<'a>(&self, slice_parameters: &'a mut SliceParameters) -> &'a mut f64 {
+        match self {
+            Parameter::RadiusA => &mut slice_parameters.radius_a,
+            Parameter::RadiusB => &mut slice_parameters.radius_b,
+            Parameter::Tilt => &mut slice_parameters.tilt,
+            Parameter::CenterOffsetX => &mut slice_parameters.center_offset_x,
+            Parameter::CenterOffsetY => &mut slice_parameters.center_offset_y,
+            Parameter::SquircleParameter => &mut slice_parameters.squircle_parameter,
+        }
+    }
+}
+
+/// Lock the target parameter to `factor * source + offset`, e.g. radius_b = 1.5 * radius_a for
+///  an ellipse with a fixed ratio
+#[derive(Debug, Clone, Copy)]
+pub struct ParameterConstraint {
+    pub target: Parameter,
+    pub source: Parameter,
+    pub factor: f64,
+    pub offset: f64,
+}
+
+impl Default for ParameterConstraint {
+    fn default() -> Self {
+        Self {
+            target: Parameter::RadiusB,
+            source: Parameter::RadiusA,
+            factor: 1.0,
+            offset: 0.0,
+        }
+    }
+}
+
+impl ParameterConstraint {
+    /// Set the target from the source, return true if the target changed
+    pub fn apply(&self, slice_parameters: &mut SliceParameters) -> bool {
+        let value = self.factor * self.source.get(slice_parameters) + self.offset;
+        let target = self.target.get_mut(slice_parameters);
+        let changed = *target != value;
+        *target = value;
+        changed
+    }
+}
+
+/// Apply the constraints in order (so a constraint can use the target of an earlier one as its
+///  source), return true if any parameter changed
+pub fn apply_constraints(
+    constraints: &[ParameterConstraint],
+    slice_parameters: &mut SliceParameters,
+) -> bool {
+    let mut changed = false;
+    for constraint in constraints {
+        changed |= constraint.apply(slice_parameters);
+    }
+    changed
+}
+
+/// Do the constraints depend on each other in a loop (or a parameter on itself)? The constraints
+///  are applied every frame, so the parameters in a loop would keep growing or shrinking.
+pub fn has_cycle(constraints: &[ParameterConstraint]) -> bool {
+    // Drop the constraints whose source is not set by another constraint, until none are left or
+    //  the rest depend on each other
+    let mut remaining = constraints.to_vec();
+    while !remaining.is_empty() {
+        let targets: Vec<Parameter> = remaining
+            .iter()
+            .map(|constraint| constraint.target)
+            .collect();
+        let nr_remaining = remaining.len();
+        remaining.retain(|constraint| targets.contains(&constraint.source));
+        if remaining.len() == nr_remaining {
+            return true;
+        }
+    }
+    false
+}
+
+/// A constraint that can be added without making a loop (the default one if possible)
+pub fn new_constraint(constraints: &[ParameterConstraint]) -> Option<ParameterConstraint> {
+    let candidates = Parameter::ALL.iter().flat_map(|target| {
+        Parameter::ALL.iter().map(|source| ParameterConstraint {
+            target: *target,
+            source: *source,
+            ..Default::default()
+        })
+    });
+    std::iter::once(ParameterConstraint::default())
+        .chain(candidates)
+        .find(|constraint| {
+            let mut with_constraint = constraints.to_vec();
+            with_constraint.push(*constraint);
+            !has_cycle(&with_constraint)
+        })
+}
+
+/// Is the parameter the target of some constraint? (Then it shouldn't be set by hand)
+pub fn is_constrained(constraints: &[ParameterConstraint], parameter: Parameter) -> bool {
+    constraints
+        .iter()
+        .any(|constraint| constraint.target == parameter)
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Parameter::RadiusA => write!(f, "Radius A"),
+            Parameter::RadiusB => write!(f, "Radius B"),
+            Parameter::Tilt => write!(f, "Tilt"),
+            Parameter::CenterOffsetX => write!(f, "x offset"),
+            Parameter::CenterOffsetY => write!(f, "y offset"),
+            Parameter::SquircleParameter => write!(f, "Squircle parameter"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_constraints, has_cycle, new_constraint, Parameter, ParameterConstraint};
+    use crate::app::data_structures::slice_parameters::SliceParameters;
+
+    fn constraint(target: Parameter, source: Parameter) -> ParameterConstraint {
+        ParameterConstraint {
+            target,
+            source,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn chain_has_no_cycle() {
+        assert!(!has_cycle(&[]));
+        assert!(!has_cycle(&[
+            constraint(Parameter::RadiusB, Parameter::RadiusA),
+            constraint(Parameter::CenterOffsetX, Parameter::RadiusB),
+        ]));
+    }
+
+    #[test]
+    fn loops_are_cycles() {
+        assert!(has_cycle(&[constraint(Parameter::Tilt, Parameter::Tilt)]));
+        assert!(has_cycle(&[
+            constraint(Parameter::RadiusB, Parameter::RadiusA),
+            constraint(Parameter::CenterOffsetX, Parameter::CenterOffsetY),
+            constraint(Parameter::RadiusA, Parameter::RadiusB),
+        ]));
+    }
+
+    #[test]
+    fn new_constraint_avoids_cycles() {
+        let constraints = [constraint(Parameter::RadiusA, Parameter::RadiusB)];
+        let new = new_constraint(&constraints).unwrap();
+        assert!(!has_cycle(&[constraints[0], new]));
+    }
+
+    #[test]
+    fn constraints_apply_in_order() {
+        let constraints = [
+            ParameterConstraint {
+                target: Parameter::RadiusB,
+                source: Parameter::RadiusA,
+                factor: 1.5,
+                offset: 0.0,
+            },
+            ParameterConstraint {
+                target: Parameter::CenterOffsetX,
+                source: Parameter::RadiusB,
+                factor: 1.0,
+                offset: -1.0,
+            },
+        ];
+        let mut slice_parameters = SliceParameters {
+            radius_a: 4.0,
+            ..Default::default()
+        };
+
+        assert!(apply_constraints(&constraints, &mut slice_parameters));
+        assert_eq!(slice_parameters.radius_b, 6.0);
+        assert_eq!(slice_parameters.center_offset_x, 5.0);
+        // Applying them again changes nothing
+        assert!(!apply_constraints(&constraints, &mut slice_parameters));
+    }
+}
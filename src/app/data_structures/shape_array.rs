@@ -0,0 +1,61 @@
+use std::fmt::{Display, Formatter};
+
+use crate::app::data_structures::slice_parameters::SliceParameters;
+
+/// Repetition of the shape of a layer. Every copy is generated separately and the union of the
+///  copies is the shape of the layer.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum ShapeArray {
+    #[default]
+    Single,
+    // Copies rotated around the origin, each by `angle_step` (radians) more than the previous one
+    Radial {
+        count: usize,
+        angle_step: f64,
+    },
+    // Copies shifted, each by `spacing` more than the previous one
+    Linear {
+        count: usize,
+        spacing: [f64; 2],
+    },
+}
+
+impl ShapeArray {
+    /// The parameters of all copies of the shape (the first one is the shape itself)
+    pub fn get_copies(&self, slice_parameters: &SliceParameters) -> Vec<SliceParameters> {
+        match *self {
+            ShapeArray::Single => vec![slice_parameters.clone()],
+            ShapeArray::Radial { count, angle_step } => (0..count.max(1))
+                .map(|k| {
+                    let angle = k as f64 * angle_step;
+                    let (s, c) = angle.sin_cos();
+                    SliceParameters {
+                        center_offset_x: c * slice_parameters.center_offset_x
+                            - s * slice_parameters.center_offset_y,
+                        center_offset_y: s * slice_parameters.center_offset_x
+                            + c * slice_parameters.center_offset_y,
+                        tilt: slice_parameters.tilt + angle,
+                        ..slice_parameters.clone()
+                    }
+                })
+                .collect(),
+            ShapeArray::Linear { count, spacing } => (0..count.max(1))
+                .map(|k| SliceParameters {
+                    center_offset_x: slice_parameters.center_offset_x + k as f64 * spacing[0],
+                    center_offset_y: slice_parameters.center_offset_y + k as f64 * spacing[1],
+                    ..slice_parameters.clone()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for ShapeArray {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeArray::Single => write!(f, "Single"),
+            ShapeArray::Radial { .. } => write!(f, "Radial array"),
+            ShapeArray::Linear { .. } => write!(f, "Linear array"),
+        }
+    }
+}
@@ -1,6 +1,14 @@
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::data_structures::shape_array::ShapeArray;
+use crate::app::formatting::format_parameter;
+use crate::app::generation::percentage::CoverageEstimator;
 use crate::app::generation::Algorithm;
 use crate::app::math::linear_algebra::{Mat2, Vec2};
 
+/// Shapes reaching further from the origin than this (in blocks) are not generated, their grid
+///  would take too much memory
+const MAX_EXTENT: f64 = 2048.0;
+
 /// All parameters necessary to run the generation algorithm
 #[derive(Debug, Clone)]
 pub struct SliceParameters {
@@ -15,6 +23,22 @@ pub struct SliceParameters {
     pub center_offset_y: f64,
 
     pub squircle_parameter: f64,
+
+    // Height at which the parameters were evaluated (the layer, or the height of a sample), for the
+    //  Custom algorithm
+    pub layer: f64,
+
+    pub shape_array: ShapeArray,
+    // Only generate the part of the shape in this sector (radians, from the a-axis counterclockwise)
+    pub sector: Option<[f64; 2]>,
+
+    // Remembered values of options that are not in use, so that switching back doesn't reset them
+    pub percentage_cache: f64, // percentage of the last Percentage algorithm
+    pub coverage_estimator_cache: CoverageEstimator, // estimator of the last Percentage algorithm
+    pub margin_cache: f64,     // margin of the last Conservative or Contained algorithm
+    pub ellipse_radii_cache: [f64; 2], // radius_a and radius_b before switching to a single radius
+
+    pub parameter_texts: ParameterTexts, // how the parameters were typed, to show it when editing
 }
 
 impl Default for SliceParameters {
@@ -34,11 +58,68 @@ impl Default for SliceParameters {
             center_offset_y: 0.0,
 
             squircle_parameter: 2.0, // default: 2.0 (circle / ellipse)
+
+            layer: 0.0,
+
+            shape_array: ShapeArray::Single,
+            sector: None,
+
+            percentage_cache: 0.5,
+            coverage_estimator_cache: CoverageEstimator::Exact,
+            margin_cache: 0.0,
+            ellipse_radii_cache: [5.0, 5.0],
+
+            parameter_texts: ParameterTexts::default(),
+        }
+    }
+}
+
+/// The texts that parameters were typed as in their sliders (e.g. "pi/4" or "1/3", see
+///  `parse_parameter`), each with the value it gave. A text is shown instead of the decimals as
+///  long as the parameter keeps that value, so editing it again doesn't start from a rounded
+///  number.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterTexts(Vec<(Parameter, f64, String)>);
+
+impl ParameterTexts {
+    /// The text the parameter was typed as if it still has the value of that text, otherwise the
+    ///  value (see `format_parameter`)
+    pub fn format(&self, parameter: Parameter, value: f64) -> String {
+        self.0
+            .iter()
+            .find(|(typed_parameter, typed_value, _)| {
+                *typed_parameter == parameter && *typed_value == value
+            })
+            .map_or_else(|| format_parameter(value), |(_, _, text)| text.clone())
+    }
+
+    /// Remember the text that gave the value of the parameter (None: forget the text, e.g. after
+    ///  dragging the slider)
+    pub fn set(&mut self, parameter: Parameter, typed: Option<(f64, String)>) {
+        self.0
+            .retain(|(typed_parameter, _, _)| *typed_parameter != parameter);
+        if let Some((value, text)) = typed {
+            self.0.push((parameter, value, text));
         }
     }
 }
 
 impl SliceParameters {
+    /// Switch to the algorithm, remembering its options for when it is selected again
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm;
+        match algorithm {
+            Algorithm::Percentage(percentage, estimator) => {
+                self.percentage_cache = percentage;
+                self.coverage_estimator_cache = estimator;
+            }
+            Algorithm::Conservative(margin) | Algorithm::Contained(margin) => {
+                self.margin_cache = margin;
+            }
+            _ => {}
+        }
+    }
+
     /// Compute the sqrt_quad_form for the configuration `self`
     pub fn get_sqrt_quad_form(&self) -> Mat2 {
         // Compute a square root of the PSD symmetric quadratic form X defining the ellipse:
@@ -51,8 +132,102 @@ impl SliceParameters {
         )
     }
 
+    /// Why the shape can't be generated, checked before generating: values that are not numbers
+    ///  (e.g. from code), negative radii, or a shape too large for a grid. An infinite squircle
+    ///  parameter is fine (a rectangle).
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("Radius a", self.radius_a),
+            ("Radius b", self.radius_b),
+            ("Tilt", self.tilt),
+            ("Center offset x", self.center_offset_x),
+            ("Center offset y", self.center_offset_y),
+        ] {
+            if !value.is_finite() {
+                return Err(format!("{} is {}", name, value));
+            }
+        }
+        if self.squircle_parameter.is_nan() {
+            return Err("Squircle parameter is NaN".to_string());
+        }
+
+        if self.radius_a < 0.0 || self.radius_b < 0.0 {
+            return Err(format!(
+                "Radius {} is negative",
+                if self.radius_a < 0.0 { "a" } else { "b" }
+            ));
+        }
+        if self.squircle_parameter < 0.0 {
+            return Err("Squircle parameter is negative".to_string());
+        }
+
+        let extent = self.center_offset_x.abs().max(self.center_offset_y.abs())
+            + std::f64::consts::SQRT_2 * self.radius_a.max(self.radius_b);
+        if extent > MAX_EXTENT {
+            return Err(format!(
+                "The shape reaches {:.0} blocks from the origin, more than {}",
+                extent, MAX_EXTENT
+            ));
+        }
+        Ok(())
+    }
+
+    /// Why the shape has no area (then there are few or no blocks), for shapes that can be
+    ///  generated
+    pub fn warning(&self) -> Option<String> {
+        if self.radius_a == 0.0 || self.radius_b == 0.0 {
+            Some("A radius is zero, the shape has no area".to_string())
+        } else if self.squircle_parameter == 0.0 {
+            Some("The squircle parameter is zero, the shape has no area".to_string())
+        } else {
+            None
+        }
+    }
+
     /// Get the squircle ui parameter (used for the slider) from the configuration `self`
     pub fn get_squircle_ui_parameter(&self) -> f64 {
         1.0 - 1.0 / (1.0 + self.squircle_parameter)
     }
+
+    /// Linear interpolation of the shape from `self` (t = 0) to `other` (t = 1), the other options
+    ///  are those of `self`. The squircle parameter is interpolated like its slider, so that
+    ///  infinity is handled.
+    pub fn interpolate(&self, other: &SliceParameters, t: f64) -> SliceParameters {
+        let lerp = |a: f64, b: f64| (1.0 - t) * a + t * b;
+        let squircle_ui_parameter = lerp(
+            self.get_squircle_ui_parameter(),
+            other.get_squircle_ui_parameter(),
+        );
+
+        SliceParameters {
+            radius_a: lerp(self.radius_a, other.radius_a),
+            radius_b: lerp(self.radius_b, other.radius_b),
+            tilt: lerp(self.tilt, other.tilt),
+            center_offset_x: lerp(self.center_offset_x, other.center_offset_x),
+            center_offset_y: lerp(self.center_offset_y, other.center_offset_y),
+            squircle_parameter: 1.0 / (1.0 - squircle_ui_parameter) - 1.0,
+            layer: lerp(self.layer, other.layer),
+            ..self.clone()
+        }
+    }
+
+    /// The shape scaled by `factor` around the origin, e.g. to generate it at a finer resolution
+    pub fn scaled(&self, factor: f64) -> SliceParameters {
+        let shape_array = match self.shape_array {
+            ShapeArray::Linear { count, spacing } => ShapeArray::Linear {
+                count,
+                spacing: spacing.map(|coord| factor * coord),
+            },
+            shape_array => shape_array,
+        };
+
+        SliceParameters {
+            radius_a: factor * self.radius_a,
+            radius_b: factor * self.radius_b,
+            center_offset_x: factor * self.center_offset_x,
+            center_offset_y: factor * self.center_offset_y,
+            shape_array,
+            ..self.clone()
+        }
+    }
 }
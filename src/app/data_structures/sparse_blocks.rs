@@ -1,12 +1,77 @@
 /// Sparse representation of a blocks object (that forgets about the origin)
-/// `indices` contains x iff there is a block at index x
-/// todo: should index be usize or [usize;2]? if the latter, forget about grid_size also
-struct SparseBlocks {
-    indices: Vec<usize>,
-    grid_size: usize
+/// `coords` contains [x, y] iff there is a block at [x, y]. The coordinates are translated so that
+///  the smallest x and the smallest y are 0, and sorted, so that two translates of the same shape
+///  have the same representation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SparseBlocks {
+    coords: Vec<[usize; 2]>,
 }
 
-// todo: functions for getting connected components,
-//  another struct which functions as a pattern to match against (SparseBlocks without grid size?)
-//  routine function which uses this to create a building thing... 
-//  (but must not forget placement on grid!)
\ No newline at end of file
+impl SparseBlocks {
+    /// Make the sparse representation from (global) coordinates of blocks
+    pub fn from_global_coords(global_coords: &[[isize; 2]]) -> Self {
+        let min_x = global_coords.iter().map(|c| c[0]).min().unwrap_or(0);
+        let min_y = global_coords.iter().map(|c| c[1]).min().unwrap_or(0);
+
+        let mut coords: Vec<[usize; 2]> = global_coords
+            .iter()
+            .map(|[x, y]| [(x - min_x) as usize, (y - min_y) as usize])
+            .collect();
+        coords.sort();
+
+        Self { coords }
+    }
+
+    pub fn get_coords(&self) -> &[[usize; 2]] {
+        &self.coords
+    }
+
+    pub fn get_nr_blocks(&self) -> usize {
+        self.coords.len()
+    }
+
+    /// Average of the centers of the blocks (in the coordinates of the sparse representation)
+    pub fn get_center(&self) -> [f64; 2] {
+        let nr_blocks = self.coords.len().max(1) as f64;
+        self.coords.iter().fold([0.0, 0.0], |acc, [x, y]| {
+            [
+                acc[0] + (*x as f64 + 0.5) / nr_blocks,
+                acc[1] + (*y as f64 + 0.5) / nr_blocks,
+            ]
+        })
+    }
+
+    /// Representative of the shape up to the symmetries of the grid (quarter turns and
+    ///  reflections): the smallest of the eight transformed shapes. Two shapes are the same up to
+    ///  symmetry iff they have the same normal form.
+    pub fn normal_form(&self) -> Self {
+        let symmetries: [fn(isize, isize) -> [isize; 2]; 8] = [
+            |x, y| [x, y],
+            |x, y| [-y, x],
+            |x, y| [-x, -y],
+            |x, y| [y, -x],
+            |x, y| [-x, y],
+            |x, y| [x, -y],
+            |x, y| [y, x],
+            |x, y| [-y, -x],
+        ];
+
+        symmetries
+            .iter()
+            .map(|symmetry| {
+                Self::from_global_coords(
+                    &self
+                        .coords
+                        .iter()
+                        .map(|[x, y]| symmetry(*x as isize, *y as isize))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .min()
+            .unwrap()
+    }
+}
+
+// todo: another struct which functions as a pattern to match against,
+//  routine function which uses this to create a building thing...
+//  (but must not forget placement on grid!)
@@ -1,6 +1,8 @@
 use std::collections::VecDeque;
 use std::fmt::Debug;
 
+use crate::app::error::VoxircleError;
+
 /// Vector whose index is a continuous interval of the integers (Z)
 #[derive(Debug, Clone)]
 pub struct ZVec<T> {
@@ -38,6 +40,42 @@ impl<T: Clone + Debug> ZVec<T> {
         }
     }
 
+    /// Like `get`, with an error for an index outside of the ZVec (or a ZVec whose data doesn't
+    ///  match its indices)
+    pub fn try_get(&self, index: isize) -> Result<T, VoxircleError> {
+        if self.maximum - self.minimum + 1 != self.data.len() as isize {
+            return Err(VoxircleError::StackMismatch {
+                expected: [self.minimum, self.maximum],
+                found: [self.minimum, self.minimum + self.data.len() as isize - 1],
+            });
+        }
+        self.get(index).ok_or_else(|| self.outside_error(index))
+    }
+
+    /// Like `get_mut`, with an error for an index outside of the ZVec
+    pub fn try_get_mut(&mut self, index: isize) -> Result<&mut T, VoxircleError> {
+        let error = self.outside_error(index);
+        self.get_mut(index).ok_or(error)
+    }
+
+    /// The layers of the ZVec are `expected`
+    pub fn check_range(&self, expected: [isize; 2]) -> Result<(), VoxircleError> {
+        let found = [self.minimum, self.maximum];
+        if found == expected {
+            Ok(())
+        } else {
+            Err(VoxircleError::StackMismatch { expected, found })
+        }
+    }
+
+    fn outside_error(&self, index: isize) -> VoxircleError {
+        VoxircleError::LayerOutsideStack {
+            layer: index,
+            lowest: self.minimum,
+            highest: self.maximum,
+        }
+    }
+
     pub fn set(&mut self, index: isize, value: T) {
         self.data[(index - self.minimum) as usize] = value;
     }
@@ -78,6 +116,38 @@ impl<T: Clone + Debug> ZVec<T> {
         self.resize_with_max(new_max, &default);
     }
 
+    /// Extend the ZVec above its maximum with its data in reverse order, as if mirrored in the
+    ///  maximum. If `repeat_maximum` is true the mirror is just above the maximum, so the data at
+    ///  the maximum appears twice.
+    pub fn mirror_upwards(&mut self, repeat_maximum: bool) {
+        let mirrored: Vec<T> = self
+            .data
+            .iter()
+            .rev()
+            .skip(if repeat_maximum { 0 } else { 1 })
+            .cloned()
+            .collect();
+        self.maximum += mirrored.len() as isize;
+        self.data.extend(mirrored);
+    }
+
+    /// Move the data to the indices `offset` higher (lower for a negative offset)
+    pub fn shift(&mut self, offset: isize) {
+        self.minimum += offset;
+        self.maximum += offset;
+    }
+
+    /// Move the data at each index to `new_index(index)`, which has to be a permutation of the
+    ///  indices of the ZVec
+    pub fn permute(&mut self, new_index: impl Fn(isize) -> isize) {
+        let mut data = self.data.clone();
+        for (offset, value) in self.data.iter().enumerate() {
+            let index = self.minimum + offset as isize;
+            data[(new_index(index) - self.minimum) as usize] = value.clone();
+        }
+        self.data = data;
+    }
+
     pub fn get_minimum(&self) -> isize {
         self.minimum
     }
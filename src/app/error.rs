@@ -0,0 +1,41 @@
+use std::fmt::{Display, Formatter};
+
+/// What can go wrong while updating the sampling points, parameters, blocks and metrics, or while
+///  showing the current layer. These are bugs (e.g. the stack was resized while a layer was being
+///  updated), the rest of the frame is skipped instead of crashing.
+#[derive(Debug, PartialEq, Clone)]
+pub enum VoxircleError {
+    LayerOutsideStack {
+        layer: isize,
+        lowest: isize,
+        highest: isize,
+    },
+    // The stacks of parameters, blocks, locks, ... don't cover the same layers
+    StackMismatch {
+        expected: [isize; 2],
+        found: [isize; 2],
+    },
+}
+
+impl std::error::Error for VoxircleError {}
+
+impl Display for VoxircleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoxircleError::LayerOutsideStack {
+                layer,
+                lowest,
+                highest,
+            } => write!(
+                f,
+                "Layer {} is not in the stack (layers {} to {})",
+                layer, lowest, highest
+            ),
+            VoxircleError::StackMismatch { expected, found } => write!(
+                f,
+                "Expected layers {} to {}, found layers {} to {}",
+                expected[0], expected[1], found[0], found[1]
+            ),
+        }
+    }
+}
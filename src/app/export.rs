@@ -0,0 +1,500 @@
+use std::collections::BTreeMap;
+
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::symmetry_type::SymmetryType;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::formatting::minecraft_z;
+use crate::app::image_export::blocks_to_world;
+use crate::app::io::SaveRequest;
+use crate::app::materials::Materials;
+use crate::app::metrics::boundary_segments::{get_boundary_segments, BoundarySegments};
+use crate::app::metrics::boundary_tour::get_boundary_tour;
+use crate::app::metrics::convex_hull::get_convex_hull;
+use crate::app::supports::SupportPattern;
+use crate::app::update::metrics::EnabledMetrics;
+use crate::app::view::View;
+
+/// The boundary blocks of each of the layers in build order (following the boundary tour), as
+///  (layer, left bottom coordinates of the blocks shifted by `translation`)
+pub fn boundary_build_order(
+    stack_blocks: &ZVec<Blocks>,
+    layers: impl Iterator<Item = isize>,
+    translation: [isize; 2],
+) -> Vec<(isize, Vec<[isize; 2]>)> {
+    layers
+        .filter_map(|layer| {
+            let blocks = stack_blocks.get(layer)?;
+            let coords = get_boundary_tour(&blocks.get_boundary())
+                .block_coords()
+                .into_iter()
+                .map(|[x, y]| [x + translation[0], y + translation[1]])
+                .collect();
+            Some((layer, coords))
+        })
+        .collect()
+}
+
+/// Ask where to save the build order of the current layer (or of all layers, see
+///  `View::build_order_all_layers`) as CSV or JSON
+pub fn build_order_save_request(
+    stack_blocks: &ZVec<Blocks>,
+    current_layer: isize,
+    layers_enabled: bool,
+    view: &View,
+    json: bool,
+) -> SaveRequest {
+    let layers = if layers_enabled && view.build_order_all_layers {
+        stack_blocks.get_minimum()..=stack_blocks.get_maximum()
+    } else {
+        current_layer..=current_layer
+    };
+    let build_order = boundary_build_order(stack_blocks, layers, view.world_translation());
+    let (contents, filter_name, extension) = if json {
+        (
+            build_order_to_json(&build_order, view.flip_z),
+            "JSON",
+            "json",
+        )
+    } else {
+        (build_order_to_csv(&build_order, view.flip_z), "CSV", "csv")
+    };
+    SaveRequest {
+        title: "Export build order".to_string(),
+        file_name: format!("boundary_build_order.{}", extension),
+        filter_name,
+        extensions: if json { &["json"] } else { &["csv"] },
+        contents: contents.into_bytes(),
+    }
+}
+
+/// One line per block with the layer, the step in the build order and the world X and Z of the
+///  block (see `minecraft_z`), for pasting into a spreadsheet
+pub fn build_order_to_csv(build_order: &[(isize, Vec<[isize; 2]>)], flip_z: bool) -> String {
+    let mut csv = "layer,step,x,z\n".to_string();
+    for (layer, coords) in build_order {
+        for (step, [x, y]) in coords.iter().enumerate() {
+            csv += &format!("{},{},{},{}\n", layer, step + 1, x, minecraft_z(*y, flip_z));
+        }
+    }
+    csv
+}
+
+/// The same as `build_order_to_csv`, as a list of layers with their blocks in build order, with
+///  the materials needed for all of them
+pub fn build_order_to_json(build_order: &[(isize, Vec<[isize; 2]>)], flip_z: bool) -> String {
+    let layers: Vec<String> = build_order
+        .iter()
+        .map(|(layer, coords)| {
+            let blocks: Vec<String> = coords
+                .iter()
+                .map(|[x, y]| format!("{{\"x\": {}, \"z\": {}}}", x, minecraft_z(*y, flip_z)))
+                .collect();
+            format!(
+                "    {{\"layer\": {}, \"blocks\": [\n      {}\n    ]}}",
+                layer,
+                blocks.join(",\n      ")
+            )
+        })
+        .collect();
+    let nr_blocks = build_order
+        .iter()
+        .map(|(_, coords)| coords.len() as u64)
+        .sum();
+    format!(
+        "{{\"materials\": {},\n\"layers\": [\n{}\n]}}\n",
+        materials_to_json(nr_blocks),
+        layers.join(",\n")
+    )
+}
+
+/// The number of blocks with how many shulker boxes, stacks and blocks that is
+fn materials_to_json(nr_blocks: u64) -> String {
+    let materials = Materials::from_block_count(nr_blocks);
+    format!(
+        "{{\"blocks_total\": {}, \"shulker_boxes\": {}, \"stacks\": {}, \"blocks\": {}}}",
+        nr_blocks, materials.shulker_boxes, materials.stacks, materials.blocks
+    )
+}
+
+/// Metrics of the current layer that the `MetricsWorker` already computed, so that the export
+///  doesn't compute them again. None if they weren't computed or are outdated.
+#[derive(Default, Clone, Copy)]
+pub struct ComputedMetrics<'a> {
+    pub symmetry_type: Option<&'a SymmetryType>,
+    pub convex_hull: Option<&'a [[f64; 2]]>,
+    pub boundary_segments: Option<&'a BoundarySegments>,
+}
+
+impl<'a> ComputedMetrics<'a> {
+    /// The metrics are up to date if they are enabled and no newer ones are being computed
+    pub fn new(
+        enabled_metrics: &EnabledMetrics,
+        is_pending: bool,
+        symmetry_type: &'a SymmetryType,
+        convex_hull: &'a [[f64; 2]],
+        boundary_segments: &'a BoundarySegments,
+    ) -> Self {
+        let is_current = |enabled: bool| enabled && !is_pending;
+        Self {
+            symmetry_type: is_current(enabled_metrics.symmetry_type).then_some(symmetry_type),
+            convex_hull: is_current(enabled_metrics.convex_hull).then_some(convex_hull),
+            boundary_segments: is_current(enabled_metrics.boundary_segments)
+                .then_some(boundary_segments),
+        }
+    }
+}
+
+/// Ask where to save the metrics of the current layer (or of all layers, if layers are enabled)
+///  as JSON
+pub fn metrics_save_request(
+    stack_blocks: &ZVec<Blocks>,
+    current_layer: isize,
+    layers_enabled: bool,
+    view: &View,
+    computed_metrics: ComputedMetrics,
+) -> SaveRequest {
+    let layers = if layers_enabled {
+        stack_blocks.get_minimum()..=stack_blocks.get_maximum()
+    } else {
+        current_layer..=current_layer
+    };
+    SaveRequest {
+        title: "Export metrics".to_string(),
+        file_name: "metrics.json".to_string(),
+        filter_name: "JSON",
+        extensions: &["json"],
+        contents: metrics_to_json(
+            stack_blocks,
+            layers,
+            current_layer,
+            computed_metrics,
+            view.world_translation(),
+            view.flip_z,
+        )
+        .into_bytes(),
+    }
+}
+
+/// The metrics of each of the layers, for analyzing or documenting a design with other tools: the
+///  numbers of blocks, the diameters, the symmetry type, the corners of the convex hull (world X
+///  and Z, shifted by `translation`) and the shapes of the boundary segments (up to rotation and
+///  reflection, with how often they occur). The expensive metrics of the current layer are taken
+///  from `computed_metrics` where it has them, the others are computed here.
+pub fn metrics_to_json(
+    stack_blocks: &ZVec<Blocks>,
+    layers: impl Iterator<Item = isize>,
+    current_layer: isize,
+    computed_metrics: ComputedMetrics,
+    translation: [isize; 2],
+    flip_z: bool,
+) -> String {
+    let layers: Vec<String> = layers
+        .filter_map(|layer| {
+            let blocks = stack_blocks.get(layer)?;
+            let boundary = blocks.get_boundary();
+            let nr_blocks = blocks.get_nr_blocks();
+            let diameters = if nr_blocks == 0 {
+                [0, 0]
+            } else {
+                blocks.get_diameters()
+            };
+            let computed = if layer == current_layer {
+                computed_metrics
+            } else {
+                ComputedMetrics::default()
+            };
+            let symmetry_type = match computed.symmetry_type {
+                Some(symmetry_type) => symmetry_type.to_string(),
+                None => blocks.get_symmetry_type().to_string(),
+            };
+            let boundary_segments;
+            let boundary_segments = match computed.boundary_segments {
+                Some(computed) => computed,
+                None => {
+                    boundary_segments = get_boundary_segments(&boundary);
+                    &boundary_segments
+                }
+            };
+
+            // Corners lie between blocks, so their Z is not that of a block (see `minecraft_z`)
+            let convex_hull = match computed.convex_hull {
+                Some(convex_hull) => convex_hull.to_vec(),
+                None => get_convex_hull(&blocks.get_outer_corners()),
+            };
+            let convex_hull: Vec<String> = convex_hull
+                .iter()
+                .map(|[x, y]| {
+                    let y = y + translation[1] as f64;
+                    let z = if flip_z { y } else { -y };
+                    format!("[{}, {}]", x + translation[0] as f64, z)
+                })
+                .collect();
+            let shapes: Vec<String> = boundary_segments
+                .shapes
+                .iter()
+                .map(|(shape, count)| {
+                    let coords: Vec<String> = shape
+                        .get_coords()
+                        .iter()
+                        .map(|[x, y]| format!("[{}, {}]", x, y))
+                        .collect();
+                    format!(
+                        "{{\"count\": {}, \"blocks\": [{}]}}",
+                        count,
+                        coords.join(", ")
+                    )
+                })
+                .collect();
+
+            Some(format!(
+                "    {{\"layer\": {}, \"blocks\": {}, \"interior\": {}, \"boundary\": {}, \
+                \"diameters\": [{}, {}], \"symmetry_type\": \"{}\",\n      \
+                \"convex_hull\": [{}],\n      \
+                \"boundary_segment_shapes\": [\n        {}\n      ]}}",
+                layer,
+                nr_blocks,
+                blocks.get_interior().get_nr_blocks(),
+                boundary.get_nr_blocks(),
+                diameters[0],
+                diameters[1],
+                symmetry_type,
+                convex_hull.join(", "),
+                shapes.join(",\n        ")
+            ))
+        })
+        .collect();
+    format!("{{\"layers\": [\n{}\n]}}\n", layers.join(",\n"))
+}
+
+// The most blocks a single fill command may change
+const MAX_FILL_VOLUME: isize = 32768;
+
+/// Where and with which blocks the stack is placed by the `.mcfunction` export
+pub struct McfunctionExport {
+    pub origin: [isize; 3], // added to X, the layer number (Y) and Z
+    pub relative: bool,     // coordinates relative to where the function runs (~)
+    pub boundary_block: String,
+    pub interior_block: String, // empty to leave the interior as it is
+    pub floor_block: String,    // interior of the lowest layer, empty for the interior block
+    pub support_block: String,  // placed over the interior, if there are supports
+    pub center_block: String,   // on the center blocks of each layer, empty for none
+}
+
+impl Default for McfunctionExport {
+    fn default() -> Self {
+        Self {
+            origin: [0, 64, 0],
+            relative: false,
+            boundary_block: "minecraft:stone_bricks".to_string(),
+            interior_block: String::new(),
+            floor_block: String::new(),
+            support_block: "minecraft:oak_log".to_string(),
+            center_block: String::new(),
+        }
+    }
+}
+
+impl McfunctionExport {
+    /// `setblock` and `fill` commands that place the layers, with the blocks of each kind merged
+    ///  into rectangles so that there are few commands. Coordinates are shifted by `translation`
+    ///  (see `View::world_translation`). The lowest layer gets the floor block in its interior, so
+    ///  that a shell with only a boundary block stands on a floor. The supports in the pattern (if
+    ///  any) replace the interior, and the center blocks come last.
+    pub fn to_mcfunction(
+        &self,
+        stack_blocks: &ZVec<Blocks>,
+        layers: impl Iterator<Item = isize>,
+        translation: [isize; 2],
+        flip_z: bool,
+        supports: Option<SupportPattern>,
+    ) -> String {
+        let mut commands = vec![];
+        let mut is_lowest = true;
+        for layer in layers {
+            let Some(blocks) = stack_blocks.get(layer) else {
+                continue;
+            };
+            // Nothing to place (the floor goes on the lowest layer with blocks)
+            if blocks.get_nr_blocks() == 0 {
+                continue;
+            }
+            let interior = blocks.get_interior();
+            let interior_block = if is_lowest && !self.floor_block.trim().is_empty() {
+                &self.floor_block
+            } else {
+                &self.interior_block
+            };
+            is_lowest = false;
+            let mut kinds = vec![
+                (&self.boundary_block, blocks.get_boundary()),
+                (interior_block, interior.clone()),
+            ];
+            if let Some(pattern) = supports {
+                let supports = pattern.get_supports(&interior, blocks.get_center_coord());
+                kinds.push((&self.support_block, supports));
+            }
+            kinds.push((&self.center_block, blocks.get_center_blocks()));
+            for (block, blocks) in kinds {
+                let block = block.trim();
+                if block.is_empty() {
+                    continue;
+                }
+                let coords = blocks_to_world(&blocks.get_all_block_coords(), translation, flip_z);
+                for [[x_1, z_1], [x_2, z_2]] in merge_into_rectangles(coords) {
+                    let y = layer + self.origin[1];
+                    let [x_1, x_2] = [x_1, x_2].map(|x| x + self.origin[0]);
+                    let [z_1, z_2] = [z_1, z_2].map(|z| z + self.origin[2]);
+                    commands.push(if [x_1, z_1] == [x_2, z_2] {
+                        format!("setblock {} {}", self.position([x_1, y, z_1]), block)
+                    } else {
+                        format!(
+                            "fill {} {} {}",
+                            self.position([x_1, y, z_1]),
+                            self.position([x_2, y, z_2]),
+                            block
+                        )
+                    });
+                }
+            }
+        }
+
+        format!(
+            "# Generated by Voxircle: {} commands\n{}\n",
+            commands.len(),
+            commands.join("\n")
+        )
+    }
+
+    fn position(&self, coords: [isize; 3]) -> String {
+        let prefix = if self.relative { "~" } else { "" };
+        coords.map(|coord| format!("{}{}", prefix, coord)).join(" ")
+    }
+}
+
+/// Cover the blocks (X and Z) with rectangles: runs along X, merged with equal runs in the next
+///  rows. No rectangle has more than `MAX_FILL_VOLUME` blocks. Output: the corners (inclusive),
+///  ordered by Z and then X.
+fn merge_into_rectangles(mut coords: Vec<[isize; 2]>) -> Vec<[[isize; 2]; 2]> {
+    coords.sort_by_key(|[x, z]| (*z, *x));
+    coords.dedup();
+
+    // Runs along X: (z, first x, last x)
+    let mut runs: Vec<(isize, isize, isize)> = vec![];
+    for [x, z] in coords {
+        match runs.last_mut() {
+            Some((run_z, _, last_x)) if *run_z == z && *last_x + 1 == x => *last_x = x,
+            _ => runs.push((z, x, x)),
+        }
+    }
+
+    // Rectangles that may still grow, by their X range: the first and last Z
+    let mut open: BTreeMap<[isize; 2], [isize; 2]> = BTreeMap::new();
+    let mut rectangles = vec![];
+    for (z, first_x, last_x) in runs {
+        let width = last_x - first_x + 1;
+        match open.get_mut(&[first_x, last_x]) {
+            Some([first_z, last_z])
+                if *last_z + 1 == z && width * (z - *first_z + 1) <= MAX_FILL_VOLUME =>
+            {
+                *last_z = z;
+            }
+            _ => {
+                if let Some([first_z, last_z]) = open.insert([first_x, last_x], [z, z]) {
+                    rectangles.push([[first_x, first_z], [last_x, last_z]]);
+                }
+            }
+        }
+    }
+    rectangles.extend(
+        open.into_iter()
+            .map(|([first_x, last_x], [first_z, last_z])| [[first_x, first_z], [last_x, last_z]]),
+    );
+
+    rectangles.sort_by_key(|[[x, z], _]| (*z, *x));
+    rectangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_order_to_csv, build_order_to_json, merge_into_rectangles, metrics_to_json,
+        ComputedMetrics,
+    };
+    use crate::app::data_structures::blocks::Blocks;
+    use crate::app::data_structures::symmetry_type::SymmetryType;
+    use crate::app::data_structures::zvec::ZVec;
+
+    fn build_order() -> Vec<(isize, Vec<[isize; 2]>)> {
+        vec![(0, vec![[1, 2], [3, -4]]), (1, vec![[0, 0]])]
+    }
+
+    #[test]
+    fn csv_build_order() {
+        assert_eq!(
+            build_order_to_csv(&build_order(), false),
+            "layer,step,x,z\n0,1,1,-3\n0,2,3,3\n1,1,0,-1\n"
+        );
+        assert!(build_order_to_csv(&build_order(), true).ends_with("1,1,0,0\n"));
+    }
+
+    #[test]
+    fn json_build_order() {
+        assert_eq!(
+            build_order_to_json(&build_order(), false),
+            "{\"materials\": {\"blocks_total\": 3, \"shulker_boxes\": 0, \"stacks\": 0, \
+            \"blocks\": 3},\n\"layers\": [\n    {\"layer\": 0, \"blocks\": [\n      \
+            {\"x\": 1, \"z\": -3},\n      {\"x\": 3, \"z\": 3}\n    ]},\n    \
+            {\"layer\": 1, \"blocks\": [\n      {\"x\": 0, \"z\": -1}\n    ]}\n]}\n"
+        );
+    }
+
+    #[test]
+    fn json_metrics() {
+        // A 2 by 2 square
+        let mut blocks = Blocks::new(vec![false; 16], 4);
+        for coord in [[-1, -1], [0, -1], [-1, 0], [0, 0]] {
+            let index = blocks.get_index_from_global_coord_usize(coord).unwrap();
+            blocks.blocks[index] = true;
+        }
+        let stack = ZVec::new([blocks.clone(), blocks].into(), 0);
+
+        let json = metrics_to_json(&stack, 0..=1, 0, ComputedMetrics::default(), [10, 0], false);
+        assert_eq!(json.matches("\"layer\": ").count(), 2);
+        assert!(
+            json.contains("\"blocks\": 4, \"interior\": 0, \"boundary\": 4, \"diameters\": [2, 2]")
+        );
+        assert!(json.contains("\"convex_hull\": [[9, 1], [11, 1], [11, -1], [9, -1]]"));
+        assert!(json.contains("{\"count\": 1, \"blocks\": [[0, 0], [0, 1], [1, 0], [1, 1]]}"));
+    }
+
+    #[test]
+    fn json_metrics_reuse_computed() {
+        let stack = ZVec::new(vec![Blocks::new(vec![false; 4], 2); 2].into(), 0);
+        let symmetry_type = SymmetryType::RotationQuarter;
+        let convex_hull = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        let computed = ComputedMetrics {
+            symmetry_type: Some(&symmetry_type),
+            convex_hull: Some(&convex_hull),
+            boundary_segments: None,
+        };
+
+        // Only the current layer (1) has the computed metrics
+        let json = metrics_to_json(&stack, 0..=1, 1, computed, [0, 0], true);
+        assert_eq!(json.matches("Rotation by 90°").count(), 1);
+        assert_eq!(
+            json.matches("\"convex_hull\": [[0, 0], [1, 0], [0, 1]]")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn rectangles_cover_the_blocks() {
+        // An L shape: a 3 by 2 rectangle with one more block on top
+        let coords = vec![[0, 0], [1, 0], [2, 0], [0, 1], [1, 1], [2, 1], [0, 2]];
+        assert_eq!(
+            merge_into_rectangles(coords),
+            vec![[[0, 0], [2, 1]], [[0, 2], [0, 2]]]
+        );
+    }
+}
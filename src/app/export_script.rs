@@ -0,0 +1,125 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, Map, Scope};
+
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::formatting::minecraft_z;
+use crate::app::scripting::new_engine;
+
+// More than other code gets (see `new_engine`), since a script goes over all blocks of the stack
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+// File types the output can be saved as
+pub static EXTENSIONS: [&str; 4] = ["txt", "csv", "json", "md"];
+
+const DEFAULT_SCRIPT: &str = r#"// `layers` holds the layers from low to high. Each has a `layer` number and the lists `blocks`
+//  and `boundary` of blocks with `x`, `y` and `z` (like the build order CSV).
+// Everything printed ends up in the file.
+for layer in layers {
+    print(`Layer ${layer.layer}: ${layer.blocks.len()} blocks`);
+    for block in layer.boundary {
+        print(`[ ] ${block.x} ${layer.layer} ${block.z}`);
+    }
+}
+"#;
+
+/// A Rhai script that turns the blocks into text, for export formats that Voxircle doesn't have
+///  (a checklist, statistics, the input of some other tool)
+pub struct ExportScript {
+    pub code: String,
+    pub extension: &'static str, // of the exported file, one of `EXTENSIONS`
+    pub output: Option<Result<String, String>>, // of the last run, or why it failed
+}
+
+impl Default for ExportScript {
+    fn default() -> Self {
+        Self {
+            code: DEFAULT_SCRIPT.to_string(),
+            extension: EXTENSIONS[0],
+            output: None,
+        }
+    }
+}
+
+impl ExportScript {
+    /// The extension as the list of extensions of a file dialog filter
+    pub fn extensions(&self) -> &'static [&'static str] {
+        let index = EXTENSIONS
+            .iter()
+            .position(|extension| *extension == self.extension)
+            .unwrap_or(0);
+        &EXTENSIONS[index..=index]
+    }
+
+    /// Run the script on the layers, the output (or the error) is kept. Coordinates are shifted by
+    ///  `translation` (see `View::world_translation`) and z is the Minecraft Z (see `minecraft_z`).
+    pub fn run(
+        &mut self,
+        stack_blocks: &ZVec<Blocks>,
+        layers: impl Iterator<Item = isize>,
+        translation: [isize; 2],
+        flip_z: bool,
+    ) {
+        let layers: Array = layers
+            .filter_map(|layer| {
+                let blocks = stack_blocks.get(layer)?;
+                let to_array = |blocks: &Blocks| -> Array {
+                    blocks
+                        .get_all_block_coords()
+                        .into_iter()
+                        .map(|[x, y]| {
+                            let [x, y] = [x as isize + translation[0], y as isize + translation[1]];
+                            let mut block = Map::new();
+                            block.insert("x".into(), Dynamic::from_int(x as i64));
+                            block.insert("y".into(), Dynamic::from_int(y as i64));
+                            block.insert(
+                                "z".into(),
+                                Dynamic::from_int(minecraft_z(y, flip_z) as i64),
+                            );
+                            Dynamic::from_map(block)
+                        })
+                        .collect()
+                };
+
+                let mut map = Map::new();
+                map.insert("layer".into(), Dynamic::from_int(layer as i64));
+                map.insert("blocks".into(), Dynamic::from_array(to_array(&blocks)));
+                map.insert(
+                    "boundary".into(),
+                    Dynamic::from_array(to_array(&blocks.get_boundary())),
+                );
+                Some(Dynamic::from_map(map))
+            })
+            .collect();
+
+        self.output = Some(run_script(&self.code, layers));
+    }
+}
+
+/// The printed lines, followed by the value of the script if it has one
+fn run_script(code: &str, layers: Array) -> Result<String, String> {
+    let output = Rc::new(RefCell::new(String::new()));
+
+    let mut engine = new_engine();
+    engine.set_max_operations(MAX_OPERATIONS);
+    let printed = output.clone();
+    engine.on_print(move |text| {
+        let mut printed = printed.borrow_mut();
+        printed.push_str(text);
+        printed.push('\n');
+    });
+
+    let mut scope = Scope::new();
+    scope.push_constant("layers", layers);
+    let value = engine
+        .eval_with_scope::<Dynamic>(&mut scope, code)
+        .map_err(|error| error.to_string())?;
+
+    let mut output = output.take();
+    if !value.is_unit() {
+        output += &value.to_string();
+    }
+    Ok(output)
+}
@@ -0,0 +1,122 @@
+use std::fmt::{Display, Formatter};
+use std::mem::discriminant;
+
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::generation::Algorithm;
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum ShapeFilter {
+    #[default]
+    Any,
+    Circle,
+    Ellipse,
+    Squircle, // squircle parameter other than 2
+}
+
+impl ShapeFilter {
+    pub fn matches(&self, slice_parameters: &SliceParameters) -> bool {
+        let round = slice_parameters.squircle_parameter == 2.0;
+        match self {
+            ShapeFilter::Any => true,
+            ShapeFilter::Circle => round && slice_parameters.radius_a == slice_parameters.radius_b,
+            ShapeFilter::Ellipse => round && slice_parameters.radius_a != slice_parameters.radius_b,
+            ShapeFilter::Squircle => !round,
+        }
+    }
+}
+
+/// Find layers by their algorithm and shape, and change them all at once (e.g. to replace an
+///  algorithm on a part of the stack)
+pub struct FindReplace {
+    // Filter
+    pub layer_range: [isize; 2],      // inclusive
+    pub algorithm: Option<Algorithm>, // only the kind of algorithm is compared, not the percentage or margin
+    pub shape: ShapeFilter,
+    pub radius_range: Option<[f64; 2]>, // the larger of the two radii is in this range (inclusive)
+
+    // Replacement
+    pub replace_algorithm: Option<Algorithm>,
+    pub replace_squircle_parameter: Option<f64>,
+}
+
+impl Default for FindReplace {
+    fn default() -> Self {
+        Self {
+            layer_range: [0, 0],
+            algorithm: None,
+            shape: ShapeFilter::Any,
+            radius_range: None,
+            replace_algorithm: None,
+            replace_squircle_parameter: None,
+        }
+    }
+}
+
+impl FindReplace {
+    pub fn matches(&self, layer: isize, slice_parameters: &SliceParameters) -> bool {
+        let radius = f64::max(slice_parameters.radius_a, slice_parameters.radius_b);
+
+        self.layer_range[0] <= layer
+            && layer <= self.layer_range[1]
+            && self.algorithm.is_none_or(|algorithm| {
+                discriminant(&algorithm) == discriminant(&slice_parameters.algorithm)
+            })
+            && self.shape.matches(slice_parameters)
+            && self
+                .radius_range
+                .is_none_or(|[min, max]| min <= radius && radius <= max)
+    }
+
+    /// The layers of the stack that match the filter, from low to high. Locked layers are never
+    ///  found, so they are not changed.
+    pub fn find(
+        &self,
+        stack_layer_config: &ZVec<SliceParameters>,
+        stack_locked: &ZVec<bool>,
+    ) -> Vec<isize> {
+        (self.layer_range[0]..=self.layer_range[1])
+            .filter(|&layer| {
+                !stack_locked.get(layer).unwrap_or(false)
+                    && stack_layer_config
+                        .get(layer)
+                        .is_some_and(|slice_parameters| self.matches(layer, &slice_parameters))
+            })
+            .collect()
+    }
+
+    /// Apply the replacement to the given layers, keeping the locked parameters
+    pub fn replace(
+        &self,
+        stack_layer_config: &mut ZVec<SliceParameters>,
+        layers: &[isize],
+        locked_parameters: &[Parameter],
+    ) {
+        let replace_squircle_parameter = self
+            .replace_squircle_parameter
+            .filter(|_| !locked_parameters.contains(&Parameter::SquircleParameter));
+        for &layer in layers {
+            let Some(slice_parameters) = stack_layer_config.get_mut(layer) else {
+                continue;
+            };
+            if let Some(algorithm) = self.replace_algorithm {
+                slice_parameters.set_algorithm(algorithm);
+            }
+            if let Some(squircle_parameter) = replace_squircle_parameter {
+                slice_parameters.squircle_parameter = squircle_parameter;
+            }
+        }
+    }
+}
+
+impl Display for ShapeFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeFilter::Any => write!(f, "Any shape"),
+            ShapeFilter::Circle => write!(f, "Circle"),
+            ShapeFilter::Ellipse => write!(f, "Ellipse"),
+            ShapeFilter::Squircle => write!(f, "Squircle"),
+        }
+    }
+}
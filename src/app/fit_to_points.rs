@@ -0,0 +1,102 @@
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::formatting::minecraft_z;
+use crate::app::math::shape_fit::{fit_blocks, fit_circle, fit_ellipse, FittedShape};
+
+// A circle needs three points and an ellipse five, more points are ignored
+pub const MAX_CLICKED_POINTS: usize = 5;
+
+/// Fit a shape to block coordinates pasted from an existing build (to extend or repair it), or
+///  through points clicked in the viewport
+pub struct FitToPoints {
+    pub text: String,
+    pub result: Option<Result<FittedShape, String>>,
+    pub clicked_points: Vec<[f64; 2]>,
+    pub snap_to_blocks: bool, // clicked points are the centers of the blocks
+}
+
+impl Default for FitToPoints {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            result: None,
+            clicked_points: vec![],
+            snap_to_blocks: true,
+        }
+    }
+}
+
+impl FitToPoints {
+    /// Add a point clicked in the viewport, if there is room
+    pub fn click(&mut self, point: [f64; 2]) {
+        if self.clicked_points.len() < MAX_CLICKED_POINTS {
+            self.clicked_points.push(if self.snap_to_blocks {
+                [point[0].floor() + 0.5, point[1].floor() + 0.5]
+            } else {
+                point
+            });
+        }
+    }
+
+    /// The circle through three or four clicked points (least squares for four), or the ellipse
+    ///  through five (the circle if there is no ellipse through them)
+    pub fn fit_clicked_points(&self) -> Option<FittedShape> {
+        if self.clicked_points.len() >= 5 {
+            fit_ellipse(&self.clicked_points).or_else(|| fit_circle(&self.clicked_points))
+        } else {
+            fit_circle(&self.clicked_points)
+        }
+    }
+
+    /// Fit a shape to the blocks in the text, the result (or what went wrong) is kept
+    pub fn fit(&mut self, translation: [isize; 2], flip_z: bool) {
+        let coords = parse_block_coords(&self.text, translation, flip_z);
+        self.result = Some(if coords.len() < 5 {
+            Err(format!("Need at least 5 blocks, found {}", coords.len()))
+        } else {
+            fit_blocks(&coords).ok_or("The blocks don't look like an ellipse".to_string())
+        });
+    }
+}
+
+/// Parse one block per line: `X Y Z` (Minecraft coordinates like on the F3 screen, the height is
+///  ignored) or `x y` (viewport coordinates). Commas, brackets and decimals are allowed, other
+///  lines are skipped. World coordinates are converted to viewport coordinates (see
+///  `View::world_translation`).
+pub fn parse_block_coords(text: &str, translation: [isize; 2], flip_z: bool) -> Vec<[isize; 2]> {
+    let [tx, ty] = translation;
+    text.lines()
+        .filter_map(|line| {
+            let numbers: Vec<isize> = line
+                .split(|c: char| c.is_whitespace() || ",;()[]".contains(c))
+                .filter(|word| !word.is_empty())
+                .map(|word| word.parse::<f64>().ok().map(|n| n.floor() as isize))
+                .collect::<Option<_>>()?;
+            match numbers[..] {
+                [x, y] => Some([x - tx, y - ty]),
+                // minecraft_z is its own inverse
+                [x, _, z] => Some([x - tx, minecraft_z(z, flip_z) - ty]),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Use the fitted shape for the layer
+pub fn apply_fitted_shape(shape: &FittedShape, slice_parameters: &mut SliceParameters) {
+    slice_parameters.radius_a = shape.radius_a;
+    slice_parameters.radius_b = shape.radius_b;
+    slice_parameters.tilt = shape.tilt;
+    slice_parameters.center_offset_x = shape.center[0];
+    slice_parameters.center_offset_y = shape.center[1];
+    slice_parameters.squircle_parameter = shape.squircle_parameter;
+}
+
+/// The layer with the fitted shape, for previewing it
+pub fn with_fitted_shape(
+    shape: &FittedShape,
+    slice_parameters: &SliceParameters,
+) -> SliceParameters {
+    let mut slice_parameters = slice_parameters.clone();
+    apply_fitted_shape(shape, &mut slice_parameters);
+    slice_parameters
+}
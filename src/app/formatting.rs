@@ -1,3 +1,10 @@
+use crate::app::block_budget::BlockBudget;
+use crate::app::materials::Materials;
+use crate::app::metrics::repeated_layers::LayerRun;
+use crate::app::scripting::new_engine;
+use rhai::{Dynamic, Engine, Scope};
+use std::f64::consts::{PI, TAU};
+
 pub fn format_block_count(nr_blocks: u64) -> String {
     if nr_blocks <= 64 {
         format!("{}", nr_blocks)
@@ -11,6 +18,28 @@ pub fn format_block_count(nr_blocks: u64) -> String {
     }
 }
 
+/// Number of blocks as shulker boxes, stacks and blocks, e.g. "1 shulker box + 3 stacks + 5
+///  blocks". Parts that are zero are left out.
+pub fn format_materials(nr_blocks: u64) -> String {
+    let materials = Materials::from_block_count(nr_blocks);
+
+    let parts: Vec<String> = [
+        (materials.shulker_boxes, "shulker box", "shulker boxes"),
+        (materials.stacks, "stack", "stacks"),
+        (materials.blocks, "block", "blocks"),
+    ]
+    .into_iter()
+    .filter(|(n, _, _)| *n != 0)
+    .map(|(n, singular, plural)| format!("{} {}", n, if n == 1 { singular } else { plural }))
+    .collect();
+
+    if parts.is_empty() {
+        "0 blocks".to_string()
+    } else {
+        parts.join(" + ")
+    }
+}
+
 pub fn format_block_diameter(diameters: [usize; 2]) -> String {
     if diameters[0] == diameters[1] {
         format!("block diameter: {}", diameters[0])
@@ -18,3 +47,165 @@ pub fn format_block_diameter(diameters: [usize; 2]) -> String {
         format!("block diameters: {}x by {}y", diameters[0], diameters[1])
     }
 }
+
+/// Area and perimeter of the continuous shape, and how the number of blocks compares to the area
+pub fn format_shape_measures(area: f64, perimeter: f64, nr_blocks: u64) -> String {
+    if area > 0.0 {
+        format!(
+            "shape area: {:.1} (blocks/area: {:.3}), perimeter: {:.1}",
+            area,
+            nr_blocks as f64 / area,
+            perimeter
+        )
+    } else {
+        format!("shape area: 0, perimeter: {:.1}", perimeter)
+    }
+}
+
+/// Number of blocks of the stack compared to the budget (followed by a comma), empty if there is
+///  no budget
+pub fn format_block_budget(budget: &BlockBudget, nr_blocks_stack: u64) -> String {
+    if !budget.enabled {
+        String::new()
+    } else if budget.is_exceeded(nr_blocks_stack) {
+        format!(
+            "stack: {} of {} blocks (over budget), ",
+            nr_blocks_stack, budget.max_blocks
+        )
+    } else {
+        format!(
+            "stack: {} of {} blocks, ",
+            nr_blocks_stack, budget.max_blocks
+        )
+    }
+}
+
+/// Run of identical layers, e.g. "layers 4–9: same as layer 4"
+pub fn format_layer_run(run: &LayerRun) -> String {
+    format!(
+        "layers {}–{}: same as layer {}",
+        run.first, run.last, run.first
+    )
+}
+
+/// The run of identical layers that the current layer is part of (followed by a comma), empty if
+///  the current layer is not repeated
+pub fn format_repeated_layers(runs: &[LayerRun], current_layer: isize) -> String {
+    match runs.iter().find(|run| run.contains(current_layer)) {
+        Some(run) => format!("{}, ", format_layer_run(run)),
+        None => String::new(),
+    }
+}
+
+/// Fill in the block coordinates in the format, replacing `{x}`, `{y}`, `{z}` (Minecraft Z, see
+///  `minecraft_z`) and `{layer}`
+pub fn format_block_coord(format: &str, coord: [isize; 2], layer: isize, flip_z: bool) -> String {
+    format
+        .replace("{x}", &coord[0].to_string())
+        .replace("{y}", &coord[1].to_string())
+        .replace("{z}", &minecraft_z(coord[1], flip_z).to_string())
+        .replace("{layer}", &layer.to_string())
+}
+
+/// The Minecraft Z coordinate of the block with (left bottom) viewport y coordinate `y`. In
+///  Minecraft Z points south, so with north up in the viewport Z goes down the screen. With
+///  `flip_z` south is up in the viewport instead.
+pub fn minecraft_z(y: isize, flip_z: bool) -> isize {
+    if flip_z {
+        y
+    } else {
+        -y - 1 // the block from y to y + 1 goes from Z = -y - 1 to Z = -y
+    }
+}
+
+/// Format a slider value with two decimals, unless it is a fraction with a small denominator that
+///  can't be written exactly with two decimals (like 1/3). Those are written as a fraction so that
+///  editing the text field and leaving it doesn't round the value.
+pub fn format_parameter(value: f64) -> String {
+    if (value * 100.0 - (value * 100.0).round()).abs() > 1e-9 {
+        for denominator in 2..=16 {
+            let numerator = value * denominator as f64;
+            if (numerator - numerator.round()).abs() < 1e-9 {
+                return format!("{}/{}", numerator.round(), denominator);
+            }
+        }
+    }
+    format!("{:.02}", value)
+}
+
+thread_local! {
+    // Set up once, parsing is done while typing in a text field
+    static PARAMETER_ENGINE: Engine = parameter_engine();
+}
+
+/// The engine for the expressions of `parse_parameter`
+fn parameter_engine() -> Engine {
+    let mut engine = new_engine();
+    engine.set_fast_operators(false); // otherwise the division below is not used
+    engine.register_fn("/", |numerator: i64, denominator: i64| {
+        numerator as f64 / denominator as f64
+    });
+    // The functions of Rhai only take decimals, also allow "sqrt(2)"
+    for (name, function) in [
+        ("sqrt", f64::sqrt as fn(f64) -> f64),
+        ("sin", f64::sin),
+        ("cos", f64::cos),
+        ("tan", f64::tan),
+        ("atan", f64::atan),
+    ] {
+        engine.register_fn(name, move |value: i64| function(value as f64));
+    }
+    engine
+}
+
+/// Parse a decimal number, or an expression like "17/2", "pi/4" or "sqrt(2) / 2" (Rhai, where a
+///  division of whole numbers is a fraction instead of rounded down). The text is kept next to
+///  the value (see `ParameterTexts`), so that it is shown again instead of the rounded decimals.
+pub fn parse_parameter(text: &str) -> Option<f64> {
+    if let Ok(value) = text.trim().parse::<f64>() {
+        return Some(value);
+    }
+
+    let mut scope = Scope::new();
+    scope.push_constant("pi", PI);
+    scope.push_constant("tau", TAU);
+
+    let value = PARAMETER_ENGINE
+        .with(|engine| engine.eval_expression_with_scope::<Dynamic>(&mut scope, text))
+        .ok()?;
+    let value = value
+        .as_float()
+        .or_else(|_| value.as_int().map(|value| value as f64))
+        .ok()?;
+    value.is_finite().then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_parameter;
+    use std::f64::consts::{FRAC_PI_4, PI};
+
+    #[test]
+    fn parse_numbers() {
+        assert_eq!(parse_parameter("-1.5"), Some(-1.5));
+        assert_eq!(parse_parameter(" 3 "), Some(3.0));
+    }
+
+    #[test]
+    fn parse_expressions() {
+        assert_eq!(parse_parameter("pi/4"), Some(FRAC_PI_4));
+        assert_eq!(parse_parameter("2*pi"), Some(2.0 * PI));
+        assert_eq!(parse_parameter("17/2"), Some(8.5));
+        assert_eq!(parse_parameter("-3/2"), Some(-1.5));
+        assert_eq!(parse_parameter("sqrt(4) / 2"), Some(1.0));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(parse_parameter(""), None);
+        assert_eq!(parse_parameter("pi/"), None);
+        assert_eq!(parse_parameter("radius"), None);
+        assert_eq!(parse_parameter("1/0"), None);
+        assert_eq!(parse_parameter("\"hello\""), None);
+    }
+}
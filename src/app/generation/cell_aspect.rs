@@ -0,0 +1,29 @@
+/// A point of a shape in cell coordinates. The `aspect` is the height of a cell of the grid relative
+///  to its width, e.g. 0.5 for the half height cells of some games (1 for Minecraft blocks). Like
+///  the Custom algorithm, there is a single one that all layers use (see `Generator`). The
+///  parameters of the shapes are in cell widths in both directions, so a circle stays round and is
+///  built from more rows than columns when the cells are flat.
+///
+/// The blocks and the viewport are in cell coordinates (each cell is a unit square, drawn `aspect`
+///  as tall as wide), the shapes are converted with this function. The margins of the Conservative
+///  and Contained algorithms are in cells, so a row of flat cells counts as one.
+pub fn to_cells([x, y]: [f64; 2], aspect: f64) -> [f64; 2] {
+    [x, y / aspect]
+}
+
+/// A point in cell coordinates (e.g. clicked in the viewport) in the coordinates of the shapes
+pub fn from_cells([x, y]: [f64; 2], aspect: f64) -> [f64; 2] {
+    [x, y * aspect]
+}
+
+/// A bounding box of a shape (bottom left and top right corner) in cell coordinates
+pub fn bounds_to_cells([min, max]: [[f64; 2]; 2], aspect: f64) -> [[f64; 2]; 2] {
+    [to_cells(min, aspect), to_cells(max, aspect)]
+}
+
+/// The angle of a direction in cell coordinates. It differs from the angle by less than a
+///  quarter turn, so angles that are a full turn apart still are.
+pub fn angle_to_cells(angle: f64, aspect: f64) -> f64 {
+    let (sin, cos) = angle.sin_cos();
+    angle + (sin / aspect).atan2(cos) - sin.atan2(cos)
+}
@@ -1,4 +1,5 @@
 use crate::app::data_structures::blocks::Blocks;
+use crate::app::generation::margin::OffsetSquircle;
 use crate::app::generation::square::Square;
 use crate::app::math::circle_geometry::get_squircle_tangent_point;
 use crate::app::math::linear_algebra::{Mat2, Vec2};
@@ -8,8 +9,17 @@ pub fn generate_alg_conservative(
     center_offset: Vec2,
     sqrt_quad_form: Mat2,
     squircle_parameter: f64,
+    margin: f64,
     grid_size: usize,
 ) -> Blocks {
+    // Blocks are decided by the distance from their center to the boundary, see `OffsetSquircle`
+    if let Some(offset_squircle) = OffsetSquircle::new(sqrt_quad_form, squircle_parameter, margin) {
+        return offset_squircle.generate(center_offset, grid_size, OffsetSquircle::intersects);
+    }
+
+    // A squircle without area (a radius of 0) has no distance to its boundary, its blocks are
+    //  those that its extreme points or the center are in
+
     let origin = Blocks::get_origin_float_from_grid_size(grid_size);
 
     // For tilt 0, there is no real need to do this sort of computation: the max x is radius_a,
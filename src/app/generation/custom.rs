@@ -0,0 +1,166 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::generation::script_shape::{to_shape_coordinates, ScriptShape};
+use crate::app::log::log_warning;
+use crate::app::scripting::new_engine;
+
+/// Code of the Custom algorithm until it is changed: the Centerpoint algorithm for circles
+pub const DEFAULT_CUSTOM_CODE: &str = "(x - params.center_offset_x) ** 2 \
+    + (y - params.center_offset_y) ** 2 <= params.radius_a ** 2";
+
+/// Generated layers that are remembered, all of them are forgotten when there are more
+const MAX_CACHED_BLOCKS: usize = 1024;
+
+/// The inclusion rule of the Custom algorithm. There is a single one that all layers use (see
+///  `Generator`), so that `Algorithm` stays a small `Copy` value. The code is compiled once when it
+///  is set, and since the same code with the same parameters gives the same blocks, the generated
+///  blocks are remembered until the code changes (most layers are generated again without a
+///  change). A shape from a script replaces the code while it is chosen.
+pub struct CustomPredicate {
+    engine: Engine,
+    ast: AST,
+    shape: Option<ScriptShape>,
+    // By the parameters that the code gets (radii, tilt, center, squircle parameter and layer), the
+    //  cell aspect and the grid size. The other fields of the parameters don't change the output:
+    //  the copies of a shape array come here one by one with their own center and tilt, the sector
+    //  is cut out of the blocks afterwards (in `LayerParameters::generate`), and the options of
+    //  the other algorithms are not used by the Custom algorithm.
+    cache: RefCell<HashMap<([u64; 8], usize), Blocks>>,
+}
+
+impl Default for CustomPredicate {
+    fn default() -> Self {
+        let engine = new_engine();
+        let ast = engine
+            .compile_expression(DEFAULT_CUSTOM_CODE)
+            .expect("the default code compiles");
+        Self {
+            engine,
+            ast,
+            shape: None,
+            cache: RefCell::default(),
+        }
+    }
+}
+
+impl CustomPredicate {
+    /// Use `code` from now on. It is an expression in the center `x`, `y` of a cell (viewport
+    ///  coordinates, for cells that aren't square `y` is in cell widths like the parameters), the
+    ///  parameters `params` of the shape (`params.radius_a` etc.) and the `layer` (or `l`, the
+    ///  height of the sample), which is true if the cell is a block. Only the cells of the grid
+    ///  around the shape are tried. If the code doesn't compile the old code is kept.
+    pub fn set_code(&mut self, code: &str) -> Result<(), String> {
+        self.ast = self
+            .engine
+            .compile_expression(code)
+            .map_err(|error| error.to_string())?;
+        self.cache.get_mut().clear();
+        Ok(())
+    }
+
+    /// Use the inclusion function of the script shape instead of the code from now on (None: the
+    ///  code again). The shape has the values of its parameters, so it is set again when they
+    ///  change.
+    pub fn set_shape(&mut self, shape: Option<ScriptShape>) {
+        self.shape = shape;
+        self.cache.get_mut().clear();
+    }
+
+    /// Run the custom code for all cells of the grid. If it fails for some cell the layer is left
+    ///  empty (with a warning).
+    pub fn generate(
+        &self,
+        slice_parameters: &SliceParameters,
+        grid_size: usize,
+        cell_aspect: f64,
+    ) -> Blocks {
+        let key = [
+            slice_parameters.radius_a,
+            slice_parameters.radius_b,
+            slice_parameters.tilt,
+            slice_parameters.center_offset_x,
+            slice_parameters.center_offset_y,
+            slice_parameters.squircle_parameter,
+            slice_parameters.layer,
+            cell_aspect,
+        ]
+        .map(f64::to_bits);
+        let key = (key, grid_size);
+
+        if let Some(blocks) = self.cache.borrow().get(&key) {
+            return blocks.clone();
+        }
+
+        match self.run(slice_parameters, grid_size, cell_aspect) {
+            Ok(blocks) => {
+                let mut cache = self.cache.borrow_mut();
+                if cache.len() >= MAX_CACHED_BLOCKS {
+                    cache.clear();
+                }
+                cache.insert(key, blocks.clone());
+                blocks
+            }
+            Err(error) => {
+                log_warning(format!("The Custom algorithm failed, {}", error));
+                Blocks::new(vec![false; grid_size.pow(2)], grid_size)
+            }
+        }
+    }
+
+    fn run(
+        &self,
+        slice_parameters: &SliceParameters,
+        grid_size: usize,
+        cell_aspect: f64,
+    ) -> Result<Blocks, String> {
+        let mut params = Map::new();
+        for (name, value) in [
+            ("radius_a", slice_parameters.radius_a),
+            ("radius_b", slice_parameters.radius_b),
+            ("tilt", slice_parameters.tilt),
+            ("center_offset_x", slice_parameters.center_offset_x),
+            ("center_offset_y", slice_parameters.center_offset_y),
+            ("squircle_parameter", slice_parameters.squircle_parameter),
+        ] {
+            params.insert(name.into(), Dynamic::from_float(value));
+        }
+
+        let mut scope = Scope::new();
+        scope.push_constant("params", params);
+        scope.push_constant("layer", slice_parameters.layer);
+        scope.push_constant("l", slice_parameters.layer);
+        let shared_length = scope.len();
+
+        let origin = Blocks::get_origin_float_from_grid_size(grid_size);
+        let blocks = (0..grid_size.pow(2))
+            .map(|i| {
+                let x = (i % grid_size) as f64 + 0.5 - origin.x;
+                let y = ((i / grid_size) as f64 + 0.5 - origin.y) * cell_aspect;
+                if let Some(shape) = &self.shape {
+                    let [x, y] = to_shape_coordinates(slice_parameters, [x, y]);
+                    return shape.inside(x, y);
+                }
+
+                scope.rewind(shared_length);
+                scope.push_constant("x", x);
+                scope.push_constant("y", y);
+
+                self.engine
+                    .eval_ast_with_scope::<bool>(&mut scope, &self.ast)
+                    .map_err(|error| {
+                        format!(
+                            "at ({}, {}) on layer {}: {}",
+                            x, y, slice_parameters.layer, error
+                        )
+                    })
+            })
+            .collect::<Result<Vec<bool>, String>>()?;
+
+        Ok(Blocks::new(blocks, grid_size))
+    }
+}
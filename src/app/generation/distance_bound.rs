@@ -0,0 +1,41 @@
+use crate::app::math::linear_algebra::Mat2;
+
+/// Half the diagonal of a block: every point of a block is at most this far from its center.
+///  Slightly enlarged so that blocks touching the boundary are still tested exactly despite
+///  rounding errors.
+pub const HALF_DIAGONAL: f64 = std::f64::consts::FRAC_1_SQRT_2 * (1.0 + 1e-9);
+
+/// Lower bound for the distance from a point to the boundary of the squircle, in terms of the
+///  p-norm r of the point in modified coordinates (see `Square`). Blocks whose center is further
+///  from the boundary than `HALF_DIAGONAL` lie entirely inside or outside the squircle, so only the
+///  blocks near the boundary need the exact tests of the Conservative and Contained algorithms.
+///
+/// For q on the boundary and M the sqrt_quad_form, the triangle inequality for the p-norm (p >= 1)
+///  gives |Mx - Mq|_p >= |r - 1|. In the plane |v|_2 >= min(1, 2^(1/2 - 1/p)) |v|_p, and
+///  |x - q|_2 >= |Mx - Mq|_2 / ||M|| with ||M|| the operator norm.
+///
+/// The distance to the boundary of a superellipse has no closed form, evaluating it (see
+///  `SquircleDistance`) takes a search along the boundary. This bound is cheap, so it culls the
+///  blocks far from the boundary before the distance is evaluated for the rest.
+pub struct BoundaryDistanceBound {
+    factor: f64,
+}
+
+impl BoundaryDistanceBound {
+    /// None if the squircle parameter is below 1, then the p-"norm" has no triangle inequality
+    pub fn new(sqrt_quad_form: Mat2, squircle_parameter: f64) -> Option<Self> {
+        if squircle_parameter < 1.0 {
+            return None;
+        }
+        let norm_equivalence = f64::min(1.0, 2.0_f64.powf(0.5 - 1.0 / squircle_parameter));
+        let operator_norm = sqrt_quad_form.operator_norm();
+        (operator_norm > 0.0 && operator_norm.is_finite()).then_some(Self {
+            factor: norm_equivalence / operator_norm,
+        })
+    }
+
+    /// Lower bound for the distance to the boundary of a point with modified p-norm `m_pnorm`
+    pub fn get(&self, m_pnorm: f64) -> f64 {
+        self.factor * (m_pnorm - 1.0).abs()
+    }
+}
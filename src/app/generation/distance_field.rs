@@ -0,0 +1,192 @@
+use crate::app::math::linear_algebra::{Mat2, Vec2};
+use std::f64::consts::TAU;
+
+/// Number of points on the boundary, the closest point to a point is searched for near the closest
+///  of them
+const NR_SAMPLES: usize = 256;
+
+/// Number of segments of the polygon that measures the boundary between two samples
+const NR_ARC_POINTS: usize = 16;
+
+/// Steps of the golden section search for the closest point, enough to get the distance far more
+///  precise than the subdivision of `OffsetSquircle`
+const NR_REFINE_STEPS: usize = 20;
+
+/// Signed distance field of a squircle: the distance from a point of the grid (relative to the
+///  center of the squircle) to its boundary, negative inside.
+///
+/// The boundary is the image of the unit squircle (cos(t)^(2/p), sin(t)^(2/p)) under the inverse of
+///  the sqrt_quad_form. The distance has no closed form, so the closest point is found by
+///  sampling the boundary and refining every local minimum of the sampled distance with a golden
+///  section search over the neighboring samples. Looking at every local minimum (instead of only
+///  the closest sample) finds the closest point of very thin shapes and of squircles with
+///  parameter below 1, which aren't convex.
+pub struct SquircleDistance {
+    sqrt_quad_form: Mat2,
+    inverse: Mat2,
+    squircle_parameter: f64,
+    samples: Vec<Vec2>,
+    max_arc: f64, // longest part of the boundary between neighboring samples
+}
+
+impl SquircleDistance {
+    /// None if the squircle has no area (then there is no boundary to measure the distance to)
+    pub fn new(sqrt_quad_form: Mat2, squircle_parameter: f64) -> Option<Self> {
+        let inverse = sqrt_quad_form.inverse()?;
+        let step = TAU / NR_SAMPLES as f64;
+        let mut distance = Self {
+            sqrt_quad_form,
+            inverse,
+            squircle_parameter,
+            samples: vec![],
+            max_arc: 0.0,
+        };
+        distance.samples = (0..NR_SAMPLES)
+            .map(|i| distance.boundary_point(i as f64 * step))
+            .collect();
+        distance.max_arc = (0..NR_SAMPLES)
+            .map(|i| distance.arc_length(i as f64 * step, step))
+            .fold(0.0, f64::max);
+        distance
+            .samples
+            .iter()
+            .all(|point| point.x.is_finite() && point.y.is_finite())
+            .then_some(distance)
+    }
+
+    /// Distance from the point (relative to the center) to the boundary, negative inside
+    pub fn signed_distance(&self, point: Vec2) -> f64 {
+        let step = TAU / NR_SAMPLES as f64;
+        let mut sample_distances = [0.0; NR_SAMPLES];
+        for (distance, sample) in sample_distances.iter_mut().zip(&self.samples) {
+            *distance = (point - *sample).normsq();
+        }
+
+        // Every local minimum of the samples may be near the closest point. If all samples are
+        //  as far (the center of a circle), any of them is.
+        let mut minima = (0..NR_SAMPLES)
+            .filter(|&i| {
+                let previous = sample_distances[(i + NR_SAMPLES - 1) % NR_SAMPLES];
+                let next = sample_distances[(i + 1) % NR_SAMPLES];
+                sample_distances[i] < previous && sample_distances[i] <= next
+            })
+            .collect::<Vec<_>>();
+        if minima.is_empty() {
+            minima.push(0);
+        }
+        minima.sort_by(|&i, &j| sample_distances[i].total_cmp(&sample_distances[j]));
+
+        // Near a minimum the boundary is within `max_arc` of its sample, so a minimum whose sample
+        //  is further than that beyond the closest point found so far can't have a closer one
+        let mut distance = f64::INFINITY;
+        for i in minima {
+            if sample_distances[i].sqrt() - self.max_arc > distance {
+                break;
+            }
+            distance = distance.min(self.refine(point, i as f64 * step, step).sqrt());
+        }
+
+        if (self.sqrt_quad_form * point).pnorm(self.squircle_parameter) <= 1.0 {
+            -distance
+        } else {
+            distance
+        }
+    }
+
+    /// Smallest squared distance from the point to the boundary between the parameters
+    ///  `t - step` and `t + step`, by golden section search
+    fn refine(&self, point: Vec2, t: f64, step: f64) -> f64 {
+        let inverse_golden_ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+        let distance_sq = |t: f64| (point - self.boundary_point(t)).normsq();
+
+        let (mut low, mut high) = (t - step, t + step);
+        let mut middle_low = high - inverse_golden_ratio * (high - low);
+        let mut middle_high = low + inverse_golden_ratio * (high - low);
+        let (mut distance_low, mut distance_high) =
+            (distance_sq(middle_low), distance_sq(middle_high));
+        for _ in 0..NR_REFINE_STEPS {
+            if distance_low < distance_high {
+                high = middle_high;
+                (middle_high, distance_high) = (middle_low, distance_low);
+                middle_low = high - inverse_golden_ratio * (high - low);
+                distance_low = distance_sq(middle_low);
+            } else {
+                low = middle_low;
+                (middle_low, distance_low) = (middle_high, distance_high);
+                middle_high = low + inverse_golden_ratio * (high - low);
+                distance_high = distance_sq(middle_high);
+            }
+        }
+        distance_sq(t).min(distance_low).min(distance_high)
+    }
+
+    /// Length of the boundary from parameter `t` to `t + step`, measured along a polygon through
+    ///  it and rounded up by 1% (the polygon is a bit shorter than the boundary)
+    fn arc_length(&self, t: f64, step: f64) -> f64 {
+        let points = (0..=NR_ARC_POINTS)
+            .map(|i| self.boundary_point(t + i as f64 * step / NR_ARC_POINTS as f64))
+            .collect::<Vec<_>>();
+        1.01 * points
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).norm())
+            .sum::<f64>()
+    }
+
+    /// Point of the boundary at parameter `t`
+    fn boundary_point(&self, t: f64) -> Vec2 {
+        let exponent = 2.0 / self.squircle_parameter;
+        self.inverse
+            * Vec2::from([
+                t.cos().abs().powf(exponent) * t.cos().signum(),
+                t.sin().abs().powf(exponent) * t.sin().signum(),
+            ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SquircleDistance;
+    use crate::app::math::linear_algebra::{Mat2, Vec2};
+
+    #[test]
+    fn distance_to_circle() {
+        let distance = SquircleDistance::new(Mat2::diagonal(0.5, 0.5), 2.0).unwrap();
+        for (point, expected) in [
+            ([0.0, 0.0], -2.0),
+            ([1.0, 0.0], -1.0),
+            ([3.0, 4.0], 3.0),
+            ([-0.6, 0.8], -1.0),
+            ([0.0, -2.0], 0.0),
+        ] {
+            let found = distance.signed_distance(Vec2::from(point));
+            assert!((found - expected).abs() < 1e-9, "{:?}: {}", point, found);
+        }
+    }
+
+    #[test]
+    fn distance_to_thin_ellipse() {
+        // Radius 40 along x, 0.5 along y: above the middle the closest point is straight below,
+        //  beyond the tip it is the tip
+        let distance = SquircleDistance::new(Mat2::diagonal(1.0 / 40.0, 2.0), 2.0).unwrap();
+        for (point, expected) in [([0.0, 2.5], 2.0), ([0.0, 0.3], -0.2), ([45.0, 0.0], 5.0)] {
+            let found = distance.signed_distance(Vec2::from(point));
+            assert!((found - expected).abs() < 1e-3, "{:?}: {}", point, found);
+        }
+    }
+
+    #[test]
+    fn distance_to_astroid() {
+        // Squircle parameter 2/3: the boundary x^(2/3) + y^(2/3) = 1 has its tips on the axes and is
+        //  closest to the center on the diagonals
+        let distance = SquircleDistance::new(Mat2::diagonal(1.0, 1.0), 2.0 / 3.0).unwrap();
+        for (point, expected) in [([2.0, 0.0], 1.0), ([0.0, -1.5], 0.5), ([0.0, 0.0], -0.5)] {
+            let found = distance.signed_distance(Vec2::from(point));
+            assert!((found - expected).abs() < 1e-6, "{:?}: {}", point, found);
+        }
+    }
+
+    #[test]
+    fn no_distance_without_area() {
+        assert!(SquircleDistance::new(Mat2::diagonal(f64::INFINITY, 1.0), 2.0).is_none());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::generation::distance_bound::{BoundaryDistanceBound, HALF_DIAGONAL};
+use crate::app::generation::distance_field::SquircleDistance;
+use crate::app::math::linear_algebra::{Mat2, Vec2};
+use std::f64::consts::FRAC_1_SQRT_2;
+
+/// Parts of a block near the boundary of the offset squircle are split in four until they are
+///  this small (in blocks). Then they count as in the offset squircle (blocks that are just not in
+///  it are included, rather than blocks that just are being excluded).
+const OFFSET_TOLERANCE: f64 = 1.0 / 256.0;
+
+/// The squircle grown (positive margin) or shrunk (negative margin) by a distance, i.e., the points
+///  whose signed distance to the boundary (negative inside) is at most the margin. For a positive
+///  margin this is the Minkowski sum with a disk, for a negative margin the Minkowski difference.
+///  With a margin of 0 it is the squircle itself.
+///
+/// Every point of a block is within half its diagonal of its center, so a block whose center has
+///  a signed distance (see `SquircleDistance`) below the margin minus half the diagonal is entirely
+///  in, and one above the margin plus half the diagonal is entirely out. The blocks in between are
+///  split in four until the parts are decided the same way.
+pub struct OffsetSquircle {
+    sqrt_quad_form: Mat2,
+    squircle_parameter: f64,
+    margin: f64,
+    distance: SquircleDistance,
+}
+
+impl OffsetSquircle {
+    /// None if the squircle has no area (then there is no boundary to offset)
+    pub fn new(sqrt_quad_form: Mat2, squircle_parameter: f64, margin: f64) -> Option<Self> {
+        Some(Self {
+            sqrt_quad_form,
+            squircle_parameter,
+            margin,
+            distance: SquircleDistance::new(sqrt_quad_form, squircle_parameter)?,
+        })
+    }
+
+    /// Run `include` (`intersects` or `is_contained`) for the blocks near the offset boundary. The
+    ///  grid is split in four until the parts are entirely in or out of the offset squircle, or
+    ///  single blocks, so the distance is only evaluated often near the boundary.
+    pub fn generate(
+        &self,
+        center_offset: Vec2,
+        grid_size: usize,
+        include: fn(&Self, Vec2, f64) -> bool,
+    ) -> Blocks {
+        let origin = Blocks::get_origin_float_from_grid_size(grid_size);
+        let distance_bound =
+            BoundaryDistanceBound::new(self.sqrt_quad_form, self.squircle_parameter);
+
+        let mut blocks = vec![false; grid_size.pow(2)];
+        // Parts of the grid that are not decided yet: their bottom left block and their side (in
+        //  blocks, a power of two)
+        let mut parts = vec![([0, 0], grid_size.next_power_of_two())];
+        while let Some(([x, y], side)) = parts.pop() {
+            if x >= grid_size || y >= grid_size {
+                continue;
+            }
+            // Bottom left corner of the part relative to the center of the squircle
+            let lb = Vec2::from([x as f64, y as f64]) - (origin + center_offset);
+
+            let decided = if side == 1 {
+                Some(include(self, lb, 1.0))
+            } else {
+                self.decide(lb, side as f64, distance_bound.as_ref())
+            };
+            match decided {
+                Some(is_in) => {
+                    for y in y..(y + side).min(grid_size) {
+                        for x in x..(x + side).min(grid_size) {
+                            blocks[x + y * grid_size] = is_in;
+                        }
+                    }
+                }
+                None => {
+                    let half = side / 2;
+                    parts.extend(
+                        [[x, y], [x + half, y], [x, y + half], [x + half, y + half]]
+                            .map(|corner| (corner, half)),
+                    );
+                }
+            }
+        }
+
+        Blocks::new(blocks, grid_size)
+    }
+
+    /// Is the square with bottom left corner `lb` and side `size` entirely in (`Some(true)`) or
+    ///  entirely out (`Some(false)`) of the offset squircle? First by the distance bound, which is
+    ///  cheap, then by the signed distance of the center.
+    fn decide(
+        &self,
+        lb: Vec2,
+        size: f64,
+        distance_bound: Option<&BoundaryDistanceBound>,
+    ) -> Option<bool> {
+        let center = lb + Vec2::from([0.5 * size, 0.5 * size]);
+        let half_diagonal = size * HALF_DIAGONAL;
+
+        if let Some(distance_bound) = distance_bound {
+            let m_center_pnorm = (self.sqrt_quad_form * center).pnorm(self.squircle_parameter);
+            if distance_bound.get(m_center_pnorm) > self.margin.abs() + half_diagonal {
+                return Some(m_center_pnorm <= 1.0);
+            }
+        }
+
+        let signed_distance = self.distance.signed_distance(center);
+        if signed_distance - half_diagonal > self.margin {
+            Some(false)
+        } else if signed_distance + half_diagonal <= self.margin {
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    /// Does the square with bottom left corner `lb` and side `size` have a point in the offset
+    ///  squircle? (Conservative)
+    pub fn intersects(&self, lb: Vec2, size: f64) -> bool {
+        // A corner in the squircle itself is in a grown squircle (a cheap test)
+        if self.margin >= 0.0 && self.any_corner_in(lb, size) {
+            return true;
+        }
+        let signed_distance = self
+            .distance
+            .signed_distance(lb + Vec2::from([0.5 * size, 0.5 * size]));
+        if signed_distance <= self.margin {
+            return true;
+        }
+        if signed_distance - FRAC_1_SQRT_2 * size > self.margin {
+            return false;
+        }
+        if size <= OFFSET_TOLERANCE {
+            return true;
+        }
+        quarters(lb, size)
+            .into_iter()
+            .any(|quarter| self.intersects(quarter, 0.5 * size))
+    }
+
+    /// Is the square with bottom left corner `lb` and side `size` contained in the offset
+    ///  squircle? (Contained)
+    pub fn is_contained(&self, lb: Vec2, size: f64) -> bool {
+        // A corner outside the squircle itself is outside a shrunk squircle (a cheap test)
+        if self.margin <= 0.0 && !self.all_corners_in(lb, size) {
+            return false;
+        }
+        let signed_distance = self
+            .distance
+            .signed_distance(lb + Vec2::from([0.5 * size, 0.5 * size]));
+        if signed_distance > self.margin {
+            return false;
+        }
+        if signed_distance + FRAC_1_SQRT_2 * size <= self.margin || size <= OFFSET_TOLERANCE {
+            return true;
+        }
+        quarters(lb, size)
+            .into_iter()
+            .all(|quarter| self.is_contained(quarter, 0.5 * size))
+    }
+}
+
+impl OffsetSquircle {
+    fn any_corner_in(&self, lb: Vec2, size: f64) -> bool {
+        corners(lb, size)
+            .into_iter()
+            .any(|corner| (self.sqrt_quad_form * corner).pnorm(self.squircle_parameter) <= 1.0)
+    }
+
+    fn all_corners_in(&self, lb: Vec2, size: f64) -> bool {
+        corners(lb, size)
+            .into_iter()
+            .all(|corner| (self.sqrt_quad_form * corner).pnorm(self.squircle_parameter) <= 1.0)
+    }
+}
+
+/// Corners of a square
+fn corners(lb: Vec2, size: f64) -> [Vec2; 4] {
+    [[0.0, 0.0], [size, 0.0], [0.0, size], [size, size]].map(|shift| lb + Vec2::from(shift))
+}
+
+/// Bottom left corners of the four quarters of a square
+fn quarters(lb: Vec2, size: f64) -> [Vec2; 4] {
+    let half = 0.5 * size;
+    [[0.0, 0.0], [half, 0.0], [0.0, half], [half, half]].map(|shift| lb + Vec2::from(shift))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::data_structures::blocks::Blocks;
+    use crate::app::generation::conservative::generate_alg_conservative;
+    use crate::app::generation::contained::generate_alg_contained;
+    use crate::app::math::linear_algebra::{Mat2, Vec2};
+
+    const RADIUS: f64 = 5.3;
+    const CENTER_OFFSET: [f64; 2] = [0.3, 0.2];
+    const GRID_SIZE: usize = 24;
+    const MARGINS: [f64; 5] = [-1.5, -0.5, 0.0, 0.5, 1.5];
+    // Blocks this close to touching the grown circle are not compared, they depend on the
+    //  tolerance of the subdivision
+    const TOLERANCE: f64 = 0.01;
+
+    /// The distances from the center of the circle to the nearest and the furthest point of each
+    ///  block. A circle grown by a margin is the circle with the radius grown by the margin, so
+    ///  these decide the blocks exactly.
+    fn block_distances() -> Vec<[f64; 2]> {
+        let origin = Blocks::get_origin_float_from_grid_size(GRID_SIZE);
+        (0..GRID_SIZE.pow(2))
+            .map(|i| {
+                let lb = [
+                    (i % GRID_SIZE) as f64 - origin.x - CENTER_OFFSET[0],
+                    (i / GRID_SIZE) as f64 - origin.y - CENTER_OFFSET[1],
+                ];
+                let [near_x, near_y] = lb.map(|t| f64::max(0.0, f64::max(t, -t - 1.0)));
+                let [far_x, far_y] = lb.map(|t| f64::max(t.abs(), (t + 1.0).abs()));
+                [near_x.hypot(near_y), far_x.hypot(far_y)]
+            })
+            .collect()
+    }
+
+    fn sqrt_quad_form() -> Mat2 {
+        Mat2::diagonal(1.0 / RADIUS, 1.0 / RADIUS)
+    }
+
+    #[test]
+    fn conservative_margin_grows_the_radius() {
+        for margin in MARGINS {
+            let blocks = generate_alg_conservative(
+                Vec2::from(CENTER_OFFSET),
+                sqrt_quad_form(),
+                2.0,
+                margin,
+                GRID_SIZE,
+            );
+            for (i, [nearest, _]) in block_distances().into_iter().enumerate() {
+                if (nearest - (RADIUS + margin)).abs() > TOLERANCE {
+                    assert_eq!(
+                        blocks.blocks[i],
+                        nearest <= RADIUS + margin,
+                        "block {} at margin {}",
+                        i,
+                        margin
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn contained_margin_grows_the_radius() {
+        for margin in MARGINS {
+            let blocks = generate_alg_contained(
+                Vec2::from(CENTER_OFFSET),
+                sqrt_quad_form(),
+                2.0,
+                margin,
+                GRID_SIZE,
+            );
+            for (i, [_, furthest]) in block_distances().into_iter().enumerate() {
+                if (furthest - (RADIUS + margin)).abs() > TOLERANCE {
+                    assert_eq!(
+                        blocks.blocks[i],
+                        furthest <= RADIUS + margin,
+                        "block {} at margin {}",
+                        i,
+                        margin
+                    );
+                }
+            }
+        }
+    }
+
+    /// Ellipses with one radius much larger than the other (and the tilts in between the axes),
+    ///  where the closest point to a block is hard to find
+    fn thin_ellipses() -> Vec<Mat2> {
+        [(40.0, 0.6), (0.6, 25.0), (30.0, 1.4)]
+            .into_iter()
+            .flat_map(|(radius_a, radius_b)| {
+                [0.0, 0.3, std::f64::consts::FRAC_PI_4, 1.2].map(|tilt: f64| {
+                    let (c, s) = (tilt.cos(), tilt.sin());
+                    Mat2::from_rows(
+                        1.0 / radius_a * Vec2::from([c, s]),
+                        1.0 / radius_b * Vec2::from([-s, c]),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// The smallest and the largest value of |Mx| (the ellipse is |Mx| <= 1) on each block: on
+    ///  the edges of the block unless it contains the center, and at the corners
+    fn block_ellipse_norms(sqrt_quad_form: Mat2, grid_size: usize) -> Vec<[f64; 2]> {
+        let origin = Blocks::get_origin_float_from_grid_size(grid_size);
+        (0..grid_size.pow(2))
+            .map(|i| {
+                let lb = Vec2::from([(i % grid_size) as f64, (i / grid_size) as f64])
+                    - (origin + Vec2::from(CENTER_OFFSET));
+                let corners = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]
+                    .map(|corner| sqrt_quad_form * (lb + Vec2::from(corner)));
+                let furthest = corners
+                    .iter()
+                    .map(|corner| corner.norm())
+                    .fold(0.0, f64::max);
+                let contains_center = lb.x <= 0.0 && lb.y <= 0.0 && lb.x >= -1.0 && lb.y >= -1.0;
+                let nearest = if contains_center {
+                    0.0
+                } else {
+                    (0..4)
+                        .map(|j| {
+                            let (start, direction) =
+                                (corners[j], corners[(j + 1) % 4] - corners[j]);
+                            let t = (-start.ip(direction) / direction.normsq()).clamp(0.0, 1.0);
+                            (start + t * direction).norm()
+                        })
+                        .fold(f64::INFINITY, f64::min)
+                };
+                [nearest, furthest]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn thin_ellipses_are_exact() {
+        let grid_size = 90;
+        for sqrt_quad_form in thin_ellipses() {
+            // |Mx| changes by at most the operator norm of M per block, so blocks with a value
+            //  further than this from 1 are further than the tolerance from the boundary
+            let tolerance = TOLERANCE * sqrt_quad_form.operator_norm();
+            let conservative = generate_alg_conservative(
+                Vec2::from(CENTER_OFFSET),
+                sqrt_quad_form,
+                2.0,
+                0.0,
+                grid_size,
+            );
+            let contained = generate_alg_contained(
+                Vec2::from(CENTER_OFFSET),
+                sqrt_quad_form,
+                2.0,
+                0.0,
+                grid_size,
+            );
+            for (i, [nearest, furthest]) in block_ellipse_norms(sqrt_quad_form, grid_size)
+                .into_iter()
+                .enumerate()
+            {
+                if (nearest - 1.0).abs() > tolerance {
+                    assert_eq!(conservative.blocks[i], nearest <= 1.0, "block {}", i);
+                }
+                if (furthest - 1.0).abs() > tolerance {
+                    assert_eq!(contained.blocks[i], furthest <= 1.0, "block {}", i);
+                }
+            }
+        }
+    }
+}
@@ -1,77 +1,177 @@
 // For outputting the bitmatrices + size. Always solid, we do interior removal in preprocessing. Bunch of algorithms
 
 use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::log::log_warning;
 use crate::app::math::linear_algebra::{Mat2, Vec2};
 use std::fmt::{Display, Formatter};
 
 use self::{
-    centerpoint::generate_alg_centerpoint, conservative::generate_alg_conservative,
-    contained::generate_alg_contained, empty::generate_alg_empty,
-    percentage::generate_alg_percentage,
+    centerpoint::generate_alg_centerpoint,
+    conservative::generate_alg_conservative,
+    contained::generate_alg_contained,
+    custom::CustomPredicate,
+    empty::generate_alg_empty,
+    percentage::{
+        generate_alg_percentage, generate_alg_percentage_subdivision, CoverageEstimator,
+        DEFAULT_COVERAGE_TOLERANCE,
+    },
 };
 
+pub mod cell_aspect; // public to draw the cells at their height
 mod centerpoint;
 mod conservative;
 mod contained;
+pub mod custom; // public to set the code of the Custom algorithm
+mod distance_bound;
+mod distance_field;
 mod empty;
+mod margin;
 pub mod percentage; // want it public because we use the circle intersection area as a widget
+pub mod script_shape; // public to open the shape scripts
 mod square;
 
 #[derive(Debug, PartialEq, Default, Clone, Copy)]
 pub enum Algorithm {
     #[default]
     Centerpoint,
-    Conservative,
-    Contained,
-    Percentage(f64),
+    Conservative(f64), // margin by which the shape is grown (negative: shrunk), in blocks
+    Contained(f64),
+    Percentage(f64, CoverageEstimator),
+    Custom, // blocks chosen by code or a script shape, see `custom::CustomPredicate`
     Empty,
 }
 
+/// What all layers are generated with besides their own parameters: the height of the cells (see
+///  `cell_aspect::to_cells`) and the rule of the Custom algorithm
+pub struct Generator {
+    pub cell_aspect: f64,
+    pub custom_predicate: CustomPredicate,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self {
+            cell_aspect: 1.0,
+            custom_predicate: CustomPredicate::default(),
+        }
+    }
+}
+
 // Switch between algorithms
 pub fn generate_all_blocks(
     algorithm: &Algorithm,
-    center_offset: Vec2,
-    sqrt_quad_form: Mat2,
-    squircle_parameter: f64,
-    radius_a: f64,
-    radius_b: f64,
+    slice_parameters: &SliceParameters,
     grid_size: usize,
+    generator: &Generator,
 ) -> Blocks {
+    // The algorithms work on unit square cells, so for cells that aren't square the shape is
+    //  stretched to cell coordinates (a point (x, y) of the grid is (x, aspect * y) of the shape)
+    let aspect = generator.cell_aspect;
+    let center_offset = Vec2::from(cell_aspect::to_cells(
+        [
+            slice_parameters.center_offset_x,
+            slice_parameters.center_offset_y,
+        ],
+        aspect,
+    ));
+    let sqrt_quad_form = slice_parameters.get_sqrt_quad_form() * Mat2::diagonal(1.0, aspect);
+    let squircle_parameter = slice_parameters.squircle_parameter;
+    let (radius_a, radius_b) = (slice_parameters.radius_a, slice_parameters.radius_b);
+
     match algorithm {
         Algorithm::Centerpoint => {
             generate_alg_centerpoint(center_offset, sqrt_quad_form, squircle_parameter, grid_size)
         }
-        Algorithm::Conservative => {
-            generate_alg_conservative(center_offset, sqrt_quad_form, squircle_parameter, grid_size)
-        }
-        Algorithm::Contained => {
-            generate_alg_contained(center_offset, sqrt_quad_form, squircle_parameter, grid_size)
-        }
-        Algorithm::Percentage(percentage) => generate_alg_percentage(
-            f64::max(radius_a, radius_b),
+        Algorithm::Conservative(margin) => generate_alg_conservative(
+            center_offset,
+            sqrt_quad_form,
+            squircle_parameter,
+            *margin,
+            grid_size,
+        ),
+        Algorithm::Contained(margin) => generate_alg_contained(
             center_offset,
-            *percentage,
+            sqrt_quad_form,
+            squircle_parameter,
+            *margin,
             grid_size,
         ),
+        Algorithm::Percentage(percentage, CoverageEstimator::Subdivision(tolerance)) => {
+            generate_alg_percentage_subdivision(
+                center_offset,
+                sqrt_quad_form,
+                squircle_parameter,
+                *percentage,
+                *tolerance,
+                grid_size,
+            )
+        }
+        Algorithm::Percentage(percentage, CoverageEstimator::Exact) if aspect != 1.0 => {
+            log_warning("The exact percentage algorithm only supports square cells, estimating by subdivision");
+            generate_alg_percentage_subdivision(
+                center_offset,
+                sqrt_quad_form,
+                squircle_parameter,
+                *percentage,
+                DEFAULT_COVERAGE_TOLERANCE,
+                grid_size,
+            )
+        }
+        Algorithm::Percentage(percentage, CoverageEstimator::Exact) => {
+            if radius_a != radius_b || squircle_parameter != 2.0 {
+                log_warning("The percentage algorithm only supports circles, using the circle with the larger radius");
+            }
+            generate_alg_percentage(
+                f64::max(radius_a, radius_b),
+                center_offset,
+                *percentage,
+                grid_size,
+            )
+        }
+        Algorithm::Custom => {
+            generator
+                .custom_predicate
+                .generate(slice_parameters, grid_size, aspect)
+        }
         Algorithm::Empty => generate_alg_empty(grid_size),
     }
 }
 
+impl Algorithm {
+    /// Distance by which the shape is grown before voxelization (negative: shrunk)
+    pub fn margin(&self) -> f64 {
+        match self {
+            Algorithm::Conservative(margin) | Algorithm::Contained(margin) => *margin,
+            _ => 0.0,
+        }
+    }
+}
+
 impl Display for Algorithm {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Algorithm::Centerpoint => {
                 write!(f, "Centerpoint")
             }
-            Algorithm::Conservative => {
+            Algorithm::Conservative(margin) if *margin == 0.0 => {
                 write!(f, "Conservative")
             }
-            Algorithm::Contained => {
+            Algorithm::Conservative(margin) => {
+                write!(f, "Conservative, margin {}", margin)
+            }
+            Algorithm::Contained(margin) if *margin == 0.0 => {
                 write!(f, "Contained")
             }
-            Algorithm::Percentage(percentage) => {
+            Algorithm::Contained(margin) => {
+                write!(f, "Contained, margin {}", margin)
+            }
+            Algorithm::Percentage(percentage, _) => {
                 write!(f, "Percentage, {:.0}%", percentage * 100.0)
             }
+            Algorithm::Custom => {
+                write!(f, "Custom")
+            }
             Algorithm::Empty => {
                 write!(f, "Empty")
             }
@@ -1,8 +1,33 @@
 use std::f64::consts::PI;
 
 use crate::app::data_structures::blocks::Blocks;
+use crate::app::generation::distance_bound::{BoundaryDistanceBound, HALF_DIAGONAL};
 use crate::app::math::circle_geometry::{area_of_semicircle_section, intersection_hline_circle};
-use crate::app::math::linear_algebra::Vec2;
+use crate::app::math::linear_algebra::{Mat2, Vec2};
+
+/// Default tolerance of the subdivision estimator (as a fraction of a block)
+pub const DEFAULT_COVERAGE_TOLERANCE: f64 = 0.01;
+
+/// Without a bound on the distance to the boundary (squircle parameter below 1) no part of a block
+///  is decided before the parts are counted, so blocks are split into 4^UNBOUNDED_SUBDIVISION_DEPTH
+///  parts instead of reaching the tolerance
+const UNBOUNDED_SUBDIVISION_DEPTH: usize = 4;
+
+/// Stop subdividing at this many undecided parts of a block, in case the tolerance is too small to
+///  reach in reasonable time (the undecided parts are along the boundary, so their number doubles
+///  as their area halves)
+const MAX_UNDECIDED_PARTS: usize = 1 << 16;
+
+/// How the Percentage algorithm computes which part of a block is covered by the shape
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum CoverageEstimator {
+    /// Exact geometry. Only for circles, other shapes use the circle with the larger radius
+    #[default]
+    Exact,
+    /// Adaptive subdivision of the block, works for any shape. The covered part is off by at most
+    ///  the tolerance (as a fraction of the block) for squircle parameters from 1 up
+    Subdivision(f64),
+}
 
 // logic + geometry + integration
 // Percentage is to be supplied as float between 0 and 1 (note that unexpected behaviour may occur if it 0.0 or 1.0 exactly due to numerical errors)
@@ -44,6 +69,93 @@ pub fn generate_alg_percentage(
     Blocks::new(blocks, grid_size)
 }
 
+/// Percentage algorithm for any shape, estimating the covered part of each block by subdivision
+pub fn generate_alg_percentage_subdivision(
+    center_offset: Vec2,
+    sqrt_quad_form: Mat2,
+    squircle_parameter: f64,
+    percentage: f64,
+    tolerance: f64,
+    grid_size: usize,
+) -> Blocks {
+    let origin = Blocks::get_origin_float_from_grid_size(grid_size);
+    let distance_bound = BoundaryDistanceBound::new(sqrt_quad_form, squircle_parameter);
+
+    let blocks = (0..grid_size.pow(2))
+        .map(|i| {
+            // Bottom left corner of the block relative to the center of the squircle
+            let lb = Vec2::from([(i % grid_size) as f64, (i / grid_size) as f64])
+                - (origin + center_offset);
+
+            cell_squircle_coverage(
+                lb,
+                sqrt_quad_form,
+                squircle_parameter,
+                distance_bound.as_ref(),
+                tolerance,
+            ) >= percentage
+        })
+        .collect();
+
+    Blocks::new(blocks, grid_size)
+}
+
+/// Estimate the part of the block with bottom left corner `lb` covered by the squircle, up to
+///  `tolerance`. Parts of the block far enough from the boundary are entirely in or out (by the
+///  distance bound). The other parts are split in four until their total area is below the
+///  tolerance, and then counted by their centerpoint, so the estimate is off by at most their
+///  area. Without a distance bound nothing is decided early, then the block is split a fixed
+///  number of times.
+fn cell_squircle_coverage(
+    lb: Vec2,
+    sqrt_quad_form: Mat2,
+    squircle_parameter: f64,
+    distance_bound: Option<&BoundaryDistanceBound>,
+    tolerance: f64,
+) -> f64 {
+    let m_pnorm = |point: Vec2| (sqrt_quad_form * point).pnorm(squircle_parameter);
+
+    let mut covered = 0.0;
+    let mut side = 1.0;
+    let mut uncertain = vec![lb]; // bottom left corners of the parts that are not decided yet
+
+    for depth in 0.. {
+        let half_side = Vec2::from([side / 2.0, side / 2.0]);
+        uncertain.retain(|&corner| {
+            let center_pnorm = m_pnorm(corner + half_side);
+            let decided =
+                distance_bound.is_some_and(|bound| bound.get(center_pnorm) > side * HALF_DIAGONAL);
+            if decided && center_pnorm <= 1.0 {
+                covered += side * side;
+            }
+            !decided
+        });
+
+        if uncertain.len() as f64 * side * side <= tolerance
+            || uncertain.len() >= MAX_UNDECIDED_PARTS
+            || (distance_bound.is_none() && depth == UNBOUNDED_SUBDIVISION_DEPTH)
+        {
+            let nr_in = uncertain
+                .iter()
+                .filter(|&&corner| m_pnorm(corner + half_side) <= 1.0)
+                .count();
+            covered += nr_in as f64 * side * side;
+            break;
+        }
+
+        side /= 2.0;
+        uncertain = uncertain
+            .into_iter()
+            .flat_map(|corner| {
+                [[0.0, 0.0], [side, 0.0], [0.0, side], [side, side]]
+                    .map(|offset| corner + Vec2::from(offset))
+            })
+            .collect();
+    }
+
+    covered
+}
+
 pub fn cell_disk_intersection_area(radius: f64, x_center: f64, y_center: f64) -> f64 {
     let area: f64; // We compute this area and see if its less than the percentage parameter
 
@@ -0,0 +1,287 @@
+use std::cell::RefCell;
+use std::f64::consts::PI;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, ImmutableString, Map, AST};
+
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::generation::custom::CustomPredicate;
+use crate::app::log::log_warning;
+use crate::app::scripting::new_engine;
+
+/// Files with this extension are opened as shape scripts
+pub const SCRIPT_EXTENSIONS: &[&str] = &["rhai"];
+
+// Key in the app storage, so that the scripts are opened again on the next start
+pub const SHAPE_SCRIPT_PATHS_KEY: &str = "shape_script_paths";
+
+/// Whether the file is opened as a shape script, by its extension
+pub fn is_script_file(name: &str) -> bool {
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension);
+    extension.is_some_and(|extension| {
+        SCRIPT_EXTENSIONS
+            .iter()
+            .any(|script_extension| extension.eq_ignore_ascii_case(script_extension))
+    })
+}
+
+/// A compiled script, shared by the shapes it registered (their functions are run in it)
+struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+/// A parameter of a script shape, set with a slider between `range`
+#[derive(Debug, Clone)]
+pub struct ShapeParameter {
+    pub name: String,
+    pub range: [f64; 2],
+    pub value: f64,
+}
+
+/// A shape defined by a Rhai script, for shapes that aren't superellipses (hearts, stars, gears).
+///  A script registers any number of shapes with
+///
+/// ```rhai
+/// register_shape("Heart", [#{ name: "size", min: 1.0, max: 30.0, value: 10.0 }],
+///     |x, y, p| { let x = x / p.size; let y = y / p.size; (x*x + y*y - 1) ** 3 <= x*x * y*y*y },
+///     |t, p| [p.size * sin(t) ** 3, p.size * (13*cos(t) - 5*cos(2*t) - 2*cos(3*t) - cos(4*t)) / 16]);
+/// ```
+///
+///  The inclusion function says whether a point `x`, `y` is in the shape, the outline function
+///  gives the point of the outline at `t` from 0 to 2π (drawn in the viewport). Both get the values
+///  of the parameters in the map `p`. The points are relative to the center of the layer and
+///  turned with its tilt, in blocks (also for cells that aren't square).
+#[derive(Clone)]
+pub struct ScriptShape {
+    pub name: String,
+    pub parameters: Vec<ShapeParameter>,
+    inside: FnPtr,
+    outline: FnPtr,
+    script: Rc<Script>,
+}
+
+impl ScriptShape {
+    /// The parameter values, as the map `p` of the functions
+    fn parameter_map(&self) -> Map {
+        self.parameters
+            .iter()
+            .map(|parameter| {
+                (
+                    parameter.name.as_str().into(),
+                    Dynamic::from_float(parameter.value),
+                )
+            })
+            .collect()
+    }
+
+    /// Whether the point (relative to the center and untilted) is in the shape
+    pub fn inside(&self, x: f64, y: f64) -> Result<bool, String> {
+        self.inside
+            .call(
+                &self.script.engine,
+                &self.script.ast,
+                (x, y, self.parameter_map()),
+            )
+            .map_err(|error| format!("{} at ({}, {}): {}", self.name, x, y, error))
+    }
+
+    /// Points of the outline (relative to the center and untilted), for `t` from 0 to 2π
+    pub fn outline(&self) -> Result<Vec<[f64; 2]>, String> {
+        let parameters = self.parameter_map();
+        (0..=1000)
+            .map(|i| {
+                let t = (i as f64) * (2.0 * PI) / 1000.0;
+                let point: Array = self
+                    .outline
+                    .call(
+                        &self.script.engine,
+                        &self.script.ast,
+                        (t, parameters.clone()),
+                    )
+                    .map_err(|error| format!("{} at t = {}: {}", self.name, t, error))?;
+                match point[..] {
+                    [ref x, ref y] => Ok([to_float(x)?, to_float(y)?]),
+                    _ => Err(format!(
+                        "{}: the outline is a point [x, y], not {} numbers",
+                        self.name,
+                        point.len()
+                    )),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A point of the shape in viewport coordinates to the coordinates of the script shape (relative to
+///  the center, turned back by the tilt)
+pub fn to_shape_coordinates(slice_parameters: &SliceParameters, [x, y]: [f64; 2]) -> [f64; 2] {
+    let (sin, cos) = slice_parameters.tilt.sin_cos();
+    let [dx, dy] = [
+        x - slice_parameters.center_offset_x,
+        y - slice_parameters.center_offset_y,
+    ];
+    [dx * cos + dy * sin, -dx * sin + dy * cos]
+}
+
+/// The inverse of `to_shape_coordinates`
+pub fn from_shape_coordinates(slice_parameters: &SliceParameters, [x, y]: [f64; 2]) -> [f64; 2] {
+    let (sin, cos) = slice_parameters.tilt.sin_cos();
+    [
+        slice_parameters.center_offset_x + x * cos - y * sin,
+        slice_parameters.center_offset_y + x * sin + y * cos,
+    ]
+}
+
+fn to_float(value: &Dynamic) -> Result<f64, String> {
+    value
+        .as_float()
+        .or_else(|_| value.as_int().map(|int| int as f64))
+        .map_err(|type_name| format!("expected a number, not {}", type_name))
+}
+
+/// A shape as the script registers it (the functions are tied to the script after it ran)
+struct Registration {
+    name: String,
+    parameters: Vec<ShapeParameter>,
+    inside: FnPtr,
+    outline: FnPtr,
+}
+
+/// Run the script and return the shapes it registers
+pub fn load_shape_script(code: &str) -> Result<Vec<ScriptShape>, String> {
+    let registrations: Rc<RefCell<Vec<Registration>>> = Rc::default();
+    // The limits hold for the run of the script and for each call of its functions (once per
+    //  sample point), also for a script opened on startup
+    let mut engine = new_engine();
+    let registered = registrations.clone();
+    engine.register_fn(
+        "register_shape",
+        move |name: ImmutableString,
+              parameters: Array,
+              inside: FnPtr,
+              outline: FnPtr|
+              -> Result<(), Box<EvalAltResult>> {
+            let parameters = parameters
+                .iter()
+                .map(to_shape_parameter)
+                .collect::<Result<_, String>>()
+                .map_err(|error| format!("Shape {}: {}", name, error))?;
+            registered.borrow_mut().push(Registration {
+                name: name.to_string(),
+                parameters,
+                inside,
+                outline,
+            });
+            Ok(())
+        },
+    );
+
+    let ast = engine.compile(code).map_err(|error| error.to_string())?;
+    engine.run_ast(&ast).map_err(|error| error.to_string())?;
+    let registrations = registrations.take();
+    if registrations.is_empty() {
+        return Err("The script doesn't register a shape (with register_shape)".to_string());
+    }
+
+    let script = Rc::new(Script { engine, ast });
+    Ok(registrations
+        .into_iter()
+        .map(|registration| ScriptShape {
+            name: registration.name,
+            parameters: registration.parameters,
+            inside: registration.inside,
+            outline: registration.outline,
+            script: script.clone(),
+        })
+        .collect())
+}
+
+/// A parameter from a map `#{ name: "size", min: 1.0, max: 30.0, value: 10.0 }` (without a value
+///  it starts at the minimum)
+fn to_shape_parameter(value: &Dynamic) -> Result<ShapeParameter, String> {
+    let map = value
+        .read_lock::<Map>()
+        .ok_or_else(|| format!("a parameter is a map, not {}", value.type_name()))?;
+    let field = |key: &str| {
+        map.get(key)
+            .ok_or_else(|| format!("a parameter has no {}", key))
+    };
+
+    let name = field("name")?
+        .clone()
+        .into_string()
+        .map_err(|type_name| format!("the name of a parameter is a string, not {}", type_name))?;
+    let range = [to_float(field("min")?)?, to_float(field("max")?)?];
+    if range[0] > range[1] {
+        return Err(format!("the minimum of {} is above its maximum", name));
+    }
+    let value = match map.get("value") {
+        Some(value) => to_float(value)?.clamp(range[0], range[1]),
+        None => range[0],
+    };
+    Ok(ShapeParameter { name, range, value })
+}
+
+/// The shapes of the opened scripts, and the one the Custom algorithm uses (if any)
+#[derive(Default)]
+pub struct ShapeLibrary {
+    pub shapes: Vec<ScriptShape>,
+    pub paths: Vec<PathBuf>, // of the scripts, to open them again on the next start
+    active: Option<usize>,
+    outline: Vec<[f64; 2]>, // of the active shape, drawn for every frame
+}
+
+impl ShapeLibrary {
+    /// Run the script and add its shapes, replacing shapes with the same name (e.g. when a script
+    ///  is opened again after editing it). Output: the number of shapes.
+    pub fn open_script(
+        &mut self,
+        path: Option<PathBuf>,
+        code: &str,
+        custom_predicate: &mut CustomPredicate,
+    ) -> Result<usize, String> {
+        let shapes = load_shape_script(code)?;
+        let nr_shapes = shapes.len();
+        if let Some(path) = path.filter(|path| !self.paths.contains(path)) {
+            self.paths.push(path);
+        }
+
+        for shape in shapes {
+            match self.shapes.iter().position(|old| old.name == shape.name) {
+                Some(index) => self.shapes[index] = shape,
+                None => self.shapes.push(shape),
+            }
+        }
+        if let Some(index) = self.active {
+            self.activate(Some(index), custom_predicate);
+        }
+        Ok(nr_shapes)
+    }
+
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+
+    /// The outline of the active shape (relative to the center and untilted)
+    pub fn outline(&self) -> Option<&[[f64; 2]]> {
+        self.active.map(|_| self.outline.as_slice())
+    }
+
+    /// Use the shape for the Custom algorithm from now on (None: use the code of the Custom
+    ///  algorithm again). Also after its parameters changed.
+    pub fn activate(&mut self, index: Option<usize>, custom_predicate: &mut CustomPredicate) {
+        self.active = index.filter(|index| *index < self.shapes.len());
+        let shape = self.active.map(|index| self.shapes[index].clone());
+        self.outline = match shape.as_ref().map(ScriptShape::outline) {
+            Some(Ok(outline)) => outline,
+            Some(Err(error)) => {
+                log_warning(format!("Couldn't draw the outline of {}", error));
+                vec![]
+            }
+            None => vec![],
+        };
+        custom_predicate.set_shape(shape);
+    }
+}
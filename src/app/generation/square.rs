@@ -53,6 +53,11 @@ impl Square {
         }
     }
 
+    /// Center of the square in modified coordinates
+    pub fn m_center(&self) -> Vec2 {
+        0.5 * (self.m_lb + self.m_rt)
+    }
+
     /// Does the supplied check hold for all corners?
     pub fn for_all_corners<F>(&self, check: F) -> bool
     where
@@ -0,0 +1,238 @@
+use std::io::Cursor;
+
+use image::{ImageOutputFormat, Rgb, RgbImage};
+
+use crate::app::formatting::minecraft_z;
+use crate::app::pixel_font::{glyph, GLYPH_HEIGHT, GLYPH_SPACING, GLYPH_WIDTH};
+
+// A map shows 128 by 128 blocks, the map at the origin covers -64 to 63 in X and Z
+const MAP_SIZE: isize = 128;
+const MAP_OFFSET: isize = 64;
+
+const MAX_PIXELS_PER_BLOCK: u32 = 8;
+const MAX_IMAGE_SIZE: u32 = 4096; // larger layers get fewer pixels per block
+const MIN_GRID_PIXELS_PER_BLOCK: u32 = 4; // below this the grid lines would hide the blocks
+const CHUNK_SIZE: isize = 16; // chunk borders get darker grid lines
+
+// Label strip above the blocks, in a 3 by 5 pixel font scaled up
+const FONT_SCALE: u32 = 3;
+const LABEL_MARGIN: u32 = 2 * FONT_SCALE;
+const LABEL_HEIGHT: u32 = GLYPH_HEIGHT as u32 * FONT_SCALE + 2 * LABEL_MARGIN;
+
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const BLOCK: Rgb<u8> = Rgb([110, 110, 110]);
+const GRID: Rgb<u8> = Rgb([215, 215, 215]);
+const CHUNK_GRID: Rgb<u8> = Rgb([150, 150, 150]);
+const TEXT: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// The blocks of a layer as a picture to build from: north up (like a map in game), with a label
+///  above saying which X and Z the picture covers (and the layer, after L)
+pub struct LayerImage {
+    pub x_range: [isize; 2], // inclusive
+    pub z_range: [isize; 2],
+    pub layer: isize,
+}
+
+impl LayerImage {
+    /// The whole layer (the bounds of the blocks). None if there are no blocks.
+    pub fn whole_layer(blocks: &[[isize; 2]], layer: isize) -> Option<Self> {
+        let x_range = [
+            blocks.iter().map(|[x, _]| *x).min()?,
+            blocks.iter().map(|[x, _]| *x).max()?,
+        ];
+        let z_range = [
+            blocks.iter().map(|[_, z]| *z).min()?,
+            blocks.iter().map(|[_, z]| *z).max()?,
+        ];
+        Some(Self {
+            x_range,
+            z_range,
+            layer,
+        })
+    }
+
+    /// The maps (aligned like the maps in game) that contain some of the blocks
+    pub fn map_tiles(blocks: &[[isize; 2]], layer: isize) -> Vec<Self> {
+        let mut maps: Vec<[isize; 2]> = blocks
+            .iter()
+            .map(|[x, z]| {
+                [
+                    (x + MAP_OFFSET).div_euclid(MAP_SIZE),
+                    (z + MAP_OFFSET).div_euclid(MAP_SIZE),
+                ]
+            })
+            .collect();
+        maps.sort_by_key(|[map_x, map_z]| (*map_z, *map_x)); // from north west, row by row
+        maps.dedup();
+
+        maps.into_iter()
+            .map(|[map_x, map_z]| {
+                let [x, z] = [map_x * MAP_SIZE - MAP_OFFSET, map_z * MAP_SIZE - MAP_OFFSET];
+                Self {
+                    x_range: [x, x + MAP_SIZE - 1],
+                    z_range: [z, z + MAP_SIZE - 1],
+                    layer,
+                }
+            })
+            .collect()
+    }
+
+    pub fn label(&self) -> String {
+        format!(
+            "X {}..{} Z {}..{} L {}",
+            self.x_range[0], self.x_range[1], self.z_range[0], self.z_range[1], self.layer
+        )
+    }
+
+    pub fn file_name(&self) -> String {
+        format!(
+            "layer_{}_x_{}_z_{}.png",
+            self.layer, self.x_range[0], self.z_range[0]
+        )
+    }
+
+    /// Draw the blocks (X and Minecraft Z, see `blocks_to_world`) that are in the image, as PNG
+    pub fn to_png(&self, blocks: &[[isize; 2]]) -> Vec<u8> {
+        let size = [
+            (self.x_range[1] - self.x_range[0] + 1) as u32,
+            (self.z_range[1] - self.z_range[0] + 1) as u32,
+        ];
+        let scale = (MAX_IMAGE_SIZE / size[0].max(size[1])).clamp(1, MAX_PIXELS_PER_BLOCK);
+        let label = self.label();
+        let label_width = ((GLYPH_WIDTH + GLYPH_SPACING) * label.len() + 1) as u32 * FONT_SCALE;
+        let width = (size[0] * scale).max(label_width + 2 * LABEL_MARGIN);
+        let mut image = RgbImage::from_pixel(width, size[1] * scale + LABEL_HEIGHT, BACKGROUND);
+
+        // Block (x, z) has its top left pixel here
+        let origin = |x: isize, z: isize| {
+            [
+                (x - self.x_range[0]) as u32 * scale,
+                (z - self.z_range[0]) as u32 * scale + LABEL_HEIGHT,
+            ]
+        };
+
+        for [x, z] in blocks {
+            if (self.x_range[0]..=self.x_range[1]).contains(x)
+                && (self.z_range[0]..=self.z_range[1]).contains(z)
+            {
+                let [left, top] = origin(*x, *z);
+                fill_rect(&mut image, [left, top], [scale, scale], BLOCK);
+            }
+        }
+
+        if scale >= MIN_GRID_PIXELS_PER_BLOCK {
+            let [bottom, right] = [size[1] * scale + LABEL_HEIGHT, size[0] * scale];
+            for x in self.x_range[0]..=self.x_range[1] + 1 {
+                let color = if x.rem_euclid(CHUNK_SIZE) == 0 {
+                    CHUNK_GRID
+                } else {
+                    GRID
+                };
+                let [left, _] = origin(x, self.z_range[0]);
+                let left = left.min(right - 1);
+                fill_rect(
+                    &mut image,
+                    [left, LABEL_HEIGHT],
+                    [1, bottom - LABEL_HEIGHT],
+                    color,
+                );
+            }
+            for z in self.z_range[0]..=self.z_range[1] + 1 {
+                let color = if z.rem_euclid(CHUNK_SIZE) == 0 {
+                    CHUNK_GRID
+                } else {
+                    GRID
+                };
+                let [_, top] = origin(self.x_range[0], z);
+                fill_rect(&mut image, [0, top.min(bottom - 1)], [right, 1], color);
+            }
+        }
+
+        draw_text(&mut image, &label, [LABEL_MARGIN, LABEL_MARGIN]);
+
+        let mut png = Cursor::new(vec![]);
+        image
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .expect("writing to memory doesn't fail");
+        png.into_inner()
+    }
+}
+
+/// One image per layer (the layer and its blocks, from the lowest layer up), all with the bounds of
+///  the whole stack so the blocks line up from one image to the next and have the same scale. The
+///  files are numbered in order of the layers, layers without blocks give an empty image.
+pub fn stack_images(layers: &[(isize, Vec<[isize; 2]>)]) -> Vec<(String, Vec<u8>)> {
+    let all_blocks: Vec<[isize; 2]> = layers
+        .iter()
+        .flat_map(|(_, blocks)| blocks.iter().copied())
+        .collect();
+    let Some(bounds) = LayerImage::whole_layer(&all_blocks, 0) else {
+        return vec![];
+    };
+    let digits = layers.len().to_string().len();
+
+    layers
+        .iter()
+        .enumerate()
+        .map(|(index, (layer, blocks))| {
+            let image = LayerImage {
+                layer: *layer,
+                ..bounds
+            };
+            let file_name = format!("{:0digits$}_layer_{}.png", index + 1, layer);
+            (file_name, image.to_png(blocks))
+        })
+        .collect()
+}
+
+/// World X and Minecraft Z (north is negative) of the blocks (left bottom viewport coordinates),
+///  shifted by `translation` (see `View::world_translation`)
+pub fn blocks_to_world(
+    coords: &[[f64; 2]],
+    translation: [isize; 2],
+    flip_z: bool,
+) -> Vec<[isize; 2]> {
+    coords
+        .iter()
+        .map(|[x, y]| {
+            [
+                *x as isize + translation[0],
+                minecraft_z(*y as isize + translation[1], flip_z),
+            ]
+        })
+        .collect()
+}
+
+fn fill_rect(
+    image: &mut RgbImage,
+    [left, top]: [u32; 2],
+    [width, height]: [u32; 2],
+    color: Rgb<u8>,
+) {
+    for x in left..(left + width).min(image.width()) {
+        for y in top..(top + height).min(image.height()) {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+/// Write the text with its top left corner at `position`, characters without a glyph are skipped
+fn draw_text(image: &mut RgbImage, text: &str, position: [u32; 2]) {
+    for (index, character) in text.chars().enumerate() {
+        let Some(rows) = glyph(character) else {
+            continue;
+        };
+        let left = position[0] + ((GLYPH_WIDTH + GLYPH_SPACING) * index) as u32 * FONT_SCALE;
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH as u32 {
+                if bits & (1 << (GLYPH_WIDTH as u32 - 1 - column)) != 0 {
+                    let corner = [
+                        left + column * FONT_SCALE,
+                        position[1] + row as u32 * FONT_SCALE,
+                    ];
+                    fill_rect(image, corner, [FONT_SCALE, FONT_SCALE], TEXT);
+                }
+            }
+        }
+    }
+}
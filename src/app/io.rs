@@ -0,0 +1,279 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::task::{Poll, Wake, Waker};
+use std::thread;
+
+use eframe::egui::{Context, DroppedFile};
+use rfd::{AsyncFileDialog, FileHandle};
+
+use crate::app::log::{log_info, log_warning};
+
+/// How long a notification is shown (seconds)
+const TOAST_DURATION: f64 = 4.0;
+
+/// Ask the user where to save a file and write `contents` there
+pub struct SaveRequest {
+    pub title: String,
+    pub file_name: String, // suggested name
+    pub filter_name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub contents: Vec<u8>,
+}
+
+/// Ask the user for a folder and write all files there (e.g. the tiles of an image)
+pub struct SaveFolderRequest {
+    pub title: String,
+    pub files: Vec<(String, Vec<u8>)>, // name and contents
+}
+
+/// Ask the user for a file and read it. It is handed back in `IoService::opened`, like a file
+///  dropped on the window.
+pub struct OpenRequest {
+    pub title: String,
+    pub filter_name: &'static str,
+    pub extensions: &'static [&'static str],
+}
+
+enum IoRequest {
+    Save(SaveRequest),
+    SaveFolder(SaveFolderRequest),
+    Open(OpenRequest),
+}
+
+enum IoOutcome {
+    Saved(PathBuf),
+    Opened(DroppedFile),
+    Failed(String),
+}
+
+/// A file dialog that is open, with the request to carry out at the path the user picks
+struct PendingDialog {
+    dialog: Pin<Box<dyn Future<Output = Option<FileHandle>>>>,
+    request: IoRequest,
+}
+
+/// Wakes the UI when a file dialog is closed, so that its future is polled again
+struct RepaintWaker(Context);
+
+impl Wake for RepaintWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.request_repaint();
+    }
+}
+
+/// Notification of a finished (or failed) file operation
+pub struct Toast {
+    pub text: String,
+    pub is_error: bool,
+    pub expires: f64, // time as in `egui::InputState::time`
+}
+
+/// File dialogs and file IO that don't freeze the UI while the user picks a file or while a large
+///  file is written. The dialogs are async (native dialogs have to be opened on the main thread on
+///  some platforms) and are polled every frame, the files are read and written on a background
+///  thread. All import and export goes through this.
+pub struct IoService {
+    dialogs: Vec<PendingDialog>,
+    waker: Waker,
+    jobs: Sender<(IoRequest, PathBuf)>,
+    outcomes: Receiver<IoOutcome>,
+    pub nr_pending: usize,
+    pub toasts: Vec<Toast>,
+    pub opened: Vec<DroppedFile>, // files read since the last poll, to be opened by the app
+    time: f64,                    // of the last poll
+}
+
+impl IoService {
+    /// Start the background thread. It requests a repaint of `ctx` when a job is done.
+    pub fn new(ctx: Context) -> Self {
+        let (jobs, jobs_receiver) = channel::<(IoRequest, PathBuf)>();
+        let (outcomes_sender, outcomes) = channel();
+
+        let repaint_ctx = ctx.clone();
+        thread::spawn(move || {
+            // Stops when the IoService (and so the sender) is dropped
+            for (request, path) in jobs_receiver {
+                let outcome = match request {
+                    IoRequest::Save(request) => save_file(request, path),
+                    IoRequest::SaveFolder(request) => save_folder(request, path),
+                    IoRequest::Open(_) => open_file(path),
+                };
+
+                if outcomes_sender.send(outcome).is_err() {
+                    break;
+                }
+                repaint_ctx.request_repaint();
+            }
+        });
+
+        Self {
+            dialogs: vec![],
+            waker: Waker::from(Arc::new(RepaintWaker(ctx))),
+            jobs,
+            outcomes,
+            nr_pending: 0,
+            toasts: vec![],
+            opened: vec![],
+            time: 0.0,
+        }
+    }
+
+    pub fn save(&mut self, request: SaveRequest) {
+        self.send(IoRequest::Save(request));
+    }
+
+    pub fn save_folder(&mut self, request: SaveFolderRequest) {
+        self.send(IoRequest::SaveFolder(request));
+    }
+
+    pub fn open(&mut self, request: OpenRequest) {
+        self.send(IoRequest::Open(request));
+    }
+
+    /// Open the file dialog of the request, it is carried out when the user has picked a path
+    fn send(&mut self, request: IoRequest) {
+        let dialog: Pin<Box<dyn Future<Output = Option<FileHandle>>>> = match &request {
+            IoRequest::Save(request) => Box::pin(
+                AsyncFileDialog::new()
+                    .set_title(&request.title)
+                    .set_file_name(&request.file_name)
+                    .add_filter(request.filter_name, request.extensions)
+                    .save_file(),
+            ),
+            IoRequest::SaveFolder(request) => Box::pin(
+                AsyncFileDialog::new()
+                    .set_title(&request.title)
+                    .pick_folder(),
+            ),
+            IoRequest::Open(request) => Box::pin(
+                AsyncFileDialog::new()
+                    .set_title(&request.title)
+                    .add_filter(request.filter_name, request.extensions)
+                    .pick_file(),
+            ),
+        };
+        self.dialogs.push(PendingDialog { dialog, request });
+        self.nr_pending += 1;
+        // The dialog is opened when it is first polled
+        self.waker.wake_by_ref();
+    }
+
+    /// Hand the paths of the closed dialogs to the background thread, turn the finished jobs into
+    ///  notifications and remove the notifications that have expired. Call every frame with the
+    ///  current time.
+    pub fn poll(&mut self, time: f64) {
+        self.time = time;
+
+        let waker = self.waker.clone();
+        let mut context = std::task::Context::from_waker(&waker);
+        let mut index = 0;
+        while index < self.dialogs.len() {
+            let Poll::Ready(handle) = self.dialogs[index].dialog.as_mut().poll(&mut context) else {
+                index += 1;
+                continue;
+            };
+            let request = self.dialogs.remove(index).request;
+            match handle {
+                None => self.nr_pending = self.nr_pending.saturating_sub(1), // cancelled
+                Some(handle) => {
+                    if self
+                        .jobs
+                        .send((request, handle.path().to_path_buf()))
+                        .is_err()
+                    {
+                        self.nr_pending = self.nr_pending.saturating_sub(1);
+                        self.notify("The file thread has stopped, couldn't save", true);
+                    }
+                }
+            }
+        }
+
+        while let Ok(outcome) = self.outcomes.try_recv() {
+            self.nr_pending = self.nr_pending.saturating_sub(1);
+            match outcome {
+                IoOutcome::Saved(path) => {
+                    let text = format!("Saved {}", path.display());
+                    log_info(&text);
+                    self.notify(text, false);
+                }
+                IoOutcome::Opened(file) => self.opened.push(file),
+                IoOutcome::Failed(text) => {
+                    log_warning(&text);
+                    self.notify(text, true);
+                }
+            }
+        }
+
+        self.toasts.retain(|toast| toast.expires > time);
+    }
+
+    pub fn notify(&mut self, text: impl Into<String>, is_error: bool) {
+        self.toasts.push(Toast {
+            text: text.into(),
+            is_error,
+            expires: self.time + TOAST_DURATION,
+        });
+    }
+}
+
+fn save_file(request: SaveRequest, path: PathBuf) -> IoOutcome {
+    match std::fs::write(&path, &request.contents) {
+        Ok(()) => IoOutcome::Saved(path),
+        Err(error) => IoOutcome::Failed(format!("Couldn't write {}: {}", path.display(), error)),
+    }
+}
+
+fn open_file(path: PathBuf) -> IoOutcome {
+    match std::fs::read(&path) {
+        Ok(bytes) => IoOutcome::Opened(DroppedFile {
+            path: Some(path),
+            bytes: Some(bytes.into()),
+            ..Default::default()
+        }),
+        Err(error) => IoOutcome::Failed(format!("Couldn't read {}: {}", path.display(), error)),
+    }
+}
+
+/// Write the files into the chosen folder, stops at the first file that can't be written
+fn save_folder(request: SaveFolderRequest, folder: PathBuf) -> IoOutcome {
+    for (name, contents) in &request.files {
+        let path = folder.join(name);
+        if let Err(error) = std::fs::write(&path, contents) {
+            return IoOutcome::Failed(format!("Couldn't write {}: {}", path.display(), error));
+        }
+    }
+    IoOutcome::Saved(folder)
+}
+
+/// The name of a file dropped on the window: its path on native, only the name on the web
+pub fn dropped_file_name(file: &DroppedFile) -> String {
+    match &file.path {
+        Some(path) => path.display().to_string(),
+        None => file.name.clone(),
+    }
+}
+
+/// The name and contents of a file dropped on the window. On native the file is read from its
+///  path, on the web only the bytes are there.
+pub fn read_dropped_bytes(file: &DroppedFile) -> Result<(String, Vec<u8>), String> {
+    let name = dropped_file_name(file);
+    let bytes = match (&file.bytes, &file.path) {
+        (Some(bytes), _) => bytes.to_vec(),
+        (None, Some(path)) => {
+            std::fs::read(path).map_err(|error| format!("Couldn't read {}: {}", name, error))?
+        }
+        (None, None) => return Err(format!("Couldn't read {}", name)),
+    };
+    Ok((name, bytes))
+}
+
+/// The name and text of a file dropped on the window
+pub fn read_dropped_file(file: &DroppedFile) -> Result<(String, String), String> {
+    let (name, bytes) = read_dropped_bytes(file)?;
+    let text = String::from_utf8(bytes).map_err(|_| format!("{} is not a text file", name))?;
+    log_info(format!("Opened {}", name));
+    Ok((name, text))
+}
@@ -0,0 +1,69 @@
+/// What happens when the playback reaches the end of the stack
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PlaybackMode {
+    Loop,     // start again from the lowest layer
+    PingPong, // turn around at the lowest and the highest layer
+}
+
+/// Step the current layer through the stack at a fixed rate, to review the whole structure layer by
+///  layer in the viewport
+pub struct LayerPlayback {
+    pub playing: bool,
+    pub layers_per_second: f64,
+    pub mode: PlaybackMode,
+    pub upwards: bool, // direction of the next step (only changes in ping-pong mode)
+    pub last_step: f64, // time of the last step, as in `egui::InputState::time`
+}
+
+impl Default for LayerPlayback {
+    fn default() -> Self {
+        Self {
+            playing: false,
+            layers_per_second: 4.0,
+            mode: PlaybackMode::Loop,
+            upwards: true,
+            last_step: 0.0,
+        }
+    }
+}
+
+impl LayerPlayback {
+    /// Play or pause (playing starts with a step right away)
+    pub fn toggle(&mut self) {
+        self.playing = !self.playing;
+    }
+
+    /// Seconds until the next step
+    pub fn time_to_next_step(&self, time: f64) -> f64 {
+        (self.last_step + 1.0 / self.layers_per_second - time).max(0.0)
+    }
+
+    /// The layer to go to if it is time for the next step. Call every frame.
+    pub fn step(
+        &mut self,
+        time: f64,
+        current_layer: isize,
+        layer_lowest: isize,
+        layer_highest: isize,
+    ) -> Option<isize> {
+        if !self.playing || self.time_to_next_step(time) > 0.0 || layer_lowest == layer_highest {
+            return None;
+        }
+        self.last_step = time;
+
+        let next = if self.upwards {
+            current_layer + 1
+        } else {
+            current_layer - 1
+        };
+        Some(match self.mode {
+            PlaybackMode::Loop if next > layer_highest => layer_lowest,
+            PlaybackMode::Loop if next < layer_lowest => layer_highest,
+            PlaybackMode::PingPong if next > layer_highest || next < layer_lowest => {
+                self.upwards = !self.upwards;
+                (2 * current_layer - next).clamp(layer_lowest, layer_highest)
+            }
+            _ => next,
+        })
+    }
+}
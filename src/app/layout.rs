@@ -0,0 +1,50 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+
+/// A generated structure placed on the layout canvas. The parameters are kept so that the
+///  structure can be loaded back into the editor.
+#[derive(Debug, Clone)]
+pub struct LayoutStructure {
+    pub name: String,
+    pub offset: [isize; 2], // position of the origin of the structure on the canvas
+    pub layer_offset: isize, // layer of the canvas that the layer 0 of the structure is on
+    pub visible: bool,
+    pub stack_layer_config: ZVec<SliceParameters>,
+    pub stack_blocks: ZVec<Blocks>,
+}
+
+impl LayoutStructure {
+    /// The blocks of the structure on a layer of the canvas, as global canvas coordinates (of the
+    ///  left bottom corners)
+    pub fn get_block_coords_on_layer(&self, canvas_layer: isize) -> Vec<[f64; 2]> {
+        match self.stack_blocks.get(canvas_layer - self.layer_offset) {
+            None => vec![],
+            Some(blocks) => blocks
+                .get_all_block_coords()
+                .into_iter()
+                .map(|[x, y]| [x + self.offset[0] as f64, y + self.offset[1] as f64])
+                .collect(),
+        }
+    }
+}
+
+/// Several structures placed together, e.g., four towers around a courtyard
+#[derive(Debug, Default)]
+pub struct StructureLayout {
+    pub structures: Vec<LayoutStructure>,
+    pub canvas_layer: isize,
+    pub new_structure_name: String,
+}
+
+impl StructureLayout {
+    /// Number of blocks of all visible structures on the current layer of the canvas. Overlapping
+    ///  structures are counted twice.
+    pub fn get_nr_blocks_on_layer(&self) -> usize {
+        self.structures
+            .iter()
+            .filter(|structure| structure.visible)
+            .map(|structure| structure.get_block_coords_on_layer(self.canvas_layer).len())
+            .sum()
+    }
+}
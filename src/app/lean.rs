@@ -0,0 +1,134 @@
+use std::fmt::{Display, Formatter};
+
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::generation::cell_aspect::to_cells;
+use crate::app::lua_field::LuaField;
+
+/// How the centers of the layers are offset
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum LeanMode {
+    #[default]
+    Off,
+    Linear, // the same offset for every layer up, a straight slanted column
+    Code,   // an offset computed from the height, e.g. for a tower that bends
+}
+
+impl Display for LeanMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeanMode::Off => write!(f, "Off"),
+            LeanMode::Linear => write!(f, "Linear"),
+            LeanMode::Code => write!(f, "Code"),
+        }
+    }
+}
+
+/// A lean of the whole stack (leaning towers, slanted columns): the center of each layer is offset
+///  by an amount that depends on its height. It comes on top of the center offsets of the layers
+///  (also those set by code), which stay as they are, so the lean can be changed or turned off
+///  without losing them. Locked layers keep their blocks.
+pub struct Lean {
+    pub mode: LeanMode,
+    pub per_layer: [f64; 2], // offset of the center from one layer to the next (linear lean)
+    pub base_layer: f64,     // the height that isn't offset (linear lean)
+    pub code_x: LuaField,    // offsets at the height `layer` (lean by code)
+    pub code_y: LuaField,
+}
+
+impl Default for Lean {
+    fn default() -> Self {
+        Self {
+            mode: LeanMode::Off,
+            per_layer: [0.5, 0.0],
+            base_layer: 0.0,
+            code_x: LuaField::new(true, false),
+            code_y: LuaField::new(true, false),
+        }
+    }
+}
+
+impl Lean {
+    /// Offset of the center at the (fractional) height. Code that fails gives no offset.
+    pub fn offset(&self, height: f64) -> [f64; 2] {
+        match self.mode {
+            LeanMode::Off => [0.0, 0.0],
+            LeanMode::Linear => self.per_layer.map(|step| step * (height - self.base_layer)),
+            LeanMode::Code => {
+                [&self.code_x, &self.code_y].map(|code| code.try_eval(height).unwrap_or_default())
+            }
+        }
+    }
+
+    /// The parameters with the center offset by the lean at their height
+    pub fn apply(&self, slice_parameters: &SliceParameters) -> SliceParameters {
+        let [dx, dy] = self.offset(slice_parameters.layer);
+        SliceParameters {
+            center_offset_x: slice_parameters.center_offset_x + dx,
+            center_offset_y: slice_parameters.center_offset_y + dy,
+            ..slice_parameters.clone()
+        }
+    }
+
+    /// The parameters of all layers with the lean applied
+    pub fn apply_to_stack(
+        &self,
+        stack_layer_config: &ZVec<SliceParameters>,
+    ) -> ZVec<SliceParameters> {
+        ZVec::new(
+            stack_layer_config
+                .data
+                .iter()
+                .map(|slice_parameters| self.apply(slice_parameters))
+                .collect(),
+            stack_layer_config.get_minimum(),
+        )
+    }
+
+    /// Angle of the linear lean from the vertical, in degrees (a layer is a block high)
+    pub fn angle(&self) -> f64 {
+        let [dx, dy] = self.per_layer;
+        dx.hypot(dy).atan().to_degrees()
+    }
+}
+
+/// What the stack looks like with the lean, drawn while the lean is edited: the centers of all
+///  layers (the axis of the tower) and the target shapes of the bottom and top layer
+pub struct LeanPreview {
+    pub layer_lowest: isize,
+    pub centers: Vec<[f64; 2]>, // from the lowest layer up
+    pub ends: Vec<SliceParameters>,
+    pub cell_aspect: f64, // to draw the axis in cell coordinates
+}
+
+impl LeanPreview {
+    pub fn new(lean: &Lean, stack_layer_config: &ZVec<SliceParameters>, cell_aspect: f64) -> Self {
+        let leaned = lean.apply_to_stack(stack_layer_config);
+        Self {
+            layer_lowest: leaned.get_minimum(),
+            centers: leaned
+                .data
+                .iter()
+                .map(|slice_parameters| {
+                    [
+                        slice_parameters.center_offset_x,
+                        slice_parameters.center_offset_y,
+                    ]
+                })
+                .collect(),
+            ends: [leaned.data.front(), leaned.data.back()]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect(),
+            cell_aspect,
+        }
+    }
+
+    /// The centers in cell coordinates, with the height of the middle of their layer
+    pub fn axis(&self) -> impl Iterator<Item = ([f64; 2], f64)> + '_ {
+        (self.layer_lowest..)
+            .zip(self.centers.iter())
+            .map(|(layer, center)| (to_cells(*center, self.cell_aspect), layer as f64 + 0.5))
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+/// Only keep the most recent entries
+const MAX_NR_ENTRIES: usize = 500;
+
+static LOG: Mutex<VecDeque<LogEntry>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LogLevel {
+    Info,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    pub message: String,
+    pub count: usize, // repeated messages are counted instead of added again
+}
+
+/// Add a message to the log, which is shown in the log window (and can be copied from there for
+///  bug reports). Can be called from anywhere.
+pub fn log(level: LogLevel, message: impl Into<String>) {
+    let message = message.into();
+    let Ok(mut log) = LOG.lock() else {
+        return;
+    };
+
+    if let Some(last) = log.back_mut() {
+        if last.level == level && last.message == message {
+            last.count += 1;
+            return;
+        }
+    }
+
+    log.push_back(LogEntry {
+        level,
+        message,
+        count: 1,
+    });
+    if log.len() > MAX_NR_ENTRIES {
+        log.pop_front();
+    }
+}
+
+pub fn log_info(message: impl Into<String>) {
+    log(LogLevel::Info, message)
+}
+
+pub fn log_warning(message: impl Into<String>) {
+    log(LogLevel::Warning, message)
+}
+
+/// Copy of all entries, oldest first
+pub fn get_entries() -> Vec<LogEntry> {
+    LOG.lock()
+        .map(|log| log.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+pub fn clear() {
+    if let Ok(mut log) = LOG.lock() {
+        log.clear();
+    }
+}
+
+/// The whole log as plain text, one entry per line
+pub fn to_text() -> String {
+    get_entries()
+        .iter()
+        .map(|entry| format!("{}\n", entry))
+        .collect()
+}
+
+impl Display for LogEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let level = match self.level {
+            LogLevel::Info => "info",
+            LogLevel::Warning => "warning",
+        };
+        if self.count > 1 {
+            write!(f, "[{}] {} (×{})", level, self.message, self.count)
+        } else {
+            write!(f, "[{}] {}", level, self.message)
+        }
+    }
+}
@@ -1,21 +1,63 @@
 use crate::app::data_structures::zvec::ZVec;
+use crate::app::log::log_warning;
+use crate::app::scripting::new_engine;
 use eframe::egui;
 use eframe::egui::{Color32, Ui};
-use rhai::{Engine, EvalAltResult, run, Scope};
+use rhai::{Engine, Scope, AST};
 
 #[derive(Clone, Eq, PartialEq)]
-enum FieldState {
+pub enum FieldState {
     RunSuccess,
     Changed,
     Invalid,
     Empty,
 }
 
+/// Single line code editor colored by the state of the code, with the error (if any) on hover.
+///  Output: whether the code was edited.
+pub fn code_edit(
+    ui: &mut Ui,
+    code: &mut String,
+    field_state: &FieldState,
+    error_message: Option<&String>,
+) -> bool {
+    let original_style = ui.style().clone();
+
+    match field_state {
+        FieldState::RunSuccess => {
+            ui.visuals_mut().extreme_bg_color = Color32::LIGHT_GREEN;
+            ui.visuals_mut().override_text_color = Some(Color32::BLACK)
+        }
+        FieldState::Changed => {
+            ui.visuals_mut().extreme_bg_color = Color32::from_rgb(227, 197, 103); //todo: make color
+            ui.visuals_mut().override_text_color = Some(Color32::BLACK)
+        }
+        FieldState::Invalid => {
+            ui.visuals_mut().extreme_bg_color = Color32::LIGHT_RED;
+            ui.visuals_mut().override_text_color = Some(Color32::BLACK)
+        }
+        FieldState::Empty => {}
+    }
+
+    let mut response = ui.add(egui::TextEdit::singleline(code).code_editor());
+    if let (FieldState::Invalid, Some(error_message)) = (field_state, error_message) {
+        response = response.on_hover_text(error_message);
+    }
+
+    ui.set_style(original_style);
+    response.changed()
+}
+
+/// Code for a parameter in terms of the height `layer` (or `l`). The code is compiled once when it
+///  is edited and then run in the same engine for every sample.
 pub struct LuaField {
     code: String,
     field_state: FieldState,
     req_finite: bool,
     req_nonnegative: bool,
+    error_message: Option<String>, // why the code failed the last time it was run (if it did)
+    engine: Engine,
+    ast: Option<AST>, // the compiled code, None if the field is empty or doesn't compile
 }
 
 impl LuaField {
@@ -25,38 +67,33 @@ impl LuaField {
             field_state: FieldState::Empty,
             req_finite,
             req_nonnegative,
+            error_message: None,
+            engine: new_engine(),
+            ast: None,
         }
     }
 
     // TODO: button to clear the lua field
     pub fn show(&mut self, ui: &mut Ui, sampling_points: &ZVec<Vec<f64>>) {
-        let original_style = ui.style().clone();
-
-        match self.field_state {
-            FieldState::RunSuccess => {
-                ui.visuals_mut().extreme_bg_color = Color32::LIGHT_GREEN;
-                ui.visuals_mut().override_text_color = Some(Color32::BLACK)
-            }
-            FieldState::Changed => {
-                ui.visuals_mut().extreme_bg_color = Color32::from_rgb(227, 197, 103); //todo: make color
-                ui.visuals_mut().override_text_color = Some(Color32::BLACK)
-            }
-            FieldState::Invalid => {
-                ui.visuals_mut().extreme_bg_color = Color32::LIGHT_RED;
-                ui.visuals_mut().override_text_color = Some(Color32::BLACK)
-            }
-            FieldState::Empty => {}
-        }
-
-        let response = ui.add(egui::TextEdit::singleline(&mut self.code).code_editor());
-        if response.changed() {
+        if code_edit(
+            ui,
+            &mut self.code,
+            &self.field_state,
+            self.error_message.as_ref(),
+        ) {
             self.update_field_state(sampling_points);
         }
+    }
 
-        ui.set_style(original_style);
+    /// Replace the code of the field (e.g., from a template)
+    pub fn set_code(&mut self, code: &str, sampling_points: &ZVec<Vec<f64>>) {
+        self.code = code.to_string();
+        self.update_field_state(sampling_points);
     }
 
     pub fn update_field_state(&mut self, sampling_points: &ZVec<Vec<f64>>) {
+        self.error_message = None;
+        self.ast = self.engine.compile_expression(&self.code).ok();
         if self.code.is_empty() {
             self.field_state = FieldState::Empty
         } else if !self.is_valid_expression(sampling_points) {
@@ -67,51 +104,60 @@ impl LuaField {
     }
 
     pub fn is_valid_expression(&self, sampling_points: &ZVec<Vec<f64>>) -> bool {
-        let mut engine = Engine::new();
-        let mut scope = Scope::new();
-        let code = self.code.clone();
-
-        // Check if the expression is valid at all sampling points. First unpack layers, then unpack
-        // sampling points
-        sampling_points.clone().data.into_iter().map(|layer| {
-            layer
-                .into_iter()
-                .map(|sample| {
-                    // is the expression valid for this sample?
-
-                    scope.push_constant("layer", sample);
-                    scope.push_constant("l", sample);
-
-                    println!("l = {}", sample.to_string());
-                    engine.eval_expression_with_scope(&mut scope, &code).is_ok_and(|x: f64| {
-                        !x.is_nan()
-                            && (!self.req_finite || x.is_finite())
-                            && (!self.req_nonnegative || x >= 0.0)
-                    })
-                })
-                // is the expression valid for this particular layer?
-                .fold(true, |a, b| a && b)
-        }).fold(true, |a, b| a && b)
+        // Check if the expression is valid at all sampling points of all layers
+        sampling_points
+            .data
+            .iter()
+            .flatten()
+            .all(|sample| self.run(*sample).is_ok_and(|x| self.is_allowed(x)))
     }
 
-
     pub fn eval(&mut self, sample: &f64) -> Option<f64> {
         // Only change the parameter if the code is valid and has changed
         // longterm: should not rerun code if there has been a success (assuming layer hasn't changed)
         if self.field_state == FieldState::Changed || self.field_state == FieldState::RunSuccess {
-            let mut engine = Engine::new();
-            let mut scope = Scope::new();
-
-            scope.push_constant("layer", sample.clone());
-            scope.push_constant("l", sample.clone());
-
-            let parameter = engine.eval_expression_with_scope(&mut scope, &*self.code).unwrap();
-            Some(parameter)
+            // The expression was only checked at the sampling points at the time it was entered, so
+            //  it can still fail here. Then fall back to the slider value and mark the field as
+            //  invalid instead of crashing.
+            match self.run(*sample) {
+                Ok(parameter) if self.is_allowed(parameter) => Some(parameter),
+                Ok(parameter) => {
+                    self.field_state = FieldState::Invalid;
+                    self.error_message =
+                        Some(format!("Invalid value {} for layer {}", parameter, sample));
+                    log_warning(format!(
+                        "Code `{}` gave the invalid value {} for layer {}, using the slider value instead",
+                        self.code, parameter, sample
+                    ));
+                    None
+                }
+                Err(error) => {
+                    self.field_state = FieldState::Invalid;
+                    self.error_message = Some(format!("Error for layer {}: {}", sample, error));
+                    log_warning(format!(
+                        "Code `{}` failed for layer {} ({}), using the slider value instead",
+                        self.code, sample, error
+                    ));
+                    None
+                }
+            }
         } else {
             None
         }
     }
 
+    /// Evaluate the code at `sample` without changing the state of the field or logging failures
+    ///  (e.g. for previews at heights that are not sampled)
+    pub fn try_eval(&self, sample: f64) -> Option<f64> {
+        if self.field_state != FieldState::Changed && self.field_state != FieldState::RunSuccess {
+            return None;
+        }
+
+        self.run(sample)
+            .ok()
+            .filter(|parameter| self.is_allowed(*parameter))
+    }
+
     /// Has the field changed (since the last time it was run) to a valid expression?
     pub fn has_changed(&self) -> bool {
         self.field_state == FieldState::Changed
@@ -122,4 +168,25 @@ impl LuaField {
             self.field_state = FieldState::RunSuccess;
         }
     }
+
+    /// Run the compiled code at the height `sample`
+    fn run(&self, sample: f64) -> Result<f64, String> {
+        let Some(ast) = &self.ast else {
+            return Err("The code doesn't compile".to_string());
+        };
+        let mut scope = Scope::new();
+        scope.push_constant("layer", sample);
+        scope.push_constant("l", sample);
+
+        self.engine
+            .eval_ast_with_scope::<f64>(&mut scope, ast)
+            .map_err(|error| error.to_string())
+    }
+
+    /// Can the parameter have this value?
+    fn is_allowed(&self, parameter: f64) -> bool {
+        !parameter.is_nan()
+            && (!self.req_finite || parameter.is_finite())
+            && (!self.req_nonnegative || parameter >= 0.0)
+    }
 }
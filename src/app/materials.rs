@@ -0,0 +1,23 @@
+// Number of blocks in a full stack in the inventory
+pub const STACK_SIZE: u64 = 64;
+// A shulker box has 27 slots, each holding a stack
+pub const SHULKER_BOX_SIZE: u64 = 27 * STACK_SIZE;
+
+/// A number of blocks split up into full shulker boxes, full stacks and the blocks left over, for
+///  collecting the materials for a build
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Materials {
+    pub shulker_boxes: u64,
+    pub stacks: u64,
+    pub blocks: u64,
+}
+
+impl Materials {
+    pub fn from_block_count(nr_blocks: u64) -> Self {
+        Self {
+            shulker_boxes: nr_blocks / SHULKER_BOX_SIZE,
+            stacks: (nr_blocks % SHULKER_BOX_SIZE) / STACK_SIZE,
+            blocks: nr_blocks % STACK_SIZE,
+        }
+    }
+}
@@ -5,10 +5,10 @@ use crate::app::math::linear_algebra::Vec2;
 /// With -radius <= x0 <= x1 <= radius, what is the area of the semicircle y=sqrt(R^2-x^2) from x0 to x1?
 /// Have an exact primitive for non-edge cases
 pub fn area_of_semicircle_section(x0: f64, x1: f64, radius: f64) -> f64 {
-    if !(-radius <= x0 && x0 <= x1 && x1 <= radius) {
-        // -radius <= x0 <= x1 <= radius
-        panic!("ooo...");
-    }
+    // The intercepts are computed with floating point, so they can land just outside of the
+    //  interval. Clamp them back in instead of failing (outside the circle there is no area)
+    let x0 = x0.clamp(-radius, radius);
+    let x1 = x1.clamp(x0, radius);
 
     // Primitive in case |x| != R
     fn primitive(x: f64, radius: f64) -> f64 {
@@ -1,4 +1,5 @@
 use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::log::log_warning;
 use crate::app::math::circle_geometry::get_squircle_tangent_point;
 use crate::app::math::linear_algebra::Vec2;
 
@@ -8,42 +9,44 @@ pub fn exact_squircle_bounds(gen_config: &SliceParameters, pad_factor: f64) -> [
     let center_offset_x = gen_config.center_offset_x;
     let center_offset_y = gen_config.center_offset_y;
 
-    if gen_config.radius_a == 0.0 || gen_config.radius_b == 0.0 {
-        [
+    // A degenerate shape (e.g. zero radius) has no inverse quadratic form, use small bounds around
+    //  the center instead
+    let Some(sqrt_quad_form_inverse) = sqrt_quad_form
+        .inverse()
+        .filter(|_| gen_config.radius_a != 0.0 && gen_config.radius_b != 0.0)
+    else {
+        log_warning(
+            "Degenerate shape (a radius is zero), the bounds are a small square around the center",
+        );
+        return [
             [-1.0 + center_offset_x, -1.0 + center_offset_y],
             [1.0 + center_offset_x, 1.0 + center_offset_y],
-        ]
-    } else {
-        let m_a = {
-            if squircle_parameter > 1.0 {
-                get_squircle_tangent_point(
-                    squircle_parameter,
-                    sqrt_quad_form * Vec2::from([1.0, 0.0]),
-                )
-            } else {
-                // don't care about which values are minimized / maximized since it's easy to compute
-                Vec2::from([1.0, 0.0])
-            }
-        };
-        let m_b = {
-            if squircle_parameter > 1.0 {
-                get_squircle_tangent_point(
-                    squircle_parameter,
-                    sqrt_quad_form * Vec2::from([0.0, 1.0]),
-                )
-            } else {
-                Vec2::from([0.0, 1.0])
-            }
-        };
+        ];
+    };
 
-        let a = pad_factor * sqrt_quad_form.inverse().unwrap() * m_a;
-        let b = pad_factor * sqrt_quad_form.inverse().unwrap() * m_b;
+    let m_a = {
+        if squircle_parameter > 1.0 {
+            get_squircle_tangent_point(squircle_parameter, sqrt_quad_form * Vec2::from([1.0, 0.0]))
+        } else {
+            // don't care about which values are minimized / maximized since it's easy to compute
+            Vec2::from([1.0, 0.0])
+        }
+    };
+    let m_b = {
+        if squircle_parameter > 1.0 {
+            get_squircle_tangent_point(squircle_parameter, sqrt_quad_form * Vec2::from([0.0, 1.0]))
+        } else {
+            Vec2::from([0.0, 1.0])
+        }
+    };
 
-        let max = [a.x.abs().max(b.x.abs()), a.y.abs().max(b.y.abs())];
-        let min = [-max[0], -max[1]];
-        [
-            [min[0] + center_offset_x, min[1] + center_offset_y],
-            [max[0] + center_offset_x, max[1] + center_offset_y],
-        ]
-    }
+    let a = pad_factor * sqrt_quad_form_inverse * m_a;
+    let b = pad_factor * sqrt_quad_form_inverse * m_b;
+
+    let max = [a.x.abs().max(b.x.abs()), a.y.abs().max(b.y.abs())];
+    let min = [-max[0], -max[1]];
+    [
+        [min[0] + center_offset_x, min[1] + center_offset_y],
+        [max[0] + center_offset_x, max[1] + center_offset_y],
+    ]
 }
@@ -144,6 +144,14 @@ impl Mat2 {
         Mat2::from([self.a, self.c, self.b, self.d])
     }
 
+    /// Largest singular value, i.e., the largest factor by which the matrix stretches a vector
+    ///  (closed form for 2x2 matrices)
+    pub fn operator_norm(&self) -> f64 {
+        let frobenius_sq = self.a.powi(2) + self.b.powi(2) + self.c.powi(2) + self.d.powi(2);
+        let discriminant = (frobenius_sq.powi(2) - 4.0 * self.det().powi(2)).max(0.0);
+        ((frobenius_sq + discriminant.sqrt()) / 2.0).sqrt()
+    }
+
     pub fn inverse(&self) -> Option<Mat2> {
         if self.det() != 0.0 {
             Some(1.0 / self.det() * Mat2::from([self.d, -self.b, -self.c, self.a]))
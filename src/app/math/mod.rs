@@ -2,4 +2,7 @@ pub mod circle_geometry;
 pub mod exact_squircle_bounds;
 pub mod linear_algebra;
 pub mod linear_geometry;
+pub mod random;
+pub mod shape_fit;
 pub mod square_max;
+pub mod superellipse_measure;
@@ -0,0 +1,49 @@
+// Key in the app storage, so that the random results are the same after a restart
+pub const RANDOM_SEED_KEY: &str = "random_seed";
+
+/// The uses of randomness. Each gets its own stream derived from the random seed of the project,
+///  so that adding a new use doesn't change the results of the existing ones. Append new uses at
+///  the end, the position is part of the derivation.
+#[derive(Debug, Clone, Copy)]
+pub enum RandomStream {
+    Variations,
+}
+
+/// Small seeded pseudorandom number generator (SplitMix64), so that random results can be
+///  reproduced from the seed. Only integer arithmetic and exact conversions are used, so the same
+///  seed gives the same numbers on every machine.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generator for `stream`, derived from the random seed of the project and a seed for the
+    ///  stream (e.g. the batch of variations)
+    pub fn for_stream(project_seed: u64, stream: RandomStream, stream_seed: u64) -> Self {
+        let project = SplitMix64::new(project_seed).next_u64();
+        let stream = SplitMix64::new(project ^ stream as u64).next_u64();
+        Self::new(SplitMix64::new(stream ^ stream_seed).next_u64())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in [-amplitude, amplitude)
+    pub fn next_symmetric(&mut self, amplitude: f64) -> f64 {
+        amplitude * (2.0 * self.next_f64() - 1.0)
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashSet;
+use std::f64::consts::PI;
+
+// Squircle parameters that are tried, log-spaced from 1/2 to 16 (and 2 for the ellipse itself)
+const NR_SQUIRCLE_PARAMETERS: usize = 50;
+const SQUIRCLE_PARAMETER_RANGE: [f64; 2] = [0.5, 16.0];
+// Tilts that are tried for superellipses, in [0, pi) (the ellipse fit fixes the tilt of ellipses)
+const NR_TILTS: usize = 36;
+// The centers of the boundary blocks are about half a block inside the shape they were generated
+//  from (for the centerpoint algorithm)
+const BOUNDARY_INSET: f64 = 0.5;
+
+/// Superellipse fitted to points, in the same terms as `SliceParameters`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FittedShape {
+    pub center: [f64; 2],
+    pub radius_a: f64,
+    pub radius_b: f64,
+    pub tilt: f64,
+    pub squircle_parameter: f64,
+    pub error: f64, // root mean square of the relative radial distances of the points to the shape
+}
+
+/// The centers of the blocks that miss one of their four neighbours, without duplicates. These
+///  are the points a shape is fitted to (the interior blocks don't say anything about the shape).
+fn boundary_block_centers(coords: &[[isize; 2]]) -> Vec<[f64; 2]> {
+    let blocks: HashSet<[isize; 2]> = coords.iter().copied().collect();
+    let mut boundary: Vec<[isize; 2]> = blocks
+        .iter()
+        .filter(|[x, y]| {
+            [[1, 0], [-1, 0], [0, 1], [0, -1]]
+                .iter()
+                .any(|[dx, dy]| !blocks.contains(&[x + dx, y + dy]))
+        })
+        .copied()
+        .collect();
+    boundary.sort(); // the fit should not depend on the order of the hash set
+    boundary
+        .into_iter()
+        .map(|[x, y]| [x as f64 + 0.5, y as f64 + 0.5])
+        .collect()
+}
+
+/// Fit a superellipse to the boundary of the blocks (left bottom coordinates, in any order and
+///  possibly with interior blocks). See `fit_squircle`.
+pub fn fit_blocks(coords: &[[isize; 2]]) -> Option<FittedShape> {
+    fit_squircle(&boundary_block_centers(coords)).map(|shape| FittedShape {
+        radius_a: shape.radius_a + BOUNDARY_INSET,
+        radius_b: shape.radius_b + BOUNDARY_INSET,
+        ..shape
+    })
+}
+
+/// Least squares fit of an ellipse to at least five points (on the conic
+///  Ax^2 + Bxy + Cy^2 + Dx + Ey = 1, after moving the points so that their mean is at the origin).
+///  The major axis is radius a. None if the points are not spread out along an ellipse.
+pub fn fit_ellipse(points: &[[f64; 2]]) -> Option<FittedShape> {
+    if points.len() < 5 {
+        return None;
+    }
+
+    // Move the mean to the origin (which is then inside the ellipse, so the conic doesn't pass
+    //  through it) and scale to unit size for a well conditioned system
+    let n = points.len() as f64;
+    let mean = [
+        points.iter().map(|p| p[0]).sum::<f64>() / n,
+        points.iter().map(|p| p[1]).sum::<f64>() / n,
+    ];
+    let scale = (points
+        .iter()
+        .map(|p| (p[0] - mean[0]).powi(2) + (p[1] - mean[1]).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt();
+    if scale == 0.0 {
+        return None;
+    }
+
+    let rows: Vec<[f64; 5]> = points
+        .iter()
+        .map(|p| {
+            let [x, y] = [(p[0] - mean[0]) / scale, (p[1] - mean[1]) / scale];
+            [x * x, x * y, y * y, x, y]
+        })
+        .collect();
+    let [a, b, c, d, e] = least_squares(&rows, &vec![1.0; rows.len()])?;
+
+    // The quadratic part must be definite, else the conic is not an ellipse
+    let det = a * c - b * b / 4.0;
+    if det <= 0.0 {
+        return None;
+    }
+    // Center: the gradient 2Qx + (D, E) vanishes
+    let center = [
+        (b * e - 2.0 * c * d) / (4.0 * det),
+        (b * d - 2.0 * a * e) / (4.0 * det),
+    ];
+    // Around the center the conic reads Q(x - center) = k
+    let k = 1.0 - (d * center[0] + e * center[1]) / 2.0;
+
+    // Eigenvalues of Q, the direction at angle theta has the larger one (the minor axis)
+    let mid = (a + c) / 2.0;
+    let spread = ((a - c) / 2.0).hypot(b / 2.0);
+    let [lambda_minor, lambda_major] = [mid + spread, mid - spread];
+    if k / lambda_minor <= 0.0 || k / lambda_major <= 0.0 {
+        return None;
+    }
+    let theta = 0.5 * b.atan2(a - c);
+
+    let mut shape = FittedShape {
+        center: [mean[0] + scale * center[0], mean[1] + scale * center[1]],
+        radius_a: scale * (k / lambda_major).sqrt(),
+        radius_b: scale * (k / lambda_minor).sqrt(),
+        tilt: normalize_tilt(theta + PI / 2.0),
+        squircle_parameter: 2.0,
+        error: 0.0,
+    };
+    shape.error = fit_error(points, &shape);
+    Some(shape)
+}
+
+/// The circle through three points, or the least squares circle x^2 + y^2 + Dx + Ey + F = 0
+///  through more. None if the points are on a line.
+pub fn fit_circle(points: &[[f64; 2]]) -> Option<FittedShape> {
+    if points.len() < 3 {
+        return None;
+    }
+
+    // Relative to the mean for a well conditioned system
+    let n = points.len() as f64;
+    let mean = [
+        points.iter().map(|p| p[0]).sum::<f64>() / n,
+        points.iter().map(|p| p[1]).sum::<f64>() / n,
+    ];
+    let centered: Vec<[f64; 2]> = points
+        .iter()
+        .map(|p| [p[0] - mean[0], p[1] - mean[1]])
+        .collect();
+    let rows: Vec<[f64; 3]> = centered.iter().map(|[x, y]| [*x, *y, 1.0]).collect();
+    let rhs: Vec<f64> = centered.iter().map(|[x, y]| -(x * x + y * y)).collect();
+    let [d, e, f] = least_squares(&rows, &rhs)?;
+
+    let radius_squared = (d * d + e * e) / 4.0 - f;
+    if radius_squared <= 0.0 {
+        return None;
+    }
+
+    let mut shape = FittedShape {
+        center: [mean[0] - d / 2.0, mean[1] - e / 2.0],
+        radius_a: radius_squared.sqrt(),
+        radius_b: radius_squared.sqrt(),
+        tilt: 0.0,
+        squircle_parameter: 2.0,
+        error: 0.0,
+    };
+    shape.error = fit_error(points, &shape);
+    Some(shape)
+}
+
+/// Fit a superellipse to at least five points: the center comes from the ellipse fit, then the
+///  squircle parameter and tilt with the least error are searched for (with the radii fitted by
+///  least squares for each). Falls back to the ellipse if no superellipse is better.
+pub fn fit_squircle(points: &[[f64; 2]]) -> Option<FittedShape> {
+    let ellipse = fit_ellipse(points)?;
+    let mut best = ellipse;
+
+    for i in 0..NR_SQUIRCLE_PARAMETERS {
+        let t = i as f64 / (NR_SQUIRCLE_PARAMETERS - 1) as f64;
+        let [low, high] = SQUIRCLE_PARAMETER_RANGE;
+        let squircle_parameter = low * (high / low).powf(t);
+
+        for j in 0..NR_TILTS {
+            let tilt = normalize_tilt(ellipse.tilt + PI * j as f64 / NR_TILTS as f64);
+            if let Some(shape) = fit_radii(points, ellipse.center, tilt, squircle_parameter) {
+                if shape.error < best.error {
+                    best = shape;
+                }
+            }
+        }
+    }
+
+    Some(best)
+}
+
+/// With the center, tilt and squircle parameter fixed, |u/a|^p + |v/b|^p = 1 is linear in a^-p
+///  and b^-p, so the radii have a least squares solution
+fn fit_radii(
+    points: &[[f64; 2]],
+    center: [f64; 2],
+    tilt: f64,
+    squircle_parameter: f64,
+) -> Option<FittedShape> {
+    let rows: Vec<[f64; 2]> = points
+        .iter()
+        .map(|p| {
+            let [u, v] = to_shape_frame(*p, center, tilt);
+            [
+                u.abs().powf(squircle_parameter),
+                v.abs().powf(squircle_parameter),
+            ]
+        })
+        .collect();
+    let [alpha, beta] = least_squares(&rows, &vec![1.0; rows.len()])?;
+    if alpha <= 0.0 || beta <= 0.0 {
+        return None;
+    }
+
+    let mut shape = FittedShape {
+        center,
+        radius_a: alpha.powf(-1.0 / squircle_parameter),
+        radius_b: beta.powf(-1.0 / squircle_parameter),
+        tilt,
+        squircle_parameter,
+        error: 0.0,
+    };
+    shape.error = fit_error(points, &shape);
+    shape.error.is_finite().then_some(shape)
+}
+
+/// Root mean square of (|u/a|^p + |v/b|^p)^(1/p) - 1, which is the distance of the point to the
+///  shape relative to the radius in its direction (measured from the center)
+fn fit_error(points: &[[f64; 2]], shape: &FittedShape) -> f64 {
+    let p = shape.squircle_parameter;
+    let sum_of_squares: f64 = points
+        .iter()
+        .map(|point| {
+            let [u, v] = to_shape_frame(*point, shape.center, shape.tilt);
+            let norm = ((u / shape.radius_a).abs().powf(p) + (v / shape.radius_b).abs().powf(p))
+                .powf(1.0 / p);
+            (norm - 1.0).powi(2)
+        })
+        .sum();
+    (sum_of_squares / points.len() as f64).sqrt()
+}
+
+/// Coordinates along the a and b axes of the shape
+fn to_shape_frame(point: [f64; 2], center: [f64; 2], tilt: f64) -> [f64; 2] {
+    let [x, y] = [point[0] - center[0], point[1] - center[1]];
+    let (s, c) = tilt.sin_cos();
+    [c * x + s * y, -s * x + c * y]
+}
+
+/// The same tilt in (-pi/2, pi/2] (a superellipse is symmetric under turning by pi)
+fn normalize_tilt(tilt: f64) -> f64 {
+    let tilt = tilt.rem_euclid(PI);
+    if tilt > PI / 2.0 {
+        tilt - PI
+    } else {
+        tilt
+    }
+}
+
+/// Least squares solution of rows * x = rhs (with the normal equations). None if the columns are
+///  (close to) linearly dependent.
+fn least_squares<const N: usize>(rows: &[[f64; N]], rhs: &[f64]) -> Option<[f64; N]> {
+    let mut matrix = [[0.0; N]; N];
+    let mut vector = [0.0; N];
+    for (row, b) in rows.iter().zip(rhs) {
+        for ((matrix_row, entry), row_i) in matrix.iter_mut().zip(vector.iter_mut()).zip(row) {
+            *entry += row_i * b;
+            for (matrix_entry, row_j) in matrix_row.iter_mut().zip(row) {
+                *matrix_entry += row_i * row_j;
+            }
+        }
+    }
+
+    // Gaussian elimination with partial pivoting
+    let size = matrix.iter().flatten().fold(0.0_f64, |a, b| a.max(b.abs()));
+    for column in 0..N {
+        let pivot = (column..N).max_by(|i, j| {
+            matrix[*i][column]
+                .abs()
+                .total_cmp(&matrix[*j][column].abs())
+        })?;
+        if matrix[pivot][column].abs() <= 1e-12 * size {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        vector.swap(column, pivot);
+
+        let (pivot_row, pivot_entry) = (matrix[column], vector[column]);
+        for (matrix_row, entry) in matrix.iter_mut().zip(vector.iter_mut()).skip(column + 1) {
+            let factor = matrix_row[column] / pivot_row[column];
+            for (matrix_entry, pivot) in matrix_row.iter_mut().zip(pivot_row).skip(column) {
+                *matrix_entry -= factor * pivot;
+            }
+            *entry -= factor * pivot_entry;
+        }
+    }
+
+    let mut solution = [0.0; N];
+    for row in (0..N).rev() {
+        let known: f64 = (row + 1..N).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (vector[row] - known) / matrix[row][row];
+    }
+    Some(solution)
+}
@@ -7,3 +7,21 @@ pub fn square_max(a: [[f64; 2]; 2], b: [[f64; 2]; 2]) -> [[f64; 2]; 2] {
 
     [[low_x, low_y], [high_x, high_y]]
 }
+
+/// Grow the square (specified by min and max vector) around its center by a factor, then add a
+///  margin on every side
+pub fn pad_square(square: [[f64; 2]; 2], factor: f64, margin: f64) -> [[f64; 2]; 2] {
+    let center = [
+        (square[0][0] + square[1][0]) / 2.0,
+        (square[0][1] + square[1][1]) / 2.0,
+    ];
+    let half_size = [
+        (square[1][0] - square[0][0]) / 2.0 * factor + margin,
+        (square[1][1] - square[0][1]) / 2.0 * factor + margin,
+    ];
+
+    [
+        [center[0] - half_size[0], center[1] - half_size[1]],
+        [center[0] + half_size[0], center[1] + half_size[1]],
+    ]
+}
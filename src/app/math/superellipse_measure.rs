@@ -0,0 +1,41 @@
+use std::f64::consts::PI;
+
+/// Number of points on the superellipse per quadrant for the numerical computation
+const NR_POINTS_PER_QUADRANT: usize = 2500;
+
+/// Area and perimeter of the superellipse |x/a|^p + |y/b|^p = 1 (with p the squircle parameter),
+///  computed with the polygon through points on the superellipse. There is a closed formula for the
+///  area (with the gamma function), but not for the perimeter, so both are done numerically.
+/// The tilt and offset don't change the area and perimeter so they are not inputs.
+pub fn superellipse_area_perimeter(
+    radius_a: f64,
+    radius_b: f64,
+    squircle_parameter: f64,
+) -> (f64, f64) {
+    // Points of the first quadrant (the superellipse is symmetric in both axes), from (a, 0) to
+    //  (0, b). Close to a square (large squircle parameter) the points bunch up in the corners, but
+    //  then the sides are straight, so the polygon still has the right area and perimeter.
+    let points: Vec<[f64; 2]> = (0..=NR_POINTS_PER_QUADRANT)
+        .map(|i| {
+            // sin(pi/2 - t) instead of cos(t), so that the last point is exactly at x = 0 (cos(pi/2)
+            //  is not exactly 0, which matters for large powers)
+            let step = PI / 2.0 / (NR_POINTS_PER_QUADRANT as f64);
+            let t = (i as f64) * step;
+            let t_complement = ((NR_POINTS_PER_QUADRANT - i) as f64) * step;
+            [
+                radius_a * t_complement.sin().powf(2.0 / squircle_parameter),
+                radius_b * t.sin().powf(2.0 / squircle_parameter),
+            ]
+        })
+        .collect();
+
+    let mut area = 0.0;
+    let mut perimeter = 0.0;
+    for pair in points.windows(2) {
+        let [[x_1, y_1], [x_2, y_2]] = [pair[0], pair[1]];
+        area += (x_1 * y_2 - x_2 * y_1) / 2.0; // shoelace formula, the triangle with the origin
+        perimeter += (x_2 - x_1).hypot(y_2 - y_1);
+    }
+
+    (4.0 * area, 4.0 * perimeter)
+}
@@ -1,80 +1,153 @@
 use crate::app::data_structures::blocks::Blocks;
 use crate::app::data_structures::zvec::ZVec;
 
+/// Which neighbors of a block count as touching it in the stack. Air that only touches at an edge
+///  or a corner leaks with `Edges` or `Corners` (for water-proofing builds), and not with `Faces`.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum Connectivity {
+    #[default]
+    Faces, // the 6 neighbors that share a face
+    Edges,   // the 18 neighbors that share a face or an edge
+    Corners, // the 26 neighbors that share a face, an edge or a corner
+}
+
+impl Connectivity {
+    pub fn all() -> [Self; 3] {
+        [Self::Faces, Self::Edges, Self::Corners]
+    }
+
+    /// Offsets [x, y, layer] of the neighbors of a block
+    pub fn neighbors(&self) -> Vec<[isize; 3]> {
+        let max_directions = match self {
+            Connectivity::Faces => 1,
+            Connectivity::Edges => 2,
+            Connectivity::Corners => 3,
+        };
+        let mut neighbors = vec![];
+        for dz in -1..=1_isize {
+            for dy in -1..=1_isize {
+                for dx in -1..=1_isize {
+                    let nr_directions = dx.abs() + dy.abs() + dz.abs();
+                    if nr_directions > 0 && nr_directions <= max_directions {
+                        neighbors.push([dx, dy, dz]);
+                    }
+                }
+            }
+        }
+        neighbors
+    }
+}
+
+impl std::fmt::Display for Connectivity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Connectivity::Faces => write!(f, "Faces (6)"),
+            Connectivity::Edges => write!(f, "Edges (18)"),
+            Connectivity::Corners => write!(f, "Corners (26)"),
+        }
+    }
+}
+
 /// For the input `stack_blocks` viewed as a 3D shape, compute which are on the boundary, i.e.,
-///  which blocks cannot be removed to alter the outside appearance of the shape. If the shape has holes,
-///  these are also counted as 'outside' (so it is a topological boundary)
+///  which blocks cannot be removed to alter the outside appearance of the shape. If the shape has
+///  holes, these are also counted as 'outside' (so it is a topological boundary). A block is on
+///  the boundary if it is at most `thickness` steps to a `connectivity` neighbor away from an empty
+///  cell, so the boundary is a wall of that thickness. Outside the grids is empty, below and above
+///  the stack only if it is floating there.
 pub fn boundary_3d(
     stack_blocks: &ZVec<Blocks>,
     layer_min: isize,
     layer_max: isize,
     floating_bottom: bool,
     floating_top: bool,
+    connectivity: Connectivity,
+    thickness: usize,
 ) -> ZVec<Blocks> {
-    let out = (layer_min..=layer_max)
-        .map(|layer| {
-            let blocks = stack_blocks.get(layer).clone().unwrap();
-
-            Blocks::new(
-                (0..blocks.grid_size.pow(2))
-                    .map(|i| {
-                        let global_coords = blocks.get_global_coord_usize_from_index(i);
-
-                        blocks.blocks[i] == true
-                            // has to be a block in self
-                            && (i % blocks.grid_size == 0
-                            // edges of layer boundary are automatically boundary  (extreme coords):
-                            || i % blocks.grid_size == blocks.grid_size - 1
-                            || i / blocks.grid_size == 0
-                            || i / blocks.grid_size == blocks.grid_size - 1
-                            // regular 2D boundary: (look in each horizontal direction, any must be empty for i not to be a boundary
-                            || !blocks.blocks[i + 1]
-                            || !blocks.blocks[i - 1]
-                            || !blocks.blocks[i + blocks.grid_size]
-                            || !blocks.blocks[i - blocks.grid_size]
-                            // top and bottom faces of stack:
-                            || (layer == layer_min && floating_bottom)
-                            || (layer == layer_max && floating_top)
-                            // vertical boundary: see if 1. the layer above exists, 2. there is no block at the global coords on the layer above
-                            || stack_blocks.get(layer + 1).is_some_and(|layer_above| !layer_above.is_block_on_global_coord(global_coords))
-                            || stack_blocks.get(layer - 1).is_some_and(|layer_above| !layer_above.is_block_on_global_coord(global_coords))
-                        )
-                    })
-                    .collect(), blocks.grid_size)
+    let neighbors = connectivity.neighbors();
+    let layers: Vec<Blocks> = (layer_min..=layer_max)
+        .map(|layer| stack_blocks.get(layer).unwrap())
+        .collect();
+    let index = |layer: isize| (layer - layer_min) as usize;
+    let is_empty = |coord: [isize; 2], layer: isize| {
+        if layer < layer_min {
+            floating_bottom
+        } else if layer > layer_max {
+            floating_top
+        } else {
+            !layers[index(layer)].is_block_on_global_coord(coord)
         }
-        )
+    };
+
+    let mut boundary: Vec<Blocks> = layers
+        .iter()
+        .map(|blocks| Blocks::new(vec![false; blocks.grid_size.pow(2)], blocks.grid_size))
         .collect();
 
-    ZVec::new(out, layer_min)
+    // Each step the wall gets one block thicker
+    for _ in 0..thickness.max(1) {
+        let is_wall = |coord: [isize; 2], layer: isize| {
+            is_empty(coord, layer)
+                || (layer_min..=layer_max).contains(&layer)
+                    && boundary[index(layer)].is_block_on_global_coord(coord)
+        };
+
+        let thicker = (layer_min..=layer_max)
+            .map(|layer| {
+                let blocks = &layers[index(layer)];
+                Blocks::new(
+                    (0..blocks.grid_size.pow(2))
+                        .map(|i| {
+                            let [x, y] = blocks.get_global_coord_usize_from_index(i);
+                            blocks.blocks[i]
+                                && neighbors
+                                    .iter()
+                                    .any(|[dx, dy, dz]| is_wall([x + dx, y + dy], layer + dz))
+                        })
+                        .collect(),
+                    blocks.grid_size,
+                )
+            })
+            .collect();
+        boundary = thicker;
+    }
+
+    ZVec::new(boundary.into(), layer_min)
 }
 
-// feels like a roundabout implementation, but there's no good reason to make it similar to boundary_3d above.
+/// The blocks of the stack that are not on the boundary (see `boundary_3d`)
 pub fn interior_3d(
     stack_blocks: &ZVec<Blocks>,
     layer_min: isize,
     layer_max: isize,
     floating_bottom: bool,
     floating_top: bool,
+    connectivity: Connectivity,
+    thickness: usize,
 ) -> ZVec<Blocks> {
+    let boundary = boundary_3d(
+        stack_blocks,
+        layer_min,
+        layer_max,
+        floating_bottom,
+        floating_top,
+        connectivity,
+        thickness,
+    );
+
     ZVec::new(
-        (layer_min..layer_max)
+        (layer_min..=layer_max)
             .map(|layer| {
+                let blocks = stack_blocks.get(layer).unwrap();
                 Blocks::new(
-                    boundary_3d(
-                        stack_blocks,
-                        layer_min,
-                        layer_max,
-                        floating_bottom,
-                        floating_top,
-                    )
-                    .get(layer)
-                    .unwrap()
-                    .blocks
-                    .iter()
-                    .zip(stack_blocks.get(layer).unwrap().blocks)
-                    .map(|(is_bdry, is_block)| is_block && !is_bdry)
-                    .collect(),
-                    stack_blocks.get(layer).unwrap().grid_size,
+                    boundary
+                        .get(layer)
+                        .unwrap()
+                        .blocks
+                        .iter()
+                        .zip(&blocks.blocks)
+                        .map(|(is_bdry, is_block)| *is_block && !is_bdry)
+                        .collect(),
+                    blocks.grid_size,
                 )
             })
             .collect(),
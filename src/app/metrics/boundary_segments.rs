@@ -0,0 +1,79 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::sparse_blocks::SparseBlocks;
+use std::collections::HashMap;
+
+/// The boundary split into segments (pieces of blocks connected through their sides), grouped by
+///  their shape up to rotation and reflection. Builders can prefabricate the repeated segments.
+#[derive(Default, Debug, Clone)]
+pub struct BoundarySegments {
+    pub segments: Vec<BoundarySegment>,
+    // Distinct shapes (normal forms) with the number of segments of that shape, most common first
+    pub shapes: Vec<(SparseBlocks, usize)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BoundarySegment {
+    pub coords: Vec<[f64; 2]>, // left bottom coordinates of the blocks
+    pub shape_index: usize,    // index in `BoundarySegments::shapes`
+    pub center: [f64; 2],      // for placing a label
+}
+
+pub fn get_boundary_segments(boundary: &Blocks) -> BoundarySegments {
+    let components = boundary.get_connected_components();
+    let normal_forms: Vec<SparseBlocks> = components
+        .iter()
+        .map(|component| SparseBlocks::from_global_coords(component).normal_form())
+        .collect();
+
+    let mut counts: HashMap<&SparseBlocks, usize> = HashMap::new();
+    for normal_form in &normal_forms {
+        *counts.entry(normal_form).or_default() += 1;
+    }
+
+    // Most common first, then larger first (ties broken by the normal form so the order is stable)
+    let mut shapes: Vec<(SparseBlocks, usize)> = counts
+        .into_iter()
+        .map(|(normal_form, count)| (normal_form.clone(), count))
+        .collect();
+    shapes.sort_by(|(shape_a, count_a), (shape_b, count_b)| {
+        count_b
+            .cmp(count_a)
+            .then(shape_b.get_nr_blocks().cmp(&shape_a.get_nr_blocks()))
+            .then(shape_a.cmp(shape_b))
+    });
+
+    let segments = components
+        .iter()
+        .zip(normal_forms.iter())
+        .map(|(component, normal_form)| {
+            let sparse_blocks = SparseBlocks::from_global_coords(component);
+            let min_x = component.iter().map(|c| c[0]).min().unwrap_or(0) as f64;
+            let min_y = component.iter().map(|c| c[1]).min().unwrap_or(0) as f64;
+            let [center_x, center_y] = sparse_blocks.get_center();
+
+            BoundarySegment {
+                coords: component
+                    .iter()
+                    .map(|[x, y]| [*x as f64, *y as f64])
+                    .collect(),
+                shape_index: shapes
+                    .iter()
+                    .position(|(shape, _)| shape == normal_form)
+                    .unwrap(),
+                center: [min_x + center_x, min_y + center_y],
+            }
+        })
+        .collect();
+
+    BoundarySegments { segments, shapes }
+}
+
+/// Letters for the shape with the given index: A, B, ..., Z, AA, AB, ...
+pub fn shape_label(index: usize) -> String {
+    let letter = (b'A' + (index % 26) as u8) as char;
+    if index < 26 {
+        letter.to_string()
+    } else {
+        format!("{}{}", shape_label(index / 26 - 1), letter)
+    }
+}
@@ -0,0 +1,142 @@
+use crate::app::data_structures::blocks::Blocks;
+
+/// Above this many boundary blocks the 2-opt improvement is skipped (it is quadratic per pass)
+const TWO_OPT_MAX_NR_BLOCKS: usize = 2000;
+/// Maximal number of full 2-opt passes over the tour
+const TWO_OPT_MAX_PASSES: usize = 20;
+
+/// Closed walk visiting every block of the boundary once, for building the boundary in sequence.
+/// `tour` contains the centers of the blocks in visiting order (the last block connects back to
+///  the first one).
+#[derive(Default, Debug, Clone)]
+pub struct BoundaryTour {
+    pub tour: Vec<[f64; 2]>,
+    pub length: f64,
+    pub nr_passes: usize, // Number of 2-opt passes that were run
+    pub capped: bool,     // True if the improvement stopped because of the complexity cap
+}
+
+impl BoundaryTour {
+    /// The left bottom coordinates of the blocks in visiting order
+    pub fn block_coords(&self) -> Vec<[isize; 2]> {
+        self.tour
+            .iter()
+            .map(|[x, y]| [x.floor() as isize, y.floor() as isize])
+            .collect()
+    }
+}
+
+/// Compute a short closed tour through all blocks of the input (usually the 2D boundary).
+/// Exhaustive search for the optimal cycle is exponential, so we construct the tour greedily by
+///  walking to the nearest unvisited block and then untangle it by 2-opt moves (reversing a
+///  section of the tour whenever that makes it shorter). For a ring-like boundary the greedy
+///  tour is already almost optimal, the 2-opt passes mostly fix the jump back to the start.
+pub fn get_boundary_tour(boundary: &Blocks) -> BoundaryTour {
+    let points: Vec<[f64; 2]> = boundary
+        .get_all_block_coords()
+        .iter()
+        .map(|[x, y]| [x + 0.5, y + 0.5])
+        .collect();
+
+    if points.len() <= 3 {
+        return BoundaryTour {
+            length: tour_length(&points),
+            tour: points,
+            nr_passes: 0,
+            capped: false,
+        };
+    }
+
+    let mut tour = nearest_neighbor_tour(&points);
+
+    let mut nr_passes = 0;
+    let mut capped = tour.len() > TWO_OPT_MAX_NR_BLOCKS;
+    if !capped {
+        loop {
+            if nr_passes == TWO_OPT_MAX_PASSES {
+                capped = true;
+                break;
+            }
+            nr_passes += 1;
+            if !two_opt_pass(&mut tour) {
+                break;
+            }
+        }
+    }
+
+    BoundaryTour {
+        length: tour_length(&tour),
+        tour,
+        nr_passes,
+        capped,
+    }
+}
+
+/// Start at the bottom left point and always walk to the nearest unvisited point
+fn nearest_neighbor_tour(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut unvisited = points.to_vec();
+    let mut tour = Vec::with_capacity(points.len());
+
+    // get_all_block_coords is ordered x first then y, so the first point is bottom left
+    let mut current = unvisited.swap_remove(0);
+    tour.push(current);
+
+    while !unvisited.is_empty() {
+        let mut nearest_index = 0;
+        let mut nearest_distance = f64::INFINITY;
+        for (index, point) in unvisited.iter().enumerate() {
+            let distance = distance(current, *point);
+            if distance < nearest_distance {
+                nearest_index = index;
+                nearest_distance = distance;
+            }
+        }
+        current = unvisited.swap_remove(nearest_index);
+        tour.push(current);
+    }
+
+    tour
+}
+
+/// Try all pairs of edges (a, b), (c, d) in the tour and replace them by (a, c), (b, d) if that
+///  is shorter. Return true if the tour was improved.
+fn two_opt_pass(tour: &mut [[f64; 2]]) -> bool {
+    let n = tour.len();
+    let mut improved = false;
+
+    for i in 0..n - 2 {
+        for j in i + 2..n {
+            // edges (i, i+1) and (j, j+1), where j+1 wraps around to the start
+            if i == 0 && j == n - 1 {
+                continue; // the two edges are adjacent
+            }
+            let a = tour[i];
+            let b = tour[i + 1];
+            let c = tour[j];
+            let d = tour[(j + 1) % n];
+
+            // small tolerance so we don't flip back and forth on equal length tours
+            if distance(a, c) + distance(b, d) < distance(a, b) + distance(c, d) - 1e-9 {
+                tour[i + 1..=j].reverse();
+                improved = true;
+            }
+        }
+    }
+
+    improved
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+/// Length of the closed tour
+fn tour_length(tour: &[[f64; 2]]) -> f64 {
+    if tour.len() < 2 {
+        return 0.0;
+    }
+    tour.iter()
+        .zip(tour.iter().cycle().skip(1))
+        .map(|(a, b)| distance(*a, *b))
+        .sum()
+}
@@ -1,59 +1,60 @@
-// from https://www.geeksforgeeks.org/convex-hull-monotone-chain-algorithm/
+/// Points closer to a line than this (relative to the length of the segment) count as colinear.
+///  The points come from block corners, so they are on an integer grid and this only guards
+///  against rounding.
+const COLINEAR_EPSILON: f64 = 1e-9;
+
+/// Andrew's monotone chain algorithm
 /// Input: point cloud (unordered collection of points)
-/// Output: sequence of extreme points of the convex hull that goes around counterclockwise
-/// note that the starting point of the output is not well-defined, cyclic permutations are also
-///  valid outputs.
-pub fn get_convex_hull(points: &Vec<[f64; 2]>) -> Vec<[f64; 2]> {
+/// Output: sequence of extreme points of the convex hull that goes around counterclockwise,
+///  starting at the leftmost (then lowest) point. Colinear points on the edges and duplicate
+///  points are left out.
+pub fn get_convex_hull(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut points = points.to_vec();
+    points.sort_by(|p, q| p[0].total_cmp(&q[0]).then(p[1].total_cmp(&q[1])));
+    points.dedup();
+
     if points.len() <= 2 {
-        let mut conv_hull = vec![];
-        for i in points {
-            conv_hull.push(*i);
-        }
-        return conv_hull; // trivial for 0, 1, or 2 points
+        return points; // trivial for 0, 1, or 2 points
     }
 
-    let mut conv_hull = vec![];
+    // Lower hull from left to right, then upper hull from right to left. The last point of each
+    //  half is the first point of the other half, so it is dropped.
+    let mut lower_hull = half_hull(points.iter());
+    let mut upper_hull = half_hull(points.iter().rev());
+    lower_hull.pop();
+    upper_hull.pop();
+    lower_hull.append(&mut upper_hull);
 
-    // Get index of leftmost point (this is necessarily in the convex hull)
-    let mut leftmost_index = 0;
-    for i in 0..points.len() {
-        if points[i][0] < points[leftmost_index][0] {
-            leftmost_index = i;
-        }
-    }
+    lower_hull
+}
 
-    let mut p = leftmost_index;
-    let mut q: usize;
-    loop {
-        conv_hull.push(points[p]);
-
-        // increment q
-        q = (p + 1) % points.len();
-
-        // Pick the largest i such that (p, i, q) is positively oriented
-        for i in 0..points.len() {
-            if
-            //  check orientation (is the triple product in 2D)
-            (points[i][1] - points[p][1]) * (points[q][0] - points[i][0])
-                - (points[i][0] - points[p][0]) * (points[q][1] - points[i][1])
-                < 0.0
-            {
-                // If the triple (p, i, q) is oriented counterclockwise (strictly, i.e., not colinear)
-                q = i;
-            }
-        }
-        p = q;
-        if p == leftmost_index {
-            break;
-            // we've checked all points
+/// Walk over the sorted points, only keeping the points where the chain turns counterclockwise
+fn half_hull<'a>(points: impl Iterator<Item = &'a [f64; 2]>) -> Vec<[f64; 2]> {
+    let mut hull: Vec<[f64; 2]> = vec![];
+    for point in points {
+        while hull.len() >= 2 && !is_left_turn(hull[hull.len() - 2], hull[hull.len() - 1], *point) {
+            hull.pop();
         }
+        hull.push(*point);
     }
+    hull
+}
 
-    conv_hull
+/// Is the triple (p, q, r) strictly oriented counterclockwise?
+fn is_left_turn(p: [f64; 2], q: [f64; 2], r: [f64; 2]) -> bool {
+    // 2D cross product of q - p and r - p
+    let cross = (q[0] - p[0]) * (r[1] - p[1]) - (q[1] - p[1]) * (r[0] - p[0]);
+    let scale = (q[0] - p[0]).hypot(q[1] - p[1]) * (r[0] - p[0]).hypot(r[1] - p[1]);
+
+    cross > COLINEAR_EPSILON * scale
 }
 
 /// Convert the sequence of points forming a convex hull to easier to draw pairs of line segments
 pub fn line_segments_from_conv_hull(conv_hull: Vec<[f64; 2]>) -> Vec<[[f64; 2]; 2]> {
+    if conv_hull.len() < 2 {
+        return vec![];
+    }
+
     let mut line_segments = vec![];
     for i in 0..conv_hull.len() - 1 {
         line_segments.push([conv_hull[i], conv_hull[i + 1]]);
@@ -62,3 +63,146 @@ pub fn line_segments_from_conv_hull(conv_hull: Vec<[f64; 2]>) -> Vec<[[f64; 2];
 
     line_segments
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{get_convex_hull, line_segments_from_conv_hull};
+
+    /// The corners of the unit cells with these lower left corners
+    fn cell_corners(cells: &[[f64; 2]]) -> Vec<[f64; 2]> {
+        cells
+            .iter()
+            .flat_map(|[x, y]| [[*x, *y], [x + 1.0, *y], [x + 1.0, y + 1.0], [*x, y + 1.0]])
+            .collect()
+    }
+
+    #[test]
+    fn colinear_points_give_the_endpoints() {
+        let points = [[2.0, 2.0], [0.0, 0.0], [3.0, 3.0], [1.0, 1.0]];
+        assert_eq!(get_convex_hull(&points), vec![[0.0, 0.0], [3.0, 3.0]]);
+    }
+
+    #[test]
+    fn colinear_points_on_the_edges_are_left_out() {
+        let points = [
+            [0.0, 0.0],
+            [1.0, 0.0],
+            [2.0, 0.0],
+            [2.0, 1.0],
+            [2.0, 2.0],
+            [1.0, 1.0],
+            [0.0, 2.0],
+            [0.0, 1.0],
+        ];
+        assert_eq!(
+            get_convex_hull(&points),
+            vec![[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]]
+        );
+    }
+
+    #[test]
+    fn duplicate_points_are_left_out() {
+        let points = [[0.0, 0.0], [1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 0.0]];
+        assert_eq!(
+            get_convex_hull(&points),
+            vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]
+        );
+        assert_eq!(get_convex_hull(&[[1.0, 1.0], [1.0, 1.0]]), vec![[1.0, 1.0]]);
+    }
+
+    #[test]
+    fn one_cell() {
+        assert_eq!(
+            get_convex_hull(&cell_corners(&[[0.0, 0.0]])),
+            vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn two_cells() {
+        // Side by side: the shared corners are duplicates and on the edges of the hull
+        assert_eq!(
+            get_convex_hull(&cell_corners(&[[0.0, 0.0], [1.0, 0.0]])),
+            vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [0.0, 1.0]]
+        );
+        // Diagonal: a hexagon
+        assert_eq!(
+            get_convex_hull(&cell_corners(&[[0.0, 0.0], [1.0, 1.0]])),
+            vec![
+                [0.0, 0.0],
+                [1.0, 0.0],
+                [2.0, 1.0],
+                [2.0, 2.0],
+                [1.0, 2.0],
+                [0.0, 1.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn no_points() {
+        assert!(get_convex_hull(&[]).is_empty());
+    }
+
+    // Point sets that the previous (gift wrapping) hull failed on
+
+    #[test]
+    fn colinear_leftmost_points() {
+        // Never got back to the leftmost point, so it didn't end
+        let points = [[0.0, 1.0], [0.0, 2.0], [0.0, 0.0], [1.0, 3.0]];
+        assert_eq!(
+            get_convex_hull(&points),
+            vec![[0.0, 0.0], [1.0, 3.0], [0.0, 2.0]]
+        );
+    }
+
+    #[test]
+    fn points_on_a_vertical_line() {
+        // Went back along the line, [0, 0], [0, 2], [0, 1] (like [0, 0], [3, 3], [1, 1], [2, 2]
+        //  for the points of `colinear_points_give_the_endpoints`)
+        let points = [[0.0, 0.0], [0.0, 2.0], [0.0, 1.0]];
+        assert_eq!(get_convex_hull(&points), vec![[0.0, 0.0], [0.0, 2.0]]);
+    }
+
+    #[test]
+    fn duplicate_first_point() {
+        // The duplicate was another point of the hull, [1, 2], [3, 1], [1, 2]
+        let points = [[1.0, 2.0], [3.0, 1.0], [1.0, 2.0]];
+        assert_eq!(get_convex_hull(&points), vec![[1.0, 2.0], [3.0, 1.0]]);
+        // Also a duplicate of the leftmost point, [0, 0], [0, 0], [1, 0], [0, 1]
+        let points = [[0.0, 0.0], [0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        assert_eq!(
+            get_convex_hull(&points),
+            vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn circle_blocks() {
+        // The blocks of the circle with radius 4 (centers in the circle). The corners halfway
+        //  along the lower diagonal sides were kept, those along the upper ones left out.
+        let cells: Vec<[f64; 2]> = (-4..4)
+            .flat_map(|x| (-4..4).map(move |y| [x as f64, y as f64]))
+            .filter(|[x, y]| (x + 0.5).powi(2) + (y + 0.5).powi(2) <= 16.0)
+            .collect();
+        assert_eq!(
+            get_convex_hull(&cell_corners(&cells)),
+            vec![
+                [-4.0, -2.0],
+                [-2.0, -4.0],
+                [2.0, -4.0],
+                [4.0, -2.0],
+                [4.0, 2.0],
+                [2.0, 4.0],
+                [-2.0, 4.0],
+                [-4.0, 2.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn no_line_segments_without_points() {
+        // Subtracted one from the length of the empty hull
+        assert!(line_segments_from_conv_hull(vec![]).is_empty());
+    }
+}
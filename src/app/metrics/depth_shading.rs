@@ -0,0 +1,42 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+
+/// Layers above and below a block that are looked at for its depth
+pub const MAX_SHADING_DEPTH: usize = 4;
+
+/// The blocks of the layer with their depth in the stack: the number of consecutive layers with a
+///  block in the same place directly above it or directly below it, whichever is fewer (at most
+///  `MAX_SHADING_DEPTH`). Blocks on the top or bottom surface of the shape have depth 0, blocks
+///  deep inside it the maximum depth. For shading the blocks in the flat view.
+pub fn get_block_depths(stack_blocks: &ZVec<Blocks>, layer: isize) -> Vec<([f64; 2], usize)> {
+    // Without cloning the layers (`ZVec::get` clones)
+    let layer_blocks = |layer: isize| {
+        usize::try_from(layer - stack_blocks.get_minimum())
+            .ok()
+            .and_then(|index| stack_blocks.data.get(index))
+    };
+    let Some(blocks) = layer_blocks(layer) else {
+        return vec![];
+    };
+
+    let run_length = |coord: [isize; 2], step: isize| {
+        (1..=MAX_SHADING_DEPTH as isize)
+            .take_while(|distance| {
+                layer_blocks(layer + step * distance)
+                    .is_some_and(|blocks| blocks.is_block_on_global_coord(coord))
+            })
+            .count()
+    };
+
+    blocks
+        .get_all_block_coords()
+        .into_iter()
+        .map(|coord| {
+            let global_coord = [coord[0] as isize, coord[1] as isize];
+            (
+                coord,
+                run_length(global_coord, 1).min(run_length(global_coord, -1)),
+            )
+        })
+        .collect()
+}
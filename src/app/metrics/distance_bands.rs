@@ -0,0 +1,69 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::formatting::format_materials;
+
+/// Assign to each block the index of the band (annulus of width `band_width` around `center`) that
+///  its centerpoint lies in. Output: left bottom coordinates of the blocks with their band index.
+pub fn get_distance_bands(
+    blocks: &Blocks,
+    center: [f64; 2],
+    band_width: f64,
+) -> Vec<([f64; 2], usize)> {
+    blocks
+        .get_all_block_coords()
+        .into_iter()
+        .map(|[x, y]| {
+            let distance = ((x + 0.5 - center[0]).powi(2) + (y + 0.5 - center[1]).powi(2)).sqrt();
+            ([x, y], (distance / band_width).floor() as usize)
+        })
+        .collect()
+}
+
+/// The bands of the current layer as drawn in the viewport. They are only recomputed when the
+///  blocks, the center or the band width have changed since the last time.
+#[derive(Default)]
+pub struct DistanceBands {
+    computed_for: Option<(Blocks, [f64; 2], f64)>,
+    bands: Vec<([f64; 2], usize)>,
+}
+
+impl DistanceBands {
+    pub fn get(
+        &mut self,
+        blocks: &Blocks,
+        center: [f64; 2],
+        band_width: f64,
+    ) -> &[([f64; 2], usize)] {
+        let up_to_date =
+            self.computed_for
+                .as_ref()
+                .is_some_and(|(old_blocks, old_center, old_band_width)| {
+                    old_blocks == blocks && *old_center == center && *old_band_width == band_width
+                });
+        if !up_to_date {
+            self.bands = get_distance_bands(blocks, center, band_width);
+            self.computed_for = Some((blocks.clone(), center, band_width));
+        }
+        &self.bands
+    }
+}
+
+/// Plain text listing of the bands, one block per line as `x y band`, preceded by the number of
+///  blocks in each band (with the materials for it)
+pub fn distance_bands_to_text(bands: &[([f64; 2], usize)]) -> String {
+    let nr_bands = bands.iter().map(|(_, band)| band + 1).max().unwrap_or(0);
+
+    let mut output = String::new();
+    for band in 0..nr_bands {
+        let nr_blocks = bands.iter().filter(|(_, b)| *b == band).count() as u64;
+        output.push_str(&format!(
+            "band {}: {} blocks ({})\n",
+            band,
+            nr_blocks,
+            format_materials(nr_blocks)
+        ));
+    }
+    for ([x, y], band) in bands {
+        output.push_str(&format!("{} {} {}\n", x, y, band));
+    }
+    output
+}
@@ -0,0 +1,253 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::metrics::boundary_3d::Connectivity;
+use std::collections::VecDeque;
+
+/// Enclosed air pockets of the current layer and of the whole stack, e.g., to check that a hollow
+///  dome is sealed
+#[derive(Debug, Clone)]
+pub struct Holes {
+    pub nr_holes_2d: usize,
+    pub holes_2d: Blocks, // the air blocks of the current layer that are enclosed in that layer
+
+    pub nr_holes_3d: usize,
+    pub holes_3d: ZVec<Blocks>, // the air blocks that are enclosed in the stack, per layer
+    pub genus_3d: usize,        // number of tunnels through the stack (like the hole of a donut)
+}
+
+impl Default for Holes {
+    fn default() -> Self {
+        Self {
+            nr_holes_2d: 0,
+            holes_2d: Blocks::default(),
+            nr_holes_3d: 0,
+            holes_3d: ZVec::new(VecDeque::from(vec![Blocks::default()]), 0),
+            genus_3d: 0,
+        }
+    }
+}
+
+/// Air components of a single layer that do not reach the edge of the grid. Air is connected
+///  through the sides of the blocks (not diagonally), since that is how water and light leak.
+pub fn get_holes_2d(blocks: &Blocks) -> (usize, Blocks) {
+    let grid_size = blocks.grid_size;
+    let mut holes = Blocks::new(vec![false; grid_size.pow(2)], grid_size);
+
+    // The grid goes from -grid_size / 2 to grid_size - 1 - grid_size / 2 in both directions
+    let lowest = -((grid_size / 2) as isize);
+    let highest = lowest + grid_size as isize - 1;
+    let on_edge = |coord: [isize; 2]| {
+        coord[0] == lowest || coord[0] == highest || coord[1] == lowest || coord[1] == highest
+    };
+
+    let mut nr_holes = 0;
+    for component in blocks.get_complement().get_connected_components() {
+        if component.iter().any(|&coord| on_edge(coord)) {
+            continue;
+        }
+        nr_holes += 1;
+        for coord in component {
+            let i = holes.get_index_from_global_coord_usize(coord).unwrap();
+            holes.blocks[i] = true;
+        }
+    }
+
+    (nr_holes, holes)
+}
+
+// States of the cells of the 3D grid
+const AIR: u8 = 0;
+const SOLID: u8 = 1;
+const OUTSIDE: u8 = 2; // air that is connected to the outside
+const HOLE: u8 = 3; // air that has been assigned to a hole
+
+/// The stack as a 3D grid with a layer of air all around, indexed by global coordinates
+struct Grid3d {
+    radius: isize, // x and y go from -radius to radius (inclusive)
+    layer_min: isize,
+    layer_max: isize,
+    cells: Vec<u8>,
+}
+
+impl Grid3d {
+    fn new(stack_blocks: &ZVec<Blocks>, layer_min: isize, layer_max: isize) -> Self {
+        let radius = stack_blocks
+            .data
+            .iter()
+            .map(|blocks| blocks.grid_size as isize / 2 + 1)
+            .max()
+            .unwrap_or(1);
+        let side = (2 * radius + 1) as usize;
+
+        let mut grid = Grid3d {
+            radius,
+            layer_min: layer_min - 1,
+            layer_max: layer_max + 1,
+            cells: vec![AIR; side.pow(2) * (layer_max - layer_min + 3) as usize],
+        };
+
+        for layer in layer_min..=layer_max {
+            if let Some(blocks) = stack_blocks.get(layer) {
+                for i in 0..blocks.grid_size.pow(2) {
+                    if blocks.blocks[i] {
+                        let [x, y] = blocks.get_global_coord_usize_from_index(i);
+                        let index = grid.index([x, y, layer]).unwrap();
+                        grid.cells[index] = SOLID;
+                    }
+                }
+            }
+        }
+
+        grid
+    }
+
+    fn index(&self, [x, y, z]: [isize; 3]) -> Option<usize> {
+        let side = 2 * self.radius + 1;
+        if x.abs() > self.radius
+            || y.abs() > self.radius
+            || z < self.layer_min
+            || z > self.layer_max
+        {
+            None
+        } else {
+            Some(
+                (((z - self.layer_min) * side + y + self.radius) * side + x + self.radius) as usize,
+            )
+        }
+    }
+
+    fn coord(&self, index: usize) -> [isize; 3] {
+        let side = (2 * self.radius + 1) as usize;
+        [
+            (index % side) as isize - self.radius,
+            (index / side % side) as isize - self.radius,
+            (index / side.pow(2)) as isize + self.layer_min,
+        ]
+    }
+
+    fn is_solid(&self, coord: [isize; 3]) -> bool {
+        self.index(coord).is_some_and(|i| self.cells[i] == SOLID)
+    }
+
+    /// Give all cells with state `from` that are connected to `start` the state `to`, through the
+    ///  neighbors of `connectivity`
+    fn flood(&mut self, start: usize, from: u8, to: u8, connectivity: Connectivity) {
+        let neighbors = connectivity.neighbors();
+        let mut stack = vec![start];
+        self.cells[start] = to;
+        while let Some(i) = stack.pop() {
+            let [x, y, z] = self.coord(i);
+            for [dx, dy, dz] in &neighbors {
+                if let Some(j) = self.index([x + dx, y + dy, z + dz]) {
+                    if self.cells[j] == from {
+                        self.cells[j] = to;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Give the air that is enclosed the state `HOLE` (the rest `OUTSIDE`) and count the holes
+    fn fill_holes(&mut self, connectivity: Connectivity) -> usize {
+        // The corner of the grid is never solid, everything connected to it is outside
+        self.flood(0, AIR, OUTSIDE, connectivity);
+
+        // The remaining air is enclosed
+        let mut nr_holes = 0;
+        for i in 0..self.cells.len() {
+            if self.cells[i] == AIR {
+                self.flood(i, AIR, HOLE, connectivity);
+                nr_holes += 1;
+            }
+        }
+        nr_holes
+    }
+
+    /// Euler characteristic of the union of the (closed) solid cubes, counted as
+    ///  vertices - edges + faces - cubes
+    fn euler_characteristic(&self) -> isize {
+        let any_solid = |x: [isize; 2], y: [isize; 2], z: [isize; 2]| {
+            (x[0]..=x[1]).any(|cx| {
+                (y[0]..=y[1]).any(|cy| (z[0]..=z[1]).any(|cz| self.is_solid([cx, cy, cz])))
+            })
+        };
+
+        let mut euler_characteristic = 0;
+        // The corner with the lowest coordinates of a cell has the same coordinates as the cell, a
+        //  lattice element is in the union if one of the cells around it is solid
+        for x in -self.radius..=self.radius + 1 {
+            for y in -self.radius..=self.radius + 1 {
+                for z in self.layer_min..=self.layer_max + 1 {
+                    let lower = |c: isize| [c - 1, c];
+                    let at = |c: isize| [c, c];
+                    // vertex
+                    euler_characteristic += any_solid(lower(x), lower(y), lower(z)) as isize;
+                    // edges in the x, y and z direction
+                    euler_characteristic -= any_solid(at(x), lower(y), lower(z)) as isize
+                        + any_solid(lower(x), at(y), lower(z)) as isize
+                        + any_solid(lower(x), lower(y), at(z)) as isize;
+                    // faces normal to the x, y and z direction
+                    euler_characteristic += any_solid(lower(x), at(y), at(z)) as isize
+                        + any_solid(at(x), lower(y), at(z)) as isize
+                        + any_solid(at(x), at(y), lower(z)) as isize;
+                    // cube
+                    euler_characteristic -= any_solid(at(x), at(y), at(z)) as isize;
+                }
+            }
+        }
+        euler_characteristic
+    }
+}
+
+/// Air components of the stack that are not connected to the outside (the space around the grids
+///  and above and below the stack), and the genus of the stack. Air is connected through the
+///  neighbors of `connectivity`, so with `Edges` or `Corners` it also leaks through diagonal gaps.
+///  The second output gives the hole blocks per layer. The third output is the number of tunnels,
+///  computed from
+///  (nr. of pieces) - (nr. of tunnels) + (nr. of holes) = (Euler characteristic)
+pub fn get_holes_3d(
+    stack_blocks: &ZVec<Blocks>,
+    layer_min: isize,
+    layer_max: isize,
+    connectivity: Connectivity,
+) -> (usize, ZVec<Blocks>, usize) {
+    let mut grid = Grid3d::new(stack_blocks, layer_min, layer_max);
+    let nr_holes = grid.fill_holes(connectivity);
+
+    let holes = ZVec::new(
+        (layer_min..=layer_max)
+            .map(|layer| {
+                let grid_size = stack_blocks.get(layer).map_or(0, |blocks| blocks.grid_size);
+                let mut holes = Blocks::new(vec![false; grid_size.pow(2)], grid_size);
+                for i in 0..grid_size.pow(2) {
+                    let [x, y] = holes.get_global_coord_usize_from_index(i);
+                    holes.blocks[i] = grid
+                        .index([x, y, layer])
+                        .is_some_and(|index| grid.cells[index] == HOLE);
+                }
+                holes
+            })
+            .collect(),
+        layer_min,
+    );
+
+    // Closed cubes that touch in an edge or a corner are connected
+    let euler_characteristic = grid.euler_characteristic();
+    let mut nr_pieces = 0;
+    for i in 0..grid.cells.len() {
+        if grid.cells[i] == SOLID {
+            grid.flood(i, SOLID, AIR, Connectivity::Corners);
+            nr_pieces += 1;
+        }
+    }
+    // The complement of the closed cubes is connected through the faces only
+    let nr_face_holes = if connectivity == Connectivity::Faces {
+        nr_holes
+    } else {
+        Grid3d::new(stack_blocks, layer_min, layer_max).fill_holes(Connectivity::Faces)
+    };
+    let genus = (nr_pieces + nr_face_holes as isize - euler_characteristic).max(0) as usize;
+
+    (nr_holes, holes, genus)
+}
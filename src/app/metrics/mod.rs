@@ -1,2 +1,11 @@
 pub mod boundary_3d;
+pub mod boundary_segments;
+pub mod boundary_tour;
 pub mod convex_hull;
+pub mod depth_shading;
+pub mod distance_bands;
+pub mod holes;
+pub mod octants;
+pub mod repeated_layers;
+pub mod thin_necks;
+pub mod vertical_slice;
@@ -0,0 +1,78 @@
+use crate::app::data_structures::blocks::Blocks;
+use std::f64::consts::{FRAC_PI_4, TAU};
+
+// Block centers closer than this to an octant boundary are on the boundary
+const TOLERANCE: f64 = 1e-9;
+
+/// Count the blocks in each octant around the center, counterclockwise starting from the positive
+///  x axis (octant 0 is between 0 and 45 degrees). A block whose center is on the boundary of two
+///  octants counts half for each, and a block at the center an eighth for every octant, so that
+///  the counts of a shape with all mirror symmetries of the grid are exactly equal.
+pub fn get_octant_counts(blocks: &Blocks, center: [f64; 2]) -> [f64; 8] {
+    let mut counts = [0.0; 8];
+
+    for [x, y] in blocks.get_all_block_coords() {
+        let [dx, dy] = [x + 0.5 - center[0], y + 0.5 - center[1]];
+        if dx.abs() < TOLERANCE && dy.abs() < TOLERANCE {
+            counts.iter_mut().for_each(|count| *count += 1.0 / 8.0);
+            continue;
+        }
+
+        let position = dy.atan2(dx).rem_euclid(TAU) / FRAC_PI_4; // in [0, 8]
+        let on_boundary = dx.abs().min(dy.abs()).min((dx.abs() - dy.abs()).abs()) < TOLERANCE;
+        if on_boundary {
+            let boundary = position.round() as usize;
+            counts[(boundary + 7) % 8] += 0.5;
+            counts[boundary % 8] += 0.5;
+        } else {
+            counts[position.floor() as usize % 8] += 1.0;
+        }
+    }
+
+    counts
+}
+
+/// Difference between the largest and the smallest count (zero for symmetric shapes)
+pub fn octant_imbalance(counts: &[f64; 8]) -> f64 {
+    let max = counts.iter().fold(f64::NEG_INFINITY, |a, b| a.max(*b));
+    let min = counts.iter().fold(f64::INFINITY, |a, b| a.min(*b));
+    max - min
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_octant_counts, octant_imbalance};
+    use crate::app::data_structures::blocks::Blocks;
+
+    /// Blocks with the given left bottom coordinates, on a grid of size 8
+    fn blocks_at(coords: &[[isize; 2]]) -> Blocks {
+        let mut blocks = Blocks::new(vec![false; 64], 8);
+        for coord in coords {
+            let index = blocks.get_index_from_global_coord_usize(*coord).unwrap();
+            blocks.blocks[index] = true;
+        }
+        blocks
+    }
+
+    #[test]
+    fn block_in_one_octant() {
+        // Center (1.5, 0.5), at about 18 degrees
+        let counts = get_octant_counts(&blocks_at(&[[1, 0]]), [0.0, 0.0]);
+        assert_eq!(counts, [1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(octant_imbalance(&counts), 1.0);
+    }
+
+    #[test]
+    fn blocks_on_boundaries_count_half() {
+        // The centers of a 2x2 square lie on the diagonals
+        let counts = get_octant_counts(&blocks_at(&[[-1, -1], [0, -1], [-1, 0], [0, 0]]), [0.0; 2]);
+        assert_eq!(counts, [0.5; 8]);
+        assert_eq!(octant_imbalance(&counts), 0.0);
+    }
+
+    #[test]
+    fn block_at_center_counts_for_all() {
+        let counts = get_octant_counts(&blocks_at(&[[0, 0]]), [0.5, 0.5]);
+        assert_eq!(counts, [1.0 / 8.0; 8]);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+
+/// A run of consecutive layers with exactly the same blocks (at least two layers)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerRun {
+    pub first: isize,
+    pub last: isize,
+}
+
+impl LayerRun {
+    pub fn contains(&self, layer: isize) -> bool {
+        (self.first..=self.last).contains(&layer)
+    }
+
+    pub fn nr_layers(&self) -> usize {
+        (self.last - self.first + 1) as usize
+    }
+}
+
+/// Find the runs of identical consecutive layers in the stack. Layers are compared by the global
+///  coordinates of their blocks, so layers generated on grids of different sizes can be equal.
+pub fn get_repeated_layers(stack_blocks: &ZVec<Blocks>) -> Vec<LayerRun> {
+    let mut runs = vec![];
+    let mut previous_coords: Option<Vec<[f64; 2]>> = None;
+    let mut run_first = stack_blocks.get_minimum();
+
+    for (layer, blocks) in (stack_blocks.get_minimum()..).zip(stack_blocks.data.iter()) {
+        let coords = blocks.get_all_block_coords();
+        if previous_coords.as_ref() != Some(&coords) {
+            if layer - 1 > run_first {
+                runs.push(LayerRun {
+                    first: run_first,
+                    last: layer - 1,
+                });
+            }
+            run_first = layer;
+        }
+        previous_coords = Some(coords);
+    }
+
+    if stack_blocks.get_maximum() > run_first {
+        runs.push(LayerRun {
+            first: run_first,
+            last: stack_blocks.get_maximum(),
+        });
+    }
+
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_repeated_layers, LayerRun};
+    use crate::app::data_structures::blocks::Blocks;
+    use crate::app::data_structures::zvec::ZVec;
+
+    /// A single block at the origin, on a grid of the given size
+    fn single_block(grid_size: usize) -> Blocks {
+        let mut blocks = Blocks::new(vec![false; grid_size.pow(2)], grid_size);
+        let index = blocks.get_index_from_global_coord_usize([0, 0]).unwrap();
+        blocks.blocks[index] = true;
+        blocks
+    }
+
+    #[test]
+    fn runs_of_equal_layers() {
+        let empty = Blocks::new(vec![false; 16], 4);
+        let stack = ZVec::new(
+            [
+                single_block(4),
+                single_block(4),
+                empty.clone(),
+                single_block(4),
+                empty.clone(),
+                empty.clone(),
+                empty,
+            ]
+            .into(),
+            -2,
+        );
+        assert_eq!(
+            get_repeated_layers(&stack),
+            vec![
+                LayerRun {
+                    first: -2,
+                    last: -1
+                },
+                LayerRun { first: 2, last: 4 }
+            ]
+        );
+    }
+
+    #[test]
+    fn layers_on_different_grids_are_equal() {
+        let stack = ZVec::new([single_block(4), single_block(6)].into(), 0);
+        assert_eq!(
+            get_repeated_layers(&stack),
+            vec![LayerRun { first: 0, last: 1 }]
+        );
+    }
+
+    #[test]
+    fn single_layer_has_no_runs() {
+        let stack = ZVec::new([single_block(4)].into(), 0);
+        assert!(get_repeated_layers(&stack).is_empty());
+    }
+}
@@ -0,0 +1,43 @@
+use crate::app::data_structures::blocks::Blocks;
+
+/// Corners where two blocks only touch diagonally, while they are not connected through the sides
+///  of the blocks otherwise (the blocks are weakly but not strongly connected). These single
+///  corner connections let water through and tend to look like a mistake in the build.
+pub fn get_thin_necks(blocks: &Blocks) -> Vec<[f64; 2]> {
+    // Component (connected through the sides) of each block, by index
+    let mut component_of = vec![usize::MAX; blocks.grid_size.pow(2)];
+    for (component_index, component) in blocks.get_connected_components().iter().enumerate() {
+        for &coord in component {
+            let i = blocks.get_index_from_global_coord_usize(coord).unwrap();
+            component_of[i] = component_index;
+        }
+    }
+    let component = |coord: [isize; 2]| {
+        blocks
+            .get_index_from_global_coord_usize(coord)
+            .filter(|&i| blocks.blocks[i])
+            .map(|i| component_of[i])
+    };
+
+    let mut corners = vec![];
+    for index in 0..blocks.grid_size.pow(2) {
+        if !blocks.blocks[index] {
+            continue;
+        }
+        let [x, y] = blocks.get_global_coord_usize_from_index(index);
+        // The diagonal neighbors to the right (those to the left find this block), with the shared
+        //  corner
+        for dy in [1, -1] {
+            let Some(diagonal) = component([x + 1, y + dy]) else {
+                continue;
+            };
+            if component([x + 1, y]).is_none()
+                && component([x, y + dy]).is_none()
+                && Some(diagonal) != component([x, y])
+            {
+                corners.push([(x + 1) as f64, (y + dy.max(0)) as f64]);
+            }
+        }
+    }
+    corners
+}
@@ -0,0 +1,112 @@
+use std::fmt::{Display, Formatter};
+
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+
+/// Along which axis the vertical plane through the stack is held constant
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum SliceAxis {
+    #[default]
+    X, // the plane x = coord, so we see the y coordinate horizontally
+    Y, // the plane y = coord, so we see the x coordinate horizontally
+}
+
+/// Side profile of the stack: for each layer which blocks lie in the slicing plane.
+/// `layers.get(layer).unwrap()[i]` is true iff there is a block at horizontal coordinate
+///  `horizontal_min + i` on that layer.
+#[derive(Debug, Clone)]
+pub struct VerticalSlice {
+    pub horizontal_min: isize,
+    pub layers: ZVec<Vec<bool>>,
+}
+
+/// Intersect the stack with the vertical plane through the column `coord` (the global coordinate
+///  of the left bottom corner of the blocks) along the given axis.
+pub fn vertical_slice(
+    stack_blocks: &ZVec<Blocks>,
+    axis: SliceAxis,
+    coord: isize,
+    layer_lowest: isize,
+    layer_highest: isize,
+) -> VerticalSlice {
+    // All layers fit in the largest grid (the grids are centered at the origin)
+    let grid_size = stack_blocks
+        .data
+        .iter()
+        .map(|b| b.grid_size)
+        .max()
+        .unwrap_or(0);
+    let horizontal_min = -((grid_size / 2) as isize);
+
+    let layers = (layer_lowest..=layer_highest)
+        .map(|layer| {
+            let blocks = stack_blocks.get(layer).unwrap_or_default();
+            (0..grid_size as isize)
+                .map(|i| match axis {
+                    SliceAxis::X => blocks.is_block_on_global_coord([coord, horizontal_min + i]),
+                    SliceAxis::Y => blocks.is_block_on_global_coord([horizontal_min + i, coord]),
+                })
+                .collect()
+        })
+        .collect();
+
+    VerticalSlice {
+        horizontal_min,
+        layers: ZVec::new(layers, layer_lowest),
+    }
+}
+
+impl VerticalSlice {
+    /// Left bottom coordinates of the blocks in the slice, with the horizontal coordinate first
+    ///  and the layer second (so it can be plotted like a regular layer)
+    pub fn get_all_block_coords(&self) -> Vec<[f64; 2]> {
+        let mut output_vec = vec![];
+        for layer in self.layers.get_minimum()..=self.layers.get_maximum() {
+            for (i, b) in self.layers.get(layer).unwrap().iter().enumerate() {
+                if *b {
+                    output_vec.push([(self.horizontal_min + i as isize) as f64, layer as f64]);
+                }
+            }
+        }
+        output_vec
+    }
+
+    /// Plain text picture of the slice, top layer first, '#' for a block and '.' for air. Columns
+    ///  that are empty on every layer are left out.
+    pub fn to_text(&self) -> String {
+        let width = self.layers.data.front().map_or(0, |row| row.len());
+        let occupied: Vec<usize> = (0..width)
+            .filter(|i| self.layers.data.iter().any(|row| row[*i]))
+            .collect();
+
+        let (Some(first), Some(last)) = (occupied.first(), occupied.last()) else {
+            return String::new();
+        };
+
+        let mut output = format!(
+            "horizontal coordinates {} to {}\n",
+            self.horizontal_min + *first as isize,
+            self.horizontal_min + *last as isize
+        );
+        for layer in (self.layers.get_minimum()..=self.layers.get_maximum()).rev() {
+            let row = self.layers.get(layer).unwrap();
+            output.push_str(&format!("{:>5} | ", layer));
+            output.extend(
+                row[*first..=*last]
+                    .iter()
+                    .map(|b| if *b { '#' } else { '.' }),
+            );
+            output.push('\n');
+        }
+        output
+    }
+}
+
+impl Display for SliceAxis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SliceAxis::X => write!(f, "Constant x"),
+            SliceAxis::Y => write!(f, "Constant y"),
+        }
+    }
+}
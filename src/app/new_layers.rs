@@ -0,0 +1,63 @@
+use std::fmt::{Display, Formatter};
+
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+
+/// Where the parameters of the layers that are added when the stack grows come from
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum NewLayerFill {
+    #[default]
+    CopyNearest, // the old lowest or highest layer, whichever is closer
+    Interpolate, // from the nearest old layer to the default parameters at the new end
+    Default,     // the default parameters
+}
+
+impl NewLayerFill {
+    pub fn all() -> [Self; 3] {
+        [Self::CopyNearest, Self::Interpolate, Self::Default]
+    }
+}
+
+impl Display for NewLayerFill {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NewLayerFill::CopyNearest => write!(f, "Copy nearest layer"),
+            NewLayerFill::Interpolate => write!(f, "Interpolate to default"),
+            NewLayerFill::Default => write!(f, "Default parameters"),
+        }
+    }
+}
+
+/// Set the parameters of the layers of `stack_layer_config` that are not in the old stack
+///  `old_layer_config`. Layers that were already in the stack are left as they are.
+pub fn fill_new_layers(
+    stack_layer_config: &mut ZVec<SliceParameters>,
+    old_layer_config: &ZVec<SliceParameters>,
+    fill: NewLayerFill,
+    default: &SliceParameters,
+) {
+    let [old_lowest, old_highest] = [
+        old_layer_config.get_minimum(),
+        old_layer_config.get_maximum(),
+    ];
+    let [lowest, highest] = [
+        stack_layer_config.get_minimum(),
+        stack_layer_config.get_maximum(),
+    ];
+
+    for layer in (lowest..=highest).filter(|layer| !(old_lowest..=old_highest).contains(layer)) {
+        let nearest_layer = layer.clamp(old_lowest, old_highest);
+        let nearest = old_layer_config.get(nearest_layer).unwrap();
+        let slice_parameters = match fill {
+            NewLayerFill::CopyNearest => nearest,
+            NewLayerFill::Interpolate => {
+                // The far end of the new layers on this side gets the default parameters
+                let far_end = if layer > old_highest { highest } else { lowest };
+                let t = (layer - nearest_layer) as f64 / (far_end - nearest_layer) as f64;
+                nearest.interpolate(default, t)
+            }
+            NewLayerFill::Default => default.clone(),
+        };
+        stack_layer_config.set(layer, slice_parameters);
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+
+// Changes less than this many seconds apart are one step, so that dragging a slider gives a
+//  single entry (with the value where the slider was let go)
+const MERGE_INTERVAL: f64 = 0.5;
+// Older entries are forgotten
+const MAX_ENTRIES: usize = 1000;
+
+/// The parameters of a layer at some moment of the session
+pub struct HistoryEntry {
+    pub time: f64, // as in `egui::InputState::time`
+    pub layer: isize,
+    pub values: [f64; 5], // in the order of `Parameter::ALL`
+}
+
+/// How the parameters of each layer changed during the session, to go back to an earlier value of
+///  one parameter without undoing the others
+pub struct ParameterHistory {
+    pub parameter: Parameter, // the one that is shown
+    pub entries: VecDeque<HistoryEntry>,
+}
+
+impl Default for ParameterHistory {
+    fn default() -> Self {
+        Self {
+            parameter: Parameter::RadiusA,
+            entries: VecDeque::new(),
+        }
+    }
+}
+
+impl ParameterHistory {
+    /// Record the parameters of the layer if they changed since they were last recorded. Call
+    ///  every frame.
+    pub fn record(&mut self, time: f64, layer: isize, slice_parameters: &SliceParameters) {
+        let values = Parameter::ALL.map(|parameter| parameter.get(slice_parameters));
+        let last_values = self
+            .entries
+            .iter()
+            .rev()
+            .find(|entry| entry.layer == layer)
+            .map(|entry| entry.values);
+        if last_values == Some(values) {
+            return;
+        }
+        // The first entry of a layer is where it started, that is never merged into
+        let has_earlier_entry = self
+            .entries
+            .iter()
+            .rev()
+            .skip(1)
+            .any(|entry| entry.layer == layer);
+
+        match self.entries.back_mut() {
+            // Still the same change
+            Some(last)
+                if last.layer == layer
+                    && has_earlier_entry
+                    && time - last.time < MERGE_INTERVAL =>
+            {
+                last.time = time;
+                last.values = values;
+            }
+            _ => {
+                self.entries.push_back(HistoryEntry {
+                    time,
+                    layer,
+                    values,
+                });
+                if self.entries.len() > MAX_ENTRIES {
+                    self.entries.pop_front();
+                }
+            }
+        }
+    }
+
+    /// The shown parameter on the layer over time, as points (time, value)
+    pub fn get_values(&self, layer: isize) -> Vec<[f64; 2]> {
+        let index = Parameter::ALL
+            .iter()
+            .position(|parameter| *parameter == self.parameter)
+            .unwrap();
+        self.entries
+            .iter()
+            .filter(|entry| entry.layer == layer)
+            .map(|entry| [entry.time, entry.values[index]])
+            .collect()
+    }
+}
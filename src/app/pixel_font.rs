@@ -0,0 +1,173 @@
+use itertools::Itertools;
+
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+pub const GLYPH_SPACING: usize = 1; // empty columns between two glyphs
+const LINE_SPACING: usize = 1; // empty rows between two lines
+
+/// Built-in 3 by 5 pixel font for stamping text in blocks: capital letters (lowercase letters are
+///  drawn as capitals), digits, some punctuation and arrows. Each glyph is five rows from top to
+///  bottom, the bits of a row are the columns from left to right. Also used for the labels of the
+///  image export.
+pub fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    Some(match c.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '?' => [0b110, 0b001, 0b010, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '↑' => [0b010, 0b111, 0b010, 0b010, 0b010],
+        '↓' => [0b010, 0b010, 0b010, 0b111, 0b010],
+        '←' => [0b010, 0b100, 0b111, 0b100, 0b010],
+        '→' => [0b010, 0b001, 0b111, 0b001, 0b010],
+        '■' => [0b111, 0b111, 0b111, 0b111, 0b111],
+        _ => return None,
+    })
+}
+
+/// The characters of the text that the font doesn't have (they are skipped), without repeats
+pub fn unsupported_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .filter(|c| *c != '\n' && glyph(*c).is_none())
+        .unique()
+        .collect()
+}
+
+/// The blocks of the text, each pixel of the font `scale` by `scale` blocks. The coordinates are
+///  relative to the bottom left block of the text, with y up like in the viewport. Lines are
+///  separated by newlines.
+pub fn rasterize(text: &str, scale: usize) -> Vec<[isize; 2]> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut pixels = vec![];
+    for (line_index, line) in lines.iter().enumerate() {
+        // The last line is at the bottom, the top row of a glyph is its highest row
+        let line_bottom = (lines.len() - 1 - line_index) * (GLYPH_HEIGHT + LINE_SPACING);
+        let glyphs = line.chars().filter_map(glyph);
+        for (glyph_index, rows) in glyphs.enumerate() {
+            let glyph_left = glyph_index * (GLYPH_WIDTH + GLYPH_SPACING);
+            for (row_index, row) in rows.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if row & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        pixels.push([
+                            glyph_left + column,
+                            line_bottom + GLYPH_HEIGHT - 1 - row_index,
+                        ]);
+                    }
+                }
+            }
+        }
+    }
+
+    pixels
+        .iter()
+        .flat_map(|[x, y]| {
+            (0..scale * scale).map(move |i| {
+                [
+                    (x * scale + i % scale) as isize,
+                    (y * scale + i / scale) as isize,
+                ]
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rasterize, unsupported_chars, GLYPH_HEIGHT, GLYPH_SPACING, GLYPH_WIDTH};
+
+    #[test]
+    fn rasterize_glyph() {
+        // I: a full top and bottom row with the middle column in between
+        let mut pixels = rasterize("I", 1);
+        pixels.sort();
+        assert_eq!(
+            pixels,
+            vec![
+                [0, 0],
+                [0, 4],
+                [1, 0],
+                [1, 1],
+                [1, 2],
+                [1, 3],
+                [1, 4],
+                [2, 0],
+                [2, 4]
+            ]
+        );
+    }
+
+    #[test]
+    fn lowercase_is_uppercase() {
+        assert_eq!(rasterize("abc", 1), rasterize("ABC", 1));
+    }
+
+    #[test]
+    fn rasterize_scaled() {
+        // L has 7 pixels, each 2 by 2 blocks
+        let pixels = rasterize("L", 2);
+        assert_eq!(pixels.len(), 28);
+        assert!(pixels.contains(&[0, 0]) && pixels.contains(&[5, 1]) && pixels.contains(&[1, 9]));
+        assert!(!pixels.contains(&[2, 2]));
+    }
+
+    #[test]
+    fn glyphs_and_lines_are_spaced() {
+        let line = rasterize("■■", 1);
+        assert_eq!(line.len(), 2 * GLYPH_WIDTH * GLYPH_HEIGHT);
+        let second_left = (GLYPH_WIDTH + GLYPH_SPACING) as isize;
+        assert!(line.contains(&[second_left, 0]) && !line.contains(&[second_left - 1, 0]));
+
+        // The first line is on top
+        let lines = rasterize("■\n.", 1);
+        assert!(lines.contains(&[0, GLYPH_HEIGHT as isize + 1]));
+        assert_eq!(lines.iter().map(|[_, y]| *y).min(), Some(0));
+    }
+
+    #[test]
+    fn unsupported_chars_without_repeats() {
+        assert_eq!(unsupported_chars("A~B~\n{"), vec!['~', '{']);
+        assert!(unsupported_chars("Room 1!\nExit →").is_empty());
+    }
+}
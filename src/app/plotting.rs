@@ -1,7 +1,11 @@
 use std::f64::consts::PI;
 
+use crate::app::data_structures::lattice::Lattice;
 use crate::app::data_structures::slice_parameters::SliceParameters;
-use egui_plot::{Line, PlotBounds, PlotPoints, Polygon};
+use crate::app::generation::cell_aspect::to_cells;
+use crate::app::generation::script_shape::from_shape_coordinates;
+use eframe::egui::Color32;
+use egui_plot::{HLine, Line, PlotBounds, PlotPoints, Polygon, VLine};
 
 /// Specifiying the bottom left coordinates of the square.
 pub fn square_at_coords(coord: [f64; 2]) -> Polygon {
@@ -18,33 +22,102 @@ pub fn square_at_coords(coord: [f64; 2]) -> Polygon {
     Polygon::new(square_pts).name("square".to_owned())
 }
 
-pub fn superellipse_at_coords(slice_parameters: &SliceParameters) -> Line {
-    let radius_a = slice_parameters.radius_a;
-    let radius_b = slice_parameters.radius_b;
-    let tilt = slice_parameters.tilt;
-    let center_x = slice_parameters.center_offset_x;
-    let center_y = slice_parameters.center_offset_y;
-    let squircle_parameter = slice_parameters.squircle_parameter;
+/// Rectangle with left bottom corner `coord` and the given width and height
+pub fn rectangle_at_coords(coord: [f64; 2], [width, height]: [f64; 2]) -> Polygon {
+    let [x, y] = coord;
 
+    Polygon::new(PlotPoints::new(vec![
+        [x, y],
+        [x, y + height],
+        [x + width, y + height],
+        [x + width, y],
+    ]))
+}
+
+/// The outline of the shape, in cell coordinates like the blocks
+pub fn superellipse_at_coords(slice_parameters: &SliceParameters, cell_aspect: f64) -> Line {
     let circlepts: PlotPoints = (0..=1005)
         // Near the square (squircle_parameter = Infinity) we get weird holes (the parameterization
         //  is not equally spaced), so need a few more points for it to make sense
         .map(|i| {
-            let t = ((i as f64) * (2.0 * PI)) / 1000.0;
-            let notilt = [
-                radius_a * t.cos().abs().powf(2.0 / squircle_parameter) * t.cos().signum(),
-                radius_b * t.sin().abs().powf(2.0 / squircle_parameter) * t.sin().signum(),
-            ]; // the power is for squircles
-            [
-                center_x + notilt[0] * tilt.cos() + notilt[1] * tilt.sin(),
-                center_y + notilt[0] * tilt.sin() - notilt[1] * tilt.cos(),
-            ]
+            to_cells(
+                point_on_superellipse(slice_parameters, ((i as f64) * (2.0 * PI)) / 1000.0),
+                cell_aspect,
+            )
         })
         .collect();
 
     Line::new(circlepts)
 }
 
+/// The outline of a script shape (its points relative to the center and untilted) placed at the
+///  center and tilt of the layer, in cell coordinates
+pub fn script_outline_at_coords(
+    outline: &[[f64; 2]],
+    slice_parameters: &SliceParameters,
+    cell_aspect: f64,
+) -> Line {
+    Line::new(
+        outline
+            .iter()
+            .map(|point| {
+                to_cells(
+                    from_shape_coordinates(slice_parameters, *point),
+                    cell_aspect,
+                )
+            })
+            .collect::<PlotPoints>(),
+    )
+}
+
+/// The point of the outline at parameter `t` (in radians, 0 is on the tilted x axis), in the
+///  coordinates of the shape (not in cells)
+pub fn point_on_superellipse(slice_parameters: &SliceParameters, t: f64) -> [f64; 2] {
+    let radius_a = slice_parameters.radius_a;
+    let radius_b = slice_parameters.radius_b;
+    let tilt = slice_parameters.tilt;
+    let squircle_parameter = slice_parameters.squircle_parameter;
+
+    let notilt = [
+        radius_a * t.cos().abs().powf(2.0 / squircle_parameter) * t.cos().signum(),
+        radius_b * t.sin().abs().powf(2.0 / squircle_parameter) * t.sin().signum(),
+    ]; // the power is for squircles
+    [
+        slice_parameters.center_offset_x + notilt[0] * tilt.cos() + notilt[1] * tilt.sin(),
+        slice_parameters.center_offset_y + notilt[0] * tilt.sin() - notilt[1] * tilt.cos(),
+    ]
+}
+
+/// The two edges of a sector of the shape, from the end of the first edge through the center to the
+///  end of the second edge. The edges are as long as the major radius. In cell coordinates.
+pub fn sector_edges(
+    slice_parameters: &SliceParameters,
+    sector: [f64; 2],
+    cell_aspect: f64,
+) -> Line {
+    let radius = f64::max(slice_parameters.radius_a, slice_parameters.radius_b);
+    let center = [
+        slice_parameters.center_offset_x,
+        slice_parameters.center_offset_y,
+    ];
+    let edge_end = |angle: f64| {
+        let angle = angle + slice_parameters.tilt;
+        to_cells(
+            [
+                center[0] + radius * angle.cos(),
+                center[1] + radius * angle.sin(),
+            ],
+            cell_aspect,
+        )
+    };
+
+    Line::new(PlotPoints::new(vec![
+        edge_end(sector[0]),
+        to_cells(center, cell_aspect),
+        edge_end(sector[1]),
+    ]))
+}
+
 /// Draw a tilted line through the origin in the given bounds
 pub fn tilted_line_in_bounds(bnds: PlotBounds, tilt: f64, offset_x: f64, offset_y: f64) -> Line {
     let [min_x, min_y] = bnds.min();
@@ -79,3 +152,58 @@ pub fn bounds_from_square(bounds: [[f64; 2]; 2]) -> Line {
         [x_1, y_1],
     ]))
 }
+
+/// Grid lines in the bounds at all multiples of the steps (in the coordinates shifted by
+///  `translation`, see `View::world_translation`). A line gets the color of the coarsest step it is
+///  a multiple of. Steps whose lines would be closer together than `min_spacing` (in plot
+///  coordinates) are left out. On the `Centers` lattice the lines go through the middle of the
+///  blocks instead of along their edges.
+pub fn grid_lines(
+    bounds: PlotBounds,
+    steps: [f64; 3],
+    colors: [Color32; 3],
+    min_spacing: f64,
+    translation: [isize; 2],
+    lattice: Lattice,
+) -> (Vec<VLine>, Vec<HLine>) {
+    let mut levels: Vec<(f64, Color32)> = steps
+        .into_iter()
+        .zip(colors)
+        .filter(|(step, _)| *step > 0.0 && *step >= min_spacing)
+        .collect();
+    levels.sort_by(|a, b| a.0.total_cmp(&b.0)); // fine to coarse
+
+    // All values on the axis between min and max that get a line, with their colors. The lines
+    //  are at multiples of the steps after translating by `shift` (and moving to the lattice).
+    let shift = |translation: isize| translation as f64 - lattice.offset();
+    let values_in_range = |min: f64, max: f64, shift: f64| {
+        let mut values = vec![];
+        for (i, (step, color)) in levels.iter().enumerate() {
+            for k in ((min + shift) / step).ceil() as i64..=((max + shift) / step).floor() as i64 {
+                let value = k as f64 * step;
+                // leave the line to a coarser step if there is one
+                if !levels[i + 1..].iter().any(|(coarser_step, _)| {
+                    let quotient = value / coarser_step;
+                    (quotient - quotient.round()).abs() < 1e-9
+                }) {
+                    values.push((value - shift, *color));
+                }
+            }
+        }
+        values
+    };
+
+    let [min_x, min_y] = bounds.min();
+    let [max_x, max_y] = bounds.max();
+
+    (
+        values_in_range(min_x, max_x, shift(translation[0]))
+            .into_iter()
+            .map(|(x, color)| VLine::new(x).color(color).width(1.0))
+            .collect(),
+        values_in_range(min_y, max_y, shift(translation[1]))
+            .into_iter()
+            .map(|(y, color)| HLine::new(y).color(color).width(1.0))
+            .collect(),
+    )
+}
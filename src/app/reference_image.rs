@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use eframe::egui::{Color32, ColorImage, Context, TextureHandle, TextureOptions};
+use egui_plot::{PlotImage, PlotPoint};
+
+// Keys in the app storage, so that the reference image is opened again where it was placed
+pub const REFERENCE_IMAGE_PATH_KEY: &str = "reference_image_path";
+pub const REFERENCE_IMAGE_PLACEMENT_KEY: &str = "reference_image_placement";
+
+/// Files with these extensions are opened as a reference image (other files as block coordinates)
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "gif", "webp"];
+
+/// Where the reference image is shown in the viewport
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ImagePlacement {
+    pub center: [f64; 2],      // in viewport coordinates
+    pub blocks_per_pixel: f64, // width of a pixel of the image
+    pub opacity: f32,
+}
+
+impl Default for ImagePlacement {
+    fn default() -> Self {
+        Self {
+            center: [0.0, 0.0],
+            blocks_per_pixel: 1.0, // like a map that has a pixel for every block
+            opacity: 0.5,
+        }
+    }
+}
+
+impl ImagePlacement {
+    /// For the app storage: the numbers separated by spaces
+    pub fn to_storage_string(self) -> String {
+        format!(
+            "{} {} {} {}",
+            self.center[0], self.center[1], self.blocks_per_pixel, self.opacity
+        )
+    }
+
+    pub fn from_storage_string(text: &str) -> Option<Self> {
+        let numbers: Vec<f64> = text
+            .split_whitespace()
+            .map(|number| number.parse().ok())
+            .collect::<Option<_>>()?;
+        match numbers[..] {
+            [x, y, blocks_per_pixel, opacity] => Some(Self {
+                center: [x, y],
+                blocks_per_pixel,
+                opacity: opacity as f32,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// An image (a map screenshot, concept art) shown under the blocks, to trace shapes over it
+pub struct ReferenceImage {
+    pub name: String,
+    pub path: Option<PathBuf>, // to open it again on the next start (there is none on the web)
+    pub placement: ImagePlacement,
+    pub shown: bool,
+    image: ColorImage,
+    texture: Option<TextureHandle>, // uploaded when the image is first drawn
+}
+
+impl ReferenceImage {
+    pub fn new(
+        name: String,
+        path: Option<PathBuf>,
+        bytes: &[u8],
+        placement: ImagePlacement,
+    ) -> Result<Self, String> {
+        let image = image::load_from_memory(bytes)
+            .map_err(|error| format!("Couldn't open {} as an image: {}", name, error))?
+            .to_rgba8();
+        let size = [image.width() as usize, image.height() as usize];
+
+        Ok(Self {
+            name,
+            path,
+            placement,
+            shown: true,
+            image: ColorImage::from_rgba_unmultiplied(size, image.as_raw()),
+            texture: None,
+        })
+    }
+
+    /// Width and height in pixels
+    pub fn size(&self) -> [usize; 2] {
+        self.image.size
+    }
+
+    /// The image to draw in the viewport. Cells that aren't square stretch it, so that it keeps
+    ///  its proportions.
+    pub fn plot_image(&mut self, ctx: &Context, cell_aspect: f64) -> PlotImage {
+        let [width, height] = self.size();
+        let blocks_per_pixel = self.placement.blocks_per_pixel as f32;
+        // Sharp pixels, to trace the blocks of a map screenshot when zoomed in
+        let texture = self.texture.get_or_insert_with(|| {
+            ctx.load_texture(&self.name, self.image.clone(), TextureOptions::NEAREST)
+        });
+
+        PlotImage::new(
+            texture.id(),
+            PlotPoint::from(self.placement.center),
+            [
+                width as f32 * blocks_per_pixel,
+                height as f32 * blocks_per_pixel / cell_aspect as f32,
+            ],
+        )
+        .tint(Color32::WHITE.gamma_multiply(self.placement.opacity))
+    }
+}
+
+/// Whether the file is opened as a reference image, by its extension
+pub fn is_image_file(name: &str) -> bool {
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension);
+    extension.is_some_and(|extension| {
+        IMAGE_EXTENSIONS
+            .iter()
+            .any(|image_extension| extension.eq_ignore_ascii_case(image_extension))
+    })
+}
@@ -1,6 +1,8 @@
 use crate::app::data_structures::zvec::ZVec;
 use std::fmt::{Display, Formatter};
 
+pub mod percentage_suggestion;
+pub mod presets;
 pub mod sampled_parameters;
 
 #[derive(Debug, PartialEq, Default, Clone, Copy)]
@@ -21,6 +23,9 @@ pub enum SampleDistributeMethod {
 /// The ZVec corresponds to the layers. Each float in the Vec for a particular layer corresponds to
 ///  a sample that that layer has
 /// Note! The layer number is the middle!
+/// The bottom (top) layer can be sampled only from its middle up (down), so that the samples stay
+///  within the stack. If both apply to a single layer with no sample in the middle, it is sampled
+///  at its middle.
 pub fn determine_sampling_points(
     sample_distribute_method: SampleDistributeMethod,
     layer_lowest: isize,
@@ -60,9 +65,10 @@ pub fn determine_sampling_points(
                                 nr_samples_per_layer
                             };
 
-                            (start..end)
+                            let samples: Vec<f64> = (start..end)
                                 .map(|sample| layer as f64 + sample_size * (sample as f64) - 0.5)
-                                .collect()
+                                .collect();
+                            at_least_middle(samples, layer)
                         })
                         .collect(),
                     layer_lowest,
@@ -88,13 +94,14 @@ pub fn determine_sampling_points(
                                 nr_samples_per_layer
                             };
 
-                            (start..=end)
+                            let samples: Vec<f64> = (start..=end)
                                 .map(|sample| {
                                     layer as f64 + sample_size * (sample as f64)
                                         - 0.5
                                         - 0.5 * sample_size
                                 })
-                                .collect()
+                                .collect();
+                            at_least_middle(samples, layer)
                         })
                         .collect(),
                     layer_lowest,
@@ -104,6 +111,44 @@ pub fn determine_sampling_points(
     }
 }
 
+/// The samples, or the middle of the layer if there are none (when only half of a layer that is
+///  both the bottom and the top is sampled)
+fn at_least_middle(samples: Vec<f64>, layer: isize) -> Vec<f64> {
+    if samples.is_empty() {
+        vec![layer as f64]
+    } else {
+        samples
+    }
+}
+
+/// Which of the samples of a whole layer are taken for the layer (e.g. only the upper half for the
+///  bottom layer), for showing which sample heights are active
+pub fn active_samples(
+    sample_distribute_method: SampleDistributeMethod,
+    nr_samples_per_layer: usize,
+    layer: isize,
+    sampling_points: &[f64],
+) -> Vec<(f64, bool)> {
+    determine_sampling_points(
+        sample_distribute_method,
+        layer,
+        layer,
+        nr_samples_per_layer,
+        false,
+        false,
+    )
+    .get(layer)
+    .unwrap_or_default()
+    .into_iter()
+    .map(|height| {
+        let active = sampling_points
+            .iter()
+            .any(|point| (point - height).abs() < 1e-9);
+        (height, active)
+    })
+    .collect()
+}
+
 impl Display for SampleCombineMethod {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -0,0 +1,160 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::generation::Generator;
+use crate::app::sampling::sampled_parameters::LayerParameters;
+
+/// The percentage for the Percentage sample combination method that gives the smoothest stack:
+///  the fewest blocks that change from one layer to the next. Every percentage in `range` (lower
+///  bound excluded) gives the same blocks.
+#[derive(Debug, Clone, Copy)]
+pub struct PercentageSuggestion {
+    pub percentage: f64,
+    pub range: [f64; 2],
+    pub nr_changes: u64,
+    pub nr_changes_before: u64, // of the stack when the suggestion was made
+}
+
+/// How many samples of a layer have a block at each position of its grid
+struct SampleCounts {
+    counts: Vec<usize>,
+    grid_size: usize,
+    nr_samples: usize,
+}
+
+impl SampleCounts {
+    fn new(layer_parameters: &LayerParameters, generator: &Generator) -> Self {
+        let samples: Vec<Blocks> = layer_parameters
+            .parameters
+            .iter()
+            .map(|slice_parameters| LayerParameters::single(slice_parameters, generator))
+            .collect();
+        let grid_size = samples.iter().map(|b| b.grid_size).max().unwrap_or(0);
+        let grid = Blocks::new(vec![false; grid_size.pow(2)], grid_size);
+
+        Self {
+            counts: (0..grid_size.pow(2))
+                .map(|i| {
+                    let global_coord = grid.get_global_coord_usize_from_index(i);
+                    samples
+                        .iter()
+                        .filter(|b| b.is_block_on_global_coord(global_coord))
+                        .count()
+                })
+                .collect(),
+            grid_size,
+            nr_samples: samples.len(),
+        }
+    }
+
+    /// The blocks of the layer for the percentage (like `Blocks::combine`)
+    fn blocks(&self, percentage: f64) -> Blocks {
+        let target_nr_samples = self.nr_samples as f64 * percentage;
+        Blocks::new(
+            self.counts
+                .iter()
+                .map(|count| *count as f64 >= target_nr_samples)
+                .collect(),
+            self.grid_size,
+        )
+    }
+}
+
+/// Try every percentage that gives different blocks and suggest the one with the fewest changes
+///  between consecutive layers. Locked layers keep their blocks. Percentages that leave a layer
+///  empty (that isn't empty for all of them) are skipped, since an empty layer changes little.
+///  Ties go to the percentage closest to 50%.
+pub fn suggest_percentage(
+    stack_layer_parameters: &ZVec<LayerParameters>,
+    stack_blocks: &ZVec<Blocks>,
+    stack_locked: &ZVec<bool>,
+    generator: &Generator,
+) -> Option<PercentageSuggestion> {
+    // Nothing for the locked layers
+    let layers: Vec<Option<SampleCounts>> = stack_layer_parameters
+        .data
+        .iter()
+        .zip(stack_locked.data.iter())
+        .map(|(layer_parameters, locked)| {
+            (!locked).then(|| SampleCounts::new(layer_parameters, generator))
+        })
+        .collect();
+
+    // A layer with n samples changes at the multiples of 1/n, so each interval between them gives
+    //  one stack (the middle of the interval avoids rounding at the ends)
+    let mut ends: Vec<f64> = layers
+        .iter()
+        .flatten()
+        .flat_map(|counts| {
+            (0..=counts.nr_samples).map(|k| k as f64 / counts.nr_samples.max(1) as f64)
+        })
+        .collect();
+    ends.sort_by(f64::total_cmp);
+    ends.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+
+    // The lower the percentage, the more blocks, so a layer has blocks for some percentage iff
+    //  it has a block in any sample
+    let sometimes_filled: Vec<bool> = layers
+        .iter()
+        .zip(stack_blocks.data.iter())
+        .map(|(layer, blocks)| match layer {
+            Some(counts) => counts.counts.iter().any(|count| *count > 0),
+            None => blocks.get_nr_blocks() > 0,
+        })
+        .collect();
+
+    // Generate the stacks one at a time and only keep the best suggestion
+    let nr_changes_before = nr_changes_in_stack(&stack_blocks.data.iter().collect::<Vec<_>>());
+    let mut best: Option<PercentageSuggestion> = None;
+    for range in ends.windows(2) {
+        let percentage = (range[0] + range[1]) / 2.0;
+        let generated: Vec<Option<Blocks>> = layers
+            .iter()
+            .map(|layer| layer.as_ref().map(|counts| counts.blocks(percentage)))
+            .collect();
+        let stack: Vec<&Blocks> = generated
+            .iter()
+            .zip(stack_blocks.data.iter())
+            .map(|(generated, blocks)| generated.as_ref().unwrap_or(blocks))
+            .collect();
+
+        if stack
+            .iter()
+            .zip(sometimes_filled.iter())
+            .any(|(blocks, filled)| *filled && blocks.get_nr_blocks() == 0)
+        {
+            continue;
+        }
+
+        let suggestion = PercentageSuggestion {
+            percentage,
+            range: [range[0], range[1]],
+            nr_changes: nr_changes_in_stack(&stack),
+            nr_changes_before,
+        };
+        let key = |suggestion: &PercentageSuggestion| {
+            (suggestion.nr_changes, (suggestion.percentage - 0.5).abs())
+        };
+        if best.iter().all(|best| key(&suggestion) < key(best)) {
+            best = Some(suggestion);
+        }
+    }
+    best
+}
+
+/// Number of blocks that change from one layer to the next, over the whole stack
+fn nr_changes_in_stack(stack: &[&Blocks]) -> u64 {
+    stack
+        .windows(2)
+        .map(|pair| nr_changed_blocks(pair[0], pair[1]))
+        .sum()
+}
+
+/// Number of positions with a block in exactly one of the two layers
+fn nr_changed_blocks(a: &Blocks, b: &Blocks) -> u64 {
+    // The grids are centered at the origin, so the larger one contains the smaller one
+    let larger = if a.grid_size >= b.grid_size { a } else { b };
+    (0..larger.blocks.len())
+        .map(|i| larger.get_global_coord_usize_from_index(i))
+        .filter(|coord| a.is_block_on_global_coord(*coord) != b.is_block_on_global_coord(*coord))
+        .count() as u64
+}
@@ -0,0 +1,52 @@
+use crate::app::sampling::{SampleCombineMethod, SampleDistributeMethod};
+
+/// Named bundle of all sampling options, so they can be set in one go
+#[derive(Debug, Clone)]
+pub struct SamplingPreset {
+    pub name: String,
+    pub nr_samples_per_layer: usize,
+    pub sample_combine_method: SampleCombineMethod,
+    pub sample_distribute_method: SampleDistributeMethod,
+    pub only_sample_half_of_bottom_layer: bool,
+    pub only_sample_half_of_top_layer: bool,
+}
+
+impl SamplingPreset {
+    /// The presets that are always available
+    pub fn built_in() -> Vec<SamplingPreset> {
+        vec![
+            SamplingPreset {
+                name: "Fast: 1 sample".to_string(),
+                nr_samples_per_layer: 1,
+                sample_combine_method: SampleCombineMethod::AnySamples,
+                sample_distribute_method: SampleDistributeMethod::IncludeEndpoints,
+                only_sample_half_of_bottom_layer: false,
+                only_sample_half_of_top_layer: false,
+            },
+            SamplingPreset {
+                name: "Smooth dome: 5 samples, 60%".to_string(),
+                nr_samples_per_layer: 5,
+                sample_combine_method: SampleCombineMethod::Percentage(0.6),
+                sample_distribute_method: SampleDistributeMethod::IncludeEndpoints,
+                only_sample_half_of_bottom_layer: false,
+                only_sample_half_of_top_layer: true, // don't sample above the top of the dome
+            },
+            SamplingPreset {
+                name: "Conservative union: 5 samples".to_string(),
+                nr_samples_per_layer: 5,
+                sample_combine_method: SampleCombineMethod::AnySamples,
+                sample_distribute_method: SampleDistributeMethod::IncludeEndpoints,
+                only_sample_half_of_bottom_layer: false,
+                only_sample_half_of_top_layer: false,
+            },
+            SamplingPreset {
+                name: "Contained intersection: 5 samples".to_string(),
+                nr_samples_per_layer: 5,
+                sample_combine_method: SampleCombineMethod::AllSamples,
+                sample_distribute_method: SampleDistributeMethod::IncludeEndpoints,
+                only_sample_half_of_bottom_layer: false,
+                only_sample_half_of_top_layer: false,
+            },
+        ]
+    }
+}
@@ -1,7 +1,8 @@
 use crate::app::data_structures::blocks::Blocks;
 use crate::app::data_structures::slice_parameters::SliceParameters;
-use crate::app::generation::{generate_all_blocks, Algorithm};
-use crate::app::math::linear_algebra::Vec2;
+use crate::app::generation::cell_aspect::{angle_to_cells, to_cells};
+use crate::app::generation::{generate_all_blocks, Algorithm, Generator};
+use crate::app::log::log_warning;
 use crate::app::sampling::SampleCombineMethod;
 
 /// Sampled parameters belonging to a single layer
@@ -36,47 +37,120 @@ impl Default for LayerParameters {
 }
 
 impl LayerParameters {
-    // fixme: make grid size determination better: needs to also take care of the offset (if
-    //  the input is very offset this method fails)
+    /// The first problem of the samples (see `SliceParameters::validate`), or a warning about them
+    pub fn problem(&self) -> Option<(bool, String)> {
+        self.parameters
+            .iter()
+            .find_map(|slice_parameters| slice_parameters.validate().err())
+            .map(|error| (true, error))
+            .or_else(|| {
+                self.parameters
+                    .iter()
+                    .find_map(SliceParameters::warning)
+                    .map(|warning| (false, warning))
+            })
+    }
+
+    /// The blocks of the shape alone, as a layer with a single sample (previews, variations and the
+    ///  shape between layers)
+    pub fn single(slice_parameters: &SliceParameters, generator: &Generator) -> Blocks {
+        LayerParameters {
+            nr_samples: 1,
+            algorithm: slice_parameters.algorithm,
+            parameters: vec![slice_parameters.clone()],
+        }
+        .generate(&SampleCombineMethod::AnySamples, generator)
+    }
+
     /// Run the generation algorithm for the configuration `self`, the output is a `Blocks` object. document.
-    pub fn generate(&self, sample_combine_method: &SampleCombineMethod) -> Blocks {
+    pub fn generate(
+        &self,
+        sample_combine_method: &SampleCombineMethod,
+        generator: &Generator,
+    ) -> Blocks {
+        let aspect = generator.cell_aspect;
+        // All copies of the shape (for shape arrays), for each sample
+        let copies: Vec<Vec<SliceParameters>> = self
+            .parameters
+            .iter()
+            .map(|slice_parameters| slice_parameters.shape_array.get_copies(slice_parameters))
+            .collect();
+
+        // Degenerate parameters would give a meaningless grid (or none at all), then the layer is
+        //  left empty
+        if let Some((height, error)) = copies.iter().flatten().find_map(|slice_parameters| {
+            let error = slice_parameters.validate().err()?;
+            Some((slice_parameters.layer, error))
+        }) {
+            log_warning(format!(
+                "The layer at height {} is not generated: {}",
+                height, error
+            ));
+            return Blocks::new(vec![false; 16], 4);
+        }
+
         // Determine grid size
         // The major radius should be included, for some metrics we need at least one layer of padding
         //  around the generated figure. Assuming a square figure (squircle parameter infinity), we
         //  need an x side length of 2.0 * sqrt(2) * radius_major. Add 4 for a padding of at least 2
-        //  on each side.
-        let largest_radius_a = self
-            .parameters
-            .iter()
-            .fold(f64::NEG_INFINITY, |a, b| a.max(b.radius_a));
-        let largest_radius_b = self
-            .parameters
-            .iter()
-            .fold(f64::NEG_INFINITY, |a, b| a.max(b.radius_b));
+        //  on each side. The grid is centered at the origin, so offset copies need more room, and
+        //  so do shapes grown by a margin.
+        let margin = self.algorithm.margin().max(0.0);
+        let largest_extent = copies.iter().flatten().fold(f64::NEG_INFINITY, |a, b| {
+            a.max(
+                b.center_offset_x.abs().max(b.center_offset_y.abs())
+                    + 1.42 * f64::max(b.radius_a, b.radius_b)
+                    + margin,
+            )
+        });
 
-        let grid_size =
-            (2.0 * 1.42 * f64::max(largest_radius_a, largest_radius_b)).ceil() as usize + 4;
+        // Flat cells need more rows for the same height
+        let largest_extent = largest_extent / aspect.min(1.0);
 
-        // Generate from circle with selected algorithm
+        let grid_size = (2.0 * largest_extent).ceil() as usize + 4;
+
+        // Generate from circle with selected algorithm, taking the union of the copies in each
+        //  sample
         Blocks::combine(
             sample_combine_method,
-            self.parameters
+            &copies
                 .iter()
-                .map(|slice_parameters| {
-                    generate_all_blocks(
-                        &self.algorithm,
-                        Vec2::from([
-                            slice_parameters.center_offset_x,
-                            slice_parameters.center_offset_y,
-                        ]),
-                        slice_parameters.get_sqrt_quad_form(),
-                        slice_parameters.squircle_parameter,
-                        slice_parameters.radius_a,
-                        slice_parameters.radius_b,
-                        grid_size,
+                .map(|copies_of_sample| {
+                    Blocks::combine(
+                        &SampleCombineMethod::AnySamples,
+                        &copies_of_sample
+                            .iter()
+                            .map(|slice_parameters| {
+                                let blocks = generate_all_blocks(
+                                    &self.algorithm,
+                                    slice_parameters,
+                                    grid_size,
+                                    generator,
+                                );
+
+                                // The sector turns with the shape (and is stretched with it
+                                //  for cells that aren't square)
+                                match slice_parameters.sector {
+                                    None => blocks,
+                                    Some([start, end]) => blocks.restrict_to_sector(
+                                        to_cells(
+                                            [
+                                                slice_parameters.center_offset_x,
+                                                slice_parameters.center_offset_y,
+                                            ],
+                                            aspect,
+                                        ),
+                                        [
+                                            angle_to_cells(start + slice_parameters.tilt, aspect),
+                                            angle_to_cells(end + slice_parameters.tilt, aspect),
+                                        ],
+                                    ),
+                                }
+                            })
+                            .collect::<Vec<_>>(),
                     )
                 })
-                .collect(),
+                .collect::<Vec<_>>(),
         )
     }
 }
@@ -0,0 +1,115 @@
+use crate::app::block_budget::{
+    estimate_fitting_scale, scale_layer_parameters, scale_slice_parameters,
+};
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::generation::Generator;
+use crate::app::math::superellipse_measure::superellipse_area_perimeter;
+use crate::app::sampling::sampled_parameters::LayerParameters;
+use crate::app::sampling::SampleCombineMethod;
+
+/// How the scale of the radii is chosen
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ScaleMode {
+    Factor,       // multiply the radii by a factor
+    TargetArea,   // the area of the shapes (summed over the layers) is at most the target
+    TargetBlocks, // the number of blocks is at most the target (generating the layers to count)
+}
+
+/// Grow or shrink a design without changing the numbers of each layer: scale the radii of the
+///  current layer or of all layers, by a factor or so that the design reaches a target area or
+///  number of blocks. Locked layers and locked radii are kept as they are.
+pub struct ScaleTool {
+    pub mode: ScaleMode,
+    pub factor: f64,
+    pub target_area: f64,
+    pub target_blocks: u64,
+    pub all_layers: bool,
+}
+
+impl Default for ScaleTool {
+    fn default() -> Self {
+        Self {
+            mode: ScaleMode::Factor,
+            factor: 1.1,
+            target_area: 100.0,
+            target_blocks: 1000,
+            all_layers: true,
+        }
+    }
+}
+
+impl ScaleTool {
+    /// The scale of the radii of the layers (parameters, sampled parameters, blocks and whether
+    ///  the layer is locked). For a target this is the largest scale within the target, as for the
+    ///  block budget.
+    pub fn scale(
+        &self,
+        layers: &[(SliceParameters, LayerParameters, Blocks, bool)],
+        sample_combine_method: &SampleCombineMethod,
+        generator: &Generator,
+        locked_parameters: &[Parameter],
+    ) -> f64 {
+        match self.mode {
+            ScaleMode::Factor => self.factor,
+            ScaleMode::TargetArea => {
+                let locked_area = layers
+                    .iter()
+                    .filter(|(_, _, _, locked)| *locked)
+                    .map(|(slice_parameters, _, _, _)| shape_area(slice_parameters))
+                    .sum::<f64>();
+                let area_at = |scale: f64| {
+                    layers
+                        .iter()
+                        .filter(|(_, _, _, locked)| !*locked)
+                        .map(|(slice_parameters, _, _, _)| {
+                            shape_area(&scale_slice_parameters(
+                                slice_parameters,
+                                scale,
+                                locked_parameters,
+                            ))
+                        })
+                        .sum::<f64>()
+                };
+                estimate_fitting_scale(area_at, locked_area, self.target_area)
+            }
+            ScaleMode::TargetBlocks => {
+                let nr_locked_blocks = layers
+                    .iter()
+                    .filter(|(_, _, _, locked)| *locked)
+                    .map(|(_, _, blocks, _)| blocks.get_nr_blocks())
+                    .sum::<u64>();
+                let nr_blocks_at = |scale: f64| {
+                    layers
+                        .iter()
+                        .filter(|(_, _, _, locked)| !*locked)
+                        .map(|(_, layer_parameters, _, _)| {
+                            scale_layer_parameters(layer_parameters, scale, locked_parameters)
+                                .generate(sample_combine_method, generator)
+                                .get_nr_blocks()
+                        })
+                        .sum::<u64>() as f64
+                };
+                estimate_fitting_scale(
+                    nr_blocks_at,
+                    nr_locked_blocks as f64,
+                    self.target_blocks as f64,
+                )
+            }
+        }
+    }
+}
+
+/// Area of the shape with all copies of an array (overlapping copies are counted twice)
+pub fn shape_area(slice_parameters: &SliceParameters) -> f64 {
+    let (area, _) = superellipse_area_perimeter(
+        slice_parameters.radius_a,
+        slice_parameters.radius_b,
+        slice_parameters.squircle_parameter,
+    );
+    area * slice_parameters
+        .shape_array
+        .get_copies(slice_parameters)
+        .len() as f64
+}
@@ -0,0 +1,16 @@
+use rhai::Engine;
+
+// Limits of each run of user code (a parameter field, the code of the Custom algorithm for a single
+//  cell, a function of a shape script), so that code that doesn't end (an endless loop, a recursion
+//  without a base case) gives an error instead of freezing the window
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+
+/// The engine that all Rhai code of the user is run in, with the limits above. Code that is meant to
+///  do more work (e.g. the export scripts) raises the number of operations.
+pub fn new_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine
+}
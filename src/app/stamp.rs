@@ -0,0 +1,200 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::pixel_font::rasterize;
+
+/// Whether a stamp places blocks or clears them
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StampMode {
+    Add,
+    Remove,
+}
+
+/// Text in blocks on a layer, e.g. to mark an entrance or label a room in the plan. It overrides
+///  the generated blocks of the layer (every time they are generated), so it stays when the
+///  parameters change.
+#[derive(Debug, Clone)]
+pub struct Stamp {
+    pub text: String,
+    pub layer: isize,
+    pub position: [isize; 2], // bottom left block of the text, in viewport coordinates
+    pub scale: usize,         // blocks per pixel of the font
+    pub mode: StampMode,
+}
+
+impl Stamp {
+    /// The blocks of the stamp, in viewport coordinates
+    pub fn coords(&self) -> Vec<[isize; 2]> {
+        let [x, y] = self.position;
+        rasterize(&self.text, self.scale)
+            .iter()
+            .map(|[dx, dy]| [x + dx, y + dy])
+            .collect()
+    }
+}
+
+/// The blocks with the stamps on the layer applied (in order, so a later stamp wins). The grid grows
+///  to fit the added blocks, unless the grid size is fixed (then they are cut off).
+pub fn apply_stamps(
+    blocks: Blocks,
+    stamps: &[Stamp],
+    layer: isize,
+    fixed_grid_size: Option<usize>,
+) -> Blocks {
+    let stamps: Vec<(Vec<[isize; 2]>, StampMode)> = stamps
+        .iter()
+        .filter(|stamp| stamp.layer == layer)
+        .map(|stamp| (stamp.coords(), stamp.mode))
+        .collect();
+    if stamps.is_empty() {
+        return blocks;
+    }
+
+    let grid_size = match fixed_grid_size {
+        Some(grid_size) => grid_size,
+        None => stamps
+            .iter()
+            .filter(|(_, mode)| *mode == StampMode::Add)
+            .flat_map(|(coords, _)| coords.iter())
+            .fold(blocks.grid_size, |grid_size, coord| {
+                grid_size_to_fit(grid_size, *coord)
+            }),
+    };
+    let mut blocks = if grid_size == blocks.grid_size {
+        blocks
+    } else {
+        blocks.with_grid_size(grid_size)
+    };
+
+    for (coords, mode) in stamps {
+        for coord in coords {
+            if let Some(index) = blocks.get_index_from_global_coord_usize(coord) {
+                blocks.blocks[index] = mode == StampMode::Add;
+            }
+        }
+    }
+    blocks
+}
+
+/// The smallest grid size at least `grid_size` that has the block at `coord`. It grows by an even
+///  number, so an odd grid stays odd.
+fn grid_size_to_fit(grid_size: usize, [x, y]: [isize; 2]) -> usize {
+    // The grid has the blocks from -grid_size/2 up to grid_size - grid_size/2 (exclusive)
+    let half = [-x, -y, x + 1, y + 1].into_iter().max().unwrap_or(0) as usize;
+    let needed = grid_size.max(2 * half);
+    needed + (needed - grid_size) % 2
+}
+
+/// The text stamp that is being placed: its text, size and mode, and the position clicked in the
+///  viewport
+pub struct StampTool {
+    pub text: String,
+    pub scale: usize,
+    pub mode: StampMode,
+    pub position: Option<[isize; 2]>,
+}
+
+impl Default for StampTool {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            scale: 1,
+            mode: StampMode::Add,
+            position: None,
+        }
+    }
+}
+
+impl StampTool {
+    /// Put the bottom left block of the text at the block under a point clicked in the viewport
+    pub fn click(&mut self, point: [f64; 2]) {
+        self.position = Some([point[0].floor() as isize, point[1].floor() as isize]);
+    }
+
+    /// The stamp as it would be placed on the layer, if a position was clicked
+    pub fn stamp(&self, layer: isize) -> Option<Stamp> {
+        Some(Stamp {
+            text: self.text.clone(),
+            layer,
+            position: self.position?,
+            scale: self.scale,
+            mode: self.mode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_stamps, grid_size_to_fit, Stamp, StampMode};
+    use crate::app::data_structures::blocks::Blocks;
+
+    fn stamp(text: &str, position: [isize; 2], mode: StampMode) -> Stamp {
+        Stamp {
+            text: text.to_string(),
+            layer: 0,
+            position,
+            scale: 1,
+            mode,
+        }
+    }
+
+    #[test]
+    fn coords_are_moved_to_the_position() {
+        let coords = stamp("■", [-3, 2], StampMode::Add).coords();
+        assert_eq!(coords.len(), 15);
+        assert!(coords.contains(&[-3, 2]) && coords.contains(&[-1, 6]));
+    }
+
+    #[test]
+    fn grid_grows_by_even_numbers() {
+        assert_eq!(grid_size_to_fit(4, [1, -2]), 4);
+        assert_eq!(grid_size_to_fit(4, [2, 0]), 6);
+        assert_eq!(grid_size_to_fit(5, [-5, 0]), 11);
+    }
+
+    #[test]
+    fn added_blocks_grow_the_grid() {
+        let blocks = apply_stamps(
+            Blocks::new(vec![false; 16], 4),
+            // The pixel of the period is in the middle column
+            &[stamp(".", [5, 0], StampMode::Add)],
+            0,
+            None,
+        );
+        assert_eq!(blocks.grid_size, 14);
+        assert_eq!(blocks.get_nr_blocks(), 1);
+        assert!(blocks.is_block_on_global_coord([6, 0]));
+    }
+
+    #[test]
+    fn fixed_grid_cuts_off_blocks() {
+        let blocks = apply_stamps(
+            Blocks::new(vec![false; 16], 4),
+            &[stamp("■", [0, 0], StampMode::Add)],
+            0,
+            Some(4),
+        );
+        assert_eq!(blocks.grid_size, 4);
+        assert_eq!(blocks.get_nr_blocks(), 4);
+    }
+
+    #[test]
+    fn later_stamps_win() {
+        let blocks = apply_stamps(
+            Blocks::new(vec![false; 100], 10),
+            &[
+                stamp("■", [0, 0], StampMode::Add),
+                stamp("■", [1, 1], StampMode::Remove),
+                Stamp {
+                    layer: 1,
+                    ..stamp("■", [-4, -4], StampMode::Add)
+                },
+            ],
+            0,
+            None,
+        );
+        // The 3x5 block minus the 2x4 part the second stamp overlaps
+        assert_eq!(blocks.get_nr_blocks(), 15 - 8);
+        assert!(blocks.is_block_on_global_coord([0, 4]));
+        assert!(!blocks.is_block_on_global_coord([1, 1]));
+        assert!(!blocks.is_block_on_global_coord([-4, -4]));
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::generation::Generator;
+use crate::app::sampling::sampled_parameters::LayerParameters;
+
+/// Which eighths of a block are filled: the bottom and the top half, each in quarters (left
+///  bottom, right bottom, left top, right top in the viewport)
+type Occupancy = [[bool; 4]; 2];
+
+/// Side of a block in the viewport
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Side {
+    PositiveX,
+    PositiveY,
+    NegativeX,
+    NegativeY,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [
+        Side::PositiveX,
+        Side::PositiveY,
+        Side::NegativeX,
+        Side::NegativeY,
+    ];
+
+    /// The direction in game: X points east, and north is up in the viewport unless `flip_z` (see
+    ///  `minecraft_z`)
+    pub fn compass(&self, flip_z: bool) -> &'static str {
+        match (self, flip_z) {
+            (Side::PositiveX, _) => "east",
+            (Side::NegativeX, _) => "west",
+            (Side::PositiveY, false) | (Side::NegativeY, true) => "north",
+            (Side::NegativeY, false) | (Side::PositiveY, true) => "south",
+        }
+    }
+
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::PositiveX => Side::NegativeX,
+            Side::PositiveY => Side::NegativeY,
+            Side::NegativeX => Side::PositiveX,
+            Side::NegativeY => Side::PositiveY,
+        }
+    }
+
+    /// The half of the block with left bottom corner `coord` on this side, as its left bottom
+    ///  corner and its size
+    pub fn half(&self, [x, y]: [f64; 2]) -> ([f64; 2], [f64; 2]) {
+        match self {
+            Side::PositiveX => ([x + 0.5, y], [0.5, 1.0]),
+            Side::PositiveY => ([x, y + 0.5], [1.0, 0.5]),
+            Side::NegativeX => ([x, y], [0.5, 1.0]),
+            Side::NegativeY => ([x, y], [1.0, 0.5]),
+        }
+    }
+
+    /// The quarters (see `Occupancy`) on this side
+    fn quarters(&self) -> [usize; 2] {
+        match self {
+            Side::PositiveX => [1, 3],
+            Side::PositiveY => [2, 3],
+            Side::NegativeX => [0, 2],
+            Side::NegativeY => [0, 1],
+        }
+    }
+}
+
+/// What to build in a block to follow the shape at half a block resolution
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SubBlock {
+    Full,
+    Slab { top: bool },
+    // Like in game, the stair is full height on the side it faces. Upside down the slab part is
+    //  the top half.
+    Stair { facing: Side, upside_down: bool },
+}
+
+impl SubBlock {
+    /// All sub-blocks, simplest first
+    fn all() -> Vec<SubBlock> {
+        let mut all = vec![
+            SubBlock::Full,
+            SubBlock::Slab { top: false },
+            SubBlock::Slab { top: true },
+        ];
+        for upside_down in [false, true] {
+            all.extend(Side::ALL.map(|facing| SubBlock::Stair {
+                facing,
+                upside_down,
+            }));
+        }
+        all
+    }
+
+    fn occupancy(&self) -> Occupancy {
+        match *self {
+            SubBlock::Full => [[true; 4]; 2],
+            SubBlock::Slab { top } => [[!top; 4], [top; 4]],
+            SubBlock::Stair {
+                facing,
+                upside_down,
+            } => {
+                let mut half = [false; 4];
+                for quarter in facing.quarters() {
+                    half[quarter] = true;
+                }
+                if upside_down {
+                    [half, [true; 4]]
+                } else {
+                    [[true; 4], half]
+                }
+            }
+        }
+    }
+
+    /// The sub-block with the fewest eighths different from `occupancy`, None if that is air (ties
+    ///  go to the simplest)
+    fn closest(occupancy: Occupancy) -> Option<SubBlock> {
+        let difference = |other: Occupancy| {
+            (0..2)
+                .flat_map(|half| (0..4).map(move |quarter| (half, quarter)))
+                .filter(|&(half, quarter)| occupancy[half][quarter] != other[half][quarter])
+                .count()
+        };
+
+        let mut closest = None;
+        let mut smallest_difference = difference([[false; 4]; 2]);
+        for sub_block in SubBlock::all() {
+            let difference = difference(sub_block.occupancy());
+            if difference < smallest_difference {
+                closest = Some(sub_block);
+                smallest_difference = difference;
+            }
+        }
+        closest
+    }
+}
+
+/// Slabs and stairs for a layer, from the shapes of its bottom and top half (a layer is the middle
+///  of its blocks, so these are the shapes a quarter below and above it). Both are generated at
+///  twice the resolution, so that every block is split into eighths. Output: left bottom corner
+///  and sub-block, for the blocks that aren't air.
+pub fn get_sub_blocks(
+    halves: [&SliceParameters; 2],
+    generator: &Generator,
+) -> Vec<([f64; 2], SubBlock)> {
+    let mut occupancy: BTreeMap<[isize; 2], Occupancy> = BTreeMap::new();
+    for (half, slice_parameters) in halves.into_iter().enumerate() {
+        let fine_blocks = LayerParameters::single(&slice_parameters.scaled(2.0), generator);
+
+        for [x, y] in fine_blocks.get_all_block_coords() {
+            let [x, y] = [x as isize, y as isize];
+            let quarter = (x.rem_euclid(2) + 2 * y.rem_euclid(2)) as usize;
+            occupancy
+                .entry([x.div_euclid(2), y.div_euclid(2)])
+                .or_default()[half][quarter] = true;
+        }
+    }
+
+    occupancy
+        .into_iter()
+        .filter_map(|([x, y], occupancy)| {
+            SubBlock::closest(occupancy).map(|sub_block| ([x as f64, y as f64], sub_block))
+        })
+        .collect()
+}
+
+/// Number of full blocks, slabs and stairs
+pub fn count_sub_blocks(sub_blocks: &[([f64; 2], SubBlock)]) -> [usize; 3] {
+    let mut counts = [0; 3];
+    for (_, sub_block) in sub_blocks {
+        counts[match sub_block {
+            SubBlock::Full => 0,
+            SubBlock::Slab { .. } => 1,
+            SubBlock::Stair { .. } => 2,
+        }] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{count_sub_blocks, get_sub_blocks, Side, SubBlock};
+    use crate::app::data_structures::slice_parameters::SliceParameters;
+    use crate::app::generation::Generator;
+
+    #[test]
+    fn closest_sub_block() {
+        assert_eq!(SubBlock::closest([[true; 4]; 2]), Some(SubBlock::Full));
+        assert_eq!(SubBlock::closest([[false; 4]; 2]), None);
+        assert_eq!(
+            SubBlock::closest([[true; 4], [false; 4]]),
+            Some(SubBlock::Slab { top: false })
+        );
+        assert_eq!(
+            SubBlock::closest([[true; 4], [false, true, false, true]]),
+            Some(SubBlock::Stair {
+                facing: Side::PositiveX,
+                upside_down: false
+            })
+        );
+        assert_eq!(
+            SubBlock::closest([[true, true, false, false], [true; 4]]),
+            Some(SubBlock::Stair {
+                facing: Side::NegativeY,
+                upside_down: true
+            })
+        );
+        // A single eighth is closer to air than to anything else
+        assert_eq!(
+            SubBlock::closest([[true, false, false, false], [false; 4]]),
+            None
+        );
+    }
+
+    #[test]
+    fn opposite_sides() {
+        for side in Side::ALL {
+            assert_ne!(side.opposite(), side);
+            assert_eq!(side.opposite().opposite(), side);
+            assert_ne!(side.compass(false), side.opposite().compass(false));
+        }
+        assert_eq!(Side::PositiveY.compass(false), "north");
+        assert_eq!(Side::PositiveY.compass(true), "south");
+    }
+
+    #[test]
+    fn empty_top_half_gives_slabs() {
+        let bottom = SliceParameters {
+            radius_a: 4.0,
+            radius_b: 4.0,
+            ..Default::default()
+        };
+        let top = SliceParameters {
+            radius_a: 0.0,
+            radius_b: 0.0,
+            ..Default::default()
+        };
+        let sub_blocks = get_sub_blocks([&bottom, &top], &Generator::default());
+
+        assert!(!sub_blocks.is_empty());
+        assert!(sub_blocks
+            .iter()
+            .all(|(_, sub_block)| *sub_block == SubBlock::Slab { top: false }));
+        assert_eq!(count_sub_blocks(&sub_blocks), [0, sub_blocks.len(), 0]);
+    }
+
+    #[test]
+    fn equal_halves_give_no_slabs() {
+        let slice_parameters = SliceParameters {
+            radius_a: 4.3,
+            radius_b: 4.3,
+            ..Default::default()
+        };
+        let sub_blocks = get_sub_blocks([&slice_parameters; 2], &Generator::default());
+        let [nr_full, nr_slabs, _] = count_sub_blocks(&sub_blocks);
+        assert!(nr_full > 0);
+        assert_eq!(nr_slabs, 0);
+    }
+}
@@ -0,0 +1,65 @@
+use std::f64::consts::TAU;
+
+use crate::app::data_structures::blocks::Blocks;
+
+// Block centers this close to half a block from a pillar, spoke or ring are still part of it
+const TOLERANCE: f64 = 1e-9;
+
+/// Structure inside a large floor, to plan where it needs support
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SupportPattern {
+    Pillars(usize), // every n blocks in both directions, lined up with the center
+    Spokes(usize),  // number of spokes from the center
+    Rings(usize),   // a ring every n blocks around the center
+}
+
+impl Default for SupportPattern {
+    fn default() -> Self {
+        SupportPattern::Pillars(8)
+    }
+}
+
+impl SupportPattern {
+    /// The interior blocks that are part of the supports, for the pattern around `center` (the
+    ///  blocks themselves are not changed)
+    pub fn get_supports(&self, interior: &Blocks, center: [f64; 2]) -> Blocks {
+        Blocks::new(
+            (0..interior.grid_size.pow(2))
+                .map(|index| {
+                    let [x, y] = interior.get_global_coord_usize_from_index(index);
+                    interior.is_block_on_global_coord([x, y])
+                        && self.contains([x as f64 + 0.5 - center[0], y as f64 + 0.5 - center[1]])
+                })
+                .collect(),
+            interior.grid_size,
+        )
+    }
+
+    /// Is the block with this center (relative to the center of the pattern) part of the
+    ///  supports?
+    fn contains(&self, [dx, dy]: [f64; 2]) -> bool {
+        match *self {
+            // The center is on a block center or a block corner (then the pillars are 2 by 2)
+            SupportPattern::Pillars(spacing) => {
+                let spacing = spacing.max(1) as f64;
+                [dx, dy].iter().all(|d| {
+                    ((d + 0.5 * spacing).rem_euclid(spacing) - 0.5 * spacing).abs()
+                        <= 0.5 + TOLERANCE
+                })
+            }
+            // Within half a block of a spoke (and not behind the center)
+            SupportPattern::Spokes(nr_spokes) => (0..nr_spokes).any(|spoke| {
+                let (sin, cos) = (TAU * spoke as f64 / nr_spokes as f64).sin_cos();
+                cos * dx + sin * dy > TOLERANCE - 0.5
+                    && (-sin * dx + cos * dy).abs() <= 0.5 + TOLERANCE
+            }),
+            // Within half a block of a ring (the center itself is not a ring)
+            SupportPattern::Rings(spacing) => {
+                let spacing = spacing.max(1) as f64;
+                let distance = dx.hypot(dy);
+                let ring = (distance / spacing).round();
+                ring >= 1.0 && (distance - ring * spacing).abs() < 0.5
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::f64::consts::TAU;
+
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::io::SaveRequest;
+use crate::app::math::exact_squircle_bounds::exact_squircle_bounds;
+use crate::app::math::square_max::square_max;
+use crate::app::plotting::point_on_superellipse;
+use crate::app::view::View;
+
+/// Points of each outline (as many as in the viewport)
+const NR_OUTLINE_POINTS: usize = 1000;
+// One pixel per texel of a block, so that the outlines line up with block textures
+const PIXELS_PER_BLOCK: f64 = 16.0;
+const STROKE_WIDTH: f64 = 0.1; // in blocks
+const MARGIN: f64 = 1.0; // space around the outlines, in blocks
+
+/// Ask where to save the outlines of the current layer, or of all layers stacked in one drawing
+pub fn outlines_save_request(
+    stack_layer_config: &ZVec<SliceParameters>,
+    current_layer: isize,
+    all_layers: bool,
+    view: &View,
+) -> SaveRequest {
+    let layers: Vec<(isize, SliceParameters)> = if all_layers {
+        (stack_layer_config.get_minimum()..=stack_layer_config.get_maximum())
+            .filter_map(|layer| Some((layer, stack_layer_config.get(layer)?)))
+            .collect()
+    } else {
+        stack_layer_config
+            .get(current_layer)
+            .map(|slice_parameters| vec![(current_layer, slice_parameters)])
+            .unwrap_or_default()
+    };
+    let file_name = if all_layers {
+        "outlines.svg".to_string()
+    } else {
+        format!("outline_layer_{}.svg", current_layer)
+    };
+
+    SaveRequest {
+        title: "Export outlines as SVG".to_string(),
+        file_name,
+        filter_name: "SVG",
+        extensions: &["svg"],
+        contents: outlines_to_svg(&layers, view.world_translation(), view.flip_z).into_bytes(),
+    }
+}
+
+/// The continuous outlines of the layers (all copies of arrays) as SVG paths, one group per layer
+///  (a layer in Inkscape). One unit is one block, in world X and Minecraft Z shifted by
+///  `translation` (like the PNG export, north is up), so the drawing can be measured and lined up
+///  with the blocks in other programs.
+pub fn outlines_to_svg(
+    layers: &[(isize, SliceParameters)],
+    translation: [isize; 2],
+    flip_z: bool,
+) -> String {
+    // Viewport coordinates to X and Z
+    let to_world = |[x, y]: [f64; 2]| {
+        let y = y + translation[1] as f64;
+        [x + translation[0] as f64, if flip_z { y } else { -y }]
+    };
+
+    let copies: Vec<(isize, Vec<SliceParameters>)> = layers
+        .iter()
+        .map(|(layer, slice_parameters)| {
+            (
+                *layer,
+                slice_parameters.shape_array.get_copies(slice_parameters),
+            )
+        })
+        .collect();
+
+    // Bounds of all outlines in X and Z (the corners of the viewport bounds in either order)
+    let bounds = copies
+        .iter()
+        .flat_map(|(_, copies)| copies.iter())
+        .map(|slice_parameters| {
+            let [[x_min, y_min], [x_max, y_max]] = exact_squircle_bounds(slice_parameters, 1.0);
+            let [a, b] = [to_world([x_min, y_min]), to_world([x_max, y_max])];
+            [
+                [a[0].min(b[0]), a[1].min(b[1])],
+                [a[0].max(b[0]), a[1].max(b[1])],
+            ]
+        })
+        .reduce(square_max)
+        .unwrap_or([[0.0, 0.0], [0.0, 0.0]]);
+    let [left, top] = bounds[0].map(|coord| coord - MARGIN);
+    let [width, height] = [
+        bounds[1][0] - bounds[0][0] + 2.0 * MARGIN,
+        bounds[1][1] - bounds[0][1] + 2.0 * MARGIN,
+    ];
+
+    let groups: Vec<String> = copies
+        .iter()
+        .map(|(layer, copies)| {
+            let paths: Vec<String> = copies
+                .iter()
+                .map(|slice_parameters| {
+                    let points: Vec<String> = (0..NR_OUTLINE_POINTS)
+                        .map(|i| {
+                            let t = i as f64 * TAU / NR_OUTLINE_POINTS as f64;
+                            let [x, z] = to_world(point_on_superellipse(slice_parameters, t));
+                            format!("{:.4} {:.4}", x, z)
+                        })
+                        .collect();
+                    format!("    <path d=\"M {} Z\"/>", points.join(" L "))
+                })
+                .collect();
+            format!(
+                "  <g id=\"layer_{}\" inkscape:groupmode=\"layer\" inkscape:label=\"Layer {}\">\n\
+                {}\n  </g>",
+                layer,
+                layer,
+                paths.join("\n")
+            )
+        })
+        .collect();
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+        xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\" \
+        width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\" \
+        fill=\"none\" stroke=\"black\" stroke-width=\"{}\">\n{}\n</svg>\n",
+        width * PIXELS_PER_BLOCK,
+        height * PIXELS_PER_BLOCK,
+        left,
+        top,
+        width,
+        height,
+        STROKE_WIDTH,
+        groups.join("\n")
+    )
+}
@@ -0,0 +1,191 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::generation::{Algorithm, Generator};
+use crate::app::lua_field::LuaField;
+use crate::app::sampling::sampled_parameters::LayerParameters;
+
+/// An example project: a stack of layers whose parameters are given by code. Empty code means
+///  the slider value (from `slice_parameters`) is used on every layer.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub description: String,
+    pub layer_lowest: isize,
+    pub layer_highest: isize,
+    pub single_radius: bool,
+    pub slice_parameters: SliceParameters,
+
+    pub code_radius_a: String,
+    pub code_radius_b: String,
+    pub code_tilt: String,
+    pub code_center_offset_x: String,
+    pub code_center_offset_y: String,
+    pub code_squircle_parameter: String,
+
+    // Blocks of all layers, generated the first time the template is shown
+    pub preview: Option<ZVec<Blocks>>,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: String::new(),
+            layer_lowest: 0,
+            layer_highest: 0,
+            single_radius: true,
+            slice_parameters: SliceParameters::default(),
+            code_radius_a: String::new(),
+            code_radius_b: String::new(),
+            code_tilt: String::new(),
+            code_center_offset_x: String::new(),
+            code_center_offset_y: String::new(),
+            code_squircle_parameter: String::new(),
+            preview: None,
+        }
+    }
+}
+
+impl Template {
+    /// The templates that are always available
+    pub fn built_in() -> Vec<Template> {
+        vec![
+            Template {
+                name: "Dome".to_string(),
+                description: "Half a sphere: the radius of each layer follows a circle".to_string(),
+                layer_highest: 11,
+                slice_parameters: SliceParameters {
+                    radius_a: 12.0,
+                    radius_b: 12.0,
+                    ..Default::default()
+                },
+                code_radius_a: "sqrt(144.0 - l * l)".to_string(),
+                ..Default::default()
+            },
+            Template {
+                name: "Lighthouse".to_string(),
+                description: "A tapering tower with a wider gallery at the top".to_string(),
+                layer_highest: 29,
+                slice_parameters: SliceParameters {
+                    radius_a: 7.0,
+                    radius_b: 7.0,
+                    algorithm: Algorithm::Conservative(0.0),
+                    ..Default::default()
+                },
+                code_radius_a: "if l < 26.0 { 7.0 - 0.1 * l } else { 6.5 }".to_string(),
+                ..Default::default()
+            },
+            Template {
+                name: "Elliptical stadium".to_string(),
+                description: "Stands rising outwards, both radii grow with the layer".to_string(),
+                layer_highest: 7,
+                single_radius: false,
+                slice_parameters: SliceParameters {
+                    radius_a: 20.0,
+                    radius_b: 13.0,
+                    ..Default::default()
+                },
+                code_radius_a: "20.0 + 1.5 * l".to_string(),
+                code_radius_b: "13.0 + 1.5 * l".to_string(),
+                ..Default::default()
+            },
+            Template {
+                name: "Spiral staircase".to_string(),
+                description: "A narrow ellipse (one step) that turns a bit on every layer"
+                    .to_string(),
+                layer_highest: 23,
+                single_radius: false,
+                slice_parameters: SliceParameters {
+                    radius_a: 6.0,
+                    radius_b: 1.0,
+                    ..Default::default()
+                },
+                code_tilt: "0.3 * l".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    /// Code fields filled with the code of the template, in the order radius_a, radius_b, tilt,
+    ///  center_offset_x, center_offset_y, squircle_parameter
+    pub fn get_lua_fields(&self, sampling_points: &ZVec<Vec<f64>>) -> [LuaField; 6] {
+        let mut fields = [
+            LuaField::new(true, true),
+            LuaField::new(true, true),
+            LuaField::new(true, false),
+            LuaField::new(true, false),
+            LuaField::new(true, false),
+            LuaField::new(false, true),
+        ];
+        for (field, code) in fields.iter_mut().zip([
+            &self.code_radius_a,
+            &self.code_radius_b,
+            &self.code_tilt,
+            &self.code_center_offset_x,
+            &self.code_center_offset_y,
+            &self.code_squircle_parameter,
+        ]) {
+            field.set_code(code, sampling_points);
+        }
+        fields
+    }
+
+    /// One sample in the middle of every layer
+    pub fn get_sampling_points(&self) -> ZVec<Vec<f64>> {
+        ZVec::new(
+            (self.layer_lowest..=self.layer_highest)
+                .map(|layer| vec![layer as f64])
+                .collect(),
+            self.layer_lowest,
+        )
+    }
+
+    /// The parameters of every layer with the code evaluated (once per layer)
+    pub fn get_stack_layer_config(&self) -> ZVec<SliceParameters> {
+        let [mut radius_a, mut radius_b, mut tilt, mut offset_x, mut offset_y, mut squircle] =
+            self.get_lua_fields(&self.get_sampling_points());
+        let default = &self.slice_parameters;
+
+        ZVec::new(
+            (self.layer_lowest..=self.layer_highest)
+                .map(|layer| {
+                    let layer = layer as f64;
+                    let radius_a = radius_a.eval(&layer).unwrap_or(default.radius_a);
+                    SliceParameters {
+                        radius_a,
+                        radius_b: if self.single_radius {
+                            radius_a
+                        } else {
+                            radius_b.eval(&layer).unwrap_or(default.radius_b)
+                        },
+                        tilt: tilt.eval(&layer).unwrap_or(default.tilt),
+                        center_offset_x: offset_x.eval(&layer).unwrap_or(default.center_offset_x),
+                        center_offset_y: offset_y.eval(&layer).unwrap_or(default.center_offset_y),
+                        squircle_parameter: squircle
+                            .eval(&layer)
+                            .unwrap_or(default.squircle_parameter),
+                        ..default.clone()
+                    }
+                })
+                .collect(),
+            self.layer_lowest,
+        )
+    }
+
+    /// Generate the blocks of all layers (with a single sample per layer) if not done yet
+    pub fn generate_preview(&mut self, generator: &Generator) -> &ZVec<Blocks> {
+        if self.preview.is_none() {
+            let stack_layer_config = self.get_stack_layer_config();
+            self.preview = Some(ZVec::new(
+                stack_layer_config
+                    .data
+                    .iter()
+                    .map(|slice_parameters| LayerParameters::single(slice_parameters, generator))
+                    .collect(),
+                self.layer_lowest,
+            ));
+        }
+        self.preview.as_ref().unwrap()
+    }
+}
@@ -0,0 +1,131 @@
+use eframe::egui::{Rect, Ui};
+
+// Key in the app storage, so that the tutorial is only started automatically on the first run
+pub const TUTORIAL_FINISHED_KEY: &str = "tutorial_finished";
+
+/// The steps of the tutorial, each highlighting a part of the window
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TutorialStep {
+    Parameters,
+    Generate,
+    Layers,
+    Code,
+    Sampling,
+    Viewport,
+}
+
+impl TutorialStep {
+    const ALL: [TutorialStep; 6] = [
+        TutorialStep::Parameters,
+        TutorialStep::Generate,
+        TutorialStep::Layers,
+        TutorialStep::Code,
+        TutorialStep::Sampling,
+        TutorialStep::Viewport,
+    ];
+
+    fn index(&self) -> usize {
+        TutorialStep::ALL
+            .iter()
+            .position(|step| step == self)
+            .unwrap()
+    }
+
+    pub fn next(&self) -> Option<TutorialStep> {
+        TutorialStep::ALL.get(self.index() + 1).copied()
+    }
+
+    pub fn previous(&self) -> Option<TutorialStep> {
+        self.index().checked_sub(1).map(|i| TutorialStep::ALL[i])
+    }
+
+    /// "Step 2 of 6"
+    pub fn progress(&self) -> String {
+        format!("Step {} of {}", self.index() + 1, TutorialStep::ALL.len())
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            TutorialStep::Parameters => "Parameters",
+            TutorialStep::Generate => "Generating blocks",
+            TutorialStep::Layers => "Layers",
+            TutorialStep::Code => "Code",
+            TutorialStep::Sampling => "Sampling",
+            TutorialStep::Viewport => "Viewport",
+        }
+    }
+
+    pub fn text(&self) -> &'static str {
+        match self {
+            TutorialStep::Parameters => {
+                "Pick an algorithm and set the radius, tilt, center offset and squircle \
+                parameter of the shape. The blocks update as you drag the sliders."
+            }
+            TutorialStep::Generate => {
+                "Blocks are generated automatically while 'Auto-generate' is on. For slow \
+                settings, turn it off and use the buttons to generate when you're done editing."
+            }
+            TutorialStep::Layers => {
+                "Enable layers to build a 3d structure: each layer has its own parameters. Use \
+                the bar at the top to move between layers and to add layers."
+            }
+            TutorialStep::Code => {
+                "With code enabled, the parameters are given by expressions in the layer `l`, \
+                for example `sqrt(100.0 - l * l)` as the radius for a dome."
+            }
+            TutorialStep::Sampling => {
+                "Take several samples of the parameters per layer and combine the generated \
+                blocks, for smoother slopes between layers."
+            }
+            TutorialStep::Viewport => {
+                "The viewport shows the blocks of the current layer. Right click a block to copy \
+                its coordinates. The viewport options add metrics like the boundary and the \
+                convex hull."
+            }
+        }
+    }
+}
+
+/// Interactive tour of the panels, started on the first run
+pub struct Tutorial {
+    pub step: Option<TutorialStep>, // None if the tutorial isn't running
+    pub finished: bool,             // finished or skipped at least once
+    pub target: Option<Rect>,       // where the part of the window of the current step was drawn
+}
+
+impl Tutorial {
+    pub fn new(finished: bool) -> Self {
+        Self {
+            step: (!finished).then_some(TutorialStep::Parameters),
+            finished,
+            target: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.step = Some(TutorialStep::Parameters);
+    }
+
+    pub fn stop(&mut self) {
+        self.step = None;
+        self.finished = true;
+    }
+
+    /// Remember where the part of the window for `step` is if it's the current step. The part
+    ///  starts at the height `top` and ends at the cursor of `ui`.
+    pub fn mark(&mut self, step: TutorialStep, ui: &Ui, top: f32) {
+        if self.step == Some(step) {
+            self.target = Some(Rect::from_x_y_ranges(
+                ui.max_rect().x_range(),
+                top..=ui.cursor().top(),
+            ));
+        }
+    }
+
+    /// Remember that the part of the window for `step` is `rect` if it's the current step
+    pub fn mark_rect(&mut self, step: TutorialStep, rect: Rect) {
+        if self.step == Some(step) {
+            self.target = Some(rect);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use crate::app::arch::{ArchCurve, ArchTool};
+use eframe::egui;
+use eframe::egui::Ui;
+
+/// The endpoints, rise and curve of an arch. Output: whether the stack is to be replaced by the
+///  layers of the arch.
+pub fn ui_arch(ui: &mut Ui, arch_tool: &mut ArchTool) -> bool {
+    for (label, point) in [("From", &mut arch_tool.start), ("To", &mut arch_tool.end)] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.add(egui::DragValue::new(&mut point[0]).speed(0.1).prefix("x "));
+            ui.add(egui::DragValue::new(&mut point[1]).speed(0.1).prefix("y "));
+        });
+    }
+    ui.add(
+        egui::Slider::new(&mut arch_tool.rise, 1.0..=64.0)
+            .clamp_to_range(false)
+            .text("Rise (layers)"),
+    );
+    arch_tool.rise = arch_tool.rise.max(1.0);
+
+    ui.horizontal(|ui| {
+        ui.label("Curve");
+        for curve in [ArchCurve::Circular, ArchCurve::Parabolic] {
+            ui.selectable_value(&mut arch_tool.curve, curve, curve.to_string());
+        }
+    });
+    ui.add(
+        egui::Slider::new(&mut arch_tool.thickness, 1.0..=8.0)
+            .text("Thickness")
+            .suffix(" blocks"),
+    )
+    .on_hover_text("Along the span, at the sides of the arch");
+    ui.add(
+        egui::Slider::new(&mut arch_tool.width, 1.0..=16.0)
+            .text("Width")
+            .suffix(" blocks"),
+    )
+    .on_hover_text("Across the span");
+
+    ui.separator();
+    ui.label(format!(
+        "The arch has {} layers, from layer 0 up",
+        arch_tool.nr_layers()
+    ));
+    ui.button("Replace the stack by the arch")
+        .on_hover_text("The layers of the current stack are lost")
+        .clicked()
+}
@@ -0,0 +1,66 @@
+use crate::app::block_budget::BlockBudget;
+use crate::app::formatting;
+use eframe::egui;
+use eframe::egui::{Color32, Ui};
+
+/// Set the maximum number of blocks of the stack (of `nr_blocks` now). Returns whether to find the
+///  scale of the radii that fits in it (see `BlockBudget::suggest_scale`), and the scale to apply
+///  to the unlocked radii of all unlocked layers, if any.
+pub fn ui_block_budget(
+    ui: &mut Ui,
+    budget: &mut BlockBudget,
+    nr_blocks: u64,
+) -> (bool, Option<f64>) {
+    ui.add_enabled_ui(budget.enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Maximum number of blocks");
+            if ui
+                .add(egui::DragValue::new(&mut budget.max_blocks).speed(10.0))
+                .changed()
+            {
+                budget.suggestion = None;
+            }
+        });
+
+        if nr_blocks > budget.max_blocks {
+            ui.colored_label(
+                Color32::LIGHT_RED,
+                format!(
+                    "Over budget by {} blocks",
+                    formatting::format_block_count(nr_blocks - budget.max_blocks)
+                ),
+            );
+        } else {
+            ui.label(format!(
+                "Within budget, {} blocks to spare",
+                formatting::format_block_count(budget.max_blocks - nr_blocks)
+            ));
+        }
+
+        let suggest = ui
+            .button("Suggest radius scale")
+            .on_hover_text(
+                "Largest scale of the radii of the unlocked layers that fits in the budget",
+            )
+            .clicked();
+
+        let mut apply = None;
+        if let Some((scale, nr_blocks_scaled)) = budget.suggestion {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Scale {:.3}: {} blocks",
+                    scale,
+                    formatting::format_block_count(nr_blocks_scaled)
+                ));
+                if ui.button("Apply").clicked() {
+                    apply = Some(scale);
+                }
+            });
+        }
+        if apply.is_some() {
+            budget.suggestion = None;
+        }
+        (suggest, apply)
+    })
+    .inner
+}
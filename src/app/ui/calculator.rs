@@ -0,0 +1,74 @@
+use crate::app::calculator::Calculator;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use eframe::egui;
+use eframe::egui::{Key, Ui};
+
+/// The calculator: an expression field (evaluated with Enter) above the earlier calculations.
+///  Clicking a calculation puts its expression back in the field.
+pub fn ui_calculator(
+    ui: &mut Ui,
+    calculator: &mut Calculator,
+    slice_parameters: &SliceParameters,
+    layer: isize,
+) {
+    ui.label(
+        "Rhai expressions with the parameters of the current layer, e.g. sqrt(2.0) * radius_a",
+    )
+    .on_hover_text(
+        "Variables: radius_a, radius_b, tilt, center_offset_x, center_offset_y, \
+            squircle_parameter, layer (or l) and ans (the previous value). Write 1.0 / 3.0 instead \
+            of 1 / 3, which divides whole numbers.",
+    );
+
+    let mut evaluate = false;
+    ui.horizontal(|ui| {
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut calculator.expression)
+                .desired_width(250.0)
+                .code_editor(),
+        );
+        if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+            evaluate = true;
+            response.request_focus();
+        }
+        evaluate |= ui.button("=").clicked();
+        if ui.button("Clear").clicked() {
+            calculator.history.clear();
+        }
+    });
+    if evaluate {
+        calculator.evaluate(slice_parameters, layer);
+    }
+
+    let mut reused = None;
+    egui::ScrollArea::vertical()
+        .stick_to_bottom(true)
+        .max_height(250.0)
+        .show(ui, |ui| {
+            for (expression, value) in &calculator.history {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add(
+                            egui::Label::new(egui::RichText::new(expression).monospace())
+                                .sense(egui::Sense::click()),
+                        )
+                        .on_hover_text("Edit again")
+                        .clicked()
+                    {
+                        reused = Some(expression.clone());
+                    }
+                    match value {
+                        Ok(value) => {
+                            ui.monospace(format!("= {}", value));
+                        }
+                        Err(message) => {
+                            ui.colored_label(ui.visuals().error_fg_color, message);
+                        }
+                    }
+                });
+            }
+        });
+    if let Some(expression) = reused {
+        calculator.expression = expression;
+    }
+}
@@ -0,0 +1,86 @@
+use crate::app::actions::{Action, CommandPalette};
+use eframe::egui;
+use eframe::egui::{Align2, Context, Key, Modifiers};
+
+/// Search box with the matching actions at the top of the window. Choose with the arrow keys and
+///  enter or by clicking, escape closes it. Output: the chosen action.
+pub fn ui_command_palette(
+    ctx: &Context,
+    palette: &mut CommandPalette,
+    layers_enabled: bool,
+    viewer_mode: bool,
+) -> Option<Action> {
+    if !palette.open {
+        return None;
+    }
+
+    // Taken before the search box sees them
+    let [up, down, enter, escape] = ctx.input_mut(|i| {
+        [Key::ArrowUp, Key::ArrowDown, Key::Enter, Key::Escape]
+            .map(|key| i.consume_key(Modifiers::NONE, key))
+    });
+    if escape {
+        palette.open = false;
+        return None;
+    }
+
+    let actions = Action::search(&palette.query, layers_enabled, viewer_mode);
+    if down {
+        palette.selected += 1;
+    }
+    if up {
+        palette.selected = palette.selected.saturating_sub(1);
+    }
+    palette.selected = palette.selected.min(actions.len().saturating_sub(1));
+    let mut chosen = if enter {
+        actions.get(palette.selected).copied()
+    } else {
+        None
+    };
+
+    egui::Window::new("Command palette")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_TOP, [0.0, 40.0])
+        .show(ctx, |ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut palette.query)
+                    .hint_text("Search actions, or type a layer number")
+                    .desired_width(400.0),
+            );
+            response.request_focus();
+            if response.changed() {
+                palette.selected = 0;
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    if actions.is_empty() {
+                        ui.weak("No matching actions");
+                    }
+                    for (index, action) in actions.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            let label =
+                                ui.selectable_label(index == palette.selected, action.to_string());
+                            if let Some(shortcut) = action.shortcut() {
+                                ui.weak(ctx.format_shortcut(&shortcut));
+                            }
+                            if label.clicked() {
+                                chosen = Some(*action);
+                            }
+                            if index == palette.selected && (up || down) {
+                                label.scroll_to_me(None);
+                            }
+                        });
+                    }
+                });
+        });
+
+    if chosen.is_some() {
+        palette.open = false;
+        palette.query.clear();
+        palette.selected = 0;
+    }
+    chosen
+}
@@ -0,0 +1,97 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::export_script::{ExportScript, EXTENSIONS};
+use crate::app::io::{IoService, SaveRequest};
+use crate::app::view::View;
+use eframe::egui;
+use eframe::egui::Ui;
+
+// Lines of the output shown in the window (the file has all of them)
+const NR_PREVIEW_LINES: usize = 200;
+
+/// Edit the export script, run it on the current layer or all layers (see
+///  `View::build_order_all_layers`) and save its output
+pub fn ui_export_script(
+    ui: &mut Ui,
+    script: &mut ExportScript,
+    stack_blocks: &ZVec<Blocks>,
+    current_layer: isize,
+    layers_enabled: bool,
+    view: &mut View,
+    io: &mut IoService,
+) {
+    ui.label("A Rhai script turns the blocks into text, everything it prints is exported.");
+    egui::ScrollArea::vertical()
+        .id_source("export_script_code")
+        .max_height(250.0)
+        .show(ui, |ui| {
+            ui.add(
+                egui::TextEdit::multiline(&mut script.code)
+                    .desired_rows(10)
+                    .desired_width(f32::INFINITY)
+                    .code_editor(),
+            );
+        });
+
+    let (mut run, mut save) = (false, false);
+    ui.horizontal(|ui| {
+        run = ui.button("Run").clicked();
+        save = ui.button("Run and save").clicked();
+        egui::ComboBox::from_id_source("export_script_extension")
+            .selected_text(format!(".{}", script.extension))
+            .width(60.0)
+            .show_ui(ui, |ui| {
+                for extension in EXTENSIONS {
+                    ui.selectable_value(
+                        &mut script.extension,
+                        extension,
+                        format!(".{}", extension),
+                    );
+                }
+            });
+        if layers_enabled {
+            ui.checkbox(&mut view.build_order_all_layers, "All layers");
+        }
+    });
+
+    if run || save {
+        let layers = if layers_enabled && view.build_order_all_layers {
+            stack_blocks.get_minimum()..=stack_blocks.get_maximum()
+        } else {
+            current_layer..=current_layer
+        };
+        script.run(stack_blocks, layers, view.world_translation(), view.flip_z);
+
+        if let (true, Some(Ok(output))) = (save, &script.output) {
+            io.save(SaveRequest {
+                title: "Export script output".to_string(),
+                file_name: format!("voxircle_export.{}", script.extension),
+                filter_name: "Script output",
+                extensions: script.extensions(),
+                contents: output.clone().into_bytes(),
+            });
+        }
+    }
+
+    match &script.output {
+        None => {}
+        Some(Err(message)) => {
+            ui.colored_label(ui.visuals().error_fg_color, message);
+        }
+        Some(Ok(output)) => {
+            let nr_lines = output.lines().count();
+            ui.label(format!("Output ({} lines):", nr_lines));
+            egui::ScrollArea::vertical()
+                .id_source("export_script_output")
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for line in output.lines().take(NR_PREVIEW_LINES) {
+                        ui.monospace(line);
+                    }
+                    if nr_lines > NR_PREVIEW_LINES {
+                        ui.weak(format!("... and {} more", nr_lines - NR_PREVIEW_LINES));
+                    }
+                });
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::find_replace::{FindReplace, ShapeFilter};
+use crate::app::generation::percentage::CoverageEstimator;
+use crate::app::generation::Algorithm;
+use crate::app::ui::options::ui_margin;
+use eframe::egui;
+use eframe::egui::Ui;
+use itertools::Itertools;
+
+const ALGORITHMS: [Algorithm; 6] = [
+    Algorithm::Centerpoint,
+    Algorithm::Conservative(0.0),
+    Algorithm::Contained(0.0),
+    Algorithm::Percentage(0.5, CoverageEstimator::Exact),
+    Algorithm::Custom,
+    Algorithm::Empty,
+];
+
+/// Pick an algorithm or none (shown as `none_text`). The percentage of the percentage algorithm
+///  gets its own slider.
+fn algorithm_combo_box(ui: &mut Ui, id: &str, none_text: &str, algorithm: &mut Option<Algorithm>) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(algorithm.map_or(none_text.to_string(), |a| a.to_string()))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(algorithm, None, none_text);
+            for option in ALGORITHMS {
+                ui.selectable_value(algorithm, Some(option), option.to_string());
+            }
+        });
+}
+
+/// Find layers by algorithm and shape and change them all at once (not the locked parameters).
+///  Returns true if layers were changed (then the parameters are outdated).
+pub fn ui_find_replace(
+    ui: &mut Ui,
+    find_replace: &mut FindReplace,
+    stack_layer_config: &mut ZVec<SliceParameters>,
+    stack_locked: &ZVec<bool>,
+    locked_parameters: &[Parameter],
+    layer_lowest: isize,
+    layer_highest: isize,
+) -> bool {
+    ui.heading("Find");
+    ui.horizontal(|ui| {
+        ui.label("Layers");
+        ui.add(
+            egui::DragValue::new(&mut find_replace.layer_range[0])
+                .speed(0.05)
+                .clamp_range(layer_lowest..=layer_highest),
+        );
+        ui.label("to");
+        ui.add(
+            egui::DragValue::new(&mut find_replace.layer_range[1])
+                .speed(0.05)
+                .clamp_range(layer_lowest..=layer_highest),
+        );
+        if ui.button("All").clicked() {
+            find_replace.layer_range = [layer_lowest, layer_highest];
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Algorithm");
+        algorithm_combo_box(ui, "find_algorithm", "Any", &mut find_replace.algorithm);
+    });
+    egui::ComboBox::from_label("Shape")
+        .selected_text(find_replace.shape.to_string())
+        .show_ui(ui, |ui| {
+            for option in [
+                ShapeFilter::Any,
+                ShapeFilter::Circle,
+                ShapeFilter::Ellipse,
+                ShapeFilter::Squircle,
+            ] {
+                ui.selectable_value(&mut find_replace.shape, option, option.to_string());
+            }
+        });
+    ui.horizontal(|ui| {
+        let mut filter_radius = find_replace.radius_range.is_some();
+        ui.checkbox(&mut filter_radius, "Radius from");
+        let mut radius_range = find_replace.radius_range.unwrap_or([0.0, 100.0]);
+        ui.add_enabled(
+            filter_radius,
+            egui::DragValue::new(&mut radius_range[0]).speed(0.05),
+        );
+        ui.label("to");
+        ui.add_enabled(
+            filter_radius,
+            egui::DragValue::new(&mut radius_range[1]).speed(0.05),
+        );
+        find_replace.radius_range = filter_radius.then_some(radius_range);
+    });
+
+    let found = find_replace.find(stack_layer_config, stack_locked);
+    ui.label(if found.is_empty() {
+        "No matching layers".to_string()
+    } else {
+        format!(
+            "{} matching layers: {}",
+            found.len(),
+            found.iter().join(", ")
+        )
+    });
+
+    ui.separator();
+    ui.heading("Replace");
+    ui.horizontal(|ui| {
+        ui.label("Algorithm");
+        algorithm_combo_box(
+            ui,
+            "replace_algorithm",
+            "Keep",
+            &mut find_replace.replace_algorithm,
+        );
+        match &mut find_replace.replace_algorithm {
+            Some(Algorithm::Percentage(percentage, _)) => {
+                ui.add(egui::Slider::new(percentage, 0.0..=1.0).text("Percentage"));
+            }
+            Some(Algorithm::Conservative(margin) | Algorithm::Contained(margin)) => {
+                ui_margin(ui, margin);
+            }
+            _ => {}
+        }
+    });
+    let squircle_locked = locked_parameters.contains(&Parameter::SquircleParameter);
+    ui.add_enabled_ui(!squircle_locked, |ui| {
+        let mut replace_squircle = find_replace.replace_squircle_parameter.is_some();
+        ui.checkbox(&mut replace_squircle, "Squircle parameter")
+            .on_disabled_hover_text("The squircle parameter is locked");
+        let mut squircle_parameter = find_replace.replace_squircle_parameter.unwrap_or(2.0);
+        ui.add_enabled(
+            replace_squircle,
+            egui::DragValue::new(&mut squircle_parameter)
+                .speed(0.01)
+                .clamp_range(0.0..=f64::INFINITY),
+        );
+        find_replace.replace_squircle_parameter = replace_squircle.then_some(squircle_parameter);
+    });
+
+    let nothing_to_replace = find_replace.replace_algorithm.is_none()
+        && (find_replace.replace_squircle_parameter.is_none() || squircle_locked);
+    if ui
+        .add_enabled(
+            !found.is_empty() && !nothing_to_replace,
+            egui::Button::new(format!("Replace in {} layers", found.len())),
+        )
+        .clicked()
+    {
+        find_replace.replace(stack_layer_config, &found, locked_parameters);
+        return true;
+    }
+
+    false
+}
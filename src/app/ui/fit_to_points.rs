@@ -0,0 +1,110 @@
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::fit_to_points::{apply_fitted_shape, FitToPoints, MAX_CLICKED_POINTS};
+use crate::app::math::shape_fit::FittedShape;
+use eframe::egui;
+use eframe::egui::Ui;
+
+/// Paste block coordinates, fit a shape to them and use it for the current layer. Returns true if
+///  the shape was applied (then the parameters are outdated).
+pub fn ui_fit_to_points(
+    ui: &mut Ui,
+    fit_to_points: &mut FitToPoints,
+    translation: [isize; 2], // see `View::world_translation`
+    flip_z: bool,
+    current_layer_config: &mut SliceParameters,
+    single_radius: &mut bool,
+    locked: bool,
+) -> bool {
+    ui.label("Paste block coordinates, one per line as X Y Z (Minecraft) or x y (viewport):");
+    egui::ScrollArea::vertical()
+        .max_height(200.0)
+        .show(ui, |ui| {
+            ui.add(
+                egui::TextEdit::multiline(&mut fit_to_points.text)
+                    .hint_text("100 64 -20\n101 64 -20\n...")
+                    .desired_rows(8)
+                    .code_editor(),
+            );
+        });
+
+    if ui.button("Fit shape").clicked() {
+        fit_to_points.fit(translation, flip_z);
+    }
+
+    match &fit_to_points.result {
+        None => false,
+        Some(Err(message)) => {
+            ui.colored_label(egui::Color32::LIGHT_RED, message);
+            false
+        }
+        Some(Ok(shape)) => ui_fitted_shape(ui, shape, current_layer_config, single_radius, locked),
+    }
+}
+
+/// Click points in the viewport (while this is shown) to put a circle or ellipse through them.
+///  Returns true if the shape was applied to the current layer.
+pub fn ui_shape_through_points(
+    ui: &mut Ui,
+    fit_to_points: &mut FitToPoints,
+    current_layer_config: &mut SliceParameters,
+    single_radius: &mut bool,
+    locked: bool,
+) -> bool {
+    ui.label("Click points in the viewport: three or four for a circle, five for an ellipse.");
+    ui.checkbox(&mut fit_to_points.snap_to_blocks, "Snap to block centers");
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "Points: {} of {}",
+            fit_to_points.clicked_points.len(),
+            MAX_CLICKED_POINTS
+        ));
+        if ui.button("Undo").clicked() {
+            fit_to_points.clicked_points.pop();
+        }
+        if ui.button("Clear").clicked() {
+            fit_to_points.clicked_points.clear();
+        }
+    });
+
+    match fit_to_points.fit_clicked_points() {
+        Some(shape) => ui_fitted_shape(ui, &shape, current_layer_config, single_radius, locked),
+        None => {
+            if fit_to_points.clicked_points.len() >= 3 {
+                ui.label("There is no circle through these points");
+            }
+            false
+        }
+    }
+}
+
+/// Describe the fitted shape, with a button to use it for the current layer (returns true if
+///  clicked)
+fn ui_fitted_shape(
+    ui: &mut Ui,
+    shape: &FittedShape,
+    current_layer_config: &mut SliceParameters,
+    single_radius: &mut bool,
+    locked: bool,
+) -> bool {
+    ui.label(format!(
+        "Center ({:.2}, {:.2}), radii {:.2} and {:.2}",
+        shape.center[0], shape.center[1], shape.radius_a, shape.radius_b
+    ));
+    ui.label(format!(
+        "Tilt {:.2}, squircle parameter {:.2}",
+        shape.tilt, shape.squircle_parameter
+    ));
+    ui.label(format!("Fit error: {:.1}%", 100.0 * shape.error))
+        .on_hover_text("Average distance of the points to the shape, relative to the radius");
+
+    let applied = ui
+        .add_enabled(!locked, egui::Button::new("Use for current layer"))
+        .clicked();
+    if applied {
+        apply_fitted_shape(shape, current_layer_config);
+        if shape.radius_a != shape.radius_b {
+            *single_radius = false;
+        }
+    }
+    applied
+}
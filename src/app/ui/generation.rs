@@ -2,6 +2,12 @@ use crate::app::control::Control;
 use eframe::egui;
 use eframe::egui::Ui;
 
+const DEFAULT_FIXED_GRID_SIZE: usize = 256;
+// Larger grids make generating and the metrics slow
+const MAX_FIXED_GRID_SIZE: usize = 1024;
+// Flatter (or taller) cells need very large grids
+const CELL_ASPECT_RANGE: [f64; 2] = [0.1, 10.0];
+
 pub fn ui_generation(
     ui: &mut Ui,
     blocks_current_layer_control: &mut Control,
@@ -116,3 +122,53 @@ fn ui_generation_buttons(
         };
     });
 }
+
+/// Pin the grid size of all layers, so that they line up cell for cell. Output: whether the setting
+///  changed (then all layers need to be regenerated).
+pub fn ui_fixed_grid_size(ui: &mut Ui, fixed_grid_size: &mut Option<usize>) -> bool {
+    let mut is_fixed = fixed_grid_size.is_some();
+    let mut grid_size = fixed_grid_size.unwrap_or(DEFAULT_FIXED_GRID_SIZE);
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut is_fixed, "Fixed grid size").on_hover_text(
+            "Use the same grid for every layer regardless of the radii, blocks outside of it are \
+            cut off",
+        );
+        ui.add_enabled(
+            is_fixed,
+            egui::DragValue::new(&mut grid_size)
+                .speed(1.0)
+                .clamp_range(4..=MAX_FIXED_GRID_SIZE),
+        );
+    });
+
+    let new_fixed_grid_size = is_fixed.then_some(grid_size);
+    let changed = new_fixed_grid_size != *fixed_grid_size;
+    *fixed_grid_size = new_fixed_grid_size;
+    changed
+}
+
+/// Height of the cells relative to their width, for games whose blocks aren't cubes. Output:
+///  whether the setting changed (then all layers need to be regenerated).
+pub fn ui_cell_aspect(ui: &mut Ui, cell_aspect: &mut f64) -> bool {
+    let old_cell_aspect = *cell_aspect;
+
+    ui.horizontal(|ui| {
+        ui.label("Cell height");
+        ui.add(
+            egui::DragValue::new(cell_aspect)
+                .speed(0.01)
+                .clamp_range(CELL_ASPECT_RANGE[0]..=CELL_ASPECT_RANGE[1])
+                .suffix(" × width"),
+        )
+        .on_hover_text(
+            "For games with cells that aren't square, e.g. 0.5 for cells half as tall as they are \
+            wide. The radii and offsets stay in cell widths.",
+        );
+        if *cell_aspect != 1.0 && ui.small_button("Square").clicked() {
+            *cell_aspect = 1.0;
+        }
+    });
+
+    *cell_aspect != old_cell_aspect
+}
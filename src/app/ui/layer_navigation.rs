@@ -1,3 +1,11 @@
+use crate::app::data_structures::layer_bookmark::{add_bookmark, LayerBookmark};
+use crate::app::data_structures::layer_group::{add_group, LayerGroup};
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::formatting::{format_layer_run, format_parameter};
+use crate::app::layer_playback::{LayerPlayback, PlaybackMode};
+use crate::app::log::log_warning;
+use crate::app::metrics::repeated_layers::LayerRun;
+use crate::app::new_layers::NewLayerFill;
 use eframe::egui;
 use eframe::egui::Ui;
 
@@ -106,3 +114,241 @@ pub fn ui_layer_navigation(
     // Expression for if the stack has grown (might be useful later):
     // prev_layer_lowest > *layer_lowest || prev_layer_highest < *layer_highest
 }
+
+/// Choose where the parameters of new layers come from, and set the default parameters to those of
+///  the current layer
+pub fn ui_new_layer_fill(
+    ui: &mut Ui,
+    fill: &mut NewLayerFill,
+    default: &mut SliceParameters,
+    current_layer_config: &SliceParameters,
+) {
+    ui.horizontal(|ui| {
+        ui.label("New layers:");
+        egui::ComboBox::from_id_source("new_layer_fill")
+            .selected_text(fill.to_string())
+            .show_ui(ui, |ui| {
+                for option in NewLayerFill::all() {
+                    ui.selectable_value(fill, option, option.to_string());
+                }
+            });
+        if *fill != NewLayerFill::CopyNearest
+            && ui
+                .button("Use current layer as default")
+                .on_hover_text(format!(
+                    "Default now: radius {} × {}, squircle parameter {}",
+                    format_parameter(default.radius_a),
+                    format_parameter(default.radius_b),
+                    format_parameter(default.squircle_parameter)
+                ))
+                .clicked()
+        {
+            *default = current_layer_config.clone();
+        }
+    });
+}
+
+/// Play through the layers at a number of layers per second, looping or going back and forth
+pub fn ui_layer_playback(ui: &mut Ui, playback: &mut LayerPlayback) {
+    ui.horizontal(|ui| {
+        let text = if playback.playing {
+            "⏸ Pause"
+        } else {
+            "▶ Play"
+        };
+        if ui
+            .button(text)
+            .on_hover_text("Step through the layers to review the whole stack")
+            .clicked()
+        {
+            playback.toggle();
+        }
+        ui.add(
+            egui::DragValue::new(&mut playback.layers_per_second)
+                .speed(0.1)
+                .clamp_range(0.5..=60.0)
+                .suffix(" layers/s"),
+        );
+        ui.selectable_value(&mut playback.mode, PlaybackMode::Loop, "Loop");
+        ui.selectable_value(&mut playback.mode, PlaybackMode::PingPong, "Ping-pong");
+    });
+}
+
+/// Bookmark the current layer under a name and jump to bookmarked layers. Bookmarks outside of the
+///  stack can't be jumped to (they come back when the stack grows again). Without `editable` the
+///  bookmarks can only be jumped to.
+pub fn ui_layer_bookmarks(
+    ui: &mut Ui,
+    bookmarks: &mut Vec<LayerBookmark>,
+    new_bookmark_name: &mut String,
+    current_layer: &mut isize,
+    layer_lowest: isize,
+    layer_highest: isize,
+    editable: bool,
+) {
+    ui.horizontal(|ui| {
+        let mut remove = None;
+        egui::ComboBox::from_id_source("layer_bookmarks")
+            .selected_text(format!("Bookmarks ({})", bookmarks.len()))
+            .show_ui(ui, |ui| {
+                if bookmarks.is_empty() {
+                    ui.label("No bookmarks yet");
+                }
+                for (index, bookmark) in bookmarks.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let in_stack = (layer_lowest..=layer_highest).contains(&bookmark.layer);
+                        let text = format!("{}: {}", bookmark.layer, bookmark.name);
+                        if ui
+                            .add_enabled(
+                                in_stack,
+                                egui::SelectableLabel::new(*current_layer == bookmark.layer, text),
+                            )
+                            .clicked()
+                        {
+                            *current_layer = bookmark.layer;
+                        }
+                        if editable && ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                }
+            });
+        if let Some(index) = remove {
+            bookmarks.remove(index);
+        }
+
+        if editable {
+            ui.add(
+                egui::TextEdit::singleline(new_bookmark_name)
+                    .hint_text("Name")
+                    .desired_width(120.0),
+            );
+            if ui.button("Bookmark layer").clicked() {
+                add_bookmark(bookmarks, new_bookmark_name, *current_layer);
+                new_bookmark_name.clear();
+            }
+        }
+    });
+}
+
+/// Group consecutive layers into floors that share their parameters, jump to a floor, and let the
+///  current layer keep its own parameters. Output: have the groups changed (so that the parameters
+///  have to be shared again)? Without `editable` the floors can only be jumped to.
+pub fn ui_layer_groups(
+    ui: &mut Ui,
+    groups: &mut Vec<LayerGroup>,
+    new_group_name: &mut String,
+    new_group_layers: &mut [isize; 2],
+    current_layer: &mut isize,
+    editable: bool,
+) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        let mut remove = None;
+        egui::ComboBox::from_id_source("layer_groups")
+            .selected_text(format!("Floors ({})", groups.len()))
+            .show_ui(ui, |ui| {
+                if groups.is_empty() {
+                    ui.label("No floors yet");
+                }
+                for (index, group) in groups.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let text =
+                            format!("{} to {}: {}", group.layers[0], group.layers[1], group.name);
+                        if ui
+                            .selectable_label(group.contains(*current_layer), text)
+                            .clicked()
+                        {
+                            *current_layer = group.layers[0];
+                        }
+                        if editable
+                            && ui
+                                .small_button("🗑")
+                                .on_hover_text("Remove (the layers keep their parameters)")
+                                .clicked()
+                        {
+                            remove = Some(index);
+                        }
+                    });
+                }
+            });
+        if let Some(index) = remove {
+            groups.remove(index);
+        }
+        if !editable {
+            return;
+        }
+
+        ui.add(
+            egui::TextEdit::singleline(new_group_name)
+                .hint_text("Name")
+                .desired_width(120.0),
+        );
+        ui.add(egui::DragValue::new(&mut new_group_layers[0]).speed(0.05));
+        ui.label("to");
+        ui.add(egui::DragValue::new(&mut new_group_layers[1]).speed(0.05));
+        if ui
+            .button("Group layers")
+            .on_hover_text(
+                "The layers get the parameters of the current layer if it is one of them",
+            )
+            .clicked()
+        {
+            match add_group(groups, new_group_name, *new_group_layers, *current_layer) {
+                Ok(()) => {
+                    new_group_name.clear();
+                    changed = true;
+                }
+                Err(message) => log_warning(message),
+            }
+        }
+    });
+
+    if let Some(group) = groups
+        .iter_mut()
+        .find(|group| group.contains(*current_layer))
+    {
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "Layer {} is on floor {} (layers {} to {})",
+                current_layer, group.name, group.layers[0], group.layers[1]
+            ));
+            let mut own_parameters = !group.shares(*current_layer);
+            if editable
+                && ui
+                    .checkbox(&mut own_parameters, "Own parameters")
+                    .on_hover_text("Edit this layer without changing the rest of the floor")
+                    .changed()
+            {
+                group.toggle_override(*current_layer);
+                changed = true;
+            }
+        });
+    }
+
+    changed
+}
+
+/// Show the run of identical layers that the current layer is part of, the tooltip lists all runs.
+///  Nothing is shown if no two consecutive layers are the same.
+pub fn ui_repeated_layers(ui: &mut Ui, runs: &[LayerRun], current_layer: isize) {
+    if runs.is_empty() {
+        return;
+    }
+
+    let text = match runs.iter().find(|run| run.contains(current_layer)) {
+        Some(run) => format_layer_run(run),
+        None => format!("layer {}: not repeated", current_layer),
+    };
+    let all_runs = runs
+        .iter()
+        .map(|run| format!("{} ({} layers)", format_layer_run(run), run.nr_layers()))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    ui.vertical_centered(|ui| {
+        ui.label(text)
+            .on_hover_text(format!("Identical consecutive layers:\n{}", all_runs));
+    });
+}
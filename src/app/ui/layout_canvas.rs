@@ -0,0 +1,119 @@
+use crate::app::colors::*;
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::layout::{LayoutStructure, StructureLayout};
+use crate::app::plotting;
+use eframe::egui;
+use eframe::egui::{Align2, Stroke, Ui};
+use egui_plot::{Plot, PlotPoint, Text};
+
+/// Place several generated structures on a shared canvas. The current structure (of the editor) can
+///  be added to the canvas. Returns the index of the structure to load back into the editor, if any.
+pub fn ui_layout_canvas(
+    ui: &mut Ui,
+    layout: &mut StructureLayout,
+    stack_layer_config: &ZVec<SliceParameters>,
+    stack_blocks: &ZVec<Blocks>,
+    color_blind_palette: bool,
+) -> Option<usize> {
+    let mut load = None;
+    let mut remove = None;
+
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(&mut layout.new_structure_name);
+        if ui.button("Add current structure").clicked() {
+            let name = if layout.new_structure_name.is_empty() {
+                format!("Structure {}", layout.structures.len() + 1)
+            } else {
+                std::mem::take(&mut layout.new_structure_name)
+            };
+            layout.structures.push(LayoutStructure {
+                name,
+                offset: [0, 0],
+                layer_offset: 0,
+                visible: true,
+                stack_layer_config: stack_layer_config.clone(),
+                stack_blocks: stack_blocks.clone(),
+            });
+        }
+    });
+
+    let palette = component_palette(layout.structures.len(), color_blind_palette);
+    egui::Grid::new("layout_structures").show(ui, |ui| {
+        for (index, structure) in layout.structures.iter_mut().enumerate() {
+            ui.checkbox(&mut structure.visible, "");
+            ui.colored_label(palette[index % palette.len()], &structure.name);
+            ui.add(
+                egui::DragValue::new(&mut structure.offset[0])
+                    .speed(0.1)
+                    .prefix("x: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut structure.offset[1])
+                    .speed(0.1)
+                    .prefix("y: "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut structure.layer_offset)
+                    .speed(0.05)
+                    .prefix("layer: "),
+            );
+            if ui
+                .button("Load")
+                .on_hover_text("Replace the structure in the editor by this one")
+                .clicked()
+            {
+                load = Some(index);
+            }
+            if ui.button("Remove").clicked() {
+                remove = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(index) = remove {
+        layout.structures.remove(index);
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Canvas layer");
+        ui.add(egui::DragValue::new(&mut layout.canvas_layer).speed(0.05));
+        ui.label(format!("{} blocks", layout.get_nr_blocks_on_layer()));
+    });
+
+    let background = viewport_background(ui.visuals().dark_mode);
+    ui.visuals_mut().extreme_bg_color = background;
+    Plot::new("layout_canvas")
+        .data_aspect(1.0)
+        .height(400.0)
+        .show_axes([false, false])
+        .show(ui, |plot_ui| {
+            for (index, structure) in layout.structures.iter().enumerate() {
+                if !structure.visible {
+                    continue;
+                }
+                let color = palette[index % palette.len()];
+                for coord in structure.get_block_coords_on_layer(layout.canvas_layer) {
+                    plot_ui.polygon(
+                        plotting::square_at_coords(coord)
+                            .stroke(Stroke {
+                                width: 1.0,
+                                color: COLOR_WIRE,
+                            })
+                            .fill_color(color),
+                    );
+                }
+                plot_ui.text(
+                    Text::new(
+                        PlotPoint::new(structure.offset[0] as f64, structure.offset[1] as f64),
+                        &structure.name,
+                    )
+                    .anchor(Align2::CENTER_CENTER)
+                    .color(with_contrast(COLOR_TARGET_SHAPE, background)),
+                );
+            }
+        });
+
+    load
+}
@@ -0,0 +1,57 @@
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::lean::{Lean, LeanMode};
+use eframe::egui;
+use eframe::egui::Ui;
+
+/// Lean the stack: a linear lean (an offset per layer) or offsets by code. While this is open the
+///  viewport shows the axis of the stack and the shapes of its bottom and top layer. Output:
+///  whether the lean changed (then all layers have to be sampled again).
+pub fn ui_lean(ui: &mut Ui, lean: &mut Lean, sampling_points: &ZVec<Vec<f64>>) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        for mode in [LeanMode::Off, LeanMode::Linear, LeanMode::Code] {
+            changed |= ui
+                .selectable_value(&mut lean.mode, mode, mode.to_string())
+                .changed();
+        }
+    });
+
+    match lean.mode {
+        LeanMode::Off => {
+            ui.label("The layers are centered at their center offsets");
+        }
+        LeanMode::Linear => {
+            ui.label("Offset of the center per layer up");
+            ui.horizontal(|ui| {
+                for (step, prefix) in lean.per_layer.iter_mut().zip(["x ", "y "]) {
+                    changed |= ui
+                        .add(egui::DragValue::new(step).speed(0.01).prefix(prefix))
+                        .changed();
+                }
+            });
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut lean.base_layer)
+                        .speed(0.1)
+                        .prefix("Upright at layer "),
+                )
+                .changed();
+            ui.label(format!("{:.1}° from the vertical", lean.angle()));
+        }
+        LeanMode::Code => {
+            ui.label("Offset of the center at the height `layer` (or `l`)");
+            ui.label("x");
+            lean.code_x.show(ui, sampling_points);
+            ui.label("y");
+            lean.code_y.show(ui, sampling_points);
+            if lean.code_x.has_changed() || lean.code_y.has_changed() {
+                changed = true;
+                lean.code_x.register_success();
+                lean.code_y.register_success();
+            }
+        }
+    }
+
+    changed
+}
@@ -0,0 +1,45 @@
+use crate::app::io::{IoService, SaveRequest};
+use crate::app::log;
+use crate::app::log::LogLevel;
+use eframe::egui;
+use eframe::egui::Ui;
+
+/// Show the log messages (warnings about fallbacks etc.), with buttons to copy, save and clear the
+///  log
+pub fn ui_log_console(ui: &mut Ui, io: &mut IoService) {
+    let entries = log::get_entries();
+
+    ui.horizontal(|ui| {
+        if ui.button("Copy log").clicked() {
+            ui.output_mut(|o| o.copied_text = log::to_text());
+        }
+        if ui.button("Save log").clicked() {
+            io.save(SaveRequest {
+                title: "Save log".to_string(),
+                file_name: "voxircle_log.txt".to_string(),
+                filter_name: "Text",
+                extensions: &["txt"],
+                contents: log::to_text().into_bytes(),
+            });
+        }
+        if ui.button("Clear").clicked() {
+            log::clear();
+        }
+        ui.label(format!("{} messages", entries.len()));
+    });
+    ui.separator();
+
+    egui::ScrollArea::vertical()
+        .stick_to_bottom(true)
+        .max_height(300.0)
+        .show(ui, |ui| {
+            for entry in entries {
+                match entry.level {
+                    LogLevel::Info => ui.label(format!("{}", entry)),
+                    LogLevel::Warning => {
+                        ui.colored_label(ui.visuals().warn_fg_color, format!("{}", entry))
+                    }
+                };
+            }
+        });
+}
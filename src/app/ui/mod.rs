@@ -1,6 +1,26 @@
+pub mod arch;
+pub mod block_budget;
+pub mod calculator;
+pub mod command_palette;
+pub mod export_script;
+pub mod find_replace;
+pub mod fit_to_points;
 pub mod generation;
 pub mod layer_navigation;
+pub mod layout_canvas;
+pub mod lean;
+pub mod log_console;
 pub mod options;
+pub mod parameter_history;
+pub mod reference_image;
 pub mod sampling;
+pub mod scaling;
+pub mod script_shapes;
+pub mod stamp;
+pub mod template_gallery;
+pub mod toasts;
+pub mod tutorial;
+pub mod variation_gallery;
+pub mod vertical_slice;
 pub mod viewport;
 pub mod viewport_options;
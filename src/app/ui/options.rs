@@ -1,30 +1,54 @@
-use std::f64::consts::PI;
+use std::cell::RefCell;
+use std::f64::consts::{PI, TAU};
 
-use crate::app::control::Control;
-use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::algorithm_field::{AlgorithmField, CustomPredicateField};
+use crate::app::data_structures::lattice::Lattice;
+use crate::app::data_structures::parameter_constraint::{
+    apply_constraints, has_cycle, is_constrained, new_constraint, Parameter, ParameterConstraint,
+};
+use crate::app::data_structures::shape_array::ShapeArray;
+use crate::app::data_structures::slice_parameters::{ParameterTexts, SliceParameters};
 use crate::app::data_structures::zvec::ZVec;
-use crate::app::generation::Algorithm;
+use crate::app::formatting;
+use crate::app::generation::cell_aspect::{from_cells, to_cells};
+use crate::app::generation::percentage::{CoverageEstimator, DEFAULT_COVERAGE_TOLERANCE};
+use crate::app::generation::script_shape::ShapeLibrary;
+use crate::app::generation::{Algorithm, Generator};
+use crate::app::io::IoService;
 use crate::app::lua_field::LuaField;
+use crate::app::ui::script_shapes::{ui_shape_parameters, ui_shape_type};
 use eframe::egui;
 use eframe::egui::{Align, Layout, Ui};
 
-// my first macro!
-// todo: there should be a way to simplify this further, there's still a lot of repetition in the code
-//  (esp. if you take into account the other ui files also)
-/// Mark the inputted control variables as outdated
-macro_rules! outdate {
-    ( $( $x:expr ),+ ) => {{
-        $($x.set_outdated();)+
-    }};
+/// How the sliders of the parameters behave: constraints between them, locks, the nudge step and
+///  the lattice the center snaps to
+pub struct ParameterControls<'a> {
+    pub single_radius: &'a mut bool,
+    pub parameter_constraints: &'a mut Vec<ParameterConstraint>,
+    pub locked_parameters: &'a mut Vec<Parameter>,
+    pub nudge_step: &'a mut f64,
+    pub center_snap: &'a mut Option<Lattice>,
 }
 
-/// Draw ui for algorithm selection, parameters of the generation (radius etc.), and lua fields.
-/// Update
+/// The code that the options edit (the algorithm field, the Custom code and script shapes), with
+///  the generator it is run by and the io to open scripts
+pub struct OptionsCode<'a> {
+    pub algorithm_field: &'a mut AlgorithmField,
+    pub custom_predicate_field: &'a mut CustomPredicateField,
+    pub shape_library: &'a mut ShapeLibrary,
+    pub generator: &'a mut Generator,
+    pub io: &'a mut IoService,
+}
+
+/// Draw ui for algorithm selection, parameters of the generation (radius etc.), and lua fields,
+///  for the configuration of a single layer. Output: whether the parameters need to be sampled
+///  again.
 pub fn ui_options(
     ui: &mut Ui,
     current_layer_config: &mut SliceParameters,
-    single_radius: &mut bool,
+    controls: ParameterControls,
     code_enabled: bool,
+    code: OptionsCode,
     lua_field_radius_a: &mut LuaField,
     lua_field_radius_b: &mut LuaField,
     lua_field_tilt: &mut LuaField,
@@ -32,10 +56,34 @@ pub fn ui_options(
     lua_field_center_offset_y: &mut LuaField,
     lua_field_squircle_parameter: &mut LuaField,
     sampling_points: &ZVec<Vec<f64>>,
-    parameters_current_layer_control: &mut Control,
-    parameters_all_layers_control: &mut Control,
-) {
-    // Select algorithm
+) -> bool {
+    let ParameterControls {
+        single_radius,
+        parameter_constraints,
+        locked_parameters,
+        nudge_step,
+        center_snap,
+    } = controls;
+    let OptionsCode {
+        algorithm_field,
+        custom_predicate_field,
+        shape_library,
+        generator,
+        io,
+    } = code;
+
+    let mut changed = ui_shape_type(
+        ui,
+        current_layer_config,
+        shape_library,
+        &mut generator.custom_predicate,
+        io,
+    );
+
+    // Select algorithm (switching back restores the last used percentage or margin)
+    let cached_percentage = current_layer_config.percentage_cache;
+    let cached_estimator = current_layer_config.coverage_estimator_cache;
+    let cached_margin = current_layer_config.margin_cache;
     egui::ComboBox::from_label("Algorithm")
         .selected_text(format!("{:}", current_layer_config.algorithm))
         // TODO: easily change algorithm for all layers
@@ -47,44 +95,66 @@ pub fn ui_options(
             );
             ui.selectable_value(
                 &mut current_layer_config.algorithm,
-                Algorithm::Conservative,
+                Algorithm::Conservative(cached_margin),
                 "Conservative",
             );
             ui.selectable_value(
                 &mut current_layer_config.algorithm,
-                Algorithm::Contained,
+                Algorithm::Contained(cached_margin),
                 "Contained",
             );
             ui.selectable_value(
                 &mut current_layer_config.algorithm,
-                Algorithm::Percentage(0.5),
+                Algorithm::Percentage(cached_percentage, cached_estimator),
                 "Percentage",
             );
+            ui.selectable_value(
+                &mut current_layer_config.algorithm,
+                Algorithm::Custom,
+                "Custom",
+            );
         });
+    if code_enabled {
+        ui.label("Algorithm by code: a name, or a number for the percentage");
+        algorithm_field.show(ui, sampling_points);
+    }
 
     // additional algorithm-specific options + description
     match current_layer_config.algorithm {
         Algorithm::Centerpoint => {
             ui.label("Include a particular block iff its centerpoint is in the ellipse");
         }
-        Algorithm::Conservative => {
+        Algorithm::Conservative(margin) => {
             ui.label(
                 "Include a particular block in the voxelization iff it has nonempty intersection with the ellipse"
             );
+            let mut margin = margin;
+            if ui_margin(ui, &mut margin).changed() {
+                current_layer_config.algorithm = Algorithm::Conservative(margin);
+                current_layer_config.margin_cache = margin;
+            }
         }
-        Algorithm::Contained => {
+        Algorithm::Contained(margin) => {
             ui.label("Include a particular block iff it is fully contained in the ellipse");
+            let mut margin = margin;
+            if ui_margin(ui, &mut margin).changed() {
+                current_layer_config.algorithm = Algorithm::Contained(margin);
+                current_layer_config.margin_cache = margin;
+            }
         }
-        Algorithm::Percentage(percentage) => {
-            // longterm: ellipse, superellipse
-            ui.label(
-                format!(
-                    "Include a particular block in the voxelization iff more than {:.0}% of it is contained in the circle. Ellipses and squircles not implemented.",
+        Algorithm::Percentage(percentage, estimator) => {
+            ui.label(match estimator {
+                CoverageEstimator::Exact => format!(
+                    "Include a particular block in the voxelization iff more than {:.0}% of it is contained in the circle. Ellipses and squircles need subdivision.",
                     100.0 * percentage
-                )
-            );
+                ),
+                CoverageEstimator::Subdivision(_) => format!(
+                    "Include a particular block in the voxelization iff more than {:.0}% of it is contained in the shape, estimated by subdividing the block.",
+                    100.0 * percentage
+                ),
+            });
             let mut perc_slider = percentage.clone();
-            if ui
+            let mut percentage_changed = ui
                 .add(
                     egui::Slider::new(&mut perc_slider, 0.0..=1.0)
                         .text("")
@@ -93,10 +163,62 @@ pub fn ui_options(
                             format!("{:.0}%", n * 100.0) //  formatting of percentage slider
                         }),
                 )
-                .changed()
-            {
-                current_layer_config.algorithm = Algorithm::Percentage(perc_slider);
+                .changed();
+
+            // Exact geometry (circles only) or adaptive subdivision with a tolerance
+            let mut subdivision = matches!(estimator, CoverageEstimator::Subdivision(_));
+            let mut tolerance = match estimator {
+                CoverageEstimator::Exact => DEFAULT_COVERAGE_TOLERANCE,
+                CoverageEstimator::Subdivision(tolerance) => tolerance,
             };
+            ui.horizontal(|ui| {
+                percentage_changed |= ui
+                    .checkbox(&mut subdivision, "Subdivision")
+                    .on_hover_text(
+                        "Estimate the covered part by subdividing the block, for any shape",
+                    )
+                    .changed();
+                if subdivision {
+                    percentage_changed |= ui
+                        .add(
+                            egui::Slider::new(&mut tolerance, 0.001..=0.1)
+                                .logarithmic(true)
+                                .text("Tolerance")
+                                .custom_formatter(|n, _| format!("{:.1}%", n * 100.0)),
+                        )
+                        .changed();
+                }
+            });
+
+            if percentage_changed {
+                let estimator = if subdivision {
+                    CoverageEstimator::Subdivision(tolerance)
+                } else {
+                    CoverageEstimator::Exact
+                };
+                current_layer_config.algorithm = Algorithm::Percentage(perc_slider, estimator);
+                current_layer_config.percentage_cache = perc_slider;
+                current_layer_config.coverage_estimator_cache = estimator;
+            };
+        }
+        Algorithm::Custom if shape_library.active().is_some() => {
+            ui.label(
+                "Include a particular block iff its center is in the shape of the script. All \
+                layers with the Custom algorithm have this shape, the cells within the radius \
+                around the center are tried.",
+            );
+            if ui_shape_parameters(ui, shape_library, &mut generator.custom_predicate) {
+                changed = true;
+            }
+        }
+        Algorithm::Custom => {
+            ui.label(
+                "Include a particular block iff the code is true at its center x, y. The code can \
+                use the parameters (params.radius_a, params.tilt, ...) and the layer.",
+            );
+            if custom_predicate_field.show(ui, &mut generator.custom_predicate) {
+                changed = true;
+            }
         }
         Algorithm::Empty => {
             ui.label("Include no blocks in the voxelization");
@@ -106,27 +228,67 @@ pub fn ui_options(
     // Radius
     ui.separator();
 
-    ui.checkbox(single_radius, "Single radius");
+    ui.horizontal(|ui| {
+        ui.label("Nudge step");
+        for step in [0.5, 0.1, 0.01] {
+            ui.selectable_value(nudge_step, step, format!("{}", step));
+        }
+    });
+
+    if ui.checkbox(single_radius, "Single radius").changed() {
+        let [radius_a, radius_b] = [current_layer_config.radius_a, current_layer_config.radius_b];
+        if *single_radius {
+            // Convert the ellipse to the circle on its major axis, but remember the ellipse
+            current_layer_config.ellipse_radii_cache = [radius_a, radius_b];
+            current_layer_config.radius_a = radius_a.max(radius_b);
+        } else {
+            // Restore the remembered ellipse, scaled so that its major radius is the radius of
+            //  the circle (so nothing changes if the circle wasn't touched in the meantime)
+            let [cached_a, cached_b] = current_layer_config.ellipse_radii_cache;
+            let cached_major = cached_a.max(cached_b);
+            if cached_major > 0.0 {
+                let scale = radius_a / cached_major;
+                current_layer_config.radius_a = cached_a * scale;
+                current_layer_config.radius_b = cached_b * scale;
+            }
+        }
+
+        lua_field_radius_a.update_field_state(sampling_points);
+        lua_field_radius_b.update_field_state(sampling_points);
+        changed = true;
+    }
 
     if *single_radius {
+        let enabled = !is_constrained(parameter_constraints, Parameter::RadiusA);
         if ui
-            .add(
-                egui::Slider::new(&mut current_layer_config.radius_a, 0.0..=30.0)
-                    .text("Radius")
-                    .clamp_to_range(false)
-                    .custom_formatter(|param, _| format!("{:.02}", param))
-                    .drag_value_speed(0.03),
-            )
-            .changed()
+            .horizontal(|ui| {
+                parameter_slider(
+                    ui,
+                    enabled,
+                    egui::Slider::new(&mut current_layer_config.radius_a, 0.0..=30.0)
+                        .text("Radius")
+                        .clamp_to_range(false)
+                        .drag_value_speed(0.03),
+                    Parameter::RadiusA,
+                    &mut current_layer_config.parameter_texts,
+                )
+                .changed()
+                    | ui_nudge_and_lock(
+                        ui,
+                        &mut current_layer_config.radius_a,
+                        enabled,
+                        &[Parameter::RadiusA, Parameter::RadiusB],
+                        locked_parameters,
+                        *nudge_step,
+                    )
+            })
+            .inner
         {
             // the code is now invalid
             lua_field_radius_a.update_field_state(sampling_points);
             lua_field_radius_b.update_field_state(sampling_points);
 
-            outdate!(
-                parameters_current_layer_control,
-                parameters_all_layers_control
-            );
+            changed = true;
         };
 
         // lua
@@ -137,42 +299,66 @@ pub fn ui_options(
         current_layer_config.radius_b = current_layer_config.radius_a;
     } else {
         // radius a
+        let enabled = !is_constrained(parameter_constraints, Parameter::RadiusA);
         if ui
-            .add(
-                egui::Slider::new(&mut current_layer_config.radius_a, 0.0..=30.0)
-                    .text("Radius A")
-                    .clamp_to_range(false)
-                    .custom_formatter(|param, _| format!("{:.02}", param))
-                    .drag_value_speed(0.03),
-            )
-            .changed()
+            .horizontal(|ui| {
+                parameter_slider(
+                    ui,
+                    enabled,
+                    egui::Slider::new(&mut current_layer_config.radius_a, 0.0..=30.0)
+                        .text("Radius A")
+                        .clamp_to_range(false)
+                        .drag_value_speed(0.03),
+                    Parameter::RadiusA,
+                    &mut current_layer_config.parameter_texts,
+                )
+                .changed()
+                    | ui_nudge_and_lock(
+                        ui,
+                        &mut current_layer_config.radius_a,
+                        enabled,
+                        &[Parameter::RadiusA],
+                        locked_parameters,
+                        *nudge_step,
+                    )
+            })
+            .inner
         {
             lua_field_radius_a.update_field_state(sampling_points);
-            outdate!(
-                parameters_current_layer_control,
-                parameters_all_layers_control
-            );
+            changed = true;
         }
         if code_enabled {
             lua_field_radius_a.show(ui, sampling_points);
         }
 
         // radius b
+        let enabled = !is_constrained(parameter_constraints, Parameter::RadiusB);
         if ui
-            .add(
-                egui::Slider::new(&mut current_layer_config.radius_b, 0.0..=30.0)
-                    .text("Radius B")
-                    .clamp_to_range(false)
-                    .custom_formatter(|param, _| format!("{:.02}", param))
-                    .drag_value_speed(0.03),
-            )
-            .changed()
+            .horizontal(|ui| {
+                parameter_slider(
+                    ui,
+                    enabled,
+                    egui::Slider::new(&mut current_layer_config.radius_b, 0.0..=30.0)
+                        .text("Radius B")
+                        .clamp_to_range(false)
+                        .drag_value_speed(0.03),
+                    Parameter::RadiusB,
+                    &mut current_layer_config.parameter_texts,
+                )
+                .changed()
+                    | ui_nudge_and_lock(
+                        ui,
+                        &mut current_layer_config.radius_b,
+                        enabled,
+                        &[Parameter::RadiusB],
+                        locked_parameters,
+                        *nudge_step,
+                    )
+            })
+            .inner
         {
             lua_field_radius_b.update_field_state(sampling_points);
-            outdate!(
-                parameters_current_layer_control,
-                parameters_all_layers_control
-            );
+            changed = true;
         }
         if code_enabled {
             lua_field_radius_b.show(ui, sampling_points);
@@ -182,20 +368,32 @@ pub fn ui_options(
     }
 
     //tilt
+    let enabled = !is_constrained(parameter_constraints, Parameter::Tilt);
     if ui
-        .add(
-            egui::Slider::new(&mut current_layer_config.tilt, -6.28..=6.28)
-                .text("Tilt (radians)")
-                .fixed_decimals(2)
-                .drag_value_speed(0.01),
-        )
-        .changed()
+        .horizontal(|ui| {
+            parameter_slider(
+                ui,
+                enabled,
+                egui::Slider::new(&mut current_layer_config.tilt, -TAU..=TAU)
+                    .text("Tilt (radians)")
+                    .drag_value_speed(0.01),
+                Parameter::Tilt,
+                &mut current_layer_config.parameter_texts,
+            )
+            .changed()
+                | ui_nudge_and_lock(
+                    ui,
+                    &mut current_layer_config.tilt,
+                    enabled,
+                    &[Parameter::Tilt],
+                    locked_parameters,
+                    *nudge_step,
+                )
+        })
+        .inner
     {
         lua_field_tilt.update_field_state(sampling_points);
-        outdate!(
-            parameters_current_layer_control,
-            parameters_all_layers_control
-        );
+        changed = true;
     };
 
     // Particular values
@@ -216,10 +414,7 @@ pub fn ui_options(
                 if ui.button(name).clicked() {
                     current_layer_config.tilt = value;
                     lua_field_tilt.update_field_state(sampling_points);
-                    outdate!(
-                        parameters_current_layer_control,
-                        parameters_all_layers_control
-                    )
+                    changed = true
                 }
             });
         },
@@ -234,19 +429,26 @@ pub fn ui_options(
         let mut squircle_ui_parameter = current_layer_config.get_squircle_ui_parameter();
         ui.separator();
         if ui
-            .add(
-                egui::Slider::new(&mut squircle_ui_parameter, 0.0..=1.0)
-                    .text("Squircicity")
-                    .custom_formatter(|param, _| format!("{:.02}", 1.0 / (1.0 - param) - 1.0))
-                    .custom_parser(|s| s.parse::<f64>().map(|t| 1.0 - 1.0 / (t + 1.0)).ok()),
-            )
-            .changed()
+            .horizontal(|ui| {
+                let changed = ui
+                    .add(
+                        egui::Slider::new(&mut squircle_ui_parameter, 0.0..=1.0)
+                            .text("Squircicity")
+                            .custom_formatter(|param, _| {
+                                format!("{:.02}", 1.0 / (1.0 - param) - 1.0)
+                            })
+                            .custom_parser(|s| {
+                                s.parse::<f64>().map(|t| 1.0 - 1.0 / (t + 1.0)).ok()
+                            }),
+                    )
+                    .changed();
+                ui_lock(ui, &[Parameter::SquircleParameter], locked_parameters);
+                changed
+            })
+            .inner
         {
             lua_field_squircle_parameter.update_field_state(sampling_points);
-            outdate!(
-                parameters_current_layer_control,
-                parameters_all_layers_control
-            );
+            changed = true;
         };
 
         // Default values
@@ -268,10 +470,7 @@ pub fn ui_options(
                     if ui.button(name).clicked() {
                         squircle_ui_parameter = value;
                         lua_field_squircle_parameter.update_field_state(sampling_points);
-                        outdate!(
-                            parameters_current_layer_control,
-                            parameters_all_layers_control
-                        );
+                        changed = true;
                     }
                 });
             },
@@ -283,39 +482,77 @@ pub fn ui_options(
         lua_field_squircle_parameter.show(ui, sampling_points);
     }
 
-    // Centerpoint
+    // Centerpoint. While snapping, the offsets stay on the lattice and the nudge buttons move to
+    //  the next point of it.
     ui.separator();
+    let offset_nudge_step = if center_snap.is_some() {
+        1.0
+    } else {
+        *nudge_step
+    };
+    let enabled = !is_constrained(parameter_constraints, Parameter::CenterOffsetX);
     if ui
-        .add(
-            egui::Slider::new(&mut current_layer_config.center_offset_x, -1.0..=1.0)
-                .text("x offset")
-                .clamp_to_range(false),
-        )
-        .changed()
+        .horizontal(|ui| {
+            parameter_slider(
+                ui,
+                enabled,
+                egui::Slider::new(&mut current_layer_config.center_offset_x, -1.0..=1.0)
+                    .text("x offset")
+                    .clamp_to_range(false),
+                Parameter::CenterOffsetX,
+                &mut current_layer_config.parameter_texts,
+            )
+            .changed()
+                | ui_nudge_and_lock(
+                    ui,
+                    &mut current_layer_config.center_offset_x,
+                    enabled,
+                    &[Parameter::CenterOffsetX],
+                    locked_parameters,
+                    offset_nudge_step,
+                )
+        })
+        .inner
     {
+        if let Some(lattice) = center_snap {
+            snap_center(current_layer_config, *lattice, generator.cell_aspect);
+        }
         lua_field_center_offset_x.update_field_state(sampling_points);
-        outdate!(
-            parameters_current_layer_control,
-            parameters_all_layers_control
-        );
+        changed = true;
     };
     if code_enabled {
         lua_field_center_offset_x.show(ui, sampling_points);
     }
 
+    let enabled = !is_constrained(parameter_constraints, Parameter::CenterOffsetY);
     if ui
-        .add(
-            egui::Slider::new(&mut current_layer_config.center_offset_y, -1.0..=1.0)
-                .text("y offset")
-                .clamp_to_range(false),
-        )
-        .changed()
+        .horizontal(|ui| {
+            parameter_slider(
+                ui,
+                enabled,
+                egui::Slider::new(&mut current_layer_config.center_offset_y, -1.0..=1.0)
+                    .text("y offset")
+                    .clamp_to_range(false),
+                Parameter::CenterOffsetY,
+                &mut current_layer_config.parameter_texts,
+            )
+            .changed()
+                | ui_nudge_and_lock(
+                    ui,
+                    &mut current_layer_config.center_offset_y,
+                    enabled,
+                    &[Parameter::CenterOffsetY],
+                    locked_parameters,
+                    offset_nudge_step,
+                )
+        })
+        .inner
     {
+        if let Some(lattice) = center_snap {
+            snap_center(current_layer_config, *lattice, generator.cell_aspect);
+        }
         lua_field_center_offset_y.update_field_state(sampling_points);
-        outdate!(
-            parameters_current_layer_control,
-            parameters_all_layers_control
-        );
+        changed = true;
     };
     if code_enabled {
         lua_field_center_offset_y.show(ui, sampling_points);
@@ -330,15 +567,218 @@ pub fn ui_options(
                 if ui.button(name).clicked() {
                     current_layer_config.center_offset_x = x;
                     current_layer_config.center_offset_y = y;
-                    outdate!(
-                        parameters_current_layer_control,
-                        parameters_all_layers_control
-                    )
+                    changed = true
                 }
             });
         },
     );
 
+    // Snap the center to the block corners (even) or the block centers (odd)
+    ui.horizontal(|ui| {
+        ui.label("Snap center to");
+        let old_center_snap = *center_snap;
+        ui.selectable_value(center_snap, None, "Off");
+        for lattice in [Lattice::Corners, Lattice::Centers] {
+            ui.selectable_value(center_snap, Some(lattice), lattice.to_string());
+        }
+        if let Some(lattice) = center_snap.filter(|_| *center_snap != old_center_snap) {
+            snap_center(current_layer_config, lattice, generator.cell_aspect);
+            changed = true;
+        }
+    });
+
+    // Shape array
+    ui.separator();
+    let mut shape_array_changed = egui::ComboBox::from_label("Array")
+        .selected_text(format!("{:}", current_layer_config.shape_array))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(
+                &mut current_layer_config.shape_array,
+                ShapeArray::Single,
+                "Single",
+            );
+            ui.selectable_value(
+                &mut current_layer_config.shape_array,
+                ShapeArray::Radial {
+                    count: 4,
+                    angle_step: PI / 2.0,
+                },
+                "Radial array",
+            );
+            ui.selectable_value(
+                &mut current_layer_config.shape_array,
+                ShapeArray::Linear {
+                    count: 3,
+                    spacing: [2.0 * current_layer_config.radius_a + 1.0, 0.0],
+                },
+                "Linear array",
+            );
+        })
+        .response
+        .changed();
+
+    match &mut current_layer_config.shape_array {
+        ShapeArray::Single => {}
+        ShapeArray::Radial { count, angle_step } => {
+            ui.label("Copies of the shape rotated around the origin");
+            shape_array_changed |= ui
+                .add(egui::Slider::new(count, 1..=32).text("Nr. copies"))
+                .changed();
+            shape_array_changed |= ui
+                .add(
+                    egui::Slider::new(angle_step, 0.0..=2.0 * PI)
+                        .text("Angle step (radians)")
+                        .fixed_decimals(2),
+                )
+                .changed();
+            if ui.button("Evenly spaced").clicked() {
+                *angle_step = 2.0 * PI / *count as f64;
+                shape_array_changed = true;
+            }
+        }
+        ShapeArray::Linear { count, spacing } => {
+            ui.label("Copies of the shape shifted along a direction");
+            shape_array_changed |= ui
+                .add(egui::Slider::new(count, 1..=32).text("Nr. copies"))
+                .changed();
+            shape_array_changed |= ui
+                .add(
+                    egui::Slider::new(&mut spacing[0], -30.0..=30.0)
+                        .text("x spacing")
+                        .clamp_to_range(false),
+                )
+                .changed();
+            shape_array_changed |= ui
+                .add(
+                    egui::Slider::new(&mut spacing[1], -30.0..=30.0)
+                        .text("y spacing")
+                        .clamp_to_range(false),
+                )
+                .changed();
+        }
+    }
+
+    // Sector (only generate a pie slice of the shape)
+    let mut sector_enabled = current_layer_config.sector.is_some();
+    let mut sector_changed = ui
+        .checkbox(&mut sector_enabled, "Sector")
+        .on_hover_text(
+            "Only generate the part of the shape between two angles (measured from the a-axis)",
+        )
+        .changed();
+    let mut sector = current_layer_config.sector.unwrap_or([0.0, PI / 2.0]);
+    if sector_enabled {
+        sector_changed |= ui
+            .add(
+                egui::Slider::new(&mut sector[0], 0.0..=2.0 * PI)
+                    .text("Start angle (radians)")
+                    .fixed_decimals(2),
+            )
+            .changed();
+        sector_changed |= ui
+            .add(
+                egui::Slider::new(&mut sector[1], 0.0..=2.0 * PI)
+                    .text("End angle (radians)")
+                    .fixed_decimals(2),
+            )
+            .changed();
+        ui.horizontal(|ui| {
+            for (name, width) in [("Quarter", PI / 2.0), ("Half", PI)] {
+                if ui.button(name).clicked() {
+                    sector = [0.0, width];
+                    sector_changed = true;
+                }
+            }
+        });
+    }
+    current_layer_config.sector = sector_enabled.then_some(sector);
+
+    if shape_array_changed || sector_changed {
+        changed = true;
+    }
+
+    // Constraints between the parameters
+    ui.separator();
+    ui.collapsing("Constraints", |ui| {
+        let mut constraints_changed = false;
+        let mut to_remove = None;
+        for index in 0..parameter_constraints.len() {
+            ui.horizontal(|ui| {
+                for id in ["target", "source"] {
+                    let constraint = parameter_constraints[index];
+                    let parameter = if id == "target" {
+                        constraint.target
+                    } else {
+                        constraint.source
+                    };
+                    egui::ComboBox::from_id_source((id, index))
+                        .selected_text(format!("{:}", parameter))
+                        .width(90.0)
+                        .show_ui(ui, |ui| {
+                            for option in Parameter::ALL {
+                                // Options that would make the constraints depend on each other in
+                                //  a loop can't be chosen
+                                let mut edited = parameter_constraints.clone();
+                                if id == "target" {
+                                    edited[index].target = option;
+                                } else {
+                                    edited[index].source = option;
+                                }
+                                let allowed = option == parameter || !has_cycle(&edited);
+                                let selected = egui::SelectableLabel::new(
+                                    option == parameter,
+                                    format!("{:}", option),
+                                );
+                                if ui.add_enabled(allowed, selected).clicked()
+                                    && option != parameter
+                                {
+                                    parameter_constraints[index] = edited[index];
+                                    constraints_changed = true;
+                                }
+                            }
+                        });
+                    if id == "target" {
+                        ui.label("=");
+                    }
+                }
+                let constraint = &mut parameter_constraints[index];
+                ui.label("×");
+                constraints_changed |= ui
+                    .add(egui::DragValue::new(&mut constraint.factor).speed(0.01))
+                    .changed();
+                ui.label("+");
+                constraints_changed |= ui
+                    .add(egui::DragValue::new(&mut constraint.offset).speed(0.01))
+                    .changed();
+                if ui.button("Remove").clicked() {
+                    to_remove = Some(index);
+                }
+            });
+        }
+        if let Some(index) = to_remove {
+            parameter_constraints.remove(index);
+            constraints_changed = true;
+        }
+        let new = new_constraint(parameter_constraints);
+        if ui
+            .add_enabled(new.is_some(), egui::Button::new("Add constraint"))
+            .on_disabled_hover_text("Every other constraint would depend on itself")
+            .clicked()
+        {
+            parameter_constraints.extend(new);
+            constraints_changed = true;
+        }
+
+        if constraints_changed {
+            changed = true;
+        }
+    });
+
+    // Constrained parameters follow the parameters they depend on
+    if apply_constraints(parameter_constraints, current_layer_config) {
+        changed = true;
+    }
+
     if lua_field_radius_a.has_changed()
         || lua_field_radius_b.has_changed()
         || lua_field_tilt.has_changed()
@@ -346,9 +786,113 @@ pub fn ui_options(
         || lua_field_center_offset_y.has_changed()
         || lua_field_squircle_parameter.has_changed()
     {
-        outdate!(
-            parameters_current_layer_control,
-            parameters_all_layers_control
-        );
+        changed = true;
+    }
+
+    changed
+}
+
+/// Buttons after the slider of a parameter: nudge the value down or up by the nudge step, and lock
+///  the parameters against bulk operations (the variations and the block budget). Output: whether
+///  the value was nudged.
+fn ui_nudge_and_lock(
+    ui: &mut Ui,
+    value: &mut f64,
+    enabled: bool,
+    parameters: &[Parameter],
+    locked_parameters: &mut Vec<Parameter>,
+    nudge_step: f64,
+) -> bool {
+    let mut nudged = false;
+    ui.add_enabled_ui(enabled, |ui| {
+        if ui.small_button("-").clicked() {
+            *value -= nudge_step;
+            nudged = true;
+        }
+        if ui.small_button("+").clicked() {
+            *value += nudge_step;
+            nudged = true;
+        }
+    });
+
+    ui_lock(ui, parameters, locked_parameters);
+    nudged
+}
+
+/// Lock the parameters against bulk changes, or unlock them
+fn ui_lock(ui: &mut Ui, parameters: &[Parameter], locked_parameters: &mut Vec<Parameter>) {
+    let locked = parameters
+        .iter()
+        .all(|parameter| locked_parameters.contains(parameter));
+    if ui
+        .selectable_label(locked, if locked { "🔒" } else { "🔓" })
+        .on_hover_text(
+            "Lock against random variations, scaling to the block budget and find and replace",
+        )
+        .clicked()
+    {
+        locked_parameters.retain(|parameter| !parameters.contains(parameter));
+        if !locked {
+            locked_parameters.extend_from_slice(parameters);
+        }
     }
 }
+
+/// Move the center of the shape to the nearest point of the lattice (of the cells, which may not be
+///  square)
+fn snap_center(slice_parameters: &mut SliceParameters, lattice: Lattice, cell_aspect: f64) {
+    let [x, y] = to_cells(
+        [
+            slice_parameters.center_offset_x,
+            slice_parameters.center_offset_y,
+        ],
+        cell_aspect,
+    );
+    [
+        slice_parameters.center_offset_x,
+        slice_parameters.center_offset_y,
+    ] = from_cells([lattice.snap(x), lattice.snap(y)], cell_aspect);
+}
+
+/// Slider of a parameter whose text field takes expressions like "pi/4" (see `parse_parameter`).
+///  The typed text is kept with the parameter and shown again while the parameter has its value.
+fn parameter_slider(
+    ui: &mut Ui,
+    enabled: bool,
+    slider: egui::Slider,
+    parameter: Parameter,
+    parameter_texts: &mut ParameterTexts,
+) -> egui::Response {
+    let typed = RefCell::new(None);
+    let response = ui.add_enabled(
+        enabled,
+        slider
+            .custom_formatter(|value, _| parameter_texts.format(parameter, value))
+            .custom_parser(|text| {
+                let value = formatting::parse_parameter(text)?;
+                *typed.borrow_mut() = Some((value, text.trim().to_string()));
+                Some(value)
+            }),
+    );
+    // Typed text is parsed as it changes, dragging the slider forgets the text
+    if response.changed() {
+        parameter_texts.set(parameter, typed.into_inner());
+    }
+    response
+}
+
+/// Margin of the Conservative and Contained algorithms, in blocks
+pub fn ui_margin(ui: &mut Ui, margin: &mut f64) -> egui::Response {
+    ui.add(
+        egui::Slider::new(margin, -3.0..=3.0)
+            .text("Margin")
+            .clamp_to_range(false)
+            .custom_formatter(|param, _| formatting::format_parameter(param))
+            .custom_parser(formatting::parse_parameter)
+            .drag_value_speed(0.03),
+    )
+    .on_hover_text(
+        "Grow the shape by this distance before choosing the blocks (shrink it if negative), \
+        e.g. to leave a gap of a block around it",
+    )
+}
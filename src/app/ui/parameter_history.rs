@@ -0,0 +1,73 @@
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::parameter_history::ParameterHistory;
+use eframe::egui;
+use eframe::egui::Ui;
+use egui_plot::{Line, Plot, Points};
+
+/// Graph of the chosen parameter of the current layer over the session (up to `time`, the current
+///  time). Clicking the graph gives the value at that time (to restore it), unless `editable` is
+///  false.
+pub fn ui_parameter_history(
+    ui: &mut Ui,
+    history: &mut ParameterHistory,
+    current_layer: isize,
+    time: f64,
+    editable: bool,
+) -> Option<f64> {
+    egui::ComboBox::from_label("Parameter")
+        .selected_text(history.parameter.to_string())
+        .show_ui(ui, |ui| {
+            for parameter in Parameter::ALL {
+                ui.selectable_value(&mut history.parameter, parameter, parameter.to_string());
+            }
+        });
+
+    let values = history.get_values(current_layer);
+    if values.len() < 2 {
+        ui.label("No changes yet on this layer");
+        return None;
+    }
+    ui.label(format!(
+        "{} changes on this layer. Click the graph to go back to a value.",
+        values.len() - 1
+    ));
+
+    // Time in seconds before now, the steps are the edits
+    let points: Vec<[f64; 2]> = values.iter().map(|[t, v]| [t - time, *v]).collect();
+    let mut steps = vec![];
+    for pair in points.windows(2) {
+        steps.extend([pair[0], [pair[1][0], pair[0][1]]]);
+    }
+    let [last_time, last_value] = *points.last().unwrap();
+    steps.extend([[last_time, last_value], [0.0, last_value]]);
+
+    let mut restored = None;
+    Plot::new("parameter_history")
+        .height(120.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .label_formatter(|_name, point| format!("{:.0} s ago\n{:.3}", -point.x, point.y))
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(steps));
+            plot_ui.points(Points::new(points.clone()).radius(3.0));
+
+            if editable && plot_ui.response().clicked() {
+                // The value at the time of the pointer (the last one before it)
+                restored = plot_ui.pointer_coordinate().map(|pointer| {
+                    points
+                        .iter()
+                        .rev()
+                        .find(|[t, _]| *t <= pointer.x)
+                        .unwrap_or(&points[0])[1]
+                });
+            }
+        });
+
+    if !editable {
+        ui.label("The parameter can't be changed (the layer is locked or it is constrained)");
+    }
+    restored
+}
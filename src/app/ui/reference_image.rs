@@ -0,0 +1,63 @@
+use crate::app::io::{IoService, OpenRequest};
+use crate::app::reference_image::{ReferenceImage, IMAGE_EXTENSIONS};
+use eframe::egui;
+use eframe::egui::Ui;
+
+/// Open an image to show under the blocks and place it: its center, the size of its pixels and
+///  how much it shows through
+pub fn ui_reference_image(
+    ui: &mut Ui,
+    reference_image: &mut Option<ReferenceImage>,
+    io: &mut IoService,
+) {
+    ui.horizontal(|ui| {
+        if ui.button("Open image…").clicked() {
+            io.open(OpenRequest {
+                title: "Open a reference image".to_string(),
+                filter_name: "Images",
+                extensions: IMAGE_EXTENSIONS,
+            });
+        }
+        ui.label("or drop one on the window");
+    });
+
+    let Some(image) = reference_image else {
+        ui.label("No image, e.g. a map screenshot or concept art to trace the shapes over");
+        return;
+    };
+
+    ui.separator();
+    let [width, height] = image.size();
+    ui.label(format!("{} ({} × {} pixels)", image.name, width, height));
+    ui.checkbox(&mut image.shown, "Show under the blocks");
+
+    let placement = &mut image.placement;
+    ui.horizontal(|ui| {
+        ui.label("Center");
+        ui.add(
+            egui::DragValue::new(&mut placement.center[0])
+                .speed(0.5)
+                .prefix("x "),
+        );
+        ui.add(
+            egui::DragValue::new(&mut placement.center[1])
+                .speed(0.5)
+                .prefix("y "),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Scale");
+        ui.add(
+            egui::DragValue::new(&mut placement.blocks_per_pixel)
+                .speed(0.01)
+                .clamp_range(0.001..=100.0)
+                .suffix(" blocks per pixel"),
+        )
+        .on_hover_text("1 for a map with a pixel for every block");
+    });
+    ui.add(egui::Slider::new(&mut placement.opacity, 0.0..=1.0).text("Opacity"));
+
+    if ui.button("Remove image").clicked() {
+        *reference_image = None;
+    }
+}
@@ -1,7 +1,18 @@
 use crate::app::control::Control;
-use crate::app::sampling::{SampleCombineMethod, SampleDistributeMethod};
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::generation::Generator;
+use crate::app::sampling::percentage_suggestion::{suggest_percentage, PercentageSuggestion};
+use crate::app::sampling::presets::SamplingPreset;
+use crate::app::sampling::sampled_parameters::LayerParameters;
+use crate::app::sampling::{active_samples, SampleCombineMethod, SampleDistributeMethod};
 use eframe::egui;
-use eframe::egui::Ui;
+use eframe::egui::{Align2, Ui};
+use egui_plot::{HLine, LineStyle, Plot, PlotPoint, Points, Text};
+use itertools::Itertools;
+
+// Layers shown below and above the current layer on the number line of the samples
+const NUMBER_LINE_RADIUS: isize = 2;
 
 /// document
 pub fn ui_sampling(
@@ -13,10 +24,54 @@ pub fn ui_sampling(
     sample_combine_method: &mut SampleCombineMethod,
     sample_distribute_method: &mut SampleDistributeMethod,
     sampling_points_control: &mut Control,
+    sampling_presets: &mut Vec<SamplingPreset>,
+    new_preset_name: &mut String,
+    stack_sampling_points: &ZVec<Vec<f64>>,
+    current_layer: isize,
+    percentage_suggestion: &mut Option<PercentageSuggestion>,
+    stack_layer_parameters: &ZVec<LayerParameters>,
+    stack_blocks: &ZVec<Blocks>,
+    stack_locked: &ZVec<bool>,
+    generator: &Generator,
 ) {
     ui.label("Vertical sampling of the code. Requires code mode to be on.");
 
     ui.add_enabled_ui(sampling_enabled, |ui| {
+        // Presets set all the options below at once
+        ui.label("Presets:");
+        ui.horizontal_wrapped(|ui| {
+            for preset in sampling_presets.iter() {
+                if ui.button(&preset.name).clicked() {
+                    *nr_samples_per_layer = preset.nr_samples_per_layer;
+                    *sample_combine_method = preset.sample_combine_method;
+                    *sample_distribute_method = preset.sample_distribute_method;
+                    *only_sample_half_of_bottom_layer = preset.only_sample_half_of_bottom_layer;
+                    *only_sample_half_of_top_layer = preset.only_sample_half_of_top_layer;
+                    sampling_points_control.set_outdated();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(new_preset_name);
+            if ui
+                .add_enabled(
+                    !new_preset_name.is_empty(),
+                    egui::Button::new("Save as preset"),
+                )
+                .clicked()
+            {
+                sampling_presets.push(SamplingPreset {
+                    name: std::mem::take(new_preset_name),
+                    nr_samples_per_layer: *nr_samples_per_layer,
+                    sample_combine_method: *sample_combine_method,
+                    sample_distribute_method: *sample_distribute_method,
+                    only_sample_half_of_bottom_layer: *only_sample_half_of_bottom_layer,
+                    only_sample_half_of_top_layer: *only_sample_half_of_top_layer,
+                });
+            }
+        });
+        ui.separator();
+
         if egui::ComboBox::from_label("Sample combination method")
             .selected_text(format!("{:}", sample_combine_method))
             .show_ui(ui, |ui| {
@@ -60,6 +115,44 @@ pub fn ui_sampling(
                     *sample_combine_method = SampleCombineMethod::Percentage(perc_slider);
                     sampling_points_control.set_outdated();
                 };
+
+                if ui
+                    .button("Suggest smoothest percentage")
+                    .on_hover_text(
+                        "The percentage with the fewest blocks that change from one layer to \
+                        the next. Generates every sample of the unlocked layers, which can take \
+                        a while.",
+                    )
+                    .clicked()
+                {
+                    *percentage_suggestion = suggest_percentage(
+                        stack_layer_parameters,
+                        stack_blocks,
+                        stack_locked,
+                        generator,
+                    );
+                }
+                if let Some(suggestion) = *percentage_suggestion {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{:.0}%: {} blocks change between layers (was {})",
+                            suggestion.percentage * 100.0,
+                            suggestion.nr_changes,
+                            suggestion.nr_changes_before
+                        ))
+                        .on_hover_text(format!(
+                            "Every percentage above {:.1}% up to {:.1}% gives the same blocks",
+                            suggestion.range[0] * 100.0,
+                            suggestion.range[1] * 100.0
+                        ));
+                        if ui.button("Use").clicked() {
+                            *sample_combine_method =
+                                SampleCombineMethod::Percentage(suggestion.percentage);
+                            *percentage_suggestion = None;
+                            sampling_points_control.set_outdated();
+                        }
+                    });
+                }
             }
             _ => {}
         }
@@ -89,6 +182,10 @@ pub fn ui_sampling(
                 only_sample_half_of_bottom_layer,
                 "Only sample half of the bottom layer",
             )
+            .on_hover_text(
+                "Sample the bottom layer only from its middle up, so that no samples are below \
+                the stack (e.g. when the shape stands on the ground)",
+            )
             .changed()
         {
             sampling_points_control.set_outdated();
@@ -99,11 +196,60 @@ pub fn ui_sampling(
                 only_sample_half_of_top_layer,
                 "Only sample half of the top layer",
             )
+            .on_hover_text(
+                "Sample the top layer only from its middle down, so that no samples are above \
+                the stack (e.g. at the top of a dome)",
+            )
             .changed()
         {
             sampling_points_control.set_outdated();
         };
 
+        // Which samples of a whole layer are taken, for the layers where it can differ
+        let lowest = stack_sampling_points.get_minimum();
+        let highest = stack_sampling_points.get_maximum();
+        egui::Grid::new("active_samples_grid").show(ui, |ui| {
+            for (name, layer) in [
+                ("Top layer", highest),
+                ("Current layer", current_layer),
+                ("Bottom layer", lowest),
+            ] {
+                if name == "Current layer" && (layer == lowest || layer == highest) {
+                    continue;
+                }
+                let Some(sampling_points) = stack_sampling_points.get(layer) else {
+                    continue;
+                };
+                ui.label(format!("{} ({})", name, layer));
+                let samples = active_samples(
+                    *sample_distribute_method,
+                    *nr_samples_per_layer,
+                    layer,
+                    &sampling_points,
+                );
+                // From the top of the layer down, like the stack
+                let marks: String = samples
+                    .iter()
+                    .rev()
+                    .map(|(_, active)| if *active { "●" } else { "○" })
+                    .join(" ");
+                let heights = sampling_points
+                    .iter()
+                    .map(|height| format!("{:.2}", height))
+                    .join(", ");
+                ui.label(marks)
+                    .on_hover_text(format!("Sampled at heights {}", heights));
+                ui.end_row();
+            }
+        });
+        ui_samples_number_line(
+            ui,
+            *sample_distribute_method,
+            *nr_samples_per_layer,
+            stack_sampling_points,
+            current_layer,
+        );
+
         ui.checkbox(
             sampling_points_control.auto(),
             "Auto recompute sampling points",
@@ -123,3 +269,86 @@ pub fn ui_sampling(
         };
     });
 }
+
+/// The sample heights of the layers around the current layer as dots on a vertical number line,
+///  with the boundaries between the layers. The samples that a layer doesn't take (like the lower
+///  half of the bottom layer) are hollow, the ends of the stack are solid lines.
+fn ui_samples_number_line(
+    ui: &mut Ui,
+    sample_distribute_method: SampleDistributeMethod,
+    nr_samples_per_layer: usize,
+    stack_sampling_points: &ZVec<Vec<f64>>,
+    current_layer: isize,
+) {
+    let lowest = stack_sampling_points.get_minimum();
+    let highest = stack_sampling_points.get_maximum();
+    let shown_lowest = (current_layer - NUMBER_LINE_RADIUS).max(lowest);
+    let shown_highest = (current_layer + NUMBER_LINE_RADIUS).min(highest);
+    let text_color = ui.visuals().text_color();
+    let current_color = ui.visuals().selection.bg_fill;
+
+    Plot::new("samples_number_line")
+        .height(40.0 * (shown_highest - shown_lowest + 1) as f32)
+        .show_axes([false, false])
+        .show_grid([false, false])
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .include_x(-1.5)
+        .include_x(1.0)
+        .include_y(shown_lowest as f64 - 0.5)
+        .include_y(shown_highest as f64 + 0.5)
+        .label_formatter(|_name, point| format!("Height {:.2}", point.y))
+        .show(ui, |plot_ui| {
+            for layer in shown_lowest..=shown_highest {
+                let color = if layer == current_layer {
+                    current_color
+                } else {
+                    text_color
+                };
+                plot_ui.text(
+                    Text::new(PlotPoint::new(-1.0, layer as f64), format!("{}", layer))
+                        .anchor(Align2::CENTER_CENTER)
+                        .color(color),
+                );
+
+                let Some(sampling_points) = stack_sampling_points.get(layer) else {
+                    continue;
+                };
+                let (active, inactive): (Vec<_>, Vec<_>) = active_samples(
+                    sample_distribute_method,
+                    nr_samples_per_layer,
+                    layer,
+                    &sampling_points,
+                )
+                .into_iter()
+                .partition(|(_, active)| *active);
+                for (samples, filled) in [(active, true), (inactive, false)] {
+                    plot_ui.points(
+                        Points::new(
+                            samples
+                                .iter()
+                                .map(|(height, _)| [0.0, *height])
+                                .collect::<Vec<_>>(),
+                        )
+                        .radius(4.0)
+                        .filled(filled)
+                        .color(color),
+                    );
+                }
+            }
+
+            // Boundaries between the layers (dashed), and the bottom and top of the stack
+            for boundary in shown_lowest..=shown_highest + 1 {
+                let height = boundary as f64 - 0.5;
+                let line = HLine::new(height).color(text_color.gamma_multiply(0.5));
+                plot_ui.hline(if boundary == lowest || boundary == highest + 1 {
+                    line.width(2.0)
+                } else {
+                    line.style(LineStyle::dashed_loose())
+                });
+            }
+        });
+}
@@ -0,0 +1,60 @@
+use crate::app::formatting;
+use crate::app::scaling::{ScaleMode, ScaleTool};
+use eframe::egui;
+use eframe::egui::Ui;
+
+/// Choose how the radii are scaled, with the area and number of blocks of the layers that are
+///  scaled for reference. Returns true if the scale should be applied.
+pub fn ui_scaling(
+    ui: &mut Ui,
+    tool: &mut ScaleTool,
+    layers_enabled: bool,
+    area: f64,
+    nr_blocks: u64,
+) -> bool {
+    if layers_enabled {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut tool.all_layers, false, "Current layer");
+            ui.selectable_value(&mut tool.all_layers, true, "All layers");
+        });
+    }
+    ui.label(format!(
+        "Area {:.1}, {} blocks",
+        area,
+        formatting::format_block_count(nr_blocks)
+    ));
+
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut tool.mode, ScaleMode::Factor, "By factor");
+        ui.selectable_value(&mut tool.mode, ScaleMode::TargetArea, "To area");
+        ui.selectable_value(&mut tool.mode, ScaleMode::TargetBlocks, "To blocks")
+            .on_hover_text("Generates the layers at several scales, which can take a while");
+    });
+    match tool.mode {
+        ScaleMode::Factor => {
+            ui.add(
+                egui::DragValue::new(&mut tool.factor)
+                    .speed(0.01)
+                    .clamp_range(0.0..=16.0)
+                    .prefix("×"),
+            );
+        }
+        ScaleMode::TargetArea => {
+            ui.add(
+                egui::DragValue::new(&mut tool.target_area)
+                    .speed(1.0)
+                    .clamp_range(0.0..=f64::INFINITY),
+            );
+        }
+        ScaleMode::TargetBlocks => {
+            ui.add(egui::DragValue::new(&mut tool.target_blocks).speed(10.0));
+        }
+    }
+
+    ui.button("Scale radii")
+        .on_hover_text(
+            "Locked layers and locked radii are kept. A target is reached with the largest scale \
+            that stays within it.",
+        )
+        .clicked()
+}
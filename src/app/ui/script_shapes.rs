@@ -0,0 +1,88 @@
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::generation::custom::CustomPredicate;
+use crate::app::generation::script_shape::{ShapeLibrary, SCRIPT_EXTENSIONS};
+use crate::app::generation::Algorithm;
+use crate::app::io::{IoService, OpenRequest};
+use eframe::egui;
+use eframe::egui::Ui;
+
+/// The shape type of the layer: the superellipse of the parameters, or a shape from a script (which
+///  is generated with the Custom algorithm). Output: whether the shape changed.
+pub fn ui_shape_type(
+    ui: &mut Ui,
+    current_layer_config: &mut SliceParameters,
+    shape_library: &mut ShapeLibrary,
+    custom_predicate: &mut CustomPredicate,
+    io: &mut IoService,
+) -> bool {
+    // The script shape is only used by the layers with the Custom algorithm
+    let active = shape_library
+        .active()
+        .filter(|_| current_layer_config.algorithm == Algorithm::Custom);
+    let mut selected = active;
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_label("Shape")
+            .selected_text(match selected {
+                Some(index) => shape_library.shapes[index].name.as_str(),
+                None => "Superellipse",
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, None, "Superellipse");
+                for (index, shape) in shape_library.shapes.iter().enumerate() {
+                    ui.selectable_value(&mut selected, Some(index), &shape.name);
+                }
+            });
+        if ui
+            .button("Open script…")
+            .on_hover_text("Add the shapes of a Rhai script (or drop one on the window)")
+            .clicked()
+        {
+            io.open(OpenRequest {
+                title: "Open a shape script".to_string(),
+                filter_name: "Rhai scripts",
+                extensions: SCRIPT_EXTENSIONS,
+            });
+        }
+    });
+
+    if selected == active {
+        return false;
+    }
+    match selected {
+        Some(_) => current_layer_config.algorithm = Algorithm::Custom,
+        // Back from a script shape
+        None => current_layer_config.algorithm = Algorithm::Centerpoint,
+    }
+    shape_library.activate(selected, custom_predicate);
+    true
+}
+
+/// Sliders for the parameters of the script shape. Output: whether a parameter changed (then the
+///  layers with the Custom algorithm have to be generated again).
+pub fn ui_shape_parameters(
+    ui: &mut Ui,
+    shape_library: &mut ShapeLibrary,
+    custom_predicate: &mut CustomPredicate,
+) -> bool {
+    let Some(index) = shape_library.active() else {
+        return false;
+    };
+
+    let mut changed = false;
+    for parameter in &mut shape_library.shapes[index].parameters {
+        changed |= ui
+            .add(
+                egui::Slider::new(
+                    &mut parameter.value,
+                    parameter.range[0]..=parameter.range[1],
+                )
+                .text(&parameter.name),
+            )
+            .changed();
+    }
+    if changed {
+        shape_library.activate(Some(index), custom_predicate);
+    }
+    changed
+}
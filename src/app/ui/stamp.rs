@@ -0,0 +1,109 @@
+use crate::app::pixel_font::unsupported_chars;
+use crate::app::stamp::{Stamp, StampMode, StampTool};
+use eframe::egui;
+use eframe::egui::Ui;
+
+/// Place text in blocks on the current layer: click in the viewport (while this is shown) for the
+///  position, then stamp it. Lists the stamps on the current layer. Returns true if the stamps of
+///  the layer changed (then its blocks are outdated).
+pub fn ui_text_stamp(
+    ui: &mut Ui,
+    tool: &mut StampTool,
+    stamps: &mut Vec<Stamp>,
+    current_layer: isize,
+    locked: bool,
+) -> bool {
+    let mut changed = false;
+
+    ui.label("Click in the viewport to place the bottom left corner of the text.");
+    ui.add(
+        egui::TextEdit::multiline(&mut tool.text)
+            .hint_text("GATE")
+            .desired_rows(2),
+    );
+    let unsupported = unsupported_chars(&tool.text);
+    if !unsupported.is_empty() {
+        ui.label(format!(
+            "Not in the font (skipped): {}",
+            unsupported.iter().collect::<String>()
+        ))
+        .on_hover_text("Letters, digits, . , : ! ? - + = / # and the arrows ↑ ↓ ← → and ■");
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Size");
+        ui.add(
+            egui::DragValue::new(&mut tool.scale)
+                .clamp_range(1..=8)
+                .suffix(" blocks per pixel"),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut tool.mode, StampMode::Add, "Add blocks");
+        ui.selectable_value(&mut tool.mode, StampMode::Remove, "Remove blocks");
+    });
+    ui.horizontal(|ui| match &mut tool.position {
+        None => {
+            ui.label("No position yet");
+        }
+        Some([x, y]) => {
+            ui.label("Position");
+            ui.add(egui::DragValue::new(x).prefix("x "));
+            ui.add(egui::DragValue::new(y).prefix("y "));
+        }
+    });
+
+    let stamp = tool.stamp(current_layer);
+    let can_stamp = !locked
+        && stamp
+            .as_ref()
+            .is_some_and(|stamp| !stamp.coords().is_empty());
+    if ui
+        .add_enabled(
+            can_stamp,
+            egui::Button::new(format!("Stamp on layer {}", current_layer)),
+        )
+        .on_disabled_hover_text(if locked {
+            "The layer is locked"
+        } else {
+            "Click a position in the viewport and enter some text"
+        })
+        .clicked()
+    {
+        stamps.extend(stamp);
+        changed = true;
+    }
+
+    // The stamps on this layer, in the order they are applied
+    ui.separator();
+    let mut removed = None;
+    for (index, stamp) in stamps.iter().enumerate() {
+        if stamp.layer != current_layer {
+            continue;
+        }
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "\"{}\" at ({}, {}), {}",
+                stamp.text.replace('\n', " "),
+                stamp.position[0],
+                stamp.position[1],
+                match stamp.mode {
+                    StampMode::Add => "adds",
+                    StampMode::Remove => "removes",
+                }
+            ));
+            if !locked && ui.small_button("🗑").on_hover_text("Remove").clicked() {
+                removed = Some(index);
+            }
+        });
+    }
+    if let Some(index) = removed {
+        stamps.remove(index);
+        changed = true;
+    }
+    if !stamps.iter().any(|stamp| stamp.layer == current_layer) {
+        ui.label("No stamps on this layer");
+    }
+
+    changed
+}
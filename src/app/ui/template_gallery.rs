@@ -0,0 +1,79 @@
+use crate::app::colors::*;
+use crate::app::generation::Generator;
+use crate::app::metrics::vertical_slice::{vertical_slice, SliceAxis};
+use crate::app::plotting;
+use crate::app::templates::Template;
+use eframe::egui;
+use eframe::egui::{Stroke, Ui};
+use egui_plot::Plot;
+
+const THUMBNAILS_PER_ROW: usize = 2;
+
+/// Grid of the example projects, each with a side view of the blocks (through the center). Returns
+///  the index of the template to start a new project from, if any.
+pub fn ui_template_gallery(
+    ui: &mut Ui,
+    templates: &mut [Template],
+    generator: &Generator,
+) -> Option<usize> {
+    ui.label("Start a new project from an example. This replaces all layers and code.");
+    ui.separator();
+
+    let mut chosen = None;
+    egui::Grid::new("template_gallery_grid").show(ui, |ui| {
+        for (i, template) in templates.iter_mut().enumerate() {
+            let (layer_lowest, layer_highest) = (template.layer_lowest, template.layer_highest);
+            let preview = template.generate_preview(generator);
+            let slice = vertical_slice(preview, SliceAxis::X, 0, layer_lowest, layer_highest);
+            let nr_blocks: u64 = preview.data.iter().map(|b| b.get_nr_blocks()).sum();
+
+            ui.vertical(|ui| {
+                ui.strong(&template.name);
+                let background = viewport_background(ui.visuals().dark_mode);
+                ui.visuals_mut().extreme_bg_color = background;
+                Plot::new(("template_thumbnail", i))
+                    .data_aspect(1.0)
+                    .width(200.0)
+                    .height(150.0)
+                    .show_axes([false, false])
+                    .show_grid([false, false])
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .allow_scroll(false)
+                    .allow_boxed_zoom(false)
+                    .show_x(false)
+                    .show_y(false)
+                    .show(ui, |plot_ui| {
+                        for coord in slice.get_all_block_coords() {
+                            plot_ui.polygon(
+                                plotting::square_at_coords(coord)
+                                    .stroke(Stroke {
+                                        width: 1.0,
+                                        color: COLOR_WIRE,
+                                    })
+                                    .fill_color(COLOR_BLOCKS),
+                            );
+                        }
+                    });
+
+                ui.horizontal(|ui| {
+                    if ui.button("New from template").clicked() {
+                        chosen = Some(i);
+                    }
+                    ui.label(format!(
+                        "{} layers, {} blocks",
+                        layer_highest - layer_lowest + 1,
+                        nr_blocks
+                    ));
+                });
+                ui.small(&template.description);
+            });
+
+            if i % THUMBNAILS_PER_ROW == THUMBNAILS_PER_ROW - 1 {
+                ui.end_row();
+            }
+        }
+    });
+
+    chosen
+}
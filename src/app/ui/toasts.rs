@@ -0,0 +1,58 @@
+use crate::app::io::IoService;
+use eframe::egui;
+use eframe::egui::{Align2, Color32, Context, Id, LayerId, Order, Vec2};
+use std::time::Duration;
+
+/// Notifications of finished file operations in the bottom right corner, above the status bar
+pub fn ui_toasts(ctx: &Context, io: &IoService) {
+    if io.toasts.is_empty() && io.nr_pending == 0 {
+        return;
+    }
+
+    egui::Area::new("toasts")
+        .anchor(Align2::RIGHT_BOTTOM, Vec2::new(-10.0, -40.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            if io.nr_pending > 0 {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Waiting for file dialog");
+                    });
+                });
+            }
+            for toast in &io.toasts {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    if toast.is_error {
+                        ui.colored_label(ui.visuals().error_fg_color, &toast.text);
+                    } else {
+                        ui.label(&toast.text);
+                    }
+                });
+            }
+        });
+
+    // Remove the notifications when they expire, also if nothing else happens
+    let time = ctx.input(|i| i.time);
+    if let Some(expires) = io.toasts.iter().map(|toast| toast.expires).reduce(f64::min) {
+        ctx.request_repaint_after(Duration::from_secs_f64((expires - time).max(0.0)));
+    }
+}
+
+/// Darken the window while files are dragged over it, and say what dropping them does
+pub fn ui_drop_hint(ctx: &Context) {
+    if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+        return;
+    }
+
+    let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("drop_hint")));
+    let screen_rect = ctx.screen_rect();
+    painter.rect_filled(screen_rect, 0.0, Color32::from_black_alpha(192));
+    painter.text(
+        screen_rect.center(),
+        Align2::CENTER_CENTER,
+        "Drop a file with block coordinates to fit a shape to them, or an image to trace over",
+        egui::TextStyle::Heading.resolve(&ctx.style()),
+        Color32::WHITE,
+    );
+}
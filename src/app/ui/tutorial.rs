@@ -0,0 +1,71 @@
+use crate::app::colors::COLOR_TUTORIAL_HIGHLIGHT;
+use crate::app::tutorial::Tutorial;
+use eframe::egui;
+use eframe::egui::{Align2, Context, Id, LayerId, Order, Rounding, Stroke, Vec2};
+
+/// Outline the part of the window of the current step and explain it in a window next to it
+pub fn ui_tutorial(ctx: &Context, tutorial: &mut Tutorial) {
+    let Some(step) = tutorial.step else {
+        return;
+    };
+
+    let screen = ctx.screen_rect();
+    let target = tutorial
+        .target
+        .unwrap_or(screen.shrink(screen.width() / 4.0));
+    ctx.layer_painter(LayerId::new(
+        Order::Foreground,
+        Id::new("tutorial_highlight"),
+    ))
+    .rect_stroke(
+        target.expand(2.0),
+        Rounding::same(4.0),
+        Stroke::new(3.0, COLOR_TUTORIAL_HIGHLIGHT),
+    );
+
+    // Next to the target, on the side with the most room
+    let (pivot, position) = if target.center().x > screen.center().x {
+        (Align2::RIGHT_TOP, target.left_top() - Vec2::new(10.0, 0.0))
+    } else {
+        (Align2::LEFT_TOP, target.left_top() + Vec2::new(10.0, 10.0))
+    };
+
+    egui::Window::new(format!("Tutorial: {}", step.title()))
+        .id(Id::new("tutorial_window"))
+        .pivot(pivot)
+        .fixed_pos(position)
+        .collapsible(false)
+        .resizable(false)
+        .default_width(300.0)
+        .show(ctx, |ui| {
+            ui.label(step.text());
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(step.progress());
+                if ui
+                    .add_enabled(step.previous().is_some(), egui::Button::new("Back"))
+                    .clicked()
+                {
+                    tutorial.step = step.previous();
+                }
+                match step.next() {
+                    Some(next) => {
+                        if ui.button("Next").clicked() {
+                            tutorial.step = Some(next);
+                        }
+                        if ui.button("Skip").clicked() {
+                            tutorial.stop();
+                        }
+                    }
+                    None => {
+                        if ui.button("Finish").clicked() {
+                            tutorial.stop();
+                        }
+                    }
+                }
+            });
+        });
+
+    // Found again on the next frame (the step may have changed)
+    tutorial.target = None;
+}
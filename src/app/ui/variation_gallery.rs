@@ -0,0 +1,121 @@
+use crate::app::colors::*;
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::generation::Generator;
+use crate::app::plotting;
+use crate::app::variations::VariationGallery;
+use eframe::egui;
+use eframe::egui::{Stroke, Ui};
+use egui_plot::Plot;
+
+const THUMBNAILS_PER_ROW: usize = 3;
+
+/// Grid of thumbnails of random variations of the current layer (the locked parameters are not
+///  varied). Clicking "Use" on a thumbnail adopts its offset and tilt for the current layer.
+///  Output: whether a variation was adopted (then the layer has to be generated again).
+pub fn ui_variation_gallery(
+    ui: &mut Ui,
+    current_layer_config: &mut SliceParameters,
+    gallery: &mut VariationGallery,
+    random_seed: u64,
+    locked_parameters: &[Parameter],
+    generator: &Generator,
+) -> bool {
+    ui.horizontal(|ui| {
+        ui.label("Seed");
+        ui.add(egui::DragValue::new(&mut gallery.seed));
+        if ui.button("Next seed").clicked() {
+            gallery.seed = gallery.seed.wrapping_add(1);
+            gallery.generate(
+                current_layer_config,
+                random_seed,
+                locked_parameters,
+                generator,
+            );
+        }
+    });
+    ui.add(egui::Slider::new(&mut gallery.offset_amplitude, 0.0..=1.0).text("Offset variation"));
+    ui.add(
+        egui::Slider::new(&mut gallery.tilt_amplitude, 0.0..=0.5).text("Tilt variation (radians)"),
+    );
+    ui.add(egui::Slider::new(&mut gallery.nr_variations, 1..=12).text("Nr. variations"));
+    if ui.button("Generate").clicked() || gallery.variations.is_empty() {
+        gallery.generate(
+            current_layer_config,
+            random_seed,
+            locked_parameters,
+            generator,
+        );
+    }
+
+    ui.separator();
+
+    let mut adopted = None;
+    egui::Grid::new("variation_gallery_grid").show(ui, |ui| {
+        for (i, (variation, blocks)) in gallery.variations.iter().enumerate() {
+            ui.vertical(|ui| {
+                let background = viewport_background(ui.visuals().dark_mode);
+                ui.visuals_mut().extreme_bg_color = background;
+                Plot::new(("variation_thumbnail", i))
+                    .data_aspect(generator.cell_aspect as f32)
+                    .width(150.0)
+                    .height(150.0)
+                    .show_axes([false, false])
+                    .show_grid([false, false])
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .allow_scroll(false)
+                    .allow_boxed_zoom(false)
+                    .show_x(false)
+                    .show_y(false)
+                    .show(ui, |plot_ui| {
+                        for coord in blocks.get_all_block_coords() {
+                            plot_ui.polygon(
+                                plotting::square_at_coords(coord)
+                                    .stroke(Stroke {
+                                        width: 1.0,
+                                        color: COLOR_WIRE,
+                                    })
+                                    .fill_color(COLOR_BLOCKS),
+                            );
+                        }
+                        plot_ui.line(
+                            plotting::superellipse_at_coords(variation, generator.cell_aspect)
+                                .color(with_contrast(COLOR_TARGET_SHAPE, background)),
+                        );
+                    });
+
+                ui.horizontal(|ui| {
+                    if ui.button("Use").clicked() {
+                        adopted = Some(variation.clone());
+                    }
+                    ui.label(format!("{} blocks", blocks.get_nr_blocks()));
+                });
+                ui.small(format!(
+                    "offset ({:.2}, {:.2}), tilt {:.2}",
+                    variation.center_offset_x, variation.center_offset_y, variation.tilt
+                ));
+            });
+
+            if i % THUMBNAILS_PER_ROW == THUMBNAILS_PER_ROW - 1 {
+                ui.end_row();
+            }
+        }
+    });
+
+    if let Some(variation) = adopted {
+        current_layer_config.center_offset_x = variation.center_offset_x;
+        current_layer_config.center_offset_y = variation.center_offset_y;
+        current_layer_config.tilt = variation.tilt;
+
+        // The adopted variation is the new starting point
+        gallery.generate(
+            current_layer_config,
+            random_seed,
+            locked_parameters,
+            generator,
+        );
+        return true;
+    }
+    false
+}
@@ -0,0 +1,94 @@
+use crate::app::colors::*;
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::lean::LeanPreview;
+use crate::app::metrics::vertical_slice::{vertical_slice, SliceAxis};
+use crate::app::plotting;
+use eframe::egui;
+use eframe::egui::{Stroke, Ui};
+use egui_plot::{uniform_grid_spacer, HLine, Line, Plot, PlotPoints};
+
+/// Side view of the stack: pick a vertical plane through a column of blocks and plot which
+///  blocks of each layer lie in it (horizontal coordinate against layer number).
+pub fn ui_vertical_slice(
+    ui: &mut Ui,
+    stack_blocks: &ZVec<Blocks>,
+    current_layer: isize,
+    slice_axis: &mut SliceAxis,
+    slice_coord: &mut isize,
+    lean_preview: Option<&LeanPreview>, // the axis of the leaning stack, projected on the plane
+) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_label("Plane")
+            .selected_text(format!("{:}", slice_axis))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(slice_axis, SliceAxis::X, "Constant x");
+                ui.selectable_value(slice_axis, SliceAxis::Y, "Constant y");
+            });
+        ui.add(egui::DragValue::new(slice_coord).speed(0.05));
+    });
+
+    let slice = vertical_slice(
+        stack_blocks,
+        *slice_axis,
+        *slice_coord,
+        stack_blocks.get_minimum(),
+        stack_blocks.get_maximum(),
+    );
+
+    if ui.button("Copy as text").clicked() {
+        ui.output_mut(|o| o.copied_text = slice.to_text());
+    }
+
+    let background = viewport_background(ui.visuals().dark_mode);
+    ui.visuals_mut().extreme_bg_color = background;
+    Plot::new("vertical_slice_plot")
+        .data_aspect(1.0)
+        .x_grid_spacer(uniform_grid_spacer(|_gridinput| [1.0, 5.0, 10.0]))
+        .y_grid_spacer(uniform_grid_spacer(|_gridinput| [1.0, 5.0, 10.0]))
+        .allow_boxed_zoom(false)
+        .show_axes([false, true])
+        .label_formatter(|_name, mouse_coord| {
+            format!(
+                "{0:.0}, layer {1:.0}",
+                mouse_coord.x.floor(),
+                mouse_coord.y.floor()
+            )
+        })
+        .height(300.0)
+        .show(ui, |plot_ui| {
+            for coord in slice.get_all_block_coords() {
+                plot_ui.polygon(
+                    plotting::square_at_coords(coord)
+                        .stroke(Stroke {
+                            width: 1.0,
+                            color: COLOR_WIRE,
+                        })
+                        .fill_color(COLOR_BLOCKS),
+                );
+            }
+
+            // The axis through the centers of the layers, seen from the side
+            if let Some(lean_preview) = lean_preview {
+                let axis: PlotPoints = lean_preview
+                    .axis()
+                    .map(|([x, y], height)| match slice_axis {
+                        SliceAxis::X => [y, height],
+                        SliceAxis::Y => [x, height],
+                    })
+                    .collect();
+                plot_ui.line(
+                    Line::new(axis)
+                        .color(with_contrast(COLOR_LEAN_AXIS, background))
+                        .width(2.0),
+                );
+            }
+
+            // Mark the current layer
+            plot_ui.hline(
+                HLine::new(current_layer as f64 + 0.5)
+                    .color(with_contrast(COLOR_VERTICAL_SLICE, background))
+                    .width(1.0),
+            );
+        });
+}
@@ -2,54 +2,179 @@ use crate::app::colors::*;
 use crate::app::data_structures::blocks::Blocks;
 use crate::app::data_structures::slice_parameters::SliceParameters;
 use crate::app::data_structures::symmetry_type::SymmetryType;
+use crate::app::generation::cell_aspect::{angle_to_cells, to_cells};
+use crate::app::lean::LeanPreview;
+use crate::app::log::log_info;
+use crate::app::math::square_max::pad_square;
+use crate::app::metrics::boundary_segments::{shape_label, BoundarySegments};
+use crate::app::metrics::boundary_tour::BoundaryTour;
 use crate::app::metrics::convex_hull::line_segments_from_conv_hull;
+use crate::app::metrics::depth_shading::MAX_SHADING_DEPTH;
+use crate::app::metrics::vertical_slice::SliceAxis;
 use crate::app::plotting::bounds_from_square;
 use crate::app::sampling::sampled_parameters::LayerParameters;
-use crate::app::view::View;
-use crate::app::{generation, plotting};
-use eframe::egui::{Stroke, Ui, Vec2b};
+use crate::app::stamp::StampMode;
+use crate::app::sub_blocks::SubBlock;
+use crate::app::view::{BlockOverlay, View};
+use crate::app::{formatting, generation, plotting};
+use eframe::egui::{Align2, Color32, Stroke, Ui, Vec2b};
 use egui_plot::{
-    uniform_grid_spacer, HLine, Line, Plot, PlotBounds, PlotPoint, PlotPoints, Points, Text, VLine,
+    HLine, Line, LineStyle, MarkerShape, Plot, PlotBounds, PlotImage, PlotPoint, PlotPoints,
+    Points, Text, VLine,
 };
-use itertools::izip;
-use std::f64::consts::PI;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// The layer that is shown in the viewport
+pub struct ViewportLayer<'a> {
+    pub slice_parameters: SliceParameters,
+    pub sampled_parameters: LayerParameters,
+    pub blocks: Option<&'a Blocks>,
+    pub sampling_enabled: bool,
+    pub sampling_points: &'a [f64], // heights of the samples of the layer
+    pub current_layer: isize,
+}
+
+/// Zoom options (used for double click to reset zoom), with the boxes that can be zoomed to
+pub struct ViewportZoom<'a> {
+    pub reset_zoom_once: &'a mut bool,
+    pub reset_zoom_continuous: &'a mut bool,
+    pub global_bounding_box: [[f64; 2]; 2], //todo: rename
+    pub global_blocks_bounding_box: [[f64; 2]; 2],
+    pub layer_bounding_box: [[f64; 2]; 2],
+    pub layer_blocks_bounding_box: [[f64; 2]; 2],
+}
+
+/// Metrics of the shown layer (the 3d ones sliced at the layer)
+pub struct ViewportMetrics<'a> {
+    pub boundary_2d: Option<&'a Blocks>,
+    pub interior_2d: Option<&'a Blocks>,
+    pub complement_2d: Option<&'a Blocks>,
+    pub boundary_3d_slice: Option<&'a Blocks>,
+    pub interior_3d_slice: Option<&'a Blocks>,
+    pub holes_2d: Option<&'a Blocks>,
+    pub holes_3d_slice: Option<&'a Blocks>,
+    pub projection_union: Option<&'a Blocks>,
+    pub projection_intersection: Option<&'a Blocks>,
+    pub convex_hull: &'a [[f64; 2]],
+    pub outer_corners: &'a [[f64; 2]],
+    pub thin_necks: &'a [[f64; 2]],
+    pub boundary_tour: &'a BoundaryTour,
+    pub boundary_segments: &'a BoundarySegments,
+    pub symmetry_type: &'a SymmetryType,
+    pub octant_counts: &'a [f64; 8],
+    pub center_coord: &'a [f64; 2],
+    pub distance_bands: Option<&'a [([f64; 2], usize)]>, // while shown
+}
+
+/// Things drawn besides the layer and its metrics, each only when it is turned on
+pub struct ViewportPreviews<'a> {
+    pub vertical_slice_plane: Option<(SliceAxis, isize)>,
+    pub pinned_outline: Option<SliceParameters>,
+    pub between_layers: Option<(f64, SliceParameters)>, // height and shape
+    pub between_layers_blocks: Option<&'a Blocks>,
+    pub sub_blocks: Option<&'a [([f64; 2], SubBlock)]>,
+    pub clicked_points: Option<(&'a [[f64; 2]], Option<SliceParameters>)>, // with the shape through them
+    pub stamp_preview: Option<(&'a [[isize; 2]], StampMode)>, // blocks of the text stamp being placed
+    pub block_depths: Option<&'a [([f64; 2], usize)]>, // to shade the blocks by their depth in the stack
+    pub reference_image: Option<PlotImage>,            // drawn under everything else
+    pub script_outline: Option<&'a [[f64; 2]]>, // outline of the script shape, instead of the superellipse
+    pub lean_preview: Option<&'a LeanPreview>,
+}
 
 pub fn ui_viewport(
     ui: &mut Ui,
-    slice_parameters: SliceParameters,
-    sampled_parameters: LayerParameters,
-    blocks: Option<&Blocks>,
-    sampling_enabled: bool,
+    layer: ViewportLayer,
     view: &View,
+    cell_aspect: f64,
+    zoom: ViewportZoom,
+    metrics: ViewportMetrics,
+    previews: ViewportPreviews,
+) -> Option<[f64; 2]> {
+    let ViewportLayer {
+        slice_parameters,
+        sampled_parameters,
+        blocks,
+        sampling_enabled,
+        sampling_points,
+        current_layer,
+    } = layer;
+    let ViewportZoom {
+        reset_zoom_once,
+        reset_zoom_continuous,
+        global_bounding_box,
+        global_blocks_bounding_box,
+        layer_bounding_box,
+        layer_blocks_bounding_box,
+    } = zoom;
+    let ViewportMetrics {
+        boundary_2d,
+        interior_2d,
+        complement_2d,
+        boundary_3d_slice,
+        interior_3d_slice,
+        holes_2d,
+        holes_3d_slice,
+        projection_union,
+        projection_intersection,
+        convex_hull,
+        outer_corners,
+        thin_necks,
+        boundary_tour,
+        boundary_segments,
+        symmetry_type,
+        octant_counts,
+        center_coord,
+        distance_bands,
+    } = metrics;
+    let ViewportPreviews {
+        vertical_slice_plane,
+        pinned_outline,
+        between_layers,
+        between_layers_blocks,
+        sub_blocks,
+        clicked_points,
+        stamp_preview,
+        block_depths,
+        reference_image,
+        script_outline,
+        lean_preview,
+    } = previews;
+
+    let background = viewport_background(ui.visuals().dark_mode);
+    ui.visuals_mut().extreme_bg_color = background;
+    // The lines drawn over the blocks have to stand out against the background in either theme
+    let readable = move |color| with_contrast(color, background);
+    let (minecraft_axes, flip_z) = (view.minecraft_axes, view.flip_z);
+    let (world_anchor, [tx, ty]) = (view.world_anchor, view.world_translation());
 
-    // Zoom options (used for double click to reset zoom)
-    reset_zoom_once: &mut bool,
-    reset_zoom_continuous: &mut bool,
-
-    // Metrics
-    boundary_2d: Option<&Blocks>,
-    interior_2d: Option<&Blocks>,
-    complement_2d: Option<&Blocks>,
-    boundary_3d_slice: Option<&Blocks>,
-    interior_3d_slice: Option<&Blocks>,
-    convex_hull: &Vec<[f64; 2]>,
-    outer_corners: &Vec<[f64; 2]>,
-    symmetry_type: &SymmetryType,
-    center_coord: &[f64; 2],
-    global_bounding_box: &[[f64; 2]; 2], //todo: rename
-) {
-    ui.visuals_mut().extreme_bg_color = COLOR_VIEWPORT_BACKGROUND;
+    // While points are being clicked (or a stamp placed), a click adds the point under the pointer
+    let mut clicked_point = None;
 
     Plot::new("my_plot")
-        .data_aspect(1.0) // so that squares in the rasterization always look square in the viewport
-        // Grid lines of increasing thickness at distance 1.0, 5.0, 10.0 for counting
-        .x_grid_spacer(uniform_grid_spacer(|_gridinput| [1.0, 5.0, 10.0]))
-        .y_grid_spacer(uniform_grid_spacer(|_gridinput| [1.0, 5.0, 10.0]))
+        .data_aspect(cell_aspect as f32) // so that cells in the rasterization look like they do in the game
+        // We draw the grid ourselves so that the steps and colors can be configured
+        .show_grid([false, false])
         .allow_boxed_zoom(false)
         // We don't need this, there's a maximal reasonable zoom in level and the reasonable zoom out level is only as big as the circle we're generating
         .auto_bounds(Vec2b::from([false, false]))
         .allow_double_click_reset(false) // we do this ourselves
         .label_formatter(move |_name, mouse_coord| {
+            // Minecraft coordinates are block coordinates, like on the F3 screen
+            if minecraft_axes {
+                return format!(
+                    "X {}, Z {}",
+                    mouse_coord.x.floor() as isize + tx,
+                    formatting::minecraft_z(mouse_coord.y.floor() as isize + ty, flip_z)
+                );
+            }
+            if world_anchor {
+                return format!(
+                    "{}, {}",
+                    mouse_coord.x.floor() as isize + tx,
+                    mouse_coord.y.floor() as isize + ty
+                );
+            }
+
             // if !name.is_empty() {  // Can condition formatting only on name of object! So if we want to have different tooltips for different objects this is what we must do
             //     format!("{}: {:.*}%", name, 1, value.y)
             // } else {
@@ -61,14 +186,32 @@ pub fn ui_viewport(
                 mouse_coord.y.trunc()
             ) // Use trunc instead of floor for symmetry preservation around the axis! Nasty but works
         })
-        .show_axes([false, false]) // Don't show number axes
+        .show_axes([minecraft_axes, minecraft_axes]) // Only show number axes for Minecraft coordinates
+        .x_axis_label("X (east)")
+        .y_axis_label(if flip_z { "Z (north)" } else { "Z (south)" })
+        .x_axis_formatter(move |mark, _, _| format!("{}", mark.value + tx as f64 + 0.0))
+        .y_axis_formatter(move |mark, _, _| {
+            // The axis marks are on block edges, where Z = -y (adding 0.0 avoids printing -0)
+            if flip_z {
+                format!("{}", mark.value + ty as f64 + 0.0)
+            } else {
+                format!("{}", -(mark.value + ty as f64) + 0.0)
+            }
+        })
         .show(ui, |plot_ui| {
             // Reset zoom (approximates default behaviour, but we get to specify the action of automatic zooming
             if *reset_zoom_once || *reset_zoom_continuous {
-                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
-                    global_bounding_box[0],
-                    global_bounding_box[1],
-                ));
+                let zoom_box = pad_square(
+                    match (view.zoom_to_layer, view.zoom_to_blocks) {
+                        (false, false) => global_bounding_box,
+                        (false, true) => global_blocks_bounding_box,
+                        (true, false) => layer_bounding_box,
+                        (true, true) => layer_blocks_bounding_box,
+                    },
+                    view.zoom_padding,
+                    view.zoom_margin,
+                );
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max(zoom_box[0], zoom_box[1]));
                 *reset_zoom_once = false
             }
 
@@ -76,120 +219,342 @@ pub fn ui_viewport(
                 *reset_zoom_continuous = false
             }
 
+            if (clicked_points.is_some() || stamp_preview.is_some()) && plot_ui.response().clicked()
+            {
+                clicked_point = plot_ui
+                    .pointer_coordinate()
+                    .map(|pointer| [pointer.x, pointer.y]);
+            }
+
             if plot_ui.response().double_clicked() {
                 *reset_zoom_continuous = true // not sure if best to reset zoom once or reset zoom continuously
             }
 
+            // Right click copies the coordinates of the block under the pointer
+            if plot_ui.response().secondary_clicked() {
+                if let Some(pointer) = plot_ui.pointer_coordinate() {
+                    let text = formatting::format_block_coord(
+                        &view.coord_copy_format,
+                        [
+                            pointer.x.floor() as isize + tx,
+                            pointer.y.floor() as isize + ty,
+                        ],
+                        current_layer,
+                        flip_z,
+                    );
+                    log_info(format!("Copied coordinates \"{}\"", text));
+                    plot_ui.ctx().output_mut(|o| o.copied_text = text);
+                }
+            }
+
+            // The reference image to trace over, below everything else
+            if let Some(reference_image) = reference_image {
+                plot_ui.image(reference_image);
+            }
+
+            // Grid lines, below the blocks. Lines closer together than a few pixels are
+            //  pointless (and expensive)
+            {
+                let pixels_per_block = plot_ui.transform().dpos_dvalue_x().abs();
+                let min_spacing = if view.hide_fine_grid { 8.0 } else { 2.0 } / pixels_per_block;
+                let (vlines, hlines) = plotting::grid_lines(
+                    plot_ui.plot_bounds(),
+                    view.grid_steps,
+                    view.grid_colors,
+                    min_spacing,
+                    [tx, ty],
+                    view.grid_lattice,
+                );
+                for vline in vlines {
+                    plot_ui.vline(vline);
+                }
+                for hline in hlines {
+                    plot_ui.hline(hline);
+                }
+            }
+
             // * Viewport plotting * //
-            // Draw order should be largest to smallest, so
-            // 1. blocks
-            // 2. complement
-            // 3. 3d boundary
-            // 4. 2d boundary
-            // 5. 2d interior
-            // 6. 3d interior
-            // then geometric overlays like the target shape, center, etc.
-
-            // First draw the blocks (the for loop is to avoid duplicate code)
-            for (view, option_blocks, color) in izip!(
-                [
-                    view.blocks,
-                    view.complement,
-                    view.boundary_3d,
-                    view.boundary_2d,
-                    view.interior_2d,
-                    view.interior_3d,
-                    view.center_blocks,
-                ],
-                [
-                    blocks,
-                    complement_2d,
-                    boundary_3d_slice,
-                    boundary_2d,
-                    interior_2d,
-                    interior_3d_slice,
-                    blocks.map(|b| b.get_center_blocks()).as_ref(), // update with other metrics?
-                ],
-                [
-                    COLOR_BLOCKS,
-                    COLOR_COMPLEMENT_2D,
-                    COLOR_BOUNDARY_3D,
-                    COLOR_BOUNDARY_2D,
-                    COLOR_INTERIOR_2D,
-                    COLOR_INTERIOR_3D,
-                    COLOR_CENTER_BLOCKS,
-                ]
-            ) {
+            // First draw the sets of blocks, in the order of the overlays in the view (largest to
+            //  smallest by default). Then geometric overlays like the target shape, center, etc.
+            //  Only the blocks themselves are shaded by the block pattern.
+            let center_blocks = blocks.map(|b| b.get_center_blocks()); // update with other metrics?
+            let supports = match (view.supports, blocks, interior_2d) {
+                (true, Some(blocks), Some(interior)) => Some(
+                    view.support_pattern
+                        .get_supports(interior, blocks.get_center_coord()),
+                ),
+                _ => None,
+            };
+            for style in &view.overlays {
+                if !style.overlay.is_shown(view) {
+                    continue;
+                }
+                let (option_blocks, color) = match style.overlay {
+                    BlockOverlay::ProjectionUnion => (projection_union, COLOR_PROJECTION_UNION),
+                    BlockOverlay::Blocks => (blocks, COLOR_BLOCKS),
+                    BlockOverlay::BetweenLayers => (between_layers_blocks, COLOR_BETWEEN_LAYERS),
+                    BlockOverlay::ProjectionIntersection => {
+                        (projection_intersection, COLOR_PROJECTION_INTERSECTION)
+                    }
+                    BlockOverlay::Complement => (complement_2d, COLOR_COMPLEMENT_2D),
+                    BlockOverlay::Holes3d => (holes_3d_slice, COLOR_HOLES_3D),
+                    BlockOverlay::Holes2d => (holes_2d, COLOR_HOLES_2D),
+                    BlockOverlay::Boundary3d => (boundary_3d_slice, COLOR_BOUNDARY_3D),
+                    BlockOverlay::Boundary2d => (boundary_2d, COLOR_BOUNDARY_2D),
+                    BlockOverlay::Interior2d => (interior_2d, COLOR_INTERIOR_2D),
+                    BlockOverlay::Interior3d => (interior_3d_slice, COLOR_INTERIOR_3D),
+                    BlockOverlay::Supports => (supports.as_ref(), COLOR_SUPPORTS),
+                    BlockOverlay::CenterBlocks => (center_blocks.as_ref(), COLOR_CENTER_BLOCKS),
+                };
+                let patterned = style.overlay == BlockOverlay::Blocks;
+                let wire = if style.wireframe {
+                    Stroke {
+                        width: view.wire_width,
+                        color: view.wire_color.gamma_multiply(style.opacity),
+                    }
+                } else {
+                    Stroke::NONE
+                };
+
                 // test if basic culling speeds up rendering all blocks for radii ~>100
                 // test: can the per-coord blocks be made persistent (up to change of generated shape)
                 //  with ids somehow?
-                if view {
-                    if let Some(blocks) = option_blocks {
-                        for coord in blocks.get_all_block_coords() {
-                            plot_ui.polygon(
-                                plotting::square_at_coords(coord)
-                                    .stroke(Stroke {
-                                        width: 1.0,
-                                        color: COLOR_WIRE,
-                                    })
-                                    .fill_color(color),
-                            );
+                if let Some(blocks) = option_blocks {
+                    // Blocks deeper inside the stack are darker
+                    let coords: Vec<([f64; 2], usize)> = match block_depths {
+                        Some(block_depths) if patterned => block_depths.to_vec(),
+                        _ => blocks
+                            .get_all_block_coords()
+                            .into_iter()
+                            .map(|coord| (coord, 0))
+                            .collect(),
+                    };
+                    for (coord, depth) in coords {
+                        let fill_color = if patterned
+                            && view
+                                .block_pattern
+                                .highlights([coord[0] + tx as f64, coord[1] + ty as f64])
+                        {
+                            COLOR_BLOCKS_PATTERN
+                        } else {
+                            color
+                        };
+                        let fill_color = linear_gradient(
+                            fill_color,
+                            COLOR_DEPTH_SHADE,
+                            depth as f64 / MAX_SHADING_DEPTH as f64,
+                        );
+                        plot_ui.polygon(
+                            plotting::square_at_coords(coord)
+                                .stroke(wire)
+                                .fill_color(fill_color.gamma_multiply(style.opacity)),
+                        );
+                    }
+                }
+            }
+
+            // Slabs and stairs as seen from above: the full height part of a stair is drawn darker
+            //  than its slab part
+            if let Some(sub_blocks) = sub_blocks {
+                let wire = Stroke {
+                    width: view.wire_width,
+                    color: view.wire_color,
+                };
+                for (coord, sub_block) in sub_blocks {
+                    let parts = match *sub_block {
+                        SubBlock::Full => vec![(*coord, [1.0, 1.0], COLOR_SUB_BLOCK_FULL)],
+                        SubBlock::Slab { top: false } => {
+                            vec![(*coord, [1.0, 1.0], COLOR_SUB_BLOCK_BOTTOM)]
+                        }
+                        SubBlock::Slab { top: true } => {
+                            vec![(*coord, [1.0, 1.0], COLOR_SUB_BLOCK_TOP)]
+                        }
+                        SubBlock::Stair {
+                            facing,
+                            upside_down,
+                        } => {
+                            let (full_corner, size) = facing.half(*coord);
+                            let (slab_corner, _) = facing.opposite().half(*coord);
+                            let slab_color = if upside_down {
+                                COLOR_SUB_BLOCK_TOP
+                            } else {
+                                COLOR_SUB_BLOCK_BOTTOM
+                            };
+                            vec![
+                                (full_corner, size, COLOR_SUB_BLOCK_FULL),
+                                (slab_corner, size, slab_color),
+                            ]
                         }
+                    };
+                    for (corner, size, color) in parts {
+                        plot_ui.polygon(
+                            plotting::rectangle_at_coords(corner, size)
+                                .stroke(wire)
+                                .fill_color(color),
+                        );
+                    }
+                }
+            }
+
+            // Color the boundary segments by their shape, and label each segment with the letter of
+            //  its shape and its number of blocks
+            if view.boundary_segments {
+                let palette =
+                    component_palette(boundary_segments.shapes.len(), view.color_blind_palette);
+                for segment in &boundary_segments.segments {
+                    for coord in &segment.coords {
+                        plot_ui.polygon(
+                            plotting::square_at_coords(*coord)
+                                .stroke(Stroke {
+                                    width: view.wire_width,
+                                    color: view.wire_color,
+                                })
+                                .fill_color(palette[segment.shape_index % palette.len()]),
+                        );
+                    }
+                }
+
+                if view.boundary_segment_labels {
+                    for segment in &boundary_segments.segments {
+                        plot_ui.text(
+                            Text::new(
+                                PlotPoint::from(segment.center),
+                                format!(
+                                    "{} {}",
+                                    shape_label(segment.shape_index),
+                                    segment.coords.len()
+                                ),
+                            )
+                            .color(COLOR_WIRE),
+                        );
                     }
                 }
             }
 
+            // Color the blocks by their distance to the center of the shape
+            if let Some(bands) = distance_bands {
+                let max_band = bands.iter().map(|(_, band)| *band).max().unwrap_or(0);
+
+                for &(coord, band) in bands {
+                    plot_ui.polygon(
+                        plotting::square_at_coords(coord)
+                            .stroke(Stroke {
+                                width: view.wire_width,
+                                color: view.wire_color,
+                            })
+                            .fill_color(linear_gradient(
+                                COLOR_BAND_INNER,
+                                COLOR_BAND_OUTER,
+                                band as f64 / (max_band.max(1) as f64),
+                            )),
+                    );
+                }
+            }
+
             // Plot onion skinned samples
-            if sampling_enabled {
+            if sampling_enabled && !view.clean_view {
                 for i in 0..sampled_parameters.nr_samples {
                     plot_ui.line(
-                        plotting::superellipse_at_coords(&sampled_parameters.parameters[i]).color(
-                            linear_gradient(
+                        plotting::superellipse_at_coords(
+                            &sampled_parameters.parameters[i],
+                            cell_aspect,
+                        )
+                        .color(readable(linear_gradient(
+                            COLOR_SAMPLE_A,
+                            COLOR_SAMPLE_B,
+                            i as f64 / (sampled_parameters.nr_samples as f64 - 1.0),
+                        ))),
+                    );
+                }
+
+                // Label each sample with its height and radius, spread over a quarter of the
+                //  outline so that equal samples don't overlap
+                if view.sample_labels {
+                    for (i, (height, sample)) in sampling_points
+                        .iter()
+                        .zip(&sampled_parameters.parameters)
+                        .enumerate()
+                    {
+                        let t = FRAC_PI_2 * (i as f64 + 0.5) / sampled_parameters.nr_samples as f64;
+                        let radius = if sample.radius_a == sample.radius_b {
+                            formatting::format_parameter(sample.radius_a)
+                        } else {
+                            format!(
+                                "{} × {}",
+                                formatting::format_parameter(sample.radius_a),
+                                formatting::format_parameter(sample.radius_b)
+                            )
+                        };
+                        plot_ui.text(
+                            Text::new(
+                                PlotPoint::from(to_cells(
+                                    plotting::point_on_superellipse(sample, t),
+                                    cell_aspect,
+                                )),
+                                format!(
+                                    "h {}, r {}",
+                                    formatting::format_parameter(*height),
+                                    radius
+                                ),
+                            )
+                            .anchor(Align2::LEFT_BOTTOM)
+                            .color(readable(linear_gradient(
                                 COLOR_SAMPLE_A,
                                 COLOR_SAMPLE_B,
                                 i as f64 / (sampled_parameters.nr_samples as f64 - 1.0),
-                            ),
-                        ),
-                    );
+                            ))),
+                        );
+                    }
                 }
             }
 
-            // Plot x and y axes through the center of the shape
-            plot_ui.hline(
-                HLine::new(slice_parameters.center_offset_y)
-                    .color(COLOR_X_AXIS)
-                    .width(2.0),
-            );
-            plot_ui.vline(
-                VLine::new(slice_parameters.center_offset_x)
-                    .color(COLOR_Y_AXIS)
-                    .width(2.0),
+            // The center of the shape in cell coordinates, like the blocks
+            let center = to_cells(
+                [
+                    slice_parameters.center_offset_x,
+                    slice_parameters.center_offset_y,
+                ],
+                cell_aspect,
             );
 
+            // Plot x and y axes through the center of the shape
+            if !view.clean_view {
+                plot_ui.hline(
+                    HLine::new(center[1])
+                        .color(readable(COLOR_X_AXIS))
+                        .width(2.0),
+                );
+                plot_ui.vline(
+                    VLine::new(center[0])
+                        .color(readable(COLOR_Y_AXIS))
+                        .width(2.0),
+                );
+            }
+
             // Plot rotated x and y axes for nonzero tilt (dark orange and purple)
-            if slice_parameters.tilt != 0.0 {
+            if slice_parameters.tilt != 0.0 && !view.clean_view {
                 plot_ui.line(
                     plotting::tilted_line_in_bounds(
                         plot_ui.plot_bounds(),
-                        slice_parameters.tilt,
-                        slice_parameters.center_offset_x,
-                        slice_parameters.center_offset_y,
+                        angle_to_cells(slice_parameters.tilt, cell_aspect),
+                        center[0],
+                        center[1],
                     )
-                    .color(COLOR_TILTED_X_AXIS),
+                    .color(readable(COLOR_TILTED_X_AXIS)),
                 );
                 plot_ui.line(
                     plotting::tilted_line_in_bounds(
                         plot_ui.plot_bounds(),
-                        slice_parameters.tilt + PI / 2.0,
-                        slice_parameters.center_offset_x,
-                        slice_parameters.center_offset_y,
+                        angle_to_cells(slice_parameters.tilt + PI / 2.0, cell_aspect),
+                        center[0],
+                        center[1],
                     )
-                    .color(COLOR_TILTED_Y_AXIS),
+                    .color(readable(COLOR_TILTED_Y_AXIS)),
                 );
             }
 
-            // Plot intersect area
-            if view.intersect_area {
+            // Plot intersect area (of square cells)
+            if view.intersect_area && cell_aspect == 1.0 {
                 let grid_size =
                     (2.0 * 1.42 * f64::max(slice_parameters.radius_a, slice_parameters.radius_b))
                         .ceil() as usize
@@ -233,9 +598,9 @@ pub fn ui_viewport(
             // Plot convex hull
             // Perhaps better to use the plot_ui.shape
             if view.convex_hull {
-                for i in line_segments_from_conv_hull(convex_hull.clone()) {
+                for i in line_segments_from_conv_hull(convex_hull.to_vec()) {
                     let pts: PlotPoints = (0..=1).map(|t| i[t]).collect();
-                    plot_ui.line(Line::new(pts).color(COLOR_CONV_HULL));
+                    plot_ui.line(Line::new(pts).color(readable(COLOR_CONV_HULL)));
                 }
             }
 
@@ -245,16 +610,70 @@ pub fn ui_viewport(
                     plot_ui.points(
                         Points::new(vec![[*i, *j]])
                             .radius(3.0)
-                            .color(COLOR_OUTER_CORNERS),
+                            .color(readable(COLOR_OUTER_CORNERS)),
                     );
                 }
             }
 
-            // Plot bounds of the blocks
+            // Warn about the corners where blocks only touch diagonally
+            if view.thin_necks && !thin_necks.is_empty() {
+                plot_ui.points(
+                    Points::new(thin_necks.to_vec())
+                        .shape(MarkerShape::Diamond)
+                        .radius(6.0)
+                        .color(readable(COLOR_THIN_NECKS)),
+                );
+            }
+
+            // Plot tour through the boundary blocks (closed, so repeat the first point at the end)
+            if view.boundary_tour && !boundary_tour.tour.is_empty() {
+                let pts: PlotPoints = boundary_tour
+                    .tour
+                    .iter()
+                    .chain(boundary_tour.tour.first())
+                    .copied()
+                    .collect();
+                plot_ui.line(
+                    Line::new(pts)
+                        .color(readable(COLOR_BOUNDARY_TOUR))
+                        .width(2.0),
+                );
+            }
+
+            // Plot bounds of the blocks, labeled with their size: the width along the bottom edge,
+            //  the height along the right edge and both above the left top corner
             if let Some(b) = blocks {
                 if view.bounds {
-                    let line = bounds_from_square(b.get_bounds_floats());
-                    plot_ui.line(line.color(COLOR_BOUNDS))
+                    let [[x_1, y_1], [x_2, y_2]] = b.get_bounds_floats();
+                    let line = bounds_from_square([[x_1, y_1], [x_2, y_2]]);
+                    plot_ui.line(line.color(readable(COLOR_BOUNDS)));
+
+                    if b.get_nr_blocks() != 0 {
+                        let [width, height] = b.get_diameters();
+                        for (point, label, anchor) in [
+                            (
+                                [x_1, y_2],
+                                format!("{} × {}", width, height),
+                                Align2::LEFT_BOTTOM,
+                            ),
+                            (
+                                [(x_1 + x_2) / 2.0, y_1],
+                                format!("{}x", width),
+                                Align2::CENTER_TOP,
+                            ),
+                            (
+                                [x_2, (y_1 + y_2) / 2.0],
+                                format!(" {}y", height),
+                                Align2::LEFT_CENTER,
+                            ),
+                        ] {
+                            plot_ui.text(
+                                Text::new(PlotPoint::from(point), label)
+                                    .anchor(anchor)
+                                    .color(readable(COLOR_BOUNDS)),
+                            );
+                        }
+                    }
                 }
             }
 
@@ -262,20 +681,28 @@ pub fn ui_viewport(
             if view.mirrors {
                 match symmetry_type {
                     SymmetryType::ReflectionHorizontal => {
-                        plot_ui.hline(HLine::new(center_coord[1]).color(COLOR_MIRRORS).width(2.0));
+                        plot_ui.hline(
+                            HLine::new(center_coord[1])
+                                .color(readable(COLOR_MIRRORS))
+                                .width(2.0),
+                        );
                     }
                     SymmetryType::ReflectionVertical => {
-                        plot_ui.vline(VLine::new(center_coord[0]).color(COLOR_MIRRORS).width(2.0));
+                        plot_ui.vline(
+                            VLine::new(center_coord[0])
+                                .color(readable(COLOR_MIRRORS))
+                                .width(2.0),
+                        );
                     }
                     SymmetryType::ReflectionDiagonalUp => {
                         plot_ui.line(
                             plotting::tilted_line_in_bounds(
                                 plot_ui.plot_bounds(),
                                 std::f64::consts::FRAC_PI_4,
-                                slice_parameters.center_offset_x,
-                                slice_parameters.center_offset_y,
+                                center[0],
+                                center[1],
                             )
-                            .color(COLOR_MIRRORS),
+                            .color(readable(COLOR_MIRRORS)),
                         );
                     }
                     SymmetryType::ReflectionDiagonalDown => {
@@ -283,56 +710,72 @@ pub fn ui_viewport(
                             plotting::tilted_line_in_bounds(
                                 plot_ui.plot_bounds(),
                                 -std::f64::consts::FRAC_PI_4,
-                                slice_parameters.center_offset_x,
-                                slice_parameters.center_offset_y,
+                                center[0],
+                                center[1],
                             )
-                            .color(COLOR_MIRRORS),
+                            .color(readable(COLOR_MIRRORS)),
                         );
                     }
                     SymmetryType::ReflectionsCardinals => {
-                        plot_ui.vline(VLine::new(center_coord[0]).color(COLOR_MIRRORS).width(2.0));
-                        plot_ui.hline(HLine::new(center_coord[1]).color(COLOR_MIRRORS).width(2.0));
+                        plot_ui.vline(
+                            VLine::new(center_coord[0])
+                                .color(readable(COLOR_MIRRORS))
+                                .width(2.0),
+                        );
+                        plot_ui.hline(
+                            HLine::new(center_coord[1])
+                                .color(readable(COLOR_MIRRORS))
+                                .width(2.0),
+                        );
                     }
                     SymmetryType::ReflectionsDiagonals => {
                         plot_ui.line(
                             plotting::tilted_line_in_bounds(
                                 plot_ui.plot_bounds(),
                                 std::f64::consts::FRAC_PI_4,
-                                slice_parameters.center_offset_x,
-                                slice_parameters.center_offset_y,
+                                center[0],
+                                center[1],
                             )
-                            .color(COLOR_MIRRORS),
+                            .color(readable(COLOR_MIRRORS)),
                         );
                         plot_ui.line(
                             plotting::tilted_line_in_bounds(
                                 plot_ui.plot_bounds(),
                                 -std::f64::consts::FRAC_PI_4,
-                                slice_parameters.center_offset_x,
-                                slice_parameters.center_offset_y,
+                                center[0],
+                                center[1],
                             )
-                            .color(COLOR_MIRRORS),
+                            .color(readable(COLOR_MIRRORS)),
                         );
                     }
                     SymmetryType::ReflectionsAll => {
-                        plot_ui.vline(VLine::new(center_coord[0]).color(COLOR_MIRRORS).width(2.0));
-                        plot_ui.hline(HLine::new(center_coord[1]).color(COLOR_MIRRORS).width(2.0));
+                        plot_ui.vline(
+                            VLine::new(center_coord[0])
+                                .color(readable(COLOR_MIRRORS))
+                                .width(2.0),
+                        );
+                        plot_ui.hline(
+                            HLine::new(center_coord[1])
+                                .color(readable(COLOR_MIRRORS))
+                                .width(2.0),
+                        );
                         plot_ui.line(
                             plotting::tilted_line_in_bounds(
                                 plot_ui.plot_bounds(),
                                 std::f64::consts::FRAC_PI_4,
-                                slice_parameters.center_offset_x,
-                                slice_parameters.center_offset_y,
+                                center[0],
+                                center[1],
                             )
-                            .color(COLOR_MIRRORS),
+                            .color(readable(COLOR_MIRRORS)),
                         );
                         plot_ui.line(
                             plotting::tilted_line_in_bounds(
                                 plot_ui.plot_bounds(),
                                 -std::f64::consts::FRAC_PI_4,
-                                slice_parameters.center_offset_x,
-                                slice_parameters.center_offset_y,
+                                center[0],
+                                center[1],
                             )
-                            .color(COLOR_MIRRORS),
+                            .color(readable(COLOR_MIRRORS)),
                         );
                     }
                     SymmetryType::RotationHalf => {} //todo: how to visualize rotational symmetry?
@@ -341,19 +784,194 @@ pub fn ui_viewport(
                 }
             }
 
-            // Plot target shape
-            plot_ui.line(
-                plotting::superellipse_at_coords(&slice_parameters).color(COLOR_TARGET_SHAPE),
-            );
+            // Plot the octant boundaries through the shape center, with the number of boundary
+            //  blocks in the middle of each octant just outside the shape
+            if view.octants {
+                plot_ui.vline(VLine::new(center[0]).color(readable(COLOR_OCTANTS)));
+                plot_ui.hline(HLine::new(center[1]).color(readable(COLOR_OCTANTS)));
+                for tilt in [std::f64::consts::FRAC_PI_4, -std::f64::consts::FRAC_PI_4] {
+                    plot_ui.line(
+                        plotting::tilted_line_in_bounds(
+                            plot_ui.plot_bounds(),
+                            tilt,
+                            center[0],
+                            center[1],
+                        )
+                        .color(readable(COLOR_OCTANTS)),
+                    );
+                }
 
-            // Plot center dot
-            plot_ui.points(
-                Points::new(vec![[
-                    slice_parameters.center_offset_x,
-                    slice_parameters.center_offset_y,
-                ]])
-                .radius(5.0)
-                .color(COLOR_CENTER_DOT),
-            );
+                // (in cells, flat cells make the shape taller)
+                let label_radius = 1.15
+                    * slice_parameters
+                        .radius_a
+                        .max(slice_parameters.radius_b)
+                        .max(1.0)
+                    * (1.0 / cell_aspect).max(1.0);
+                for (octant, count) in octant_counts.iter().enumerate() {
+                    let angle = (octant as f64 + 0.5) * std::f64::consts::FRAC_PI_4;
+                    plot_ui.text(
+                        Text::new(
+                            PlotPoint::new(
+                                center[0] + label_radius * angle.cos(),
+                                center[1] + label_radius * angle.sin(),
+                            ),
+                            count.to_string(),
+                        )
+                        .color(readable(COLOR_OCTANTS)),
+                    );
+                }
+            }
+
+            // Plot the plane of the side view (through the middle of the column)
+            match vertical_slice_plane {
+                Some((SliceAxis::X, coord)) => plot_ui.vline(
+                    VLine::new(coord as f64 + 0.5)
+                        .color(readable(COLOR_VERTICAL_SLICE))
+                        .width(2.0),
+                ),
+                Some((SliceAxis::Y, coord)) => plot_ui.hline(
+                    HLine::new(coord as f64 + 0.5)
+                        .color(readable(COLOR_VERTICAL_SLICE))
+                        .width(2.0),
+                ),
+                None => {}
+            }
+
+            // Plot the outline of the pinned layer (below the target shape of the current layer)
+            if let Some(pinned_outline) = pinned_outline {
+                plot_ui.line(
+                    plotting::superellipse_at_coords(&pinned_outline, cell_aspect)
+                        .color(readable(COLOR_PINNED_OUTLINE))
+                        .style(LineStyle::dashed_loose()),
+                );
+            }
+
+            // Plot the shape at the height between layers (all copies), labelled with the height
+            if let Some((height, between_layers)) = &between_layers {
+                for copy in between_layers.shape_array.get_copies(between_layers) {
+                    plot_ui.line(
+                        plotting::superellipse_at_coords(&copy, cell_aspect)
+                            .color(readable(COLOR_BETWEEN_LAYERS))
+                            .style(LineStyle::dashed_dense())
+                            .name(format!("Layer {:.2}", height)),
+                    );
+                }
+            }
+
+            // Plot the axis of the leaning stack, with the shapes of its bottom and top layer
+            if let Some(lean_preview) = lean_preview {
+                let axis: Vec<[f64; 2]> = lean_preview.axis().map(|(center, _)| center).collect();
+                plot_ui.line(
+                    Line::new(PlotPoints::new(axis.clone()))
+                        .color(readable(COLOR_LEAN_AXIS))
+                        .width(2.0)
+                        .name("Lean"),
+                );
+                plot_ui.points(
+                    Points::new(axis)
+                        .radius(2.0)
+                        .color(readable(COLOR_LEAN_AXIS)),
+                );
+                for end in &lean_preview.ends {
+                    plot_ui.line(
+                        plotting::superellipse_at_coords(end, cell_aspect)
+                            .color(readable(COLOR_LEAN_AXIS))
+                            .style(LineStyle::dashed_dense())
+                            .name(format!("Layer {}", end.layer)),
+                    );
+                }
+            }
+
+            // Plot target shape (all copies if it is repeated in an array), with the edges of the
+            //  sector if only a sector is generated
+            if !view.clean_view {
+                for copy in slice_parameters.shape_array.get_copies(&slice_parameters) {
+                    let outline = match script_outline {
+                        Some(outline) => {
+                            plotting::script_outline_at_coords(outline, &copy, cell_aspect)
+                        }
+                        None => plotting::superellipse_at_coords(&copy, cell_aspect),
+                    };
+                    plot_ui.line(outline.color(readable(COLOR_TARGET_SHAPE)));
+                    if let Some(sector) = copy.sector {
+                        plot_ui.line(
+                            plotting::sector_edges(&copy, sector, cell_aspect)
+                                .color(readable(COLOR_TARGET_SHAPE))
+                                .style(LineStyle::dashed_dense()),
+                        );
+                    }
+                }
+
+                // Plot center dot
+                plot_ui.points(
+                    Points::new(vec![center])
+                        .radius(5.0)
+                        .color(readable(COLOR_CENTER_DOT)),
+                );
+            }
+
+            // Points clicked to construct a shape (numbered), with the shape through them
+            if let Some((points, shape)) = &clicked_points {
+                let points: Vec<[f64; 2]> = points
+                    .iter()
+                    .map(|point| to_cells(*point, cell_aspect))
+                    .collect();
+                if let Some(shape) = shape {
+                    plot_ui.line(
+                        plotting::superellipse_at_coords(shape, cell_aspect)
+                            .color(readable(COLOR_CLICKED_POINTS))
+                            .style(LineStyle::dashed_loose()),
+                    );
+                }
+                plot_ui.points(
+                    Points::new(points.clone())
+                        .radius(4.0)
+                        .color(readable(COLOR_CLICKED_POINTS)),
+                );
+                for (index, point) in points.iter().enumerate() {
+                    plot_ui.text(
+                        Text::new(PlotPoint::new(point[0], point[1]), format!("{}", index + 1))
+                            .anchor(Align2::LEFT_BOTTOM)
+                            .color(readable(COLOR_CLICKED_POINTS)),
+                    );
+                }
+            }
+
+            // Text stamp that is being placed, outlined if it removes blocks
+            if let Some((coords, mode)) = stamp_preview {
+                let fill_color = match mode {
+                    StampMode::Add => COLOR_STAMP.gamma_multiply(0.6),
+                    StampMode::Remove => Color32::TRANSPARENT,
+                };
+                for [x, y] in coords {
+                    plot_ui.polygon(
+                        plotting::square_at_coords([*x as f64, *y as f64])
+                            .stroke(Stroke {
+                                width: view.wire_width.max(1.0),
+                                color: readable(COLOR_STAMP),
+                            })
+                            .fill_color(fill_color),
+                    );
+                }
+            }
+
+            // North arrow at the edge of the viewport that faces north
+            if minecraft_axes {
+                let bounds = plot_ui.plot_bounds();
+                let x = (bounds.min()[0] + bounds.max()[0]) / 2.0;
+                let (y, text, anchor) = if flip_z {
+                    (bounds.min()[1], "N\n▼", Align2::CENTER_BOTTOM)
+                } else {
+                    (bounds.max()[1], "▲\nN", Align2::CENTER_TOP)
+                };
+                plot_ui.text(
+                    Text::new(PlotPoint::new(x, y), text)
+                        .anchor(anchor)
+                        .color(readable(COLOR_NORTH_ARROW)),
+                );
+            }
         });
+
+    clicked_point
 }
@@ -1,16 +1,52 @@
+use crate::app::colors::{
+    component_palette, COLOR_SUB_BLOCK_BOTTOM, COLOR_SUB_BLOCK_FULL, COLOR_SUB_BLOCK_TOP,
+};
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::lattice::Lattice;
+use crate::app::data_structures::slice_parameters::SliceParameters;
 use crate::app::data_structures::symmetry_type::SymmetryType;
-use crate::app::view::View;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::export::{
+    build_order_save_request, metrics_save_request, ComputedMetrics, McfunctionExport,
+};
+use crate::app::image_export::{blocks_to_world, stack_images, LayerImage};
+use crate::app::io::{IoService, SaveFolderRequest, SaveRequest};
+use crate::app::log::log_warning;
+use crate::app::metrics::boundary_3d::Connectivity;
+use crate::app::metrics::boundary_segments::{shape_label, BoundarySegments};
+use crate::app::metrics::boundary_tour::BoundaryTour;
+use crate::app::metrics::distance_bands::distance_bands_to_text;
+use crate::app::metrics::holes::Holes;
+use crate::app::metrics::octants::octant_imbalance;
+use crate::app::sub_blocks::{count_sub_blocks, Side, SubBlock};
+use crate::app::supports::SupportPattern;
+use crate::app::svg_export::outlines_save_request;
+use crate::app::view::{BlockPattern, View};
 use eframe::egui;
 use eframe::egui::Ui;
+use itertools::{izip, Itertools};
 
 pub fn ui_viewport_options(
     ui: &mut Ui,
     layers_enabled: bool,
     single_radius: bool,
     symmetry_type: &SymmetryType,
+    boundary_tour: &BoundaryTour,
+    distance_bands: impl FnOnce(f64) -> Vec<([f64; 2], usize)>, // for a band width, when copied
     view: &mut View,
 ) {
-    ui.checkbox(&mut view.blocks, "Blocks");
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut view.blocks, "Blocks");
+        ui_block_pattern(ui, &mut view.block_pattern);
+    });
+    let mut clean_view = view.clean_view;
+    if ui
+        .checkbox(&mut clean_view, "Clean view")
+        .on_hover_text("Hide everything except the blocks, until unticked (Ctrl+Shift+H)")
+        .changed()
+    {
+        view.set_clean_view(clean_view);
+    }
     ui.columns(2, |columns| {
         // Better to fully hide 3d settings
         if layers_enabled {
@@ -18,20 +54,208 @@ pub fn ui_viewport_options(
             columns[0].checkbox(&mut view.interior_2d, "Layer Interior");
             columns[1].checkbox(&mut view.boundary_3d, "3D Boundary");
             columns[1].checkbox(&mut view.interior_3d, "3D Interior");
+            columns[0].checkbox(&mut view.projection_union, "Union of layers");
+            columns[1].checkbox(&mut view.projection_intersection, "Intersection of layers");
+            columns[0].checkbox(&mut view.vertical_slice, "Side view");
+            columns[1].horizontal(|ui| {
+                ui.checkbox(&mut view.pinned_outline, "Pin layer");
+                ui.add_enabled(
+                    view.pinned_outline,
+                    egui::DragValue::new(&mut view.pinned_layer).speed(0.05),
+                );
+            });
+            columns[0].horizontal(|ui| {
+                ui.checkbox(&mut view.between_layers, "Between layers");
+                ui.add_enabled(
+                    view.between_layers,
+                    egui::DragValue::new(&mut view.between_layers_height)
+                        .speed(0.01)
+                        .fixed_decimals(2),
+                );
+            });
+            columns[1].add_enabled(
+                view.between_layers,
+                egui::Checkbox::new(&mut view.between_layers_blocks, "Voxelize between layers"),
+            );
+            columns[0]
+                .checkbox(&mut view.sub_blocks, "Slabs and stairs")
+                .on_hover_text("Follow the shape at half a block resolution with slabs and stairs");
+            columns[1]
+                .checkbox(&mut view.depth_shading, "Depth shading")
+                .on_hover_text(
+                    "Shade the blocks darker the more layers are filled directly above and below \
+                    them, so the top and bottom surfaces stand out",
+                );
         } else {
             columns[0].checkbox(&mut view.boundary_2d, "Boundary");
             columns[0].checkbox(&mut view.interior_2d, "Interior");
         }
     });
+    if layers_enabled && (view.boundary_3d || view.interior_3d || view.holes) {
+        ui_3d_settings(ui, view);
+    }
+
+    ui.collapsing("Overlays", |ui| ui_overlays(ui, view, layers_enabled));
+
+    // The windows that change the shape are closed in viewer mode
+    if !view.viewer_mode {
+        ui.checkbox(&mut view.variation_gallery, "Variation gallery");
+        ui.checkbox(&mut view.layout_canvas, "Layout of several structures");
+        ui.checkbox(&mut view.template_gallery, "New from template");
+        ui.checkbox(&mut view.arch, "New arch");
+        ui.checkbox(&mut view.fit_to_points, "Fit shape to blocks");
+        ui.checkbox(
+            &mut view.shape_through_points,
+            "Shape through clicked points",
+        );
+        ui.checkbox(&mut view.text_stamp, "Text stamp");
+    }
+    ui.checkbox(&mut view.reference_image, "Reference image");
+    ui.checkbox(&mut view.parameter_history, "Parameter history");
+    ui.checkbox(&mut view.export_script, "Export with a script");
+    ui.checkbox(&mut view.calculator, "Calculator");
+    if layers_enabled && !view.viewer_mode {
+        ui.checkbox(&mut view.find_replace, "Find & replace in layers");
+        ui.checkbox(&mut view.lean, "Lean the stack");
+    }
 
     ui.collapsing("Symmetry & Building", |ui| {
-        ui.label(format!("Symmetry type: {:}", symmetry_type));
         ui.checkbox(&mut view.center_blocks, "Center blocks");
         ui.checkbox(&mut view.bounds, "Bounds");
         ui.checkbox(&mut view.mirrors, "Mirrors");
+        // The symmetry type is only computed when the mirrors are shown
+        if view.mirrors {
+            ui.label(format!("Symmetry type: {:}", symmetry_type));
+        }
+        ui.checkbox(&mut view.octants, "Octants")
+            .on_hover_text("Boundary blocks in each octant around the shape center");
+        ui.checkbox(&mut view.boundary_segments, "Boundary segments");
+        if view.boundary_segments {
+            ui.checkbox(&mut view.boundary_segment_labels, "Segment labels");
+        }
+        ui.checkbox(&mut view.holes, "Holes");
+        ui.checkbox(&mut view.thin_necks, "Diagonal-only connections")
+            .on_hover_text("Blocks that touch the rest of the layer only at a corner");
+        ui.checkbox(&mut view.supports, "Supports")
+            .on_hover_text("Interior blocks in a pattern, to support large floors");
+        if view.supports {
+            ui_support_pattern(ui, &mut view.support_pattern);
+        }
+        ui.checkbox(&mut view.boundary_tour, "Boundary tour");
+        if view.boundary_tour {
+            ui.label(format!(
+                "Tour length: {:.1} through {} blocks ({} improvement passes{})",
+                boundary_tour.length,
+                boundary_tour.tour.len(),
+                boundary_tour.nr_passes,
+                if boundary_tour.capped {
+                    ", stopped early"
+                } else {
+                    ""
+                }
+            ));
+        }
+    });
+
+    ui.collapsing("Distance bands", |ui| {
+        ui.checkbox(&mut view.distance_bands, "Distance bands");
+        ui.add(
+            egui::Slider::new(&mut view.distance_band_width, 0.5..=10.0)
+                .text("Band width")
+                .clamp_to_range(false),
+        );
+        view.distance_band_width = view.distance_band_width.max(0.1);
+        if ui.button("Copy bands as text").clicked() {
+            let bands = distance_bands(view.distance_band_width);
+            ui.output_mut(|o| o.copied_text = distance_bands_to_text(&bands));
+        }
+    });
+
+    ui.collapsing("Copy coordinates", |ui| {
+        ui.label("Right click a block in the viewport to copy its coordinates in this format:");
+        ui.text_edit_singleline(&mut view.coord_copy_format)
+            .on_hover_text(
+                "{x} and {y} are the coordinates in the viewport, {z} the Minecraft Z coordinate \
+                (see \"Axes\"), {layer} the layer number",
+            );
+    });
+
+    ui.collapsing("Zoom", |ui| {
+        ui.label("Double click the viewport to zoom to:");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut view.zoom_to_blocks, false, "Shapes");
+            ui.selectable_value(&mut view.zoom_to_blocks, true, "Blocks");
+        });
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut view.zoom_to_layer, false, "Whole stack");
+            ui.selectable_value(&mut view.zoom_to_layer, true, "Current layer")
+                .on_hover_text("Follows the current layer while the zoom is automatic");
+        });
+        ui.add(egui::Slider::new(&mut view.zoom_padding, 1.0..=2.0).text("Padding factor"));
+        ui.add(egui::Slider::new(&mut view.zoom_margin, 0.0..=20.0).text("Margin (blocks)"));
+    });
+
+    ui.collapsing("Axes", |ui| {
+        ui.checkbox(&mut view.minecraft_axes, "Minecraft axes (X and Z)")
+            .on_hover_text("Label the axes like the F3 screen, with a north arrow");
+        ui.add_enabled(
+            view.minecraft_axes,
+            egui::Checkbox::new(&mut view.flip_z, "Flip Z (south up)"),
+        );
+        ui.checkbox(&mut view.world_anchor, "World coordinates")
+            .on_hover_text(
+                "Put the shape center at a position in the world. The coordinates in the \
+                viewport and the exports are world coordinates, and the center offsets of all \
+                layers are shifted by the position of the center within its block.",
+            );
+        ui.add_enabled_ui(view.world_anchor, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Center X");
+                ui.add(egui::DragValue::new(&mut view.world_center[0]).speed(0.5));
+                ui.label("Z");
+                ui.add(egui::DragValue::new(&mut view.world_center[1]).speed(0.5));
+            });
+        });
+    });
+
+    ui.collapsing("Grid", |ui| {
+        for (name, step, color) in izip!(
+            ["Fine", "Medium", "Coarse"],
+            view.grid_steps.iter_mut(),
+            view.grid_colors.iter_mut()
+        ) {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(color);
+                ui.add(
+                    egui::DragValue::new(step)
+                        .speed(0.1)
+                        .clamp_range(0.5..=256.0),
+                );
+                ui.label(name);
+            });
+        }
+        ui.checkbox(
+            &mut view.hide_fine_grid,
+            "Hide fine grid lines when zoomed out",
+        );
+        ui.horizontal(|ui| {
+            ui.label("Lines through");
+            for lattice in [Lattice::Corners, Lattice::Centers] {
+                ui.selectable_value(&mut view.grid_lattice, lattice, lattice.to_string());
+            }
+        })
+        .response
+        .on_hover_text("Through the block centers for walls that are centered on a row of blocks");
     });
 
     ui.collapsing("Technical", |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            egui::widgets::global_dark_light_mode_buttons(ui);
+        });
+        ui.checkbox(&mut view.log_console, "Log");
+        ui.checkbox(&mut view.color_blind_palette, "Color blind safe palette")
+            .on_hover_text("Colors of the boundary segments and of the structures in the layout");
         ui.checkbox(&mut view.complement, "Complement");
         ui.checkbox(&mut view.convex_hull, "Convex hull");
         ui.checkbox(&mut view.outer_corners, "Outer corners");
@@ -44,3 +268,506 @@ pub fn ui_viewport_options(
         );
     });
 }
+
+/// Visibility, opacity, wireframe and draw order of the sets of blocks in the viewport (the last
+///  one is drawn on top)
+fn ui_overlays(ui: &mut Ui, view: &mut View, layers_enabled: bool) {
+    let mut swap = None;
+    egui::Grid::new("overlays_grid").show(ui, |ui| {
+        let nr_overlays = view.overlays.len();
+        for index in 0..nr_overlays {
+            let overlay = view.overlays[index].overlay;
+            if overlay.is_3d() && !layers_enabled {
+                continue;
+            }
+
+            ui.toggle_value(overlay.shown_mut(view), "👁")
+                .on_hover_text("Show");
+            ui.label(overlay.name());
+            ui.add(egui::Slider::new(&mut view.overlays[index].opacity, 0.0..=1.0).text("Opacity"));
+            ui.checkbox(&mut view.overlays[index].wireframe, "Wire");
+            if ui
+                .add_enabled(index > 0, egui::Button::new("⬆"))
+                .on_hover_text("Draw below")
+                .clicked()
+            {
+                swap = Some(index - 1);
+            }
+            if ui
+                .add_enabled(index + 1 < nr_overlays, egui::Button::new("⬇"))
+                .on_hover_text("Draw on top")
+                .clicked()
+            {
+                swap = Some(index);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(index) = swap {
+        view.overlays.swap(index, index + 1);
+    }
+
+    ui.horizontal(|ui| {
+        ui.color_edit_button_srgba(&mut view.wire_color);
+        ui.add(
+            egui::DragValue::new(&mut view.wire_width)
+                .speed(0.05)
+                .clamp_range(0.0..=5.0),
+        );
+        ui.label("Wireframe color and width");
+    });
+}
+
+/// List the distinct shapes of the boundary segments with their color, letter and count
+pub fn ui_boundary_segments_legend(
+    ui: &mut Ui,
+    boundary_segments: &BoundarySegments,
+    color_blind_palette: bool,
+) {
+    let palette = component_palette(boundary_segments.shapes.len(), color_blind_palette);
+    ui.label(format!(
+        "{} boundary segments of {} distinct shapes",
+        boundary_segments.segments.len(),
+        boundary_segments.shapes.len()
+    ));
+    egui::ScrollArea::vertical()
+        .id_source("boundary_segments_legend")
+        .max_height(150.0)
+        .show(ui, |ui| {
+            for (index, (shape, count)) in boundary_segments.shapes.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(palette[index % palette.len()], "■");
+                    ui.label(format!(
+                        "{}: {} × {} blocks",
+                        shape_label(index),
+                        count,
+                        shape.get_nr_blocks()
+                    ));
+                });
+            }
+        });
+}
+
+/// Save the boundary blocks in build order (following the boundary tour) as CSV or JSON, to use
+///  as a checklist while building
+pub fn ui_build_order_export(
+    ui: &mut Ui,
+    view: &mut View,
+    stack_blocks: &ZVec<Blocks>,
+    current_layer: isize,
+    layers_enabled: bool,
+    io: &mut IoService,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Export build order:");
+        let csv = ui.button("CSV").clicked();
+        let json = ui.button("JSON").clicked();
+        if layers_enabled {
+            ui.checkbox(&mut view.build_order_all_layers, "All layers");
+        }
+        if csv || json {
+            io.save(build_order_save_request(
+                stack_blocks,
+                current_layer,
+                layers_enabled,
+                view,
+                json,
+            ));
+        }
+    });
+}
+
+/// Save the metrics of the current layer (or of all layers) as JSON, for analyzing the design with
+///  other tools
+pub fn ui_metrics_export(
+    ui: &mut Ui,
+    stack_blocks: &ZVec<Blocks>,
+    current_layer: isize,
+    layers_enabled: bool,
+    view: &View,
+    computed_metrics: ComputedMetrics,
+    io: &mut IoService,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Export metrics:");
+        let text = if layers_enabled {
+            "Counts, diameters, symmetry, convex hull and boundary segments of all layers"
+        } else {
+            "Counts, diameters, symmetry, convex hull and boundary segments of the layer"
+        };
+        if ui.button("JSON").on_hover_text(text).clicked() {
+            io.save(metrics_save_request(
+                stack_blocks,
+                current_layer,
+                layers_enabled,
+                view,
+                computed_metrics,
+            ));
+        }
+    });
+}
+
+/// Save the blocks of the current layer as a PNG, or as one PNG per map (128 by 128 blocks, aligned
+///  like the maps in game) for map art. With layers, all layers can be saved as numbered PNGs.
+pub fn ui_image_export(
+    ui: &mut Ui,
+    stack_blocks: &ZVec<Blocks>,
+    current_layer: isize,
+    layers_enabled: bool,
+    view: &View,
+    io: &mut IoService,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Export layer as PNG:");
+        let whole = ui.button("Image").clicked();
+        let tiles = ui
+            .button("Map tiles")
+            .on_hover_text("One image per map (128 by 128 blocks), saved in a folder")
+            .clicked();
+        let all_layers = layers_enabled
+            && ui
+                .button("All layers")
+                .on_hover_text(
+                    "One numbered image per layer, with the same scale and bounds, saved in a \
+                    folder",
+                )
+                .clicked();
+
+        if all_layers {
+            let layers: Vec<(isize, Vec<[isize; 2]>)> = (stack_blocks.get_minimum()
+                ..=stack_blocks.get_maximum())
+                .map(|layer| {
+                    let blocks = blocks_to_world(
+                        &stack_blocks.get(layer).unwrap().get_all_block_coords(),
+                        view.world_translation(),
+                        view.flip_z,
+                    );
+                    (layer, blocks)
+                })
+                .collect();
+            let files = stack_images(&layers);
+            if files.is_empty() {
+                log_warning("There are no blocks to export");
+            } else {
+                io.save_folder(SaveFolderRequest {
+                    title: "Folder for the layer images".to_string(),
+                    files,
+                });
+            }
+        }
+        if !whole && !tiles {
+            return;
+        }
+
+        let blocks = blocks_to_world(
+            &stack_blocks
+                .get(current_layer)
+                .unwrap()
+                .get_all_block_coords(),
+            view.world_translation(),
+            view.flip_z,
+        );
+        if whole {
+            if let Some(image) = LayerImage::whole_layer(&blocks, current_layer) {
+                io.save(SaveRequest {
+                    title: "Export layer as PNG".to_string(),
+                    file_name: image.file_name(),
+                    filter_name: "PNG",
+                    extensions: &["png"],
+                    contents: image.to_png(&blocks),
+                });
+            }
+        } else {
+            let files = LayerImage::map_tiles(&blocks, current_layer)
+                .into_iter()
+                .map(|image| (image.file_name(), image.to_png(&blocks)))
+                .collect();
+            io.save_folder(SaveFolderRequest {
+                title: "Folder for the map tiles".to_string(),
+                files,
+            });
+        }
+    });
+}
+
+/// Save the continuous outline of the current layer (or of all layers stacked) as SVG, to refine it
+///  in a drawing program
+pub fn ui_outline_export(
+    ui: &mut Ui,
+    stack_layer_config: &ZVec<SliceParameters>,
+    current_layer: isize,
+    layers_enabled: bool,
+    view: &View,
+    io: &mut IoService,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Export outline as SVG:");
+        if ui
+            .button("Layer")
+            .on_hover_text("One unit is one block, in X and Z")
+            .clicked()
+        {
+            io.save(outlines_save_request(
+                stack_layer_config,
+                current_layer,
+                false,
+                view,
+            ));
+        }
+        if layers_enabled
+            && ui
+                .button("All layers")
+                .on_hover_text("The outlines of all layers on top of each other, a group per layer")
+                .clicked()
+        {
+            io.save(outlines_save_request(
+                stack_layer_config,
+                current_layer,
+                true,
+                view,
+            ));
+        }
+    });
+}
+
+/// Save the stack (or only the current layer without layers) as Minecraft commands, to place it
+///  with a datapack
+pub fn ui_mcfunction_export(
+    ui: &mut Ui,
+    export: &mut McfunctionExport,
+    stack_blocks: &ZVec<Blocks>,
+    current_layer: isize,
+    layers_enabled: bool,
+    view: &View,
+    io: &mut IoService,
+) {
+    ui.collapsing("Export as .mcfunction", |ui| {
+        egui::Grid::new("mcfunction_grid").show(ui, |ui| {
+            ui.label("Boundary block");
+            ui.text_edit_singleline(&mut export.boundary_block);
+            ui.end_row();
+            ui.label("Interior block");
+            ui.add(
+                egui::TextEdit::singleline(&mut export.interior_block)
+                    .hint_text("empty: leave as it is"),
+            );
+            ui.end_row();
+            ui.label("Floor block")
+                .on_hover_text("The interior of the lowest layer, for a shell that has a floor");
+            ui.add(
+                egui::TextEdit::singleline(&mut export.floor_block)
+                    .hint_text("empty: the interior block"),
+            );
+            ui.end_row();
+            if view.supports {
+                ui.label("Support block")
+                    .on_hover_text("The supports shown in the viewport, over the interior");
+                ui.text_edit_singleline(&mut export.support_block);
+                ui.end_row();
+            }
+            ui.label("Center block")
+                .on_hover_text("The center blocks of each layer (see the overlay), placed last");
+            ui.add(egui::TextEdit::singleline(&mut export.center_block).hint_text("empty: none"));
+            ui.end_row();
+            ui.label("Origin (X, Y, Z)").on_hover_text(
+                "Added to the coordinates of the blocks, the layer number is the height",
+            );
+            ui.horizontal(|ui| {
+                for coord in export.origin.iter_mut() {
+                    ui.add(egui::DragValue::new(coord));
+                }
+            });
+            ui.end_row();
+        });
+        ui.checkbox(
+            &mut export.relative,
+            "Relative to where the function runs (~)",
+        );
+
+        if ui.button("Save .mcfunction").clicked() {
+            let layers = if layers_enabled {
+                stack_blocks.get_minimum()..=stack_blocks.get_maximum()
+            } else {
+                current_layer..=current_layer
+            };
+            let contents = export.to_mcfunction(
+                stack_blocks,
+                layers,
+                view.world_translation(),
+                view.flip_z,
+                view.supports.then_some(view.support_pattern),
+            );
+            io.save(SaveRequest {
+                title: "Export as .mcfunction".to_string(),
+                file_name: "voxircle.mcfunction".to_string(),
+                filter_name: "Minecraft function",
+                extensions: &["mcfunction"],
+                contents: contents.into_bytes(),
+            });
+        }
+    });
+}
+
+/// Number of enclosed air pockets in the current layer and in the stack
+pub fn ui_holes_summary(ui: &mut Ui, holes: &Holes, layers_enabled: bool) {
+    ui.label(format!("Enclosed air in this layer: {}", holes.nr_holes_2d));
+    if layers_enabled {
+        ui.label(format!(
+            "Enclosed air in the stack: {} (tunnels: {})",
+            holes.nr_holes_3d, holes.genus_3d
+        ));
+    }
+}
+
+/// Boundary blocks in each octant, counterclockwise from the positive x axis. For a symmetric shape
+///  these are all equal.
+pub fn ui_octant_counts(ui: &mut Ui, octant_counts: &[f64; 8]) {
+    ui.label("Boundary blocks per octant (counterclockwise from +x):");
+    ui.label(
+        octant_counts
+            .iter()
+            .map(|count| count.to_string())
+            .join(", "),
+    );
+    ui.label(format!(
+        "Largest difference: {}",
+        octant_imbalance(octant_counts)
+    ));
+}
+
+/// Number of slabs and stairs in the current layer (and which way the stairs face), with the colors
+///  of the viewport
+pub fn ui_sub_blocks_summary(ui: &mut Ui, sub_blocks: &[([f64; 2], SubBlock)], flip_z: bool) {
+    let [nr_full, nr_slabs, nr_stairs] = count_sub_blocks(sub_blocks);
+    ui.label(format!(
+        "Slabs and stairs: {} full blocks, {} slabs, {} stairs",
+        nr_full, nr_slabs, nr_stairs
+    ));
+    for (color, text) in [
+        (COLOR_SUB_BLOCK_FULL, "Full height"),
+        (COLOR_SUB_BLOCK_BOTTOM, "Bottom half (slab, stair)"),
+        (
+            COLOR_SUB_BLOCK_TOP,
+            "Top half (top slab, upside down stair)",
+        ),
+    ] {
+        ui.horizontal(|ui| {
+            ui.colored_label(color, "■");
+            ui.label(text);
+        });
+    }
+    if nr_stairs > 0 {
+        let facings = Side::ALL.map(|side| {
+            let count = sub_blocks
+                .iter()
+                .filter(|(_, sub_block)| {
+                    matches!(sub_block, SubBlock::Stair { facing, .. } if *facing == side)
+                })
+                .count();
+            format!("{} {}", count, side.compass(flip_z))
+        });
+        ui.label(format!("Stairs facing: {}", facings.join(", ")));
+    }
+}
+
+/// Which neighbors touch in the stack (for the 3D boundary, interior and holes) and how thick the
+///  3D boundary is
+fn ui_3d_settings(ui: &mut Ui, view: &mut View) {
+    ui.horizontal(|ui| {
+        ui.label("3D neighbors:");
+        egui::ComboBox::from_id_source("connectivity_3d")
+            .selected_text(view.connectivity_3d.to_string())
+            .show_ui(ui, |ui| {
+                for connectivity in Connectivity::all() {
+                    ui.selectable_value(
+                        &mut view.connectivity_3d,
+                        connectivity,
+                        connectivity.to_string(),
+                    );
+                }
+            })
+            .response
+            .on_hover_text(
+                "Blocks and air that share only an edge or a corner touch with 18 or 26 \
+                neighbors, so diagonal gaps leak (e.g. for water-proofing)",
+            );
+    });
+    if view.boundary_3d || view.interior_3d {
+        ui.horizontal(|ui| {
+            ui.label("Wall thickness:");
+            ui.add(
+                egui::DragValue::new(&mut view.wall_thickness)
+                    .speed(0.05)
+                    .clamp_range(1..=16)
+                    .suffix(" blocks"),
+            )
+            .on_hover_text("Blocks within this many steps of the outside are 3D boundary");
+        });
+    }
+}
+
+/// Choose the shading pattern of the blocks, with the period for the column and row patterns
+fn ui_block_pattern(ui: &mut Ui, block_pattern: &mut BlockPattern) {
+    let period = match *block_pattern {
+        BlockPattern::EveryNthColumn(n) | BlockPattern::EveryNthRow(n) => n,
+        _ => 2,
+    };
+    egui::ComboBox::from_id_source("block_pattern")
+        .selected_text(match block_pattern {
+            BlockPattern::None => "No pattern",
+            BlockPattern::Checkerboard => "Checkerboard",
+            BlockPattern::EveryNthColumn(_) => "Columns",
+            BlockPattern::EveryNthRow(_) => "Rows",
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(block_pattern, BlockPattern::None, "No pattern");
+            ui.selectable_value(block_pattern, BlockPattern::Checkerboard, "Checkerboard");
+            ui.selectable_value(
+                block_pattern,
+                BlockPattern::EveryNthColumn(period),
+                "Columns",
+            );
+            ui.selectable_value(block_pattern, BlockPattern::EveryNthRow(period), "Rows");
+        });
+    if let BlockPattern::EveryNthColumn(n) | BlockPattern::EveryNthRow(n) = block_pattern {
+        ui.add(
+            egui::DragValue::new(n)
+                .speed(0.05)
+                .clamp_range(2..=64)
+                .prefix("every "),
+        );
+    }
+}
+
+/// Choose the pattern of the supports, with its spacing or number of spokes
+fn ui_support_pattern(ui: &mut Ui, support_pattern: &mut SupportPattern) {
+    let spacing = match *support_pattern {
+        SupportPattern::Pillars(n) | SupportPattern::Rings(n) => n,
+        SupportPattern::Spokes(_) => 8,
+    };
+    egui::ComboBox::from_id_source("support_pattern")
+        .selected_text(match support_pattern {
+            SupportPattern::Pillars(_) => "Pillars",
+            SupportPattern::Spokes(_) => "Spokes",
+            SupportPattern::Rings(_) => "Rings",
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(support_pattern, SupportPattern::Pillars(spacing), "Pillars");
+            ui.selectable_value(support_pattern, SupportPattern::Spokes(8), "Spokes");
+            ui.selectable_value(support_pattern, SupportPattern::Rings(spacing), "Rings");
+        });
+    match support_pattern {
+        SupportPattern::Pillars(n) | SupportPattern::Rings(n) => ui.add(
+            egui::DragValue::new(n)
+                .speed(0.05)
+                .clamp_range(2..=64)
+                .prefix("every "),
+        ),
+        SupportPattern::Spokes(n) => ui.add(
+            egui::DragValue::new(n)
+                .speed(0.05)
+                .clamp_range(1..=64)
+                .suffix(" spokes"),
+        ),
+    };
+}
@@ -1,11 +1,19 @@
+use crate::app::algorithm_field::AlgorithmField;
+use crate::app::block_cache::BlockCache;
 use crate::app::control::Control;
 use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::layer_group::LayerGroup;
+use crate::app::data_structures::parameter_constraint::{apply_constraints, ParameterConstraint};
 use crate::app::data_structures::slice_parameters::SliceParameters;
 use crate::app::data_structures::zvec::ZVec;
-use crate::app::generation::Algorithm;
+use crate::app::error::VoxircleError;
+use crate::app::generation::Generator;
+use crate::app::lean::Lean;
+use crate::app::log::log_warning;
 use crate::app::lua_field::LuaField;
 use crate::app::sampling::sampled_parameters::LayerParameters;
 use crate::app::sampling::{SampleCombineMethod, SampleDistributeMethod};
+use crate::app::stamp::{apply_stamps, Stamp};
 
 pub fn sampling_points_update(
     only_sample_half_of_bottom_layer: bool,
@@ -20,9 +28,10 @@ pub fn sampling_points_update(
     layer_lowest: isize,
     layer_highest: isize,
 ) {
-    // Activates if the sampling options have changed (this update) or if the stack grows
-    //  (previous update). The points (may) also have to be recomputed if the stack shrinks,
-    //  when half_of_bottom or half_of_top layer options are implemented.
+    // Activates if the sampling options have changed (this update). When the stack is resized the
+    //  points are recomputed right away (see `App::recompute_sampling_points`), whether this
+    //  control is automatic or not, since the old points are at the wrong heights for new layers
+    //  (and the old bottom and top layer may only be sampled in half).
     if sampling_points_control.update() {
         // if the sampling points have changed the parameters become outdated
         parameters_current_layer_control.set_outdated();
@@ -39,188 +48,358 @@ pub fn sampling_points_update(
     }
 }
 
+/// The stacks that the parameters are computed into, with what they are computed from
+pub struct ParameterStacks<'a> {
+    pub layer_config: &'a mut ZVec<SliceParameters>,
+    pub sampled_parameters: &'a mut ZVec<LayerParameters>, // Store the configuration for each layer, handily indexed by integers
+    pub sampling_points: &'a ZVec<Vec<f64>>,
+    pub locked: &'a ZVec<bool>, // locked layers are left as they are
+    pub layer_groups: &'a mut [LayerGroup],
+}
+
+/// What the parameters of the layers are computed from besides their sliders: the code for the
+///  algorithm and for each parameter, and the options that apply to all parameters
+pub struct ParameterInputs<'a> {
+    pub algorithm_field: &'a mut AlgorithmField,
+    pub lua_fields: [&'a mut LuaField; 6], // radius_a, radius_b, tilt, offset x, offset y, squircle parameter
+    pub single_radius: bool,
+    pub parameter_constraints: &'a [ParameterConstraint],
+    pub lean: &'a Lean,
+}
+
+impl ParameterInputs<'_> {
+    fn register_success(&mut self) {
+        self.algorithm_field.register_success();
+        for lua_field in self.lua_fields.iter_mut() {
+            lua_field.register_success();
+        }
+    }
+}
+
+/// Whether the parameters and the blocks of the current layer and of all layers are outdated
+pub struct UpdateControls<'a> {
+    pub parameters_current_layer: &'a mut Control,
+    pub parameters_all_layers: &'a mut Control,
+    pub blocks_current_layer: &'a mut Control,
+    pub blocks_all_layers: &'a mut Control,
+}
+
+/// What the blocks of the layers are generated with besides their parameters
+pub struct BlockInputs<'a> {
+    pub sample_combine_method: &'a SampleCombineMethod,
+    pub fixed_grid_size: Option<usize>,
+    pub generator: &'a Generator,
+    pub stamps: &'a [Stamp],
+    pub block_cache: &'a mut BlockCache,
+}
+
 pub fn parameters_update(
-    stack_layer_config: &mut ZVec<SliceParameters>,
-    stack_sampled_parameters: &mut ZVec<LayerParameters>, // Store the configuration for each layer, handily indexed by integers
-    stack_sampling_points: &ZVec<Vec<f64>>,
-    parameters_current_layer_control: &mut Control,
-    parameters_all_layers_control: &mut Control,
-    blocks_current_layer_control: &mut Control,
-    blocks_all_layers_control: &mut Control, // fixme: we really should use this at least somewhere
+    stacks: ParameterStacks,
+    controls: &mut UpdateControls,
 
     current_layer: isize,
     layer_lowest: isize,
     layer_highest: isize,
 
-    single_radius: bool,
+    inputs: &mut ParameterInputs,
+) -> Result<(), VoxircleError> {
+    let ParameterStacks {
+        layer_config: stack_layer_config,
+        sampled_parameters: stack_sampled_parameters,
+        sampling_points: stack_sampling_points,
+        locked: stack_locked,
+        layer_groups,
+    } = stacks;
 
-    lua_field_radius_a: &mut LuaField,
-    lua_field_radius_b: &mut LuaField,
-    lua_field_tilt: &mut LuaField,
-    lua_field_center_offset_x: &mut LuaField,
-    lua_field_center_offset_y: &mut LuaField,
-    lua_field_squircle_parameter: &mut LuaField,
-) {
-    // Generate parameters to be sampled
-    if parameters_current_layer_control.update() {
-        blocks_current_layer_control.set_outdated();
+    // Generate parameters to be sampled (locked layers are left as they are)
+    if controls.parameters_current_layer.update()
+        && !stack_locked.get(current_layer).unwrap_or(false)
+    {
+        controls.blocks_current_layer.set_outdated();
+
+        // The algorithm is picked before the parameters are sampled with it
+        update_algorithm(
+            stack_layer_config.try_get_mut(current_layer)?,
+            current_layer,
+            inputs.algorithm_field,
+        );
 
         // Update parameters for the sampling
         set_parameters(
-            stack_sampled_parameters.get_mut(current_layer).unwrap(),
-            &stack_sampling_points.get(current_layer).unwrap(),
-            stack_layer_config.get(current_layer).unwrap(),
-            stack_layer_config.get(current_layer).unwrap().algorithm,
-            lua_field_radius_a,
-            lua_field_radius_b,
-            lua_field_tilt,
-            lua_field_center_offset_x,
-            lua_field_center_offset_y,
-            lua_field_squircle_parameter,
-            single_radius,
+            stack_sampled_parameters.try_get_mut(current_layer)?,
+            &stack_sampling_points.try_get(current_layer)?,
+            stack_layer_config.try_get(current_layer)?,
+            inputs,
         );
 
         // Update parameters for the sliders
         update_control_parameters(
-            stack_layer_config.get_mut(current_layer).unwrap(),
+            stack_layer_config.try_get_mut(current_layer)?,
             current_layer,
-            lua_field_radius_a,
-            lua_field_radius_b,
-            lua_field_tilt,
-            lua_field_center_offset_x,
-            lua_field_center_offset_y,
-            lua_field_squircle_parameter,
-            single_radius,
+            inputs,
         );
 
-        lua_field_radius_a.register_success();
-        lua_field_radius_b.register_success();
-        lua_field_tilt.register_success();
-        lua_field_center_offset_x.register_success();
-        lua_field_center_offset_y.register_success();
-        lua_field_squircle_parameter.register_success();
+        // The rest of its group gets the same parameters, so their blocks change too
+        if let Some(group) = layer_groups
+            .iter_mut()
+            .find(|group| group.shares(current_layer))
+        {
+            group.source = current_layer;
+            share_group_parameters(
+                group,
+                current_layer,
+                stack_layer_config,
+                stack_sampled_parameters,
+                stack_locked,
+            );
+            controls.blocks_all_layers.set_outdated();
+        }
+
+        inputs.register_success();
     }
 
     // Generate parameters to be sampled
-    if parameters_all_layers_control.update() {
-        blocks_current_layer_control.set_outdated();
+    if controls.parameters_all_layers.update() {
+        controls.blocks_current_layer.set_outdated();
 
         // Update parameters for the sampling
         for layer in layer_lowest..=layer_highest {
+            if stack_locked.get(layer).unwrap_or(false) {
+                continue;
+            }
+
+            update_algorithm(
+                stack_layer_config.try_get_mut(layer)?,
+                layer,
+                inputs.algorithm_field,
+            );
+
             set_parameters(
-                stack_sampled_parameters.get_mut(layer).unwrap(),
-                &stack_sampling_points.get(layer).unwrap(),
-                stack_layer_config.get(layer).unwrap(),
-                stack_layer_config.get(layer).unwrap().algorithm,
-                lua_field_radius_a,
-                lua_field_radius_b,
-                lua_field_tilt,
-                lua_field_center_offset_x,
-                lua_field_center_offset_y,
-                lua_field_squircle_parameter,
-                single_radius,
+                stack_sampled_parameters.try_get_mut(layer)?,
+                &stack_sampling_points.try_get(layer)?,
+                stack_layer_config.try_get(layer)?,
+                inputs,
             );
 
             // Update parameters for the sliders
-            update_control_parameters(
-                stack_layer_config.get_mut(layer).unwrap(),
-                layer,
-                lua_field_radius_a,
-                lua_field_radius_b,
-                lua_field_tilt,
-                lua_field_center_offset_x,
-                lua_field_center_offset_y,
-                lua_field_squircle_parameter,
-                single_radius,
-            )
+            update_control_parameters(stack_layer_config.try_get_mut(layer)?, layer, inputs)
         }
 
-        lua_field_radius_a.register_success();
-        lua_field_radius_b.register_success();
-        lua_field_tilt.register_success();
-        lua_field_center_offset_x.register_success();
-        lua_field_center_offset_y.register_success();
-        lua_field_squircle_parameter.register_success();
+        // Every group gets the parameters of its source layer again (the lowest layer that shares
+        //  them if the source is outside of the stack)
+        for group in layer_groups.iter() {
+            let source = Some(group.source)
+                .filter(|layer| stack_layer_config.get(*layer).is_some())
+                .or_else(|| {
+                    (group.layers[0]..=group.layers[1]).find(|layer| {
+                        group.shares(*layer) && stack_layer_config.get(*layer).is_some()
+                    })
+                });
+            if let Some(source) = source {
+                share_group_parameters(
+                    group,
+                    source,
+                    stack_layer_config,
+                    stack_sampled_parameters,
+                    stack_locked,
+                );
+            }
+        }
+
+        inputs.register_success();
     }
+
+    Ok(())
 }
 
+/// Generate the blocks of the outdated layers, output: whether any blocks changed (then the
+///  metrics have to be recomputed)
 pub fn blocks_update(
     stack_sampled_parameters: &ZVec<LayerParameters>, // Store the configuration for each layer, handily indexed by integers
     stack_blocks: &mut ZVec<Blocks>,
-    blocks_current_layer_control: &mut Control,
-    blocks_all_layers_control: &mut Control,
-    recompute_metrics: &mut bool,
+    stack_locked: &ZVec<bool>,
+    controls: &mut UpdateControls,
     current_layer: isize,
     layer_lowest: isize,
-    sample_combine_method: &SampleCombineMethod,
-) {
-    if blocks_current_layer_control.update() {
-        *recompute_metrics = true;
+    inputs: &mut BlockInputs,
+) -> Result<bool, VoxircleError> {
+    let mut changed = false;
 
-        stack_blocks.set(
+    if controls.blocks_current_layer.update() && !stack_locked.get(current_layer).unwrap_or(false) {
+        changed = true;
+
+        *stack_blocks.try_get_mut(current_layer)? = generate_layer(
+            &stack_sampled_parameters.try_get(current_layer)?,
             current_layer,
-            stack_sampled_parameters
-                .get(current_layer)
-                .unwrap()
-                .generate(sample_combine_method),
+            inputs,
         );
     }
 
-    if blocks_all_layers_control.update() {
-        *recompute_metrics = true;
+    if controls.blocks_all_layers.update() {
+        changed = true;
 
+        // The layers are zipped, so they have to be the same
+        let layers = [stack_blocks.get_minimum(), stack_blocks.get_maximum()];
+        stack_sampled_parameters.check_range(layers)?;
+        stack_locked.check_range(layers)?;
+
+        // Locked layers keep their blocks
         *stack_blocks = ZVec::new(
             stack_sampled_parameters
                 .data
                 .iter()
-                .map(|config| config.generate(sample_combine_method))
+                .zip(stack_locked.data.iter())
+                .zip(stack_blocks.data.iter())
+                .zip(layer_lowest..)
+                .map(|(((config, locked), blocks), layer)| {
+                    if *locked {
+                        blocks.clone()
+                    } else {
+                        generate_layer(config, layer, inputs)
+                    }
+                })
                 .collect(),
             layer_lowest,
         );
     }
+
+    Ok(changed)
+}
+
+/// The blocks of a layer with its stamps, generated unless they are in the cache
+fn generate_layer(
+    layer_parameters: &LayerParameters,
+    layer: isize,
+    inputs: &mut BlockInputs,
+) -> Blocks {
+    let BlockInputs {
+        sample_combine_method,
+        fixed_grid_size,
+        generator,
+        stamps,
+        block_cache,
+    } = inputs;
+    let key = BlockCache::key(
+        layer_parameters,
+        sample_combine_method,
+        *fixed_grid_size,
+        generator,
+    );
+    apply_stamps(
+        block_cache.get_or_generate(key, || {
+            generate_on_grid(
+                layer_parameters,
+                sample_combine_method,
+                *fixed_grid_size,
+                generator,
+            )
+        }),
+        stamps,
+        layer,
+        *fixed_grid_size,
+    )
+}
+
+/// Give the layers of the group that share its parameters (except locked layers) the parameters of
+///  the layer `source`, including the sampled parameters so that the layers are identical
+fn share_group_parameters(
+    group: &LayerGroup,
+    source: isize,
+    stack_layer_config: &mut ZVec<SliceParameters>,
+    stack_sampled_parameters: &mut ZVec<LayerParameters>,
+    stack_locked: &ZVec<bool>,
+) {
+    let (Some(config), Some(sampled_parameters)) = (
+        stack_layer_config.get(source),
+        stack_sampled_parameters.get(source),
+    ) else {
+        return;
+    };
+
+    for layer in group.layers[0]..=group.layers[1] {
+        if layer == source || !group.shares(layer) || stack_locked.get(layer).unwrap_or(true) {
+            continue;
+        }
+        if let Some(layer_config) = stack_layer_config.get_mut(layer) {
+            *layer_config = config.clone();
+        }
+        if let Some(layer_sampled_parameters) = stack_sampled_parameters.get_mut(layer) {
+            *layer_sampled_parameters = sampled_parameters.clone();
+        }
+    }
+}
+
+/// Generate the blocks of a layer, on the fixed grid size if there is one
+fn generate_on_grid(
+    layer_parameters: &LayerParameters,
+    sample_combine_method: &SampleCombineMethod,
+    fixed_grid_size: Option<usize>,
+    generator: &Generator,
+) -> Blocks {
+    let blocks = layer_parameters.generate(sample_combine_method, generator);
+    match fixed_grid_size {
+        None => blocks,
+        Some(grid_size) => {
+            let fixed_blocks = blocks.with_grid_size(grid_size);
+            if fixed_blocks.get_nr_blocks() < blocks.get_nr_blocks() {
+                log_warning("Blocks outside of the fixed grid size were cut off");
+            }
+            fixed_blocks
+        }
+    }
+}
+
+/// Let the algorithm code (if any) pick the algorithm of the layer
+fn update_algorithm(
+    slice_parameters: &mut SliceParameters,
+    layer: isize,
+    algorithm_field: &mut AlgorithmField,
+) {
+    if let Some(algorithm) = algorithm_field.eval(layer, slice_parameters) {
+        slice_parameters.set_algorithm(algorithm);
+    }
 }
 
 fn update_control_parameters(
     current_layer: &mut SliceParameters,
     layer: isize,
-
-    lua_field_radius_a: &mut LuaField,
-    lua_field_radius_b: &mut LuaField,
-    lua_field_tilt: &mut LuaField,
-    lua_field_center_offset_x: &mut LuaField,
-    lua_field_center_offset_y: &mut LuaField,
-    lua_field_squircle_parameter: &mut LuaField,
-    single_radius: bool,
+    inputs: &mut ParameterInputs,
 ) {
+    let [code_radius_a, code_radius_b, code_tilt, code_offset_x, code_offset_y, code_squircle] =
+        &mut inputs.lua_fields;
 
     // evaluate the lua field at the layer
-    if let Some(radius_a) = lua_field_radius_a.eval(&(layer as f64)) {
+    if let Some(radius_a) = code_radius_a.eval(&(layer as f64)) {
         current_layer.radius_a = radius_a
     }
 
-    if single_radius {
-        if let Some(radius_a) = lua_field_radius_a.eval(&(layer as f64)) {
+    if inputs.single_radius {
+        if let Some(radius_a) = code_radius_a.eval(&(layer as f64)) {
             current_layer.radius_b = radius_a
         }
     } else {
-        if let Some(radius_b) = lua_field_radius_b.eval(&(layer as f64)) {
+        if let Some(radius_b) = code_radius_b.eval(&(layer as f64)) {
             current_layer.radius_b = radius_b
         }
     }
 
-    if let Some(tilt) = lua_field_tilt.eval(&(layer as f64)) {
+    if let Some(tilt) = code_tilt.eval(&(layer as f64)) {
         current_layer.tilt = tilt
     }
-    if let Some(center_offset_x) = lua_field_center_offset_x.eval(&(layer as f64)) {
+    if let Some(center_offset_x) = code_offset_x.eval(&(layer as f64)) {
         current_layer.center_offset_x = center_offset_x
     }
-    if let Some(center_offset_y) = lua_field_center_offset_y.eval(&(layer as f64)) {
+    if let Some(center_offset_y) = code_offset_y.eval(&(layer as f64)) {
         current_layer.center_offset_y = center_offset_y
     }
 
-    if let Some(squircle_parameter) = lua_field_squircle_parameter.eval(&(layer as f64)) {
+    if let Some(squircle_parameter) = code_squircle.eval(&(layer as f64)) {
         current_layer.squircle_parameter = squircle_parameter
     }
+
+    current_layer.layer = layer as f64;
+
+    apply_constraints(inputs.parameter_constraints, current_layer);
 }
 
 /// Update (old) input LayerParameters object with new values evaluated from the code
@@ -228,17 +407,18 @@ fn set_parameters(
     sampled_parameters: &mut LayerParameters,
     sampling_points: &Vec<f64>,
     default_parameters: SliceParameters,
-
-    algorithm: Algorithm,
-    lua_field_radius_a: &mut LuaField,
-    lua_field_radius_b: &mut LuaField,
-    lua_field_tilt: &mut LuaField,
-    lua_field_center_offset_x: &mut LuaField,
-    lua_field_center_offset_y: &mut LuaField,
-    lua_field_squircle_parameter: &mut LuaField,
-    single_radius: bool,
+    inputs: &mut ParameterInputs,
 ) {
+    let [code_radius_a, code_radius_b, code_tilt, code_offset_x, code_offset_y, code_squircle] =
+        &mut inputs.lua_fields;
+    let (single_radius, parameter_constraints, lean) = (
+        inputs.single_radius,
+        inputs.parameter_constraints,
+        inputs.lean,
+    );
+
     // Set the algorithm & nr. of samples
+    let algorithm = default_parameters.algorithm;
     sampled_parameters.algorithm = algorithm;
     sampled_parameters.nr_samples = sampling_points.len();
 
@@ -246,31 +426,73 @@ fn set_parameters(
     sampled_parameters.parameters = sampling_points
         .iter()
         .map(|layer| {
-            SliceParameters {
+            let mut slice_parameters = SliceParameters {
                 algorithm,
-                radius_a: lua_field_radius_a
+                radius_a: code_radius_a
                     .eval(layer)
                     .unwrap_or(default_parameters.radius_a),
                 radius_b: if single_radius {
-                    lua_field_radius_a
+                    code_radius_a
                         .eval(layer)
                         .unwrap_or(default_parameters.radius_a)
                 } else {
-                    lua_field_radius_b
+                    code_radius_b
                         .eval(layer)
                         .unwrap_or(default_parameters.radius_b)
                 },
-                tilt: lua_field_tilt.eval(layer).unwrap_or(default_parameters.tilt),
-                center_offset_x: lua_field_center_offset_x
+                tilt: code_tilt.eval(layer).unwrap_or(default_parameters.tilt),
+                center_offset_x: code_offset_x
                     .eval(layer)
                     .unwrap_or(default_parameters.center_offset_x),
-                center_offset_y: lua_field_center_offset_y
+                center_offset_y: code_offset_y
                     .eval(layer)
                     .unwrap_or(default_parameters.center_offset_y),
-                squircle_parameter: lua_field_squircle_parameter
+                squircle_parameter: code_squircle
                     .eval(layer)
                     .unwrap_or(default_parameters.squircle_parameter),
-            }
+                layer: *layer,
+                ..default_parameters.clone()
+            };
+            apply_constraints(parameter_constraints, &mut slice_parameters);
+            // The lean comes on top of the center offset (the sliders keep the offset without it)
+            lean.apply(&slice_parameters)
         })
         .collect()
 }
+
+/// The parameters at a (fractional) height between two layers, for previewing how the shape
+///  changes between the layers. The code is evaluated at the height, the parameters without code
+///  are interpolated between the layers below and above. The fields are not changed if the code
+///  fails (then the interpolated parameter is used).
+pub fn parameters_between_layers(
+    height: f64,
+    stack_layer_config: &ZVec<SliceParameters>,
+    lua_fields: [&LuaField; 6], // radius_a, radius_b, tilt, offset x, offset y, squircle parameter
+    single_radius: bool,
+    parameter_constraints: &[ParameterConstraint],
+) -> Option<SliceParameters> {
+    let below = stack_layer_config.get(height.floor() as isize)?;
+    let above = stack_layer_config.get(height.ceil() as isize)?;
+    let interpolated = below.interpolate(&above, height - height.floor());
+
+    let [radius_a, radius_b, tilt, center_offset_x, center_offset_y, squircle_parameter] =
+        lua_fields.map(|lua_field| lua_field.try_eval(height));
+    let radius_a = radius_a.unwrap_or(interpolated.radius_a);
+
+    let mut slice_parameters = SliceParameters {
+        radius_a,
+        radius_b: if single_radius {
+            radius_a
+        } else {
+            radius_b.unwrap_or(interpolated.radius_b)
+        },
+        tilt: tilt.unwrap_or(interpolated.tilt),
+        center_offset_x: center_offset_x.unwrap_or(interpolated.center_offset_x),
+        center_offset_y: center_offset_y.unwrap_or(interpolated.center_offset_y),
+        squircle_parameter: squircle_parameter.unwrap_or(interpolated.squircle_parameter),
+        layer: height,
+        ..interpolated
+    };
+    apply_constraints(parameter_constraints, &mut slice_parameters);
+    Some(slice_parameters)
+}
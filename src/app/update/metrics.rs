@@ -1,70 +1,139 @@
 use crate::app;
 use crate::app::data_structures::blocks::Blocks;
 use crate::app::data_structures::slice_parameters::SliceParameters;
-use crate::app::data_structures::symmetry_type::SymmetryType;
 use crate::app::data_structures::zvec::ZVec;
+use crate::app::error::VoxircleError;
+use crate::app::generation::cell_aspect::{bounds_to_cells, to_cells};
 use crate::app::math::exact_squircle_bounds::exact_squircle_bounds;
 use crate::app::math::square_max::square_max;
-use app::metrics::convex_hull::get_convex_hull;
+use crate::app::math::superellipse_measure::superellipse_area_perimeter;
+use crate::app::sampling::SampleCombineMethod;
+use crate::app::view::View;
+use app::metrics::boundary_3d::Connectivity;
+use app::metrics::octants::get_octant_counts;
 
+/// The expensive metrics that are only computed when they are shown (most on the background
+///  thread, see `MetricsWorker`), and the settings of the 3D metrics
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct EnabledMetrics {
+    pub symmetry_type: bool,
+    pub convex_hull: bool,
+    pub projection_union: bool,
+    pub projection_intersection: bool,
+    pub boundary_segments: bool, // needs the connected components of the boundary
+    pub boundary_tour: bool,
+    pub boundary_3d: bool,
+    pub interior_3d: bool,
+    pub holes: bool,
+    pub thin_necks: bool,
+    pub repeated_layers: bool, // in the layer navigation and the status bar, only with layers
+    pub connectivity_3d: Connectivity, // which neighbors touch, for the 3D boundary and holes
+    pub wall_thickness: usize, // of the 3D boundary
+}
+
+impl EnabledMetrics {
+    pub fn from_view(view: &View, layers_enabled: bool) -> Self {
+        Self {
+            symmetry_type: view.mirrors,
+            convex_hull: view.convex_hull,
+            projection_union: view.projection_union,
+            projection_intersection: view.projection_intersection,
+            boundary_segments: view.boundary_segments,
+            boundary_tour: view.boundary_tour,
+            boundary_3d: view.boundary_3d,
+            interior_3d: view.interior_3d,
+            holes: view.holes,
+            thin_necks: view.thin_necks,
+            repeated_layers: layers_enabled,
+            connectivity_3d: view.connectivity_3d,
+            wall_thickness: view.wall_thickness,
+        }
+    }
+
+    /// Is some metric enabled in `self` that was not enabled in `previous`, or has a setting
+    ///  changed? (Then the metrics have to be recomputed, the disabled metric may be outdated)
+    pub fn newly_enabled(&self, previous: &Self) -> bool {
+        (self.symmetry_type && !previous.symmetry_type)
+            || (self.convex_hull && !previous.convex_hull)
+            || (self.projection_union && !previous.projection_union)
+            || (self.projection_intersection && !previous.projection_intersection)
+            || (self.boundary_segments && !previous.boundary_segments)
+            || (self.boundary_tour && !previous.boundary_tour)
+            || (self.boundary_3d && !previous.boundary_3d)
+            || (self.interior_3d && !previous.interior_3d)
+            || (self.holes && !previous.holes)
+            || (self.thin_necks && !previous.thin_necks)
+            || (self.repeated_layers && !previous.repeated_layers)
+            || self.connectivity_3d != previous.connectivity_3d
+            || self.wall_thickness != previous.wall_thickness
+    }
+}
+
+/// The metrics that are computed on the UI thread whenever the blocks change (the others are
+///  `ExpensiveMetrics`)
+#[derive(Default)]
+pub struct CheapMetrics {
+    pub nr_blocks_total: u64,
+    pub nr_blocks_interior: u64,
+    pub nr_blocks_boundary: u64,
+    pub nr_blocks_stack: u64, // the whole stack, for the budget and the materials
+    pub boundary_2d: Blocks,
+    pub interior_2d: Blocks,
+    pub complement_2d: Blocks,
+    pub projection_union: Blocks, // while shown
+    pub projection_intersection: Blocks,
+    pub outer_corners: Vec<[f64; 2]>,
+    pub octant_counts: [f64; 8], // boundary blocks per octant around the shape center
+    pub center_coord: [f64; 2],
+    pub shape_area: f64, // of the continuous shape of the current layer
+    pub shape_perimeter: f64,
+
+    pub global_bounding_box: [[f64; 2]; 2], // Is for viewport zoom
+    pub global_blocks_bounding_box: [[f64; 2]; 2], // same, but for the blocks instead of the shapes
+    pub layer_bounding_box: [[f64; 2]; 2],  // same, but only for the current layer
+    pub layer_blocks_bounding_box: [[f64; 2]; 2],
+}
+
+/// Update the cheap metrics. The expensive metrics are computed by the `MetricsWorker` from the
+///  updated boundary and outer corners.
 pub fn update_metrics(
     current_layer: isize,
-    layer_lowest: isize,
-    layer_highest: isize,
     current_layer_blocks: Blocks,
     stack_blocks: &ZVec<Blocks>,
     stack_layer_config: &ZVec<SliceParameters>,
-
-    // Metrics
-    nr_blocks_total: &mut u64,
-    nr_blocks_interior: &mut u64,
-    nr_blocks_boundary: &mut u64,
-    boundary_2d: &mut Blocks,
-    interior_2d: &mut Blocks,
-    complement_2d: &mut Blocks,
-    boundary_3d: &mut ZVec<Blocks>,
-    interior_3d: &mut ZVec<Blocks>,
-    convex_hull: &mut Vec<[f64; 2]>,
-    outer_corners: &mut Vec<[f64; 2]>,
-    symmetry_type: &mut SymmetryType,
-    center_coord: &mut [f64; 2],
-
-    global_bounding_box: &mut [[f64; 2]; 2],
-) {
+    cell_aspect: f64,
+    enabled_metrics: &EnabledMetrics,
+    metrics: &mut CheapMetrics,
+) -> Result<(), VoxircleError> {
     // update 2d spatial metrics
-    *interior_2d = current_layer_blocks.get_interior();
-    *boundary_2d = current_layer_blocks.get_boundary();
-    *complement_2d = current_layer_blocks.get_complement();
-
-    // update 3d spatial metrics
-    *boundary_3d = app::metrics::boundary_3d::boundary_3d(
-        stack_blocks,
-        layer_lowest,
-        layer_highest,
-        true,
-        true,
-    );
+    metrics.interior_2d = current_layer_blocks.get_interior();
+    metrics.boundary_2d = current_layer_blocks.get_boundary();
+    metrics.complement_2d = current_layer_blocks.get_complement();
 
-    *interior_3d = app::metrics::boundary_3d::interior_3d(
-        stack_blocks,
-        layer_lowest,
-        layer_highest,
-        true,
-        true,
-    );
+    // update projections of the whole stack (footprint of the structure and the part common to
+    //  all layers), while they are shown
+    let layers = stack_blocks.data.iter().collect::<Vec<_>>();
+    if enabled_metrics.projection_union {
+        metrics.projection_union = Blocks::combine(&SampleCombineMethod::AnySamples, &layers);
+    }
+    if enabled_metrics.projection_intersection {
+        metrics.projection_intersection =
+            Blocks::combine(&SampleCombineMethod::AllSamples, &layers);
+    }
 
     // update numerical metrics
-    *nr_blocks_total = current_layer_blocks.get_nr_blocks();
-    *nr_blocks_interior = interior_2d.get_nr_blocks();
-    *nr_blocks_boundary = boundary_2d.get_nr_blocks();
+    metrics.nr_blocks_total = current_layer_blocks.get_nr_blocks();
+    metrics.nr_blocks_interior = metrics.interior_2d.get_nr_blocks();
+    metrics.nr_blocks_boundary = metrics.boundary_2d.get_nr_blocks();
+    metrics.nr_blocks_stack = stack_blocks.data.iter().map(|b| b.get_nr_blocks()).sum();
 
-    *outer_corners = current_layer_blocks.get_outer_corners();
-    *convex_hull = get_convex_hull(&outer_corners);
+    metrics.outer_corners = current_layer_blocks.get_outer_corners();
 
-    *global_bounding_box = stack_layer_config
+    metrics.global_bounding_box = stack_layer_config
         .data
         .iter()
-        .map(|g_c| exact_squircle_bounds(g_c, 1.1))
+        .flat_map(|g_c| g_c.shape_array.get_copies(g_c)) // include all copies of arrays
+        .map(|g_c| bounds_to_cells(exact_squircle_bounds(&g_c, 1.0), cell_aspect))
         .fold(
             [
                 [f64::INFINITY, f64::INFINITY],
@@ -73,12 +142,56 @@ pub fn update_metrics(
             |a, b| square_max(a, b),
         );
 
-    *symmetry_type = current_layer_blocks.get_symmetry_type();
+    metrics.center_coord = current_layer_blocks.get_center_coord();
 
-    *center_coord = current_layer_blocks.get_center_coord();
+    // continuous shape of the current layer (a single copy of arrays)
+    let slice_parameters = stack_layer_config.try_get(current_layer)?;
+    (metrics.shape_area, metrics.shape_perimeter) = superellipse_area_perimeter(
+        slice_parameters.radius_a,
+        slice_parameters.radius_b,
+        slice_parameters.squircle_parameter,
+    );
+    metrics.octant_counts = get_octant_counts(
+        &metrics.boundary_2d,
+        to_cells(
+            [
+                slice_parameters.center_offset_x,
+                slice_parameters.center_offset_y,
+            ],
+            cell_aspect,
+        ),
+    );
 
-    *global_bounding_box = square_max(
-        *global_bounding_box,
-        exact_squircle_bounds(&stack_layer_config.get(current_layer).unwrap(), 1.1),
+    metrics.global_bounding_box = square_max(
+        metrics.global_bounding_box,
+        bounds_to_cells(exact_squircle_bounds(&slice_parameters, 1.0), cell_aspect),
     );
+
+    // The blocks can stick out of the shape (or be much smaller, for thin shapes). Without any
+    //  blocks, use the shape instead.
+    metrics.global_blocks_bounding_box = stack_blocks
+        .data
+        .iter()
+        .filter(|blocks| blocks.get_nr_blocks() != 0)
+        .map(|blocks| blocks.get_bounds_floats())
+        .reduce(square_max)
+        .unwrap_or(metrics.global_bounding_box);
+
+    // The same for only the current layer
+    metrics.layer_bounding_box = slice_parameters
+        .shape_array
+        .get_copies(&slice_parameters)
+        .iter()
+        .map(|g_c| bounds_to_cells(exact_squircle_bounds(g_c, 1.0), cell_aspect))
+        .fold(
+            bounds_to_cells(exact_squircle_bounds(&slice_parameters, 1.0), cell_aspect),
+            square_max,
+        );
+    metrics.layer_blocks_bounding_box = if current_layer_blocks.get_nr_blocks() != 0 {
+        current_layer_blocks.get_bounds_floats()
+    } else {
+        metrics.layer_bounding_box
+    };
+
+    Ok(())
 }
@@ -0,0 +1,177 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use eframe::egui::Context;
+
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::symmetry_type::SymmetryType;
+use crate::app::data_structures::zvec::ZVec;
+use crate::app::metrics::boundary_3d::{boundary_3d, interior_3d};
+use crate::app::metrics::boundary_segments::{get_boundary_segments, BoundarySegments};
+use crate::app::metrics::boundary_tour::{get_boundary_tour, BoundaryTour};
+use crate::app::metrics::convex_hull::get_convex_hull;
+use crate::app::metrics::holes::{get_holes_2d, get_holes_3d, Holes};
+use crate::app::metrics::repeated_layers::{get_repeated_layers, LayerRun};
+use crate::app::metrics::thin_necks::get_thin_necks;
+use crate::app::update::metrics::EnabledMetrics;
+
+/// Snapshot of everything the expensive metrics are computed from
+pub struct MetricsJob {
+    pub enabled_metrics: EnabledMetrics,
+    pub layer_lowest: isize,
+    pub layer_highest: isize,
+    pub current_layer_blocks: Blocks,
+    pub stack_blocks: ZVec<Blocks>,
+    pub boundary_2d: Blocks,
+    pub outer_corners: Vec<[f64; 2]>,
+}
+
+/// The expensive metrics of a job, None if they were not enabled (or their computation failed)
+#[derive(Default)]
+pub struct ExpensiveMetrics {
+    pub symmetry_type: Option<SymmetryType>,
+    pub convex_hull: Option<Vec<[f64; 2]>>,
+    pub boundary_segments: Option<BoundarySegments>,
+    pub boundary_tour: Option<BoundaryTour>,
+    pub boundary_3d: Option<ZVec<Blocks>>,
+    pub interior_3d: Option<ZVec<Blocks>>,
+    pub holes: Option<Holes>,
+    pub thin_necks: Option<Vec<[f64; 2]>>,
+    pub repeated_layers: Option<Vec<LayerRun>>,
+}
+
+impl ExpensiveMetrics {
+    /// Compute the enabled metrics, each on its own thread (they don't depend on each other)
+    fn compute(job: &MetricsJob) -> Self {
+        let enabled = job.enabled_metrics;
+        let (lowest, highest) = (job.layer_lowest, job.layer_highest);
+        let (connectivity, thickness) = (enabled.connectivity_3d, enabled.wall_thickness);
+
+        thread::scope(|scope| {
+            let symmetry_type = enabled
+                .symmetry_type
+                .then(|| scope.spawn(|| job.current_layer_blocks.get_symmetry_type()));
+            let convex_hull = enabled
+                .convex_hull
+                .then(|| scope.spawn(|| get_convex_hull(&job.outer_corners)));
+            let boundary_segments = enabled
+                .boundary_segments
+                .then(|| scope.spawn(|| get_boundary_segments(&job.boundary_2d)));
+            let boundary_tour = enabled
+                .boundary_tour
+                .then(|| scope.spawn(|| get_boundary_tour(&job.boundary_2d)));
+            let boundary_3d = enabled.boundary_3d.then(|| {
+                scope.spawn(|| {
+                    boundary_3d(
+                        &job.stack_blocks,
+                        lowest,
+                        highest,
+                        true,
+                        true,
+                        connectivity,
+                        thickness,
+                    )
+                })
+            });
+            let interior_3d = enabled.interior_3d.then(|| {
+                scope.spawn(|| {
+                    interior_3d(
+                        &job.stack_blocks,
+                        lowest,
+                        highest,
+                        true,
+                        true,
+                        connectivity,
+                        thickness,
+                    )
+                })
+            });
+            let holes = enabled.holes.then(|| {
+                scope.spawn(|| {
+                    let mut holes = Holes::default();
+                    (holes.nr_holes_2d, holes.holes_2d) = get_holes_2d(&job.current_layer_blocks);
+                    (holes.nr_holes_3d, holes.holes_3d, holes.genus_3d) =
+                        get_holes_3d(&job.stack_blocks, lowest, highest, connectivity);
+                    holes
+                })
+            });
+            let thin_necks = enabled
+                .thin_necks
+                .then(|| scope.spawn(|| get_thin_necks(&job.current_layer_blocks)));
+            let repeated_layers = enabled
+                .repeated_layers
+                .then(|| scope.spawn(|| get_repeated_layers(&job.stack_blocks)));
+
+            // A metric that panics is left out (so the old value stays), the others still arrive
+            Self {
+                symmetry_type: symmetry_type.and_then(|handle| handle.join().ok()),
+                convex_hull: convex_hull.and_then(|handle| handle.join().ok()),
+                boundary_segments: boundary_segments.and_then(|handle| handle.join().ok()),
+                boundary_tour: boundary_tour.and_then(|handle| handle.join().ok()),
+                boundary_3d: boundary_3d.and_then(|handle| handle.join().ok()),
+                interior_3d: interior_3d.and_then(|handle| handle.join().ok()),
+                holes: holes.and_then(|handle| handle.join().ok()),
+                thin_necks: thin_necks.and_then(|handle| handle.join().ok()),
+                repeated_layers: repeated_layers.and_then(|handle| handle.join().ok()),
+            }
+        })
+    }
+}
+
+/// Computes the expensive metrics on a background thread, so that the UI doesn't stutter on large
+///  stacks. The old metrics are shown until the new ones are ready. Jobs that are outdated before
+///  they are started are skipped.
+pub struct MetricsWorker {
+    jobs: Sender<(u64, MetricsJob)>,
+    results: Receiver<(u64, ExpensiveMetrics)>,
+    last_id: u64,
+    pub is_pending: bool, // a job was submitted whose metrics have not arrived yet
+}
+
+impl MetricsWorker {
+    /// Start the background thread. It requests a repaint of `ctx` when a job is done.
+    pub fn new(ctx: Context) -> Self {
+        let (jobs, jobs_receiver) = channel::<(u64, MetricsJob)>();
+        let (results_sender, results) = channel();
+
+        thread::spawn(move || {
+            // Stops when the MetricsWorker (and so the sender) is dropped
+            while let Ok(mut job) = jobs_receiver.recv() {
+                // Only the newest job matters
+                while let Ok(newer_job) = jobs_receiver.try_recv() {
+                    job = newer_job;
+                }
+
+                let (id, job) = job;
+                if results_sender
+                    .send((id, ExpensiveMetrics::compute(&job)))
+                    .is_err()
+                {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        Self {
+            jobs,
+            results,
+            last_id: 0,
+            is_pending: false,
+        }
+    }
+
+    pub fn submit(&mut self, job: MetricsJob) {
+        self.last_id += 1;
+        self.is_pending = self.jobs.send((self.last_id, job)).is_ok();
+    }
+
+    /// The newest metrics that arrived since the last poll, if any. Call every frame.
+    pub fn poll(&mut self) -> Option<ExpensiveMetrics> {
+        let (id, metrics) = self.results.try_iter().last()?;
+        if id == self.last_id {
+            self.is_pending = false;
+        }
+        Some(metrics)
+    }
+}
@@ -1,2 +1,3 @@
 pub mod logic;
 pub mod metrics;
+pub mod metrics_worker;
@@ -0,0 +1,67 @@
+use crate::app::data_structures::blocks::Blocks;
+use crate::app::data_structures::parameter_constraint::Parameter;
+use crate::app::data_structures::slice_parameters::SliceParameters;
+use crate::app::generation::Generator;
+use crate::app::math::random::{RandomStream, SplitMix64};
+use crate::app::sampling::sampled_parameters::LayerParameters;
+
+/// Randomly perturbed copies of the current layer, to explore which small change of the offset
+///  and tilt gives the nicest block pattern
+pub struct VariationGallery {
+    pub seed: u64, // of this batch of variations, combined with the random seed of the project
+    pub nr_variations: usize,
+    pub offset_amplitude: f64, // largest change of the center offset (in both directions)
+    pub tilt_amplitude: f64,   // largest change of the tilt (radians)
+
+    // The generated variations (the first one is the unchanged layer)
+    pub variations: Vec<(SliceParameters, Blocks)>,
+}
+
+impl Default for VariationGallery {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            nr_variations: 9,
+            offset_amplitude: 0.5,
+            tilt_amplitude: 0.1,
+            variations: vec![],
+        }
+    }
+}
+
+impl VariationGallery {
+    /// Generate the variations of the input parameters (the same seeds give the same variations),
+    ///  keeping the locked parameters
+    pub fn generate(
+        &mut self,
+        slice_parameters: &SliceParameters,
+        random_seed: u64,
+        locked_parameters: &[Parameter],
+        generator: &Generator,
+    ) {
+        let mut rng = SplitMix64::for_stream(random_seed, RandomStream::Variations, self.seed);
+
+        self.variations = (0..self.nr_variations)
+            .map(|i| {
+                let mut variation = slice_parameters.clone();
+                if i != 0 {
+                    // Locked parameters still draw their change, so they don't change the others
+                    for (parameter, amplitude) in [
+                        (Parameter::CenterOffsetX, self.offset_amplitude),
+                        (Parameter::CenterOffsetY, self.offset_amplitude),
+                        (Parameter::Tilt, self.tilt_amplitude),
+                    ] {
+                        let change = rng.next_symmetric(amplitude);
+                        if !locked_parameters.contains(&parameter) {
+                            *parameter.get_mut(&mut variation) += change;
+                        }
+                    }
+                }
+
+                let blocks = LayerParameters::single(&variation, generator);
+
+                (variation, blocks)
+            })
+            .collect();
+    }
+}
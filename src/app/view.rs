@@ -1,33 +1,431 @@
+use crate::app::colors::{COLOR_GRID_COARSE, COLOR_GRID_FINE, COLOR_GRID_MEDIUM, COLOR_WIRE};
+use crate::app::data_structures::lattice::Lattice;
+use crate::app::generation::cell_aspect::from_cells;
+use crate::app::metrics::boundary_3d::Connectivity;
+use crate::app::supports::SupportPattern;
+use eframe::egui::Color32;
+
+// Key in the app storage, so that the viewport keeps zooming to the current layer (or the whole
+//  stack) after a restart
+pub const ZOOM_TO_LAYER_KEY: &str = "zoom_to_layer";
+
+/// Shading of the blocks to help counting in game (aligned to the global coordinates)
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub enum BlockPattern {
+    #[default]
+    None,
+    Checkerboard,
+    EveryNthColumn(usize),
+    EveryNthRow(usize),
+}
+
+impl BlockPattern {
+    /// Is the block with left bottom corner `coord` highlighted by the pattern?
+    pub fn highlights(&self, coord: [f64; 2]) -> bool {
+        let [x, y] = [coord[0] as isize, coord[1] as isize];
+        match *self {
+            BlockPattern::None => false,
+            BlockPattern::Checkerboard => (x + y).rem_euclid(2) == 0,
+            BlockPattern::EveryNthColumn(n) => x.rem_euclid(n.max(1) as isize) == 0,
+            BlockPattern::EveryNthRow(n) => y.rem_euclid(n.max(1) as isize) == 0,
+        }
+    }
+}
+
+/// Sets of blocks drawn over each other in the viewport
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlockOverlay {
+    ProjectionUnion,
+    Blocks,
+    BetweenLayers,
+    ProjectionIntersection,
+    Complement,
+    Holes3d,
+    Holes2d,
+    Boundary3d,
+    Boundary2d,
+    Interior2d,
+    Interior3d,
+    Supports,
+    CenterBlocks,
+}
+
+impl BlockOverlay {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockOverlay::ProjectionUnion => "Union of layers",
+            BlockOverlay::Blocks => "Blocks",
+            BlockOverlay::BetweenLayers => "Between layers",
+            BlockOverlay::ProjectionIntersection => "Intersection of layers",
+            BlockOverlay::Complement => "Complement",
+            BlockOverlay::Holes3d => "Holes in the stack",
+            BlockOverlay::Holes2d => "Holes in the layer",
+            BlockOverlay::Boundary3d => "3D Boundary",
+            BlockOverlay::Boundary2d => "Layer Boundary",
+            BlockOverlay::Interior2d => "Layer Interior",
+            BlockOverlay::Interior3d => "3D Interior",
+            BlockOverlay::Supports => "Supports",
+            BlockOverlay::CenterBlocks => "Center blocks",
+        }
+    }
+
+    /// Does the overlay only make sense for a stack of layers?
+    pub fn is_3d(&self) -> bool {
+        matches!(
+            self,
+            BlockOverlay::ProjectionUnion
+                | BlockOverlay::BetweenLayers
+                | BlockOverlay::ProjectionIntersection
+                | BlockOverlay::Holes3d
+                | BlockOverlay::Boundary3d
+                | BlockOverlay::Interior3d
+        )
+    }
+
+    pub fn is_shown(&self, view: &View) -> bool {
+        match self {
+            BlockOverlay::ProjectionUnion => view.projection_union,
+            BlockOverlay::Blocks => view.blocks,
+            BlockOverlay::BetweenLayers => view.between_layers && view.between_layers_blocks,
+            BlockOverlay::ProjectionIntersection => view.projection_intersection,
+            BlockOverlay::Complement => view.complement,
+            BlockOverlay::Holes3d | BlockOverlay::Holes2d => view.holes,
+            BlockOverlay::Boundary3d => view.boundary_3d,
+            BlockOverlay::Boundary2d => view.boundary_2d,
+            BlockOverlay::Interior2d => view.interior_2d,
+            BlockOverlay::Interior3d => view.interior_3d,
+            BlockOverlay::Supports => view.supports,
+            BlockOverlay::CenterBlocks => view.center_blocks,
+        }
+    }
+
+    /// The toggle of the overlay (the holes in the layer and the stack share one)
+    pub fn shown_mut<'a>(&self, view: &'a mut View) -> &'a mut bool {
+        match self {
+            BlockOverlay::ProjectionUnion => &mut view.projection_union,
+            BlockOverlay::Blocks => &mut view.blocks,
+            BlockOverlay::BetweenLayers => &mut view.between_layers_blocks,
+            BlockOverlay::ProjectionIntersection => &mut view.projection_intersection,
+            BlockOverlay::Complement => &mut view.complement,
+            BlockOverlay::Holes3d | BlockOverlay::Holes2d => &mut view.holes,
+            BlockOverlay::Boundary3d => &mut view.boundary_3d,
+            BlockOverlay::Boundary2d => &mut view.boundary_2d,
+            BlockOverlay::Interior2d => &mut view.interior_2d,
+            BlockOverlay::Interior3d => &mut view.interior_3d,
+            BlockOverlay::Supports => &mut view.supports,
+            BlockOverlay::CenterBlocks => &mut view.center_blocks,
+        }
+    }
+}
+
+/// How an overlay is drawn, the overlays are drawn in the order of `View::overlays`
+#[derive(Debug, Clone, Copy)]
+pub struct OverlayStyle {
+    pub overlay: BlockOverlay,
+    pub opacity: f32,    // of the fill and the wireframe
+    pub wireframe: bool, // outline every block
+}
+
+/// Everything drawn over the blocks in the viewport, hidden in the clean view
+const OVERLAY_TOGGLES: [fn(&mut View) -> &mut bool; 23] = [
+    |view| &mut view.distance_bands,
+    |view| &mut view.boundary_2d,
+    |view| &mut view.interior_2d,
+    |view| &mut view.complement,
+    |view| &mut view.intersect_area,
+    |view| &mut view.boundary_3d,
+    |view| &mut view.interior_3d,
+    |view| &mut view.projection_union,
+    |view| &mut view.projection_intersection,
+    |view| &mut view.convex_hull,
+    |view| &mut view.outer_corners,
+    |view| &mut view.boundary_tour,
+    |view| &mut view.boundary_segments,
+    |view| &mut view.holes,
+    |view| &mut view.thin_necks,
+    |view| &mut view.center_blocks,
+    |view| &mut view.supports,
+    |view| &mut view.bounds,
+    |view| &mut view.mirrors,
+    |view| &mut view.octants,
+    |view| &mut view.pinned_outline,
+    |view| &mut view.between_layers,
+    |view| &mut view.sub_blocks,
+];
+
 pub struct View {
+    // Grid lines: steps and colors from fine to coarse
+    pub grid_steps: [f64; 3],
+    pub grid_colors: [Color32; 3],
+    pub hide_fine_grid: bool, // hide grid lines that are very close together when zoomed out
+    pub grid_lattice: Lattice, // lines along the block edges, or through the block centers
+    pub distance_bands: bool,
+    pub distance_band_width: f64,
     pub blocks: bool,
+    pub block_pattern: BlockPattern,
+    pub overlays: Vec<OverlayStyle>, // in draw order, the last one is on top
+    pub wire_color: Color32,
+    pub wire_width: f32,
     pub boundary_2d: bool,
     pub interior_2d: bool,
     pub complement: bool,
     pub intersect_area: bool,
     pub boundary_3d: bool,
     pub interior_3d: bool,
+    pub connectivity_3d: Connectivity, // which neighbors touch for the 3D boundary, interior and holes
+    pub wall_thickness: usize,         // blocks of the 3D boundary measured from the outside
+    pub projection_union: bool,
+    pub projection_intersection: bool,
     pub convex_hull: bool,
     pub outer_corners: bool,
+    pub boundary_tour: bool,
+    pub boundary_segments: bool,
+    pub boundary_segment_labels: bool, // letter and block count on each segment
+    pub holes: bool,
+    pub thin_necks: bool, // warn about blocks that only touch the rest diagonally
     pub center_blocks: bool,
+    pub supports: bool, // interior blocks in the support pattern
+    pub support_pattern: SupportPattern,
     pub bounds: bool,
     pub mirrors: bool,
+    pub octants: bool, // octant boundaries around the shape center, with the block counts
+    pub vertical_slice: bool,
+    pub variation_gallery: bool,
+    pub find_replace: bool,
+    pub lean: bool, // the axis of the stack is shown while this is open
+    pub fit_to_points: bool,
+    pub parameter_history: bool,
+    pub export_script: bool,
+    pub calculator: bool,
+    pub shape_through_points: bool, // viewport clicks construct a shape while this is open
+    pub text_stamp: bool,           // viewport clicks place the text stamp while this is open
+    pub reference_image: bool,
+    pub layout_canvas: bool,
+    pub template_gallery: bool,
+    pub arch: bool,
+    pub log_console: bool,
+    pub viewer_mode: bool, // only the viewport, navigation and metrics, for following the plan
+    // Colors for components (boundary segments, structures of the layout)
+    pub color_blind_palette: bool,
+    pub pinned_outline: bool, // show the target shape of layer `pinned_layer` on every layer
+    pub pinned_layer: isize,
+    // Label the onion skinned samples with their height and evaluated radius
+    pub sample_labels: bool,
+    // Show the shape at the fractional height `between_layers_height` (and its blocks)
+    pub between_layers: bool,
+    pub between_layers_height: f64,
+    pub between_layers_blocks: bool,
+    pub sub_blocks: bool, // slabs and stairs that follow the shape at half a block resolution
+    pub depth_shading: bool, // blocks deeper inside the stack are darker
+    pub minecraft_axes: bool, // label the axes X and Z with a north arrow instead of x and y
+    pub flip_z: bool,     // south up instead of north up
+    // Show world coordinates, with the shape center at the Minecraft X and Z of `world_center`
+    pub world_anchor: bool,
+    pub world_center: [f64; 2],
+    pub zoom_to_blocks: bool, // zoom to the blocks instead of the continuous shapes
+    pub zoom_to_layer: bool,  // zoom to the current layer instead of the whole stack
+    pub zoom_padding: f64,    // factor by which the zoomed box is larger than the shapes or blocks
+    pub zoom_margin: f64,     // extra space around the zoomed box (in blocks)
+    pub coord_copy_format: String, // format of the block coordinates copied by right clicking
+    pub build_order_all_layers: bool, // export the build order of all layers (or only this one)
+    // Show the parameters of `split_layer` next to those of the current layer
+    pub split_parameters: bool,
+    pub split_layer: isize,
+    // Only the blocks in the viewport (no overlays, target shape or axes), for screenshots. The
+    //  overlays that were shown before are shown again when leaving the clean view.
+    pub clean_view: bool,
+    pub shown_before_clean_view: Vec<bool>,
+}
+
+impl View {
+    /// Split the world position of the shape center into the whole number of blocks that the
+    ///  viewport coordinates are shifted by, and the center offset of the shape within its block
+    ///  (in viewport coordinates, between 0 and 1). None without a world anchor.
+    pub fn world_anchor_split(&self) -> Option<([isize; 2], [f64; 2])> {
+        if !self.world_anchor {
+            return None;
+        }
+        let [x, z] = self.world_center;
+        let y = if self.flip_z { z } else { -z }; // see `minecraft_z`
+
+        Some((
+            [x.floor() as isize, y.floor() as isize],
+            [x - x.floor(), y - y.floor()],
+        ))
+    }
+
+    /// Hide every overlay except the blocks, or show the overlays that were hidden again
+    pub fn set_clean_view(&mut self, clean_view: bool) {
+        if clean_view == self.clean_view {
+            return;
+        }
+        self.clean_view = clean_view;
+
+        if clean_view {
+            self.shown_before_clean_view = OVERLAY_TOGGLES
+                .iter()
+                .map(|shown_mut| std::mem::take(shown_mut(self)))
+                .collect();
+        } else {
+            let shown_before = std::mem::take(&mut self.shown_before_clean_view);
+            for (shown_mut, shown) in OVERLAY_TOGGLES.iter().zip(shown_before) {
+                *shown_mut(self) = shown;
+            }
+        }
+    }
+
+    /// Add this to viewport block coordinates to get world block coordinates
+    pub fn world_translation(&self) -> [isize; 2] {
+        self.world_anchor_split()
+            .map_or([0, 0], |(translation, _)| translation)
+    }
+
+    /// The center offset that the world anchor gives the layers: the position of the shape center
+    ///  within its block, in the coordinates of the shapes (see `from_cells`). Zero without a world
+    ///  anchor.
+    pub fn world_anchor_shift(&self, cell_aspect: f64) -> [f64; 2] {
+        self.world_anchor_split()
+            .map_or([0.0, 0.0], |(_, within_block)| {
+                from_cells(within_block, cell_aspect)
+            })
+    }
 }
 
 impl Default for View {
     fn default() -> Self {
         Self {
+            grid_steps: [1.0, 5.0, 10.0], // for counting
+            grid_colors: [COLOR_GRID_FINE, COLOR_GRID_MEDIUM, COLOR_GRID_COARSE],
+            hide_fine_grid: true,
+            grid_lattice: Lattice::Corners,
+            distance_bands: false,
+            distance_band_width: 2.0,
             blocks: true,
+            block_pattern: BlockPattern::None,
+            // Largest to smallest, so that the smaller sets are visible on top of the larger ones
+            overlays: [
+                BlockOverlay::ProjectionUnion,
+                BlockOverlay::Blocks,
+                BlockOverlay::BetweenLayers,
+                BlockOverlay::ProjectionIntersection,
+                BlockOverlay::Complement,
+                BlockOverlay::Holes3d,
+                BlockOverlay::Holes2d,
+                BlockOverlay::Boundary3d,
+                BlockOverlay::Boundary2d,
+                BlockOverlay::Interior2d,
+                BlockOverlay::Interior3d,
+                BlockOverlay::Supports,
+                BlockOverlay::CenterBlocks,
+            ]
+            .map(|overlay| OverlayStyle {
+                overlay,
+                // The blocks between layers are compared to the blocks of the layer below them
+                opacity: if overlay == BlockOverlay::BetweenLayers {
+                    0.5
+                } else {
+                    1.0
+                },
+                wireframe: true,
+            })
+            .to_vec(),
+            wire_color: COLOR_WIRE,
+            wire_width: 1.0,
             boundary_2d: false,
             interior_2d: false,
             intersect_area: false,
             complement: false,
             boundary_3d: false,
             interior_3d: false,
+            connectivity_3d: Connectivity::Faces,
+            wall_thickness: 1,
+            projection_union: false,
+            projection_intersection: false,
             convex_hull: false,
             outer_corners: false,
+            boundary_tour: false,
+            boundary_segments: false,
+            boundary_segment_labels: true,
+            holes: false,
+            thin_necks: false,
             center_blocks: false,
+            supports: false,
+            support_pattern: Default::default(),
             bounds: false,
             mirrors: true, //debug false
+            octants: false,
+            vertical_slice: false,
+            variation_gallery: false,
+            find_replace: false,
+            lean: false,
+            fit_to_points: false,
+            parameter_history: false,
+            export_script: false,
+            calculator: false,
+            shape_through_points: false,
+            text_stamp: false,
+            reference_image: false,
+            layout_canvas: false,
+            template_gallery: false,
+            arch: false,
+            log_console: false,
+            viewer_mode: false,
+            color_blind_palette: false,
+            pinned_outline: false,
+            pinned_layer: 0,
+            between_layers: false,
+            between_layers_height: 0.5,
+            between_layers_blocks: false,
+            sample_labels: false,
+            sub_blocks: false,
+            depth_shading: false,
+            minecraft_axes: false,
+            flip_z: false,
+            world_anchor: false,
+            world_center: [0.5, 0.5], // the middle of a block
+            zoom_to_blocks: false,
+            zoom_to_layer: false,
+            zoom_padding: 1.1,
+            zoom_margin: 0.0,
+            coord_copy_format: "{x} {layer} {y}".to_string(), // Minecraft order (height second)
+            build_order_all_layers: true,
+            split_parameters: false,
+            split_layer: 0,
+            clean_view: false,
+            shown_before_clean_view: vec![],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::View;
+
+    #[test]
+    fn world_anchor_shift_without_anchor() {
+        assert_eq!(View::default().world_anchor_shift(0.5), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn world_anchor_shift_square_cells() {
+        let view = View {
+            world_anchor: true,
+            world_center: [10.25, -3.5],
+            ..Default::default()
+        };
+        assert_eq!(view.world_translation(), [10, 3]);
+        assert_eq!(view.world_anchor_shift(1.0), [0.25, 0.5]);
+    }
+
+    #[test]
+    fn world_anchor_shift_flat_cells() {
+        // Half height cells: the center is a quarter of a cell width above the bottom of its cell
+        let view = View {
+            world_anchor: true,
+            world_center: [10.25, -3.5],
+            ..Default::default()
+        };
+        assert_eq!(view.world_anchor_shift(0.5), [0.25, 0.25]);
+        assert_eq!(view.world_anchor_shift(2.0), [0.25, 1.0]);
+    }
+}
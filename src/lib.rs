@@ -0,0 +1,3 @@
+// The app as a library, so that the benchmarks (in `benches/`) can use the generation algorithms
+//  and metrics. The binary is in main.rs.
+pub mod app;
@@ -3,15 +3,16 @@
 
 use std::default::Default;
 
-use eframe::egui::ViewportBuilder;
+use eframe::egui::{DroppedFile, ViewportBuilder};
 use eframe::{HardwareAcceleration, Theme};
-
-mod app;
+use voxircle::app;
 
 fn main() {
     let options = eframe::NativeOptions {
         viewport: ViewportBuilder::default(),
-        vsync: false,
+        // The app only repaints when something changes (see `App::update`), and then vsync keeps it
+        //  from rendering more frames than the screen shows
+        vsync: true,
         multisampling: 0,
         depth_buffer: 0,
         stencil_buffer: 0,
@@ -31,6 +32,23 @@ fn main() {
     let _ = eframe::run_native(
         "Voxircle",
         options,
-        Box::new(|cc| Box::new(app::App::new(cc))),
+        Box::new(|cc| {
+            let mut app = app::App::new(cc);
+            // A file given on the command line, for example by opening it with Voxircle, and
+            //  `--viewer` to start in viewer mode
+            let (flags, paths): (Vec<_>, Vec<_>) = std::env::args_os()
+                .skip(1)
+                .partition(|arg| arg == "--viewer");
+            if let Some(path) = paths.into_iter().next() {
+                app.open_file(&DroppedFile {
+                    path: Some(path.into()),
+                    ..Default::default()
+                });
+            }
+            if !flags.is_empty() {
+                app.set_viewer_mode(true);
+            }
+            Box::new(app)
+        }),
     );
 }